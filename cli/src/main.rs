@@ -0,0 +1,704 @@
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::{Args, Parser, Subcommand};
+use cwpt_core::models::preservation::{ArchiveRequest, ArchiveResult, BagInfoField};
+use cwpt_core::http::{HttpServer, DEFAULT_HTTP_PORT};
+use cwpt_core::utils::bag_update::PayloadChange;
+use cwpt_core::utils::s3::S3Config;
+use cwpt_core::utils::watch::WatchTracker;
+use cwpt_core::utils::webdav::WebDavConfig;
+use cwpt_core::{store, BagOptions, Error, ExportFormat, Vault, VaultConfig};
+use serde::Serialize;
+use std::process::ExitCode;
+use std::time::Duration;
+
+/// Exit status when a command ran but found problems, such as an invalid bag
+const EXIT_CHECK_FAILED: u8 = 1;
+/// Exit status for bad arguments or a request the project's state does not allow
+const EXIT_INVALID: u8 = 2;
+/// Exit status when a project, bag or path does not exist
+const EXIT_NOT_FOUND: u8 = 3;
+/// Exit status for I/O, database and other runtime failures
+const EXIT_FAILURE: u8 = 4;
+
+/// Passphrase that unlocks encryption for encrypted replicas and exports
+const PASSPHRASE_VAR: &str = "CWPT_PASSPHRASE";
+/// Passphrase that replaces the current one
+const NEW_PASSPHRASE_VAR: &str = "CWPT_NEW_PASSPHRASE";
+/// Recovery key exported when encryption was set up
+const RECOVERY_KEY_VAR: &str = "CWPT_RECOVERY_KEY";
+/// Passphrase that unlocks an encrypted database
+const DATABASE_PASSPHRASE_VAR: &str = "CWPT_DATABASE_PASSPHRASE";
+/// Database passphrase that replaces the current one
+const NEW_DATABASE_PASSPHRASE_VAR: &str = "CWPT_NEW_DATABASE_PASSPHRASE";
+
+fn exit_code(error: &Error) -> u8 {
+    match error {
+        Error::ProjectNotFound(_) | Error::NotFound(_) => EXIT_NOT_FOUND,
+        Error::InvalidState(_) | Error::WrongPassphrase => EXIT_INVALID,
+        Error::Io(_) | Error::Failed(_) => EXIT_FAILURE,
+    }
+}
+
+/// Creative Work Preservation Toolkit, without the GUI
+///
+/// Every command prints a JSON result on stdout. Errors are printed as JSON
+/// on stderr. Exit status: 0 success, 1 check found problems, 2 invalid
+/// request, 3 not found, 4 failure.
+///
+/// Encryption secrets are read from the environment only, never from flags,
+/// so they stay out of shell history and the process list: CWPT_PASSPHRASE,
+/// CWPT_NEW_PASSPHRASE, CWPT_RECOVERY_KEY, CWPT_DATABASE_PASSPHRASE and
+/// CWPT_NEW_DATABASE_PASSPHRASE.
+#[derive(Parser)]
+#[command(name = "cwpt", version)]
+struct Cli {
+    /// Preservation database to use instead of the desktop app's; its bags are kept beside it
+    #[arg(long, env = "CWPT_DATABASE", global = true)]
+    database: Option<Utf8PathBuf>,
+
+    /// Indent the JSON output
+    #[arg(long, global = true)]
+    pretty: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Record a new archived project from files or folders
+    Archive {
+        /// Project name
+        #[arg(long)]
+        name: String,
+        #[arg(long)]
+        description: Option<String>,
+        /// Environment profile to attach; may be given more than once
+        #[arg(long = "environment")]
+        environment_profile_ids: Vec<String>,
+        /// Files and folders that make up the project
+        #[arg(required = true)]
+        paths: Vec<String>,
+    },
+    /// Create the BagIt package of an archived project
+    Bag {
+        project_id: String,
+        /// BagIt profile the bag must conform to
+        #[arg(long)]
+        profile: Option<Utf8PathBuf>,
+        /// Extra bag-info.txt field; may be given more than once
+        #[arg(long = "bag-info", value_name = "LABEL=VALUE", value_parser = parse_bag_info_field)]
+        bag_info: Vec<BagInfoField>,
+    },
+    /// Validate a project's bag, or a bag directory or serialized bag given by path
+    Validate {
+        #[arg(required_unless_present = "path", conflicts_with = "path")]
+        project_id: Option<String>,
+        #[arg(long)]
+        path: Option<Utf8PathBuf>,
+        /// BagIt profile the bag at --path must also conform to
+        #[arg(long, requires = "path")]
+        profile: Option<Utf8PathBuf>,
+    },
+    /// Validate a tar, tar.gz or zip serialized bag and add it to the vault as a new project
+    Import {
+        archive: Utf8PathBuf,
+        /// BagIt profile the bag must conform to
+        #[arg(long)]
+        profile: Option<Utf8PathBuf>,
+    },
+    /// Add, remove or replace files in a project's bag in place, as a new bag version
+    UpdateBag {
+        project_id: String,
+        #[arg(long, value_name = "PAYLOAD_PATH=SOURCE", value_parser = parse_payload_source)]
+        add: Vec<(String, String)>,
+        #[arg(long, value_name = "PAYLOAD_PATH")]
+        remove: Vec<String>,
+        #[arg(long, value_name = "PAYLOAD_PATH=SOURCE", value_parser = parse_payload_source)]
+        replace: Vec<(String, String)>,
+    },
+    /// Move a project's bag into quarantine
+    Quarantine {
+        project_id: String,
+        #[arg(long)]
+        reason: String,
+    },
+    /// Bring a quarantined project back into the vault
+    Restore { project_id: String },
+    /// Validate every bag in the vault and record an integrity snapshot
+    Scan,
+    /// Export a project's bag or metadata
+    ///
+    /// Without a project ID, exports every bagged project as one VRA record.
+    Export {
+        project_id: Option<String>,
+        /// One of tar, tar-gz, zip, vra or premis
+        #[arg(long, value_parser = parse_export_format)]
+        format: ExportFormat,
+        /// Directory for bag archives, or the file for VRA and PREMIS records
+        #[arg(long)]
+        output: Utf8PathBuf,
+        /// Encrypt the bag archive; needs CWPT_PASSPHRASE
+        #[arg(long)]
+        encrypt: bool,
+    },
+    /// List what changed in a project's working folder since it was archived
+    Changes { project_id: String },
+    /// Archive the changes in a project's working folder as a new version
+    ///
+    /// The new bag holds only added and changed files and references the
+    /// rest from the previous version's bag through fetch.txt.
+    Update { project_id: String },
+    /// Link projects as versions or branches of each other and compare them
+    #[command(subcommand)]
+    Lineage(LineageCommand),
+    /// Add files kept elsewhere to a bag through fetch.txt, and fetch them in later
+    #[command(subcommand)]
+    Fetch(FetchCommand),
+    /// Manage and run watch folders that archive new work automatically
+    #[command(subcommand)]
+    Watch(WatchCommand),
+    /// Manage replication targets and the replicas of each bag on them
+    #[command(subcommand)]
+    Replicas(ReplicasCommand),
+    /// Set up and manage client-side encryption of replicas and exports
+    #[command(subcommand)]
+    Encryption(EncryptionCommand),
+    /// Encrypt the database at rest and manage its passphrase
+    #[command(subcommand)]
+    Database(DatabaseCommand),
+    /// Serve the local HTTP API on 127.0.0.1 until interrupted
+    ///
+    /// Prints the API URL and bearer token as JSON once listening.
+    Serve {
+        #[arg(long, default_value_t = DEFAULT_HTTP_PORT)]
+        port: u16,
+    },
+}
+
+#[derive(Subcommand)]
+enum LineageCommand {
+    /// Record that a project is a later version or a branch of another
+    Link {
+        parent_project_id: String,
+        child_project_id: String,
+        /// version or branch
+        #[arg(long, default_value = "version")]
+        relation_type: String,
+        #[arg(long)]
+        label: Option<String>,
+    },
+    /// Remove the link between two projects
+    Unlink {
+        parent_project_id: String,
+        child_project_id: String,
+    },
+    /// Show the history tree a project belongs to
+    History { project_id: String },
+    /// Compare the payload of two versions by checksum
+    Diff {
+        from_project_id: String,
+        to_project_id: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum FetchCommand {
+    /// List a file on external storage in a project's bag without copying it
+    Add {
+        project_id: String,
+        source: Utf8PathBuf,
+        /// Where the file belongs in the payload, e.g. scans/plate-1.tif
+        #[arg(long)]
+        payload_path: String,
+    },
+    /// Copy in every file the bag's fetch.txt lists, checking each against the manifests
+    Complete { project_id: String },
+}
+
+#[derive(Subcommand)]
+enum WatchCommand {
+    /// Watch a folder; each file or folder dropped into it becomes a project
+    Add(WatchAddArgs),
+    /// List watch folders
+    List,
+    /// Stop watching a folder
+    Remove { watch_folder_id: String },
+    /// Poll the watch folders until interrupted, printing each ingestion as a JSON line
+    Run {
+        /// Seconds between polls
+        #[arg(long, default_value_t = 5)]
+        interval: u64,
+    },
+}
+
+#[derive(Subcommand)]
+enum ReplicasCommand {
+    /// Add a folder, such as an external drive or NAS mount, that every bag is copied to
+    AddTarget {
+        #[arg(long)]
+        name: String,
+        path: Utf8PathBuf,
+    },
+    /// Add an S3-compatible bucket, such as AWS S3 or MinIO, that every bag is copied to
+    ///
+    /// The secret key is stored encrypted, so encryption must be set up and CWPT_PASSPHRASE set.
+    AddS3Target(S3TargetArgs),
+    /// Add a WebDAV share, such as a Nextcloud folder, that every bag is copied to
+    ///
+    /// The password is stored encrypted, so encryption must be set up and CWPT_PASSPHRASE set.
+    AddWebdavTarget {
+        #[arg(long)]
+        name: String,
+        /// Folder URL, e.g. https://cloud.example.edu/remote.php/dav/files/alice/Archive
+        #[arg(long)]
+        url: String,
+        #[arg(long)]
+        username: String,
+        /// Password, or an app password for accounts with two-factor login
+        #[arg(long, env = "CWPT_WEBDAV_PASSWORD", hide_env_values = true)]
+        password: String,
+    },
+    /// List replication targets
+    Targets,
+    /// Stop replicating to a target; copies already there are left in place
+    RemoveTarget { target_id: String },
+    /// Copy bags to targets that do not have them yet, or recopy one project's bag
+    Sync { project_id: Option<String> },
+    /// Show the recorded status of each replica
+    Status { project_id: Option<String> },
+    /// Check replicas against their manifests
+    Verify {
+        /// Only check replicas not verified within the configured interval
+        #[arg(long)]
+        due: bool,
+        /// Repair corrupted or missing replicas from a healthy copy
+        #[arg(long)]
+        repair: bool,
+    },
+    /// Repair a project's damaged bag or replicas from a healthy copy
+    Repair { project_id: String },
+    /// Encrypt a target's replicas from now on, rewriting existing ones; needs CWPT_PASSPHRASE
+    Encrypt {
+        target_id: String,
+        /// Store the target's replicas as plain bags again
+        #[arg(long)]
+        off: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum EncryptionCommand {
+    /// Show whether encryption is set up and how many keys it has
+    Status,
+    /// Set up encryption with the passphrase in CWPT_PASSPHRASE
+    SetUp,
+    /// Replace the passphrase in CWPT_PASSPHRASE with the one in CWPT_NEW_PASSPHRASE
+    ChangePassphrase,
+    /// Set the passphrase in CWPT_NEW_PASSPHRASE with the recovery key in
+    /// CWPT_RECOVERY_KEY, when the old one is forgotten
+    Recover,
+    /// Write a new recovery key to a file; earlier recovery keys stop working
+    ExportRecoveryKey {
+        #[arg(long)]
+        output: Utf8PathBuf,
+    },
+    /// Encrypt with a new key from now on and rewrite encrypted replicas under it
+    RotateKey,
+    /// Decrypt an encrypted export
+    Decrypt {
+        input: Utf8PathBuf,
+        #[arg(long)]
+        output: Utf8PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum DatabaseCommand {
+    /// Show whether the database is encrypted
+    Status,
+    /// Encrypt the database with the passphrase in CWPT_DATABASE_PASSPHRASE
+    Encrypt,
+    /// Replace the passphrase in CWPT_DATABASE_PASSPHRASE with the one in CWPT_NEW_DATABASE_PASSPHRASE
+    ChangePassphrase,
+    /// Write an unencrypted copy of the database, to move the vault elsewhere
+    ExportUnencrypted {
+        #[arg(long)]
+        output: Utf8PathBuf,
+    },
+}
+
+#[derive(Args)]
+struct S3TargetArgs {
+    #[arg(long)]
+    name: String,
+    /// Service URL, e.g. https://s3.eu-central-1.amazonaws.com or http://localhost:9000
+    #[arg(long)]
+    endpoint: String,
+    #[arg(long)]
+    bucket: String,
+    #[arg(long, default_value = "us-east-1")]
+    region: String,
+    /// Key prefix bags are stored under
+    #[arg(long, default_value = "")]
+    prefix: String,
+    #[arg(long, env = "AWS_ACCESS_KEY_ID")]
+    access_key_id: String,
+    #[arg(long, env = "AWS_SECRET_ACCESS_KEY", hide_env_values = true)]
+    secret_access_key: String,
+}
+
+#[derive(Args)]
+struct WatchAddArgs {
+    path: Utf8PathBuf,
+    /// Seconds an entry must stay unchanged before it is archived
+    #[arg(long)]
+    settle_seconds: Option<i64>,
+    /// Only record the project; do not create its bag
+    #[arg(long)]
+    no_bag: bool,
+}
+
+fn parse_bag_info_field(value: &str) -> Result<BagInfoField, String> {
+    let (label, value) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected LABEL=VALUE, got `{}`", value))?;
+    Ok(BagInfoField {
+        label: label.trim().to_string(),
+        value: value.trim().to_string(),
+    })
+}
+
+fn parse_payload_source(value: &str) -> Result<(String, String), String> {
+    let (payload_path, source) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected PAYLOAD_PATH=SOURCE, got `{}`", value))?;
+    Ok((payload_path.to_string(), source.to_string()))
+}
+
+fn parse_export_format(value: &str) -> Result<ExportFormat, String> {
+    ExportFormat::parse(value).ok_or_else(|| {
+        let formats: Vec<&str> = ExportFormat::ALL.iter().map(|f| f.as_str()).collect();
+        format!("expected one of {}", formats.join(", "))
+    })
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let pretty = cli.pretty;
+
+    match run(cli).await {
+        Ok((output, passed)) => {
+            println!("{}", render(&output, pretty));
+            if passed {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::from(EXIT_CHECK_FAILED)
+            }
+        }
+        Err(e) => {
+            let output = serde_json::json!({ "success": false, "error": format!("{:#}", e) });
+            eprintln!("{}", render(&output, pretty));
+            ExitCode::from(exit_code(&e))
+        }
+    }
+}
+
+/// Read a secret from the environment, treating an empty value as unset
+fn env_secret(var: &str) -> Option<String> {
+    std::env::var(var).ok().filter(|value| !value.is_empty())
+}
+
+fn required_secret(var: &str) -> Result<String, Error> {
+    env_secret(var).ok_or_else(|| Error::InvalidState(format!("Set {} in the environment", var)))
+}
+
+/// Run a command, returning its JSON output and whether any check it made passed
+async fn run(cli: Cli) -> Result<(serde_json::Value, bool), Error> {
+    // The app's vault keeps its bags in the app's data folder; any other database keeps them beside it
+    let (database, data_dir) = match cli.database {
+        Some(path) => {
+            let data_dir = path.parent().map(Utf8Path::to_path_buf).unwrap_or_default();
+            (path, data_dir)
+        }
+        None => (store::default_database_path()?, store::default_data_dir()?),
+    };
+    let vault = Vault::open(VaultConfig::with_data_dir(database, &data_dir)).await?;
+    let pretty = cli.pretty;
+    if vault.is_database_locked() && !matches!(cli.command, Command::Database(DatabaseCommand::Status)) {
+        vault.unlock_database(&required_secret(DATABASE_PASSPHRASE_VAR)?).await?;
+    }
+    let recovering = matches!(cli.command, Command::Encryption(EncryptionCommand::Recover));
+    let passphrase = env_secret(PASSPHRASE_VAR);
+    if let Some(passphrase) = passphrase.as_deref().filter(|_| !recovering) {
+        if vault.encryption_status().await?.is_set_up {
+            vault.unlock_encryption(passphrase).await?;
+        }
+    }
+
+    match cli.command {
+        Command::Archive {
+            name,
+            description,
+            environment_profile_ids,
+            paths,
+        } => {
+            let request = ArchiveRequest {
+                name,
+                description,
+                files: paths,
+                environment_profile_ids,
+            };
+            let project = vault.archive_project(&request).await?;
+            let result = ArchiveResult {
+                success: true,
+                project_id: Some(project.id),
+                error: None,
+            };
+            to_output(result, true)
+        }
+        Command::Bag { project_id, profile, bag_info } => {
+            let options = BagOptions {
+                bag_info_fields: bag_info,
+                profile_path: profile,
+            };
+            let result = vault.create_bag(&project_id, options).await?;
+            let passed = result.success;
+            to_output(result, passed)
+        }
+        Command::Validate { project_id, path, profile } => {
+            let result = match (project_id, path) {
+                (Some(project_id), _) => vault.validate_project(&project_id).await?,
+                (None, Some(path)) => vault.validate_path(&path, profile.as_deref()).await?,
+                (None, None) => unreachable!("clap requires a project ID or --path"),
+            };
+            let passed = result.success;
+            to_output(result, passed)
+        }
+        Command::Import { archive, profile } => {
+            let result = vault.import_serialized_bag(&archive, profile.as_deref()).await?;
+            let passed = result.success;
+            to_output(result, passed)
+        }
+        Command::UpdateBag { project_id, add, remove, replace } => {
+            let mut changes = Vec::new();
+            for (payload_path, source_path) in add {
+                changes.push(PayloadChange::Add { source_path, payload_path });
+            }
+            for payload_path in remove {
+                changes.push(PayloadChange::Remove { payload_path });
+            }
+            for (payload_path, source_path) in replace {
+                changes.push(PayloadChange::Replace { source_path, payload_path });
+            }
+            let result = vault.update_bag(&project_id, &changes).await?;
+            let passed = result.success;
+            to_output(result, passed)
+        }
+        Command::Quarantine { project_id, reason } => {
+            to_output(vault.quarantine_project(&project_id, &reason).await?, true)
+        }
+        Command::Restore { project_id } => to_output(vault.restore_project(&project_id).await?, true),
+        Command::Scan => {
+            let report = vault.scan_integrity().await?;
+            let passed = report.is_healthy;
+            to_output(report, passed)
+        }
+        Command::Export { project_id: Some(project_id), format, output, encrypt: false } => {
+            to_output(vault.export_project(&project_id, format, &output).await?, true)
+        }
+        Command::Export { project_id: Some(project_id), format, output, encrypt: true } => {
+            to_output(vault.export_encrypted_bag(&project_id, format, &output).await?, true)
+        }
+        Command::Export { project_id: None, format: ExportFormat::Vra, output, encrypt: false } => {
+            to_output(vault.export_vault_vra(&output).await?, true)
+        }
+        Command::Export { project_id: None, .. } => Err(Error::InvalidState(
+            "Give a project ID; only an unencrypted vra export covers the whole vault".to_string(),
+        )),
+        Command::Changes { project_id } => to_output(vault.detect_source_changes(&project_id).await?, true),
+        Command::Update { project_id } => {
+            let result = vault.create_delta_version(&project_id).await?;
+            let passed = result.bag.success;
+            to_output(result, passed)
+        }
+        Command::Lineage(LineageCommand::Link { parent_project_id, child_project_id, relation_type, label }) => {
+            let relation = vault
+                .link_projects(&parent_project_id, &child_project_id, &relation_type, label.as_deref())
+                .await?;
+            to_output(relation, true)
+        }
+        Command::Lineage(LineageCommand::Unlink { parent_project_id, child_project_id }) => {
+            vault.unlink_projects(&parent_project_id, &child_project_id).await?;
+            to_output(serde_json::json!({ "success": true }), true)
+        }
+        Command::Lineage(LineageCommand::History { project_id }) => {
+            to_output(vault.project_history(&project_id).await?, true)
+        }
+        Command::Lineage(LineageCommand::Diff { from_project_id, to_project_id }) => {
+            to_output(vault.diff_versions(&from_project_id, &to_project_id).await?, true)
+        }
+        Command::Fetch(FetchCommand::Add { project_id, source, payload_path }) => {
+            to_output(vault.add_external_reference(&project_id, &source, &payload_path).await?, true)
+        }
+        Command::Fetch(FetchCommand::Complete { project_id }) => {
+            let result = vault.complete_fetch(&project_id).await?;
+            let passed = result.success;
+            to_output(result, passed)
+        }
+        Command::Watch(WatchCommand::Add(args)) => {
+            to_output(vault.add_watch_folder(&args.path, args.settle_seconds, !args.no_bag).await?, true)
+        }
+        Command::Watch(WatchCommand::List) => to_output(vault.list_watch_folders().await?, true),
+        Command::Watch(WatchCommand::Remove { watch_folder_id }) => {
+            vault.remove_watch_folder(&watch_folder_id).await?;
+            to_output(serde_json::json!({ "success": true }), true)
+        }
+        Command::Watch(WatchCommand::Run { interval }) => {
+            let mut tracker = WatchTracker::default();
+            let mut ticker = tokio::time::interval(Duration::from_secs(interval.max(1)));
+            let mut failed = false;
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {}
+                    _ = tokio::signal::ctrl_c() => break,
+                }
+                for ingestion in vault.poll_watch_folders(&mut tracker).await? {
+                    failed |= ingestion.error.is_some();
+                    println!("{}", render(&serde_json::to_value(&ingestion).unwrap_or_default(), pretty));
+                }
+            }
+            to_output(serde_json::json!({ "success": !failed }), !failed)
+        }
+        Command::Replicas(ReplicasCommand::AddTarget { name, path }) => {
+            to_output(vault.add_replication_target(&name, &path).await?, true)
+        }
+        Command::Replicas(ReplicasCommand::AddS3Target(args)) => {
+            let config = S3Config {
+                endpoint: args.endpoint,
+                bucket: args.bucket,
+                region: args.region,
+                prefix: args.prefix,
+                access_key_id: args.access_key_id,
+                secret_access_key: args.secret_access_key,
+            };
+            to_output(vault.add_s3_replication_target(&args.name, config).await?, true)
+        }
+        Command::Replicas(ReplicasCommand::AddWebdavTarget { name, url, username, password }) => {
+            let config = WebDavConfig { url, username, password };
+            to_output(vault.add_webdav_replication_target(&name, config).await?, true)
+        }
+        Command::Replicas(ReplicasCommand::Targets) => to_output(vault.list_replication_targets().await?, true),
+        Command::Replicas(ReplicasCommand::RemoveTarget { target_id }) => {
+            vault.remove_replication_target(&target_id).await?;
+            to_output(serde_json::json!({ "success": true }), true)
+        }
+        Command::Replicas(ReplicasCommand::Sync { project_id }) => {
+            let replicas = match project_id {
+                Some(project_id) => vault.replicate_project(&project_id).await?,
+                None => vault.replicate_all().await?,
+            };
+            let passed = replicas.iter().all(|r| r.status == "healthy");
+            to_output(replicas, passed)
+        }
+        Command::Replicas(ReplicasCommand::Status { project_id }) => {
+            to_output(vault.replica_statuses(project_id.as_deref()).await?, true)
+        }
+        Command::Replicas(ReplicasCommand::Verify { due, repair }) => {
+            let replicas = vault.verify_replicas(due).await?;
+            let mut damaged: Vec<&str> = replicas
+                .iter()
+                .filter(|r| r.status == "corrupted" || r.status == "missing")
+                .map(|r| r.archived_project_id.as_str())
+                .collect();
+            damaged.sort();
+            damaged.dedup();
+
+            let mut repairs = Vec::new();
+            if repair {
+                for project_id in &damaged {
+                    repairs.extend(vault.repair_project(project_id).await?);
+                }
+            }
+            let passed = if repair { repairs.iter().all(|r| r.success) } else { damaged.is_empty() };
+            to_output(serde_json::json!({ "replicas": replicas, "repairs": repairs }), passed)
+        }
+        Command::Replicas(ReplicasCommand::Repair { project_id }) => {
+            let repairs = vault.repair_project(&project_id).await?;
+            let passed = repairs.iter().all(|r| r.success);
+            to_output(repairs, passed)
+        }
+        Command::Replicas(ReplicasCommand::Encrypt { target_id, off }) => {
+            to_output(vault.set_replication_target_encrypted(&target_id, !off).await?, true)
+        }
+        Command::Encryption(EncryptionCommand::Status) => to_output(vault.encryption_status().await?, true),
+        Command::Encryption(EncryptionCommand::SetUp) => {
+            let passphrase = required_secret(PASSPHRASE_VAR)?;
+            to_output(vault.set_up_encryption(&passphrase).await?, true)
+        }
+        Command::Encryption(EncryptionCommand::ChangePassphrase) => {
+            let passphrase = required_secret(PASSPHRASE_VAR)?;
+            let new_passphrase = required_secret(NEW_PASSPHRASE_VAR)?;
+            vault.change_encryption_passphrase(&passphrase, &new_passphrase).await?;
+            to_output(serde_json::json!({ "success": true }), true)
+        }
+        Command::Encryption(EncryptionCommand::Recover) => {
+            let recovery_key = required_secret(RECOVERY_KEY_VAR)?;
+            let new_passphrase = required_secret(NEW_PASSPHRASE_VAR)?;
+            to_output(vault.recover_encryption(&recovery_key, &new_passphrase).await?, true)
+        }
+        Command::Encryption(EncryptionCommand::ExportRecoveryKey { output }) => {
+            vault.export_recovery_key(&output).await?;
+            to_output(serde_json::json!({ "success": true, "output_path": output.as_str() }), true)
+        }
+        Command::Encryption(EncryptionCommand::RotateKey) => {
+            let replicas = vault.rotate_encryption_key().await?;
+            let passed = replicas.iter().all(|r| r.status == "healthy");
+            to_output(replicas, passed)
+        }
+        Command::Encryption(EncryptionCommand::Decrypt { input, output }) => {
+            vault.decrypt_file(&input, &output).await?;
+            to_output(serde_json::json!({ "success": true, "output_path": output.as_str() }), true)
+        }
+        Command::Database(DatabaseCommand::Status) => to_output(vault.database_status().await?, true),
+        Command::Database(DatabaseCommand::Encrypt) => {
+            let passphrase = required_secret(DATABASE_PASSPHRASE_VAR)?;
+            to_output(vault.encrypt_database(&passphrase).await?, true)
+        }
+        Command::Database(DatabaseCommand::ChangePassphrase) => {
+            let passphrase = required_secret(DATABASE_PASSPHRASE_VAR)?;
+            let new_passphrase = required_secret(NEW_DATABASE_PASSPHRASE_VAR)?;
+            vault.change_database_passphrase(&passphrase, &new_passphrase).await?;
+            to_output(serde_json::json!({ "success": true }), true)
+        }
+        Command::Database(DatabaseCommand::ExportUnencrypted { output }) => {
+            vault.export_unencrypted_database(&output).await?;
+            to_output(serde_json::json!({ "success": true, "output_path": output.as_str() }), true)
+        }
+        Command::Serve { port } => {
+            let server = HttpServer::bind(vault, port).await?;
+            let listening = serde_json::json!({ "url": server.url()?, "token": server.token() });
+            println!("{}", render(&listening, pretty));
+            server
+                .run(async {
+                    let _ = tokio::signal::ctrl_c().await;
+                })
+                .await?;
+            to_output(serde_json::json!({ "success": true }), true)
+        }
+    }
+}
+
+fn to_output(result: impl Serialize, passed: bool) -> Result<(serde_json::Value, bool), Error> {
+    Ok((serde_json::to_value(result).map_err(anyhow::Error::from)?, passed))
+}
+
+fn render(output: &serde_json::Value, pretty: bool) -> String {
+    let text = if pretty {
+        serde_json::to_string_pretty(output)
+    } else {
+        serde_json::to_string(output)
+    };
+    text.unwrap_or_default()
+}
@@ -0,0 +1,16 @@
+-- Lineage between archived projects: sketch -> draft -> final, plus side branches
+CREATE TABLE project_relations (
+    id TEXT PRIMARY KEY,
+    parent_project_id TEXT NOT NULL,
+    child_project_id TEXT NOT NULL,
+    relation_type TEXT NOT NULL,        -- version, branch
+    label TEXT,                         -- Stage name such as sketch, draft or final
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (parent_project_id) REFERENCES archived_projects(id),
+    FOREIGN KEY (child_project_id) REFERENCES archived_projects(id),
+    UNIQUE(parent_project_id, child_project_id),
+    CHECK (parent_project_id != child_project_id)
+);
+
+CREATE INDEX idx_project_relations_parent ON project_relations(parent_project_id);
+CREATE INDEX idx_project_relations_child ON project_relations(child_project_id);
\ No newline at end of file
@@ -0,0 +1,25 @@
+-- Named collections (course, semester, portfolio) that can nest inside each other
+CREATE TABLE collections (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    description TEXT,
+    parent_collection_id TEXT,          -- NULL for top-level collections
+    position INTEGER NOT NULL DEFAULT 0, -- Order among siblings
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (parent_collection_id) REFERENCES collections(id)
+);
+
+-- Many-to-many membership of archived projects in collections
+CREATE TABLE collection_members (
+    collection_id TEXT NOT NULL,
+    archived_project_id TEXT NOT NULL,
+    position INTEGER NOT NULL DEFAULT 0, -- Order within the collection
+    added_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (collection_id, archived_project_id),
+    FOREIGN KEY (collection_id) REFERENCES collections(id) ON DELETE CASCADE,
+    FOREIGN KEY (archived_project_id) REFERENCES archived_projects(id)
+);
+
+CREATE INDEX idx_collections_parent ON collections(parent_collection_id);
+CREATE INDEX idx_collection_members_project ON collection_members(archived_project_id);
\ No newline at end of file
@@ -0,0 +1,42 @@
+-- Vocabularies group tags such as medium or technique; controlled ones only accept listed terms
+CREATE TABLE vocabularies (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+    description TEXT,
+    is_controlled BOOLEAN DEFAULT FALSE,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Tags: free-form when vocabulary_id is NULL, otherwise terms of a vocabulary
+CREATE TABLE tags (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    vocabulary_id TEXT,
+    usage_count INTEGER NOT NULL DEFAULT 0, -- Maintained by the tag_assignments triggers
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (vocabulary_id) REFERENCES vocabularies(id) ON DELETE CASCADE
+);
+
+-- Tags attached to a whole project (file_path = '') or to one payload file
+CREATE TABLE tag_assignments (
+    tag_id TEXT NOT NULL,
+    archived_project_id TEXT NOT NULL,
+    file_path TEXT NOT NULL DEFAULT '',
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (tag_id, archived_project_id, file_path),
+    FOREIGN KEY (tag_id) REFERENCES tags(id) ON DELETE CASCADE,
+    FOREIGN KEY (archived_project_id) REFERENCES archived_projects(id)
+);
+
+CREATE TRIGGER trg_tag_assignments_insert AFTER INSERT ON tag_assignments
+BEGIN
+    UPDATE tags SET usage_count = usage_count + 1 WHERE id = NEW.tag_id;
+END;
+
+CREATE TRIGGER trg_tag_assignments_delete AFTER DELETE ON tag_assignments
+BEGIN
+    UPDATE tags SET usage_count = usage_count - 1 WHERE id = OLD.tag_id;
+END;
+
+CREATE UNIQUE INDEX idx_tags_vocabulary_name ON tags(COALESCE(vocabulary_id, ''), name COLLATE NOCASE);
+CREATE INDEX idx_tag_assignments_project ON tag_assignments(archived_project_id);
\ No newline at end of file
@@ -0,0 +1,12 @@
+-- Full-text index over project records and their payload files
+-- One row per project (file_path = '') plus one row per payload file
+CREATE VIRTUAL TABLE search_index USING fts5(
+    archived_project_id UNINDEXED,
+    file_path UNINDEXED,
+    name,                               -- Project name, or payload path and filename words
+    description,
+    metadata,                           -- Descriptive bag-info.txt values
+    tags,
+    content,                            -- Text extracted from text, markdown and PDF payloads
+    tokenize = 'porter unicode61 remove_diacritics 2'
+);
\ No newline at end of file
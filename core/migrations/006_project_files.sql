@@ -0,0 +1,16 @@
+-- Catalog of payload files per project, used for format and risk facets
+CREATE TABLE project_files (
+    archived_project_id TEXT NOT NULL,
+    path TEXT NOT NULL,                 -- Payload path inside the bag, e.g. data/final.psd
+    size INTEGER NOT NULL,
+    media_type TEXT NOT NULL,
+    risk_level TEXT NOT NULL,           -- low, medium, high
+    risk_rank INTEGER NOT NULL,         -- 1 = low, 2 = medium, 3 = high; a project's risk is its highest rank
+    PRIMARY KEY (archived_project_id, path),
+    FOREIGN KEY (archived_project_id) REFERENCES archived_projects(id)
+);
+
+CREATE INDEX idx_project_files_media_type ON project_files(media_type);
+CREATE INDEX idx_archived_projects_archived_at ON archived_projects(archived_at);
+CREATE INDEX idx_archived_projects_total_size ON archived_projects(total_size);
+CREATE INDEX idx_archived_projects_name ON archived_projects(name);
\ No newline at end of file
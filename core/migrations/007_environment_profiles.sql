@@ -0,0 +1,32 @@
+-- Reusable descriptions of the software environment a work was made in
+CREATE TABLE environment_profiles (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+    description TEXT,
+    os_name TEXT,
+    os_version TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Software, plugins, fonts and libraries listed by a profile, in display order
+CREATE TABLE environment_components (
+    profile_id TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    component_type TEXT NOT NULL CHECK (component_type IN ('software', 'plugin', 'font', 'library')),
+    name TEXT NOT NULL,
+    version TEXT,
+    PRIMARY KEY (profile_id, position),
+    FOREIGN KEY (profile_id) REFERENCES environment_profiles(id) ON DELETE CASCADE
+);
+
+-- Profiles attached to a project at archive time
+CREATE TABLE project_environments (
+    archived_project_id TEXT NOT NULL,
+    profile_id TEXT NOT NULL,
+    attached_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (archived_project_id, profile_id),
+    FOREIGN KEY (archived_project_id) REFERENCES archived_projects(id),
+    FOREIGN KEY (profile_id) REFERENCES environment_profiles(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_project_environments_profile ON project_environments(profile_id);
\ No newline at end of file
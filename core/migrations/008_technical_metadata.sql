@@ -0,0 +1,11 @@
+-- Embedded technical metadata (EXIF, XMP, PDF and audio properties) per payload file
+CREATE TABLE file_technical_metadata (
+    archived_project_id TEXT NOT NULL,
+    path TEXT NOT NULL,                 -- Payload path inside the bag
+    key TEXT NOT NULL,                  -- Normalized key, e.g. exif.model or audio.duration_seconds
+    value TEXT NOT NULL,
+    PRIMARY KEY (archived_project_id, path, key),
+    FOREIGN KEY (archived_project_id) REFERENCES archived_projects(id)
+);
+
+CREATE INDEX idx_file_technical_metadata_key ON file_technical_metadata(key, value);
\ No newline at end of file
@@ -0,0 +1,21 @@
+-- Folders watched for new work that is archived automatically once it stops changing
+CREATE TABLE watch_folders (
+    id TEXT PRIMARY KEY,
+    path TEXT NOT NULL UNIQUE,
+    settle_seconds INTEGER NOT NULL,    -- How long an entry must be unchanged before it is ingested
+    create_bag BOOLEAN NOT NULL DEFAULT TRUE,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- The last ingestion of each top-level entry of a watch folder
+CREATE TABLE watch_folder_entries (
+    watch_folder_id TEXT NOT NULL,
+    entry_name TEXT NOT NULL,
+    fingerprint TEXT NOT NULL,          -- File count, total size and latest modification time when ingested
+    archived_project_id TEXT NOT NULL,
+    ingested_at DATETIME NOT NULL,
+    PRIMARY KEY (watch_folder_id, entry_name),
+    FOREIGN KEY (watch_folder_id) REFERENCES watch_folders(id) ON DELETE CASCADE,
+    FOREIGN KEY (archived_project_id) REFERENCES archived_projects(id)
+);
\ No newline at end of file
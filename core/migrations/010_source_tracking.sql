@@ -0,0 +1,18 @@
+-- Where each project was archived from, so its working folder can be checked for changes later
+CREATE TABLE project_sources (
+    archived_project_id TEXT PRIMARY KEY,
+    source_root TEXT NOT NULL,          -- Common root of the archived paths; payload paths are relative to it
+    recorded_at DATETIME NOT NULL,
+    FOREIGN KEY (archived_project_id) REFERENCES archived_projects(id)
+);
+
+-- Source files as they were when a project's bag was written
+CREATE TABLE source_file_states (
+    archived_project_id TEXT NOT NULL,
+    path TEXT NOT NULL,                 -- Relative to the source root, i.e. the payload path without data/
+    size INTEGER NOT NULL,
+    modified_at INTEGER NOT NULL,       -- Nanoseconds since the Unix epoch
+    sha256 TEXT NOT NULL,
+    PRIMARY KEY (archived_project_id, path),
+    FOREIGN KEY (archived_project_id) REFERENCES archived_projects(id)
+);
\ No newline at end of file
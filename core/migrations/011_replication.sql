@@ -0,0 +1,24 @@
+-- Places every bag is copied to, such as external drives and NAS mount points
+CREATE TABLE replication_targets (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    path TEXT NOT NULL UNIQUE,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- One copy of a project's bag on one target
+CREATE TABLE replicas (
+    archived_project_id TEXT NOT NULL,
+    target_id TEXT NOT NULL,
+    replica_path TEXT NOT NULL,
+    status TEXT NOT NULL,               -- pending, healthy, corrupted, missing, unavailable
+    last_copied_at DATETIME,
+    last_verified_at DATETIME,
+    last_error TEXT,
+    PRIMARY KEY (archived_project_id, target_id),
+    FOREIGN KEY (archived_project_id) REFERENCES archived_projects(id),
+    FOREIGN KEY (target_id) REFERENCES replication_targets(id)
+);
+
+CREATE INDEX idx_replicas_last_verified_at ON replicas(last_verified_at);
\ No newline at end of file
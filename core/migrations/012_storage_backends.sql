@@ -0,0 +1,5 @@
+-- Replication targets can be object stores as well as local folders
+ALTER TABLE replication_targets ADD COLUMN kind TEXT NOT NULL DEFAULT 'local';  -- local, s3
+
+-- Backend connection details as JSON, including credentials; NULL for local folders
+ALTER TABLE replication_targets ADD COLUMN settings TEXT;
\ No newline at end of file
@@ -0,0 +1,14 @@
+-- What was uploaded for each file of a replica on a remote target, so interrupted
+-- uploads can resume and later listings can be checked against it
+CREATE TABLE replica_objects (
+    archived_project_id TEXT NOT NULL,
+    target_id TEXT NOT NULL,
+    path TEXT NOT NULL,                 -- relative to the bag root
+    size INTEGER NOT NULL,
+    sha256 TEXT NOT NULL,
+    etag TEXT,                          -- as the target reported it right after the upload
+    stored_at DATETIME NOT NULL,
+    PRIMARY KEY (archived_project_id, target_id, path),
+    FOREIGN KEY (archived_project_id) REFERENCES archived_projects(id),
+    FOREIGN KEY (target_id) REFERENCES replication_targets(id)
+);
\ No newline at end of file
@@ -0,0 +1,14 @@
+-- Data keys for client-side encryption, each sealed under the master key. The master
+-- key itself is kept in settings, wrapped under the passphrase and the recovery key.
+CREATE TABLE encryption_keys (
+    id TEXT PRIMARY KEY,
+    sealed_key TEXT NOT NULL,
+    created_at DATETIME NOT NULL
+);
+
+-- Replicas on encrypted targets hold only ciphertext
+ALTER TABLE replication_targets ADD COLUMN encrypted BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Which data key each file of an encrypted replica was encrypted with, so replicas
+-- can be rewritten under a new key after a rotation
+ALTER TABLE replica_objects ADD COLUMN key_id TEXT;
\ No newline at end of file
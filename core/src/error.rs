@@ -0,0 +1,20 @@
+/// Errors returned by the vault API
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Project not found: {0}")]
+    ProjectNotFound(String),
+    #[error("Not found: {0}")]
+    NotFound(String),
+    /// The request is well formed but the project's current state does not allow it
+    #[error("{0}")]
+    InvalidState(String),
+    /// A passphrase or recovery key did not unlock what it was given for
+    #[error("Wrong passphrase")]
+    WrongPassphrase,
+    #[error(transparent)]
+    Failed(#[from] anyhow::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,323 @@
+//! Local HTTP API so scripts and other desktop tools can drive a vault
+//!
+//! The server only listens on the loopback interface and every request must
+//! carry `Authorization: Bearer <token>`, the token from
+//! [`Vault::http_api_token`]. Responses are JSON; failures are
+//! `{"error": "..."}` with a matching status code.
+
+use crate::error::Error;
+use crate::models::preservation::{ArchiveRequest, ArchivedProject, BagInfoField, BagResult, SearchHit};
+use crate::vault::{BagOptions, ExportFormat, Vault};
+use axum::{
+    body::Body,
+    extract::{rejection::JsonRejection, Path, Query, Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use camino::{Utf8Path, Utf8PathBuf};
+use serde::Deserialize;
+use std::future::Future;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tempfile::TempDir;
+use tokio::net::TcpListener;
+use tokio_util::io::ReaderStream;
+
+/// Port the API listens on unless another is asked for
+pub const DEFAULT_HTTP_PORT: u16 = 7438;
+
+/// A bound, not yet running, API server
+pub struct HttpServer {
+    listener: TcpListener,
+    router: Router,
+    token: String,
+}
+
+impl HttpServer {
+    /// Bind the API on 127.0.0.1; port 0 picks a free port
+    pub async fn bind(vault: Vault, port: u16) -> crate::Result<Self> {
+        let token = vault.http_api_token().await?;
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port)).await?;
+        Ok(Self {
+            listener,
+            router: router(vault, token.clone()),
+            token,
+        })
+    }
+
+    pub fn local_addr(&self) -> crate::Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Base URL of the API, e.g. `http://127.0.0.1:7438`
+    pub fn url(&self) -> crate::Result<String> {
+        Ok(format!("http://{}", self.local_addr()?))
+    }
+
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    /// Serve requests until `shutdown` completes
+    pub async fn run(self, shutdown: impl Future<Output = ()> + Send + 'static) -> crate::Result<()> {
+        axum::serve(self.listener, self.router)
+            .with_graceful_shutdown(shutdown)
+            .await?;
+        Ok(())
+    }
+}
+
+/// The API routes, all behind the bearer token check
+pub fn router(vault: Vault, token: String) -> Router {
+    Router::new()
+        .route("/api/projects", get(list_projects).post(archive_project))
+        .route("/api/projects/:id", get(get_project))
+        .route("/api/projects/:id/bag", get(download_bag).post(create_bag))
+        .route("/api/projects/:id/validate", post(validate_project))
+        .route("/api/search", get(search))
+        .layer(middleware::from_fn_with_state(token, require_token))
+        .with_state(vault)
+}
+
+/// An error response: `{"error": message}` with a status code
+struct ApiError(StatusCode, String);
+
+impl From<Error> for ApiError {
+    fn from(error: Error) -> Self {
+        let status = match &error {
+            Error::ProjectNotFound(_) | Error::NotFound(_) => StatusCode::NOT_FOUND,
+            Error::InvalidState(_) => StatusCode::CONFLICT,
+            Error::WrongPassphrase => StatusCode::FORBIDDEN,
+            Error::Io(_) | Error::Failed(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        ApiError(status, format!("{:#}", error))
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        ApiError(rejection.status(), rejection.body_text())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(serde_json::json!({ "error": self.1 }))).into_response()
+    }
+}
+
+type ApiResult<T> = std::result::Result<T, ApiError>;
+
+async fn require_token(State(token): State<String>, request: Request, next: Next) -> Response {
+    let presented = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match presented {
+        Some(presented) if constant_time_eq(presented.as_bytes(), token.as_bytes()) => next.run(request).await,
+        _ => ApiError(StatusCode::UNAUTHORIZED, "Missing or invalid bearer token".to_string()).into_response(),
+    }
+}
+
+/// Compare without returning early, so response timing does not leak the token
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+async fn list_projects(State(vault): State<Vault>) -> ApiResult<Json<Vec<ArchivedProject>>> {
+    Ok(Json(vault.list_projects().await?))
+}
+
+async fn archive_project(
+    State(vault): State<Vault>,
+    request: std::result::Result<Json<ArchiveRequest>, JsonRejection>,
+) -> ApiResult<(StatusCode, Json<ArchivedProject>)> {
+    let Json(request) = request?;
+    Ok((StatusCode::CREATED, Json(vault.archive_project(&request).await?)))
+}
+
+async fn get_project(State(vault): State<Vault>, Path(id): Path<String>) -> ApiResult<Json<ArchivedProject>> {
+    Ok(Json(vault.get_project(&id).await?))
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct BagRequest {
+    #[serde(default)]
+    bag_info_fields: Vec<BagInfoField>,
+    profile_path: Option<String>,
+}
+
+async fn create_bag(
+    State(vault): State<Vault>,
+    Path(id): Path<String>,
+    request: Option<Json<BagRequest>>,
+) -> ApiResult<Json<BagResult>> {
+    let request = request.map(|Json(r)| r).unwrap_or_default();
+    let options = BagOptions {
+        bag_info_fields: request.bag_info_fields,
+        profile_path: request.profile_path.map(Utf8PathBuf::from),
+    };
+    Ok(Json(vault.create_bag(&id, options).await?))
+}
+
+async fn validate_project(State(vault): State<Vault>, Path(id): Path<String>) -> ApiResult<Json<BagResult>> {
+    Ok(Json(vault.validate_project(&id).await?))
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchQuery {
+    q: String,
+    limit: Option<u32>,
+}
+
+async fn search(State(vault): State<Vault>, Query(query): Query<SearchQuery>) -> ApiResult<Json<Vec<SearchHit>>> {
+    Ok(Json(vault.search(&query.q, query.limit).await?))
+}
+
+#[derive(Debug, Deserialize)]
+struct DownloadQuery {
+    format: Option<String>,
+}
+
+/// Serialize a project's bag into a temporary directory and stream it back
+async fn download_bag(
+    State(vault): State<Vault>,
+    Path(id): Path<String>,
+    Query(query): Query<DownloadQuery>,
+) -> ApiResult<Response> {
+    let format_name = query.format.as_deref().unwrap_or("zip");
+    let serialization = ExportFormat::parse(format_name)
+        .and_then(|format| format.serialization().map(|s| (format, s)));
+    let Some((format, serialization)) = serialization else {
+        return Err(ApiError(
+            StatusCode::BAD_REQUEST,
+            format!("Unsupported bag format '{}', expected tar, tar-gz or zip", format_name),
+        ));
+    };
+
+    let temp_dir = TempDir::new().map_err(Error::from)?;
+    let output = Utf8Path::from_path(temp_dir.path())
+        .ok_or_else(|| Error::Failed(anyhow::anyhow!("Non-UTF8 temporary directory")))?
+        .to_path_buf();
+    let exported = vault.export_project(&id, format, &output).await?;
+    let archive_path = Utf8PathBuf::from(exported.output_path.unwrap_or_default());
+    let file_name = archive_path.file_name().unwrap_or("bag").to_string();
+    let file = tokio::fs::File::open(&archive_path).await.map_err(Error::from)?;
+
+    let body = Body::from_stream(TempFileStream {
+        inner: ReaderStream::new(file),
+        _temp_dir: temp_dir,
+    });
+    Ok((
+        [
+            (header::CONTENT_TYPE, serialization.media_type().to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", file_name)),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// A file stream that removes its temporary directory once the body is dropped
+struct TempFileStream {
+    inner: ReaderStream<tokio::fs::File>,
+    _temp_dir: TempDir,
+}
+
+impl futures_core::Stream for TempFileStream {
+    type Item = std::io::Result<axum::body::Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.inner).poll_next(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VaultConfig;
+    use axum::http::Request;
+    use std::fs;
+    use tower::ServiceExt;
+
+    const TOKEN: &str = "test-token";
+
+    async fn send(app: &Router, method: &str, uri: &str, body: Option<serde_json::Value>) -> (StatusCode, Vec<u8>) {
+        let mut request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::AUTHORIZATION, format!("Bearer {}", TOKEN));
+        let body = match body {
+            Some(json) => {
+                request = request.header(header::CONTENT_TYPE, "application/json");
+                Body::from(json.to_string())
+            }
+            None => Body::empty(),
+        };
+        let response = app.clone().oneshot(request.body(body).unwrap()).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, bytes.to_vec())
+    }
+
+    #[tokio::test]
+    async fn test_api_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let vault = Vault::open(VaultConfig {
+            database_path: root.join("preservation.db"),
+            bags_root: root.join("bags"),
+            quarantine_root: root.join("quarantine"),
+            derivatives_root: root.join("derivatives"),
+        })
+        .await
+        .unwrap();
+        let app = router(vault, TOKEN.to_string());
+        fs::write(root.join("syllabus.txt"), b"week one: gesture drawing").unwrap();
+
+        let unauthorized = app
+            .clone()
+            .oneshot(Request::get("/api/projects").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(unauthorized.status(), StatusCode::UNAUTHORIZED);
+
+        let request = serde_json::json!({
+            "name": "Drawing I",
+            "description": null,
+            "files": [root.join("syllabus.txt").as_str()],
+        });
+        let (status, body) = send(&app, "POST", "/api/projects", Some(request)).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let project: ArchivedProject = serde_json::from_slice(&body).unwrap();
+
+        let (status, _) = send(&app, "POST", &format!("/api/projects/{}/bag", project.id), None).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = send(&app, "POST", &format!("/api/projects/{}/bag", project.id), None).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        let (status, body) = send(&app, "POST", &format!("/api/projects/{}/validate", project.id), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(serde_json::from_slice::<BagResult>(&body).unwrap().success);
+
+        let (_, body) = send(&app, "GET", "/api/search?q=gesture", None).await;
+        let hits: Vec<SearchHit> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(hits[0].project_id, project.id);
+
+        let (status, body) = send(&app, "GET", &format!("/api/projects/{}/bag?format=zip", project.id), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.starts_with(b"PK"));
+
+        let (status, _) = send(&app, "GET", &format!("/api/projects/{}/bag?format=premis", project.id), None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, body) = send(&app, "GET", "/api/projects/missing", None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(String::from_utf8(body).unwrap().contains("\"error\""));
+    }
+}
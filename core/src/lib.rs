@@ -0,0 +1,16 @@
+//! Preservation core of the Creative Work Preservation Toolkit
+//!
+//! Bag handling, checksums, the vault and its persistence, with no GUI
+//! dependencies. The desktop app and the `cwpt` command line are thin
+//! adapters over [`Vault`].
+
+pub mod error;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod models;
+pub mod store;
+pub mod utils;
+pub mod vault;
+
+pub use error::{Error, Result};
+pub use vault::{BagOptions, ExportFormat, Vault, VaultConfig};
@@ -0,0 +1,603 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ArchivedProject {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub archived_at: DateTime<Utc>,
+    pub bagit_package_id: Option<String>,
+    pub file_count: i32,
+    pub total_size: i64,
+    pub is_quarantined: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BagitPackage {
+    pub id: String,
+    pub archived_project_id: String,
+    pub bag_path: String,
+    pub manifest_sha256: String,
+    pub bag_size: i64,
+    pub payload_file_count: i32,
+    pub is_valid: bool,
+    pub validated_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QuarantineEntry {
+    pub id: String,
+    pub archived_project_id: String,
+    pub quarantined_at: DateTime<Utc>,
+    pub original_bag_path: String,
+    pub scheduled_for_deletion_at: Option<DateTime<Utc>>,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VaultSnapshot {
+    pub id: String,
+    pub snapshot_at: DateTime<Utc>,
+    pub user_layer_checksum: String,
+    pub bags_layer_checksum: String,
+    pub anomalies_detected: Option<Vec<String>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An entry in the audit trail of a project or the vault
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PreservationEvent {
+    pub id: String,
+    pub event_type: String,
+    pub aggregate_id: String,
+    pub payload: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FileMetadata {
+    pub path: String,
+    pub name: String,
+    pub size: u64,
+    pub mime_type: Option<String>,
+    pub checksum_sha256: Option<String>,
+    pub checksum_md5: Option<String>,
+    #[serde(default)]
+    pub technical_metadata: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProjectRelation {
+    pub id: String,
+    pub parent_project_id: String,
+    pub child_project_id: String,
+    pub relation_type: String, // 'version', 'branch'
+    pub label: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LineageNode {
+    pub project_id: String,
+    pub name: String,
+    pub relation_type: Option<String>,
+    pub label: Option<String>,
+    pub children: Vec<LineageNode>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct VersionDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+    pub unchanged_count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Collection {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub parent_collection_id: Option<String>,
+    pub position: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CollectionMember {
+    pub collection_id: String,
+    pub archived_project_id: String,
+    pub position: i32,
+    pub added_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CollectionTree {
+    pub collection: Collection,
+    pub project_ids: Vec<String>,
+    pub children: Vec<CollectionTree>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Vocabulary {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub is_controlled: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Tag {
+    pub id: String,
+    pub name: String,
+    pub vocabulary_id: Option<String>,
+    pub usage_count: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TagAssignment {
+    pub tag_id: String,
+    pub archived_project_id: String,
+    pub file_path: Option<String>, // None when the tag applies to the whole project
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct EnvironmentComponent {
+    pub component_type: String, // software, plugin, font or library
+    pub name: String,
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EnvironmentProfile {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub os_name: Option<String>,
+    pub os_version: Option<String>,
+    pub components: Vec<EnvironmentComponent>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProjectEnvironment {
+    pub archived_project_id: String,
+    pub profile_id: String,
+    pub attached_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FileTechnicalMetadata {
+    pub path: String,                        // Payload path inside the bag
+    pub properties: BTreeMap<String, String>, // Normalized keys such as exif.model or audio.duration_seconds
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProjectPreview {
+    pub path: String,           // Payload path of the original
+    pub checksum: String,       // Payload checksum the derivatives are keyed by
+    pub thumbnail_path: String,
+    pub preview_path: String,
+    pub width: u32,             // Dimensions of the preview
+    pub height: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ProjectPreviews {
+    pub previews: Vec<ProjectPreview>,
+    pub skipped: Vec<SkippedPreview>, // Previewable files that could not be decoded
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SkippedPreview {
+    pub path: String,
+    pub error: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FileDependency {
+    pub file_path: String,           // Payload file that needs the dependency
+    pub dependency_type: String,     // font or linked_file
+    pub reference: String,           // Font name or path as written in the file
+    pub embedded: bool,              // Font carried inside the file itself
+    pub included_as: Option<String>, // Payload file that satisfies the reference
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DependencyReport {
+    pub project_id: String,
+    pub dependencies: Vec<FileDependency>,
+    pub missing_count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SearchHit {
+    pub project_id: String,
+    pub project_name: String,
+    pub file_path: Option<String>, // None when the project record itself matched
+    pub snippet: String,
+    pub score: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProjectFile {
+    pub archived_project_id: String,
+    pub path: String,
+    pub size: i64,
+    pub media_type: String,
+    pub risk_level: String, // 'low', 'medium', 'high'
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectSort {
+    #[default]
+    NewestFirst,
+    OldestFirst,
+    NameAsc,
+    NameDesc,
+    LargestFirst,
+    SmallestFirst,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ProjectQuery {
+    pub formats: Vec<String>, // Media types; a project matches if any payload file has one
+    pub archived_after: Option<DateTime<Utc>>,
+    pub archived_before: Option<DateTime<Utc>>,
+    pub min_size: Option<i64>,
+    pub max_size: Option<i64>,
+    pub quarantined: Option<bool>,
+    pub collection_id: Option<String>, // Includes sub-collections
+    pub tag_ids: Vec<String>,          // A project must carry every tag
+    pub risk_levels: Vec<String>,
+    pub sort: ProjectSort,
+    pub page: u32, // 1-based
+    pub page_size: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FacetCount {
+    pub value: String,
+    pub label: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ProjectFacets {
+    pub formats: Vec<FacetCount>,
+    pub archived_years: Vec<FacetCount>,
+    pub size_ranges: Vec<FacetCount>,
+    pub quarantined: Vec<FacetCount>,
+    pub collections: Vec<FacetCount>,
+    pub tags: Vec<FacetCount>,
+    pub risk_levels: Vec<FacetCount>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProjectPage {
+    pub projects: Vec<ArchivedProject>,
+    pub total_count: i64,
+    pub page: u32,
+    pub page_size: u32,
+    pub facets: ProjectFacets,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WatchFolder {
+    pub id: String,
+    pub path: String,
+    pub settle_seconds: i64,
+    pub create_bag: bool,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WatchIngestion {
+    pub watch_folder_id: String,
+    pub entry_path: String,
+    pub project_id: Option<String>,
+    pub parent_project_id: Option<String>, // Set when the entry was ingested before and this is a new version
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReplicationTarget {
+    pub id: String,
+    pub name: String,
+    pub path: String, // A folder for local targets, otherwise the backend location, e.g. s3://bucket/prefix
+    pub kind: String, // 'local', 's3', 'webdav'
+    #[serde(skip)]
+    pub settings: Option<String>, // Backend connection details as JSON; holds credentials, so never serialized
+    pub encrypted: bool,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReplicaStatus {
+    pub archived_project_id: String,
+    pub target_id: String,
+    pub replica_path: String,
+    pub status: String, // 'pending', 'healthy', 'corrupted', 'missing', 'unavailable'
+    pub last_copied_at: Option<DateTime<Utc>>,
+    pub last_verified_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReplicaObject {
+    pub archived_project_id: String,
+    pub target_id: String,
+    pub path: String, // Relative to the bag root
+    pub size: i64,
+    pub sha256: String,
+    pub etag: Option<String>, // As the target reported it right after the upload
+    pub key_id: Option<String>, // Data key the file was encrypted with, on encrypted targets
+    pub stored_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EncryptionKey {
+    pub id: String,
+    #[serde(skip)]
+    pub sealed_key: String, // Sealed under the master key; useless without the passphrase, but never serialized
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EncryptionStatus {
+    pub is_set_up: bool,
+    pub is_unlocked: bool,
+    pub has_recovery_key: bool,
+    pub current_key_id: Option<String>,
+    pub key_count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DatabaseStatus {
+    pub is_encrypted: bool,
+    pub is_locked: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BagRepair {
+    pub archived_project_id: String,
+    pub repaired_path: String,
+    pub source_path: Option<String>, // The healthy copy files were taken from
+    pub repaired_files: Vec<String>,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SourceChanges {
+    pub project_id: String,
+    pub source_root: String,
+    pub added: Vec<String>,   // Paths relative to the source root
+    pub changed: Vec<String>,
+    pub removed: Vec<String>,
+    pub unchanged_count: usize,
+    pub has_changes: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeltaVersionResult {
+    pub project_id: String,
+    pub parent_project_id: String,
+    pub changes: SourceChanges,
+    pub bag: BagResult,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub files: Vec<String>,
+    #[serde(default)]
+    pub environment_profile_ids: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveResult {
+    pub success: bool,
+    pub project_id: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BagInfoField {
+    pub label: String,
+    pub value: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BagResult {
+    pub success: bool,
+    pub bag_path: Option<String>,
+    pub validation_results: Option<Vec<ValidationResult>>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportResult {
+    pub success: bool,
+    pub output_path: Option<String>,
+    pub record_count: usize,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ValidationResult {
+    pub result_type: String, // 'error', 'warning', 'info'
+    pub message: String,
+    pub file: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IntegrityReport {
+    pub is_healthy: bool,
+    pub issues: Vec<IntegrityIssue>,
+    pub last_scan_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IntegrityIssue {
+    pub issue_type: String, // 'missing_file', 'corrupted_file', 'external_modification', 'replica_*'
+    pub severity: String,   // 'critical', 'warning', 'info'
+    pub message: String,
+    pub affected_files: Vec<String>,
+}
+
+impl ArchivedProject {
+    pub fn new(name: String, description: Option<String>, file_count: i32, total_size: i64) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4().to_string(),
+            name,
+            description,
+            archived_at: now,
+            bagit_package_id: None,
+            file_count,
+            total_size,
+            is_quarantined: false,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+impl ProjectRelation {
+    pub fn new(parent_project_id: String, child_project_id: String, relation_type: String, label: Option<String>) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            parent_project_id,
+            child_project_id,
+            relation_type,
+            label,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+impl WatchFolder {
+    pub fn new(path: String, settle_seconds: i64, create_bag: bool) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            path,
+            settle_seconds,
+            create_bag,
+            enabled: true,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+impl ReplicationTarget {
+    pub fn new(name: String, path: String) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            name,
+            path,
+            kind: "local".to_string(),
+            settings: None,
+            encrypted: false,
+            enabled: true,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+impl Collection {
+    pub fn new(name: String, description: Option<String>, parent_collection_id: Option<String>, position: i32) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4().to_string(),
+            name,
+            description,
+            parent_collection_id,
+            position,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+impl Vocabulary {
+    pub fn new(name: String, description: Option<String>, is_controlled: bool) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            name,
+            description,
+            is_controlled,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+impl Tag {
+    pub fn new(name: String, vocabulary_id: Option<String>) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            name,
+            vocabulary_id,
+            usage_count: 0,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+impl EnvironmentProfile {
+    pub fn new(
+        name: String,
+        description: Option<String>,
+        os_name: Option<String>,
+        os_version: Option<String>,
+        components: Vec<EnvironmentComponent>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            name,
+            description,
+            os_name,
+            os_version,
+            components,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+impl FileDependency {
+    /// True when neither the file nor the archive provides the dependency
+    pub fn is_missing(&self) -> bool {
+        !self.embedded && self.included_as.is_none()
+    }
+}
+
+impl BagitPackage {
+    pub fn new(archived_project_id: String, bag_path: String, manifest_sha256: String, bag_size: i64, payload_file_count: i32) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            archived_project_id,
+            bag_path,
+            manifest_sha256,
+            bag_size,
+            payload_file_count,
+            is_valid: true,
+            validated_at: Some(Utc::now()),
+            created_at: Utc::now(),
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,1746 @@
+use anyhow::{Context, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use chrono::{DateTime, Utc};
+use crate::models::preservation::{
+    ArchivedProject, Collection, CollectionMember, EncryptionKey, EnvironmentComponent, EnvironmentProfile,
+    FacetCount, FileTechnicalMetadata, PreservationEvent, ProjectEnvironment, ProjectFacets, ProjectFile,
+    ProjectPage, ProjectQuery, ProjectRelation, QuarantineEntry, ReplicaObject, ReplicaStatus, ReplicationTarget,
+    SearchHit, Tag, TagAssignment, VaultSnapshot, Vocabulary, WatchFolder,
+};
+use crate::utils::format_risk::RiskLevel;
+use crate::utils::project_query::{self, Facet};
+use crate::utils::search::SearchDocument;
+use crate::utils::source_changes::SourceFileState;
+use rusqlite::{params, params_from_iter, types::Value as SqlValue, Connection, OptionalExtension, Row};
+use sha2::{Digest, Sha384};
+use std::io::Read;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// Identifier the desktop app stores its data under
+const APP_IDENTIFIER: &str = "com.cwpt.preservation-toolkit";
+
+/// How every unencrypted SQLite database starts; SQLCipher files start with random salt instead
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+/// Schema migrations as (version, description, SQL), shared with the app's `get_migrations()`
+pub const MIGRATIONS: &[(i64, &str, &str)] = &[
+    (1, "create_initial_schema", include_str!("../migrations/001_initial_schema.sql")),
+    (2, "create_project_lineage", include_str!("../migrations/002_project_lineage.sql")),
+    (3, "create_collections", include_str!("../migrations/003_collections.sql")),
+    (4, "create_tags_and_vocabularies", include_str!("../migrations/004_tags.sql")),
+    (5, "create_search_index", include_str!("../migrations/005_search_index.sql")),
+    (6, "create_project_files", include_str!("../migrations/006_project_files.sql")),
+    (7, "create_environment_profiles", include_str!("../migrations/007_environment_profiles.sql")),
+    (8, "create_file_technical_metadata", include_str!("../migrations/008_technical_metadata.sql")),
+    (9, "create_watch_folders", include_str!("../migrations/009_watch_folders.sql")),
+    (10, "create_source_tracking", include_str!("../migrations/010_source_tracking.sql")),
+    (11, "create_replication", include_str!("../migrations/011_replication.sql")),
+    (12, "add_replication_target_backends", include_str!("../migrations/012_storage_backends.sql")),
+    (13, "create_replica_objects", include_str!("../migrations/013_replica_objects.sql")),
+    (14, "add_encryption", include_str!("../migrations/014_encryption.sql")),
+    // Future migrations will be added here
+];
+
+/// The database the desktop app uses, so both see the same vault
+pub fn default_database_path() -> Result<Utf8PathBuf> {
+    let config_dir = dirs::config_dir().ok_or_else(|| anyhow::anyhow!("No configuration directory for this user"))?;
+    Utf8PathBuf::from_path_buf(config_dir.join(APP_IDENTIFIER).join("preservation.db"))
+        .map_err(|p| anyhow::anyhow!("Non-UTF8 path encountered: {}", p.display()))
+}
+
+/// Where the desktop app keeps bags, quarantine and derivatives
+pub fn default_data_dir() -> Result<Utf8PathBuf> {
+    let data_dir = dirs::data_dir().ok_or_else(|| anyhow::anyhow!("No data directory for this user"))?;
+    Utf8PathBuf::from_path_buf(data_dir.join(APP_IDENTIFIER))
+        .map_err(|p| anyhow::anyhow!("Non-UTF8 path encountered: {}", p.display()))
+}
+
+/// Whether a database file is encrypted; a missing or empty file is not
+pub fn database_is_encrypted(path: &Utf8Path) -> Result<bool> {
+    let mut header = [0u8; 16];
+    let read = match std::fs::File::open(path) {
+        Ok(mut file) => file.read_exact(&mut header),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e.into()),
+    };
+    match read {
+        Ok(()) => Ok(&header != SQLITE_HEADER),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// The preservation database, opened directly with SQLite
+pub struct Store {
+    conn: Mutex<Connection>,
+    path: Utf8PathBuf,
+    /// Set while an encrypted database waits for its passphrase
+    locked: AtomicBool,
+}
+
+impl Store {
+    /// Open the database, creating it and applying any pending migrations
+    ///
+    /// An encrypted database opens locked: nothing can be read from it, and
+    /// migrations wait, until [`Store::unlock`] is given the passphrase.
+    pub fn open(path: &Utf8Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path).with_context(|| format!("Failed to open database {}", path))?;
+        let locked = database_is_encrypted(path)?;
+        let store = Self {
+            conn: Mutex::new(conn),
+            path: path.to_path_buf(),
+            locked: AtomicBool::new(locked),
+        };
+        if !locked {
+            store.migrate()?;
+        }
+        Ok(store)
+    }
+
+    /// Whether the database is encrypted and not unlocked yet
+    pub fn is_locked(&self) -> bool {
+        self.locked.load(Ordering::SeqCst)
+    }
+
+    /// Whether the database file is encrypted
+    pub fn is_encrypted(&self) -> Result<bool> {
+        database_is_encrypted(&self.path)
+    }
+
+    /// Unlock an encrypted database and apply pending migrations
+    ///
+    /// Returns false, leaving the database locked, if the passphrase is wrong.
+    #[cfg(feature = "sqlcipher")]
+    pub fn unlock(&self, passphrase: &str) -> Result<bool> {
+        let Some(conn) = open_keyed(&self.path, passphrase)? else {
+            return Ok(false);
+        };
+        *self.conn() = conn;
+        self.locked.store(false, Ordering::SeqCst);
+        self.migrate()?;
+        Ok(true)
+    }
+
+    /// Encrypt an unencrypted database in place under a passphrase
+    ///
+    /// The encrypted copy is written next to the database and renamed over it,
+    /// so an interruption leaves the unencrypted database as it was.
+    #[cfg(feature = "sqlcipher")]
+    pub fn encrypt(&self, passphrase: &str) -> Result<()> {
+        if self.is_encrypted()? {
+            return Err(anyhow::anyhow!("The database is already encrypted"));
+        }
+        let encrypted_path = Utf8PathBuf::from(format!("{}.encrypting", self.path));
+        remove_if_exists(&encrypted_path)?;
+
+        let mut conn = self.conn();
+        export_attached(&conn, &encrypted_path, passphrase)?;
+        // Close the unencrypted connection before the file under it is replaced
+        *conn = Connection::open_in_memory()?;
+        std::fs::rename(&encrypted_path, &self.path)?;
+        *conn = open_keyed(&self.path, passphrase)?
+            .ok_or_else(|| anyhow::anyhow!("The encrypted database did not open with its passphrase"))?;
+        Ok(())
+    }
+
+    /// Re-encrypt the database under a new passphrase
+    ///
+    /// Returns false, changing nothing, if the current passphrase is wrong.
+    /// A locked database must be unlocked first, since a rekey through a
+    /// connection without the key would leave the file unreadable.
+    #[cfg(feature = "sqlcipher")]
+    pub fn change_passphrase(&self, current: &str, new: &str) -> Result<bool> {
+        if self.is_locked() {
+            return Err(anyhow::anyhow!("The database is locked"));
+        }
+        if !self.is_encrypted()? {
+            return Err(anyhow::anyhow!("The database is not encrypted"));
+        }
+        if open_keyed(&self.path, current)?.is_none() {
+            return Ok(false);
+        }
+        self.conn().pragma_update(None, "rekey", new)?;
+        Ok(true)
+    }
+
+    /// Write an unencrypted copy of the database, e.g. to move it to a build without encryption
+    #[cfg(feature = "sqlcipher")]
+    pub fn export_unencrypted(&self, output: &Utf8Path) -> Result<()> {
+        if output.exists() {
+            return Err(anyhow::anyhow!("{} already exists", output));
+        }
+        export_attached(&self.conn(), output, "")
+    }
+
+    fn conn(&self) -> MutexGuard<'_, Connection> {
+        // A panic mid-statement leaves nothing half-applied that SQLite has not already rolled back
+        self.conn.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Apply pending migrations, recording them the way the app's migrator does
+    ///
+    /// The app tracks migrations in sqlx's `_sqlx_migrations` table and checks
+    /// each one's SHA-384, so a database migrated here opens cleanly in the app
+    /// and vice versa.
+    fn migrate(&self) -> Result<()> {
+        let conn = self.conn();
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS _sqlx_migrations (
+                version BIGINT PRIMARY KEY,
+                description TEXT NOT NULL,
+                installed_on TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                success BOOLEAN NOT NULL,
+                checksum BLOB NOT NULL,
+                execution_time BIGINT NOT NULL
+            );",
+        )?;
+
+        for (version, description, sql) in MIGRATIONS {
+            let applied: Option<Vec<u8>> = conn
+                .query_row(
+                    "SELECT checksum FROM _sqlx_migrations WHERE version = ? AND success = TRUE",
+                    [version],
+                    |row| row.get(0),
+                )
+                .optional()?;
+
+            let checksum = Sha384::digest(sql.as_bytes()).to_vec();
+            match applied {
+                Some(existing) if existing == checksum => continue,
+                Some(_) => return Err(anyhow::anyhow!("Migration {} was changed after it was applied", version)),
+                None => {}
+            }
+
+            let started = std::time::Instant::now();
+            let tx = conn.unchecked_transaction()?;
+            tx.execute_batch(sql)
+                .with_context(|| format!("Migration {} ({}) failed", version, description))?;
+            tx.execute(
+                "INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time)
+                 VALUES (?, ?, TRUE, ?, ?)",
+                params![version, description, checksum, started.elapsed().as_nanos() as i64],
+            )?;
+            tx.commit()?;
+        }
+
+        Ok(())
+    }
+
+    /// Insert a new archived project
+    pub fn insert_archived_project(&self, project: &ArchivedProject) -> Result<()> {
+        insert_project_row(&self.conn(), project)
+    }
+
+    /// Record a newly archived project in one transaction: its row, its
+    /// ProjectArchived event, where its sources live, the environment
+    /// profiles attached to it and, for a new version, its lineage link
+    pub fn record_archived_project(
+        &self,
+        project: &ArchivedProject,
+        archived_payload: &str,
+        source_root: &str,
+        environments: &[ProjectEnvironment],
+        relation: Option<&ProjectRelation>,
+    ) -> Result<()> {
+        let conn = self.conn();
+        let tx = conn.unchecked_transaction()?;
+        insert_project_row(&tx, project)?;
+        insert_event_row(&tx, "ProjectArchived", &project.id, archived_payload)?;
+        set_source_row(&tx, &project.id, source_root)?;
+        for attachment in environments {
+            insert_environment_row(&tx, attachment)?;
+            let payload = serde_json::json!({ "profile_id": attachment.profile_id });
+            insert_event_row(&tx, "EnvironmentProfileAttached", &project.id, &payload.to_string())?;
+        }
+        if let Some(relation) = relation {
+            insert_relation_row(&tx, relation)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Record a project that arrived with its bag already made, with the event
+    /// that brought it in, in one transaction
+    pub fn record_imported_project(&self, project: &ArchivedProject, event_type: &str, payload: &str) -> Result<()> {
+        let conn = self.conn();
+        let tx = conn.unchecked_transaction()?;
+        insert_project_row(&tx, project)?;
+        insert_event_row(&tx, event_type, &project.id, payload)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Remove a project that never made it into the vault, with everything recorded about it
+    ///
+    /// Only for undoing an ingest that failed part way; projects that were
+    /// archived successfully are quarantined, never deleted.
+    pub fn delete_archived_project(&self, project_id: &str) -> Result<()> {
+        let conn = self.conn();
+        let tx = conn.unchecked_transaction()?;
+        for table in [
+            "search_index",
+            "project_files",
+            "file_technical_metadata",
+            "source_file_states",
+            "project_sources",
+            "tag_assignments",
+            "collection_members",
+            "project_environments",
+            "watch_folder_entries",
+            "replica_objects",
+            "replicas",
+            "quarantine_entries",
+            "bagit_packages",
+        ] {
+            tx.execute(&format!("DELETE FROM {} WHERE archived_project_id = ?", table), [project_id])?;
+        }
+        tx.execute(
+            "DELETE FROM project_relations WHERE parent_project_id = ?1 OR child_project_id = ?1",
+            [project_id],
+        )?;
+        tx.execute("DELETE FROM events WHERE aggregate_id = ?", [project_id])?;
+        tx.execute("DELETE FROM archived_projects WHERE id = ?", [project_id])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Get all archived projects, newest first
+    pub fn get_all_archived_projects(&self) -> Result<Vec<ArchivedProject>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(
+            "SELECT id, name, description, archived_at, bagit_package_id, file_count, total_size,
+                    is_quarantined, created_at, updated_at
+             FROM archived_projects ORDER BY created_at DESC",
+        )?;
+        let rows = statement.query_map([], |row| Ok(project_from_row(row)))?;
+
+        let mut projects = Vec::new();
+        for row in rows {
+            projects.push(row??);
+        }
+        Ok(projects)
+    }
+
+    /// Get one page of archived projects matching a query, with facet counts
+    pub fn query_archived_projects(&self, query: &ProjectQuery) -> Result<ProjectPage> {
+        let (page, page_size, _) = project_query::page_bounds(query);
+        let conn = self.conn();
+
+        let (sql, params) = project_query::page_sql(query);
+        let mut statement = conn.prepare(&sql)?;
+        let rows = statement.query_map(params_from_iter(sql_values(&params)), |row| Ok(project_from_row(row)))?;
+        let mut projects = Vec::new();
+        for row in rows {
+            projects.push(row??);
+        }
+
+        let (sql, params) = project_query::count_sql(query);
+        let total_count = conn.query_row(&sql, params_from_iter(sql_values(&params)), |row| row.get(0))?;
+
+        let facet = |facet: Facet| -> Result<Vec<FacetCount>> {
+            let (sql, params) = project_query::facet_sql(query, facet);
+            let mut statement = conn.prepare(&sql)?;
+            let rows = statement.query_map(params_from_iter(sql_values(&params)), |row| {
+                Ok(FacetCount {
+                    value: row.get("value")?,
+                    label: row.get("label")?,
+                    count: row.get("count")?,
+                })
+            })?;
+            Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+        };
+        let facets = ProjectFacets {
+            formats: facet(Facet::Format)?,
+            archived_years: facet(Facet::ArchivedYear)?,
+            size_ranges: facet(Facet::SizeRange)?,
+            quarantined: facet(Facet::Quarantined)?,
+            collections: facet(Facet::Collection)?,
+            tags: facet(Facet::Tag)?,
+            risk_levels: facet(Facet::RiskLevel)?,
+        };
+
+        Ok(ProjectPage {
+            projects,
+            total_count,
+            page,
+            page_size,
+            facets,
+        })
+    }
+
+    /// Get one archived project by ID
+    pub fn get_archived_project(&self, project_id: &str) -> Result<Option<ArchivedProject>> {
+        self.conn()
+            .query_row(
+                "SELECT id, name, description, archived_at, bagit_package_id, file_count, total_size,
+                        is_quarantined, created_at, updated_at
+                 FROM archived_projects WHERE id = ?",
+                [project_id],
+                |row| Ok(project_from_row(row)),
+            )
+            .optional()?
+            .transpose()
+    }
+
+    /// Update a project's quarantine status
+    pub fn update_quarantine_status(&self, project_id: &str, is_quarantined: bool) -> Result<()> {
+        self.conn().execute(
+            "UPDATE archived_projects SET is_quarantined = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+            params![is_quarantined, project_id],
+        )?;
+        Ok(())
+    }
+
+    /// Insert an event record for audit trail
+    pub fn insert_event(&self, event_type: &str, aggregate_id: &str, payload: &str) -> Result<()> {
+        insert_event_row(&self.conn(), event_type, aggregate_id, payload)
+    }
+
+    /// Get the events recorded for a project or the vault, oldest first
+    pub fn get_events(&self, aggregate_id: &str) -> Result<Vec<PreservationEvent>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(
+            "SELECT id, event_type, aggregate_id, payload, created_at
+             FROM events WHERE aggregate_id = ? ORDER BY created_at, rowid",
+        )?;
+        let rows = statement.query_map([aggregate_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+            ))
+        })?;
+
+        let mut events = Vec::new();
+        for row in rows {
+            let (id, event_type, aggregate_id, payload, created_at) = row?;
+            events.push(PreservationEvent {
+                payload: serde_json::from_str(&payload).with_context(|| format!("Invalid payload for event {}", id))?,
+                created_at: parse_timestamp(&created_at)?,
+                id,
+                event_type,
+                aggregate_id,
+            });
+        }
+        Ok(events)
+    }
+
+    /// The source paths a project was archived from, as recorded in its ProjectArchived event
+    pub fn archived_source_paths(&self, project_id: &str) -> Result<Vec<String>> {
+        let payload: Option<String> = self
+            .conn()
+            .query_row(
+                "SELECT payload FROM events WHERE event_type = 'ProjectArchived' AND aggregate_id = ?
+                 ORDER BY created_at DESC LIMIT 1",
+                [project_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        let Some(payload) = payload else {
+            return Ok(Vec::new());
+        };
+        let payload: serde_json::Value = serde_json::from_str(&payload)?;
+        Ok(payload["files"]
+            .as_array()
+            .map(|files| files.iter().filter_map(|f| f.as_str().map(str::to_string)).collect())
+            .unwrap_or_default())
+    }
+
+    /// Replace the catalog of a project's payload files
+    pub fn replace_project_files(&self, project_id: &str, files: &[ProjectFile]) -> Result<()> {
+        let conn = self.conn();
+        let tx = conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM project_files WHERE archived_project_id = ?", [project_id])?;
+        for file in files {
+            let risk_rank = RiskLevel::parse(&file.risk_level)
+                .map(|level| level.rank())
+                .unwrap_or(RiskLevel::High.rank());
+            tx.execute(
+                "INSERT INTO project_files (archived_project_id, path, size, media_type, risk_level, risk_rank)
+                 VALUES (?, ?, ?, ?, ?, ?)",
+                params![file.archived_project_id, file.path, file.size, file.media_type, file.risk_level, risk_rank],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Get the catalogued payload files of a project
+    pub fn get_project_files(&self, project_id: &str) -> Result<Vec<ProjectFile>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(
+            "SELECT archived_project_id, path, size, media_type, risk_level
+             FROM project_files WHERE archived_project_id = ? ORDER BY path",
+        )?;
+        let rows = statement.query_map([project_id], |row| {
+            Ok(ProjectFile {
+                archived_project_id: row.get(0)?,
+                path: row.get(1)?,
+                size: row.get(2)?,
+                media_type: row.get(3)?,
+                risk_level: row.get(4)?,
+            })
+        })?;
+
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    /// Replace the technical metadata stored for a project's payload files
+    pub fn replace_technical_metadata(&self, project_id: &str, files: &[FileTechnicalMetadata]) -> Result<()> {
+        let conn = self.conn();
+        let tx = conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM file_technical_metadata WHERE archived_project_id = ?", [project_id])?;
+        for file in files {
+            for (key, value) in &file.properties {
+                tx.execute(
+                    "INSERT INTO file_technical_metadata (archived_project_id, path, key, value) VALUES (?, ?, ?, ?)",
+                    params![project_id, file.path, key, value],
+                )?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Get the technical metadata of a project's payload files, grouped by file
+    pub fn get_technical_metadata(&self, project_id: &str) -> Result<Vec<FileTechnicalMetadata>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(
+            "SELECT path, key, value FROM file_technical_metadata
+             WHERE archived_project_id = ? ORDER BY path, key",
+        )?;
+        let rows = statement.query_map([project_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+        })?;
+
+        let mut files: Vec<FileTechnicalMetadata> = Vec::new();
+        for row in rows {
+            let (path, key, value) = row?;
+            match files.last_mut() {
+                Some(file) if file.path == path => {
+                    file.properties.insert(key, value);
+                }
+                _ => files.push(FileTechnicalMetadata {
+                    path,
+                    properties: [(key, value)].into_iter().collect(),
+                }),
+            }
+        }
+        Ok(files)
+    }
+
+    /// Get all tags, most used first
+    pub fn get_all_tags(&self) -> Result<Vec<Tag>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(
+            "SELECT id, name, vocabulary_id, usage_count, created_at
+             FROM tags ORDER BY usage_count DESC, name ASC",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, i64>(3)?,
+                row.get::<_, String>(4)?,
+            ))
+        })?;
+
+        let mut tags = Vec::new();
+        for row in rows {
+            let (id, name, vocabulary_id, usage_count, created_at) = row?;
+            tags.push(Tag {
+                id,
+                name,
+                vocabulary_id,
+                usage_count,
+                created_at: parse_timestamp(&created_at)?,
+            });
+        }
+        Ok(tags)
+    }
+
+    /// Get every tag assignment
+    pub fn get_all_tag_assignments(&self) -> Result<Vec<TagAssignment>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(
+            "SELECT tag_id, archived_project_id, file_path, created_at
+             FROM tag_assignments ORDER BY created_at ASC",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+            ))
+        })?;
+
+        let mut assignments = Vec::new();
+        for row in rows {
+            let (tag_id, archived_project_id, file_path, created_at) = row?;
+            assignments.push(TagAssignment {
+                tag_id,
+                archived_project_id,
+                file_path: (!file_path.is_empty()).then_some(file_path),
+                created_at: parse_timestamp(&created_at)?,
+            });
+        }
+        Ok(assignments)
+    }
+
+    /// Insert a new vocabulary
+    pub fn insert_vocabulary(&self, vocabulary: &Vocabulary) -> Result<()> {
+        self.conn().execute(
+            "INSERT INTO vocabularies (id, name, description, is_controlled, created_at) VALUES (?, ?, ?, ?, ?)",
+            params![
+                vocabulary.id,
+                vocabulary.name,
+                vocabulary.description,
+                vocabulary.is_controlled,
+                vocabulary.created_at.to_rfc3339(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Get all vocabularies by name
+    pub fn get_all_vocabularies(&self) -> Result<Vec<Vocabulary>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(
+            "SELECT id, name, description, is_controlled, created_at FROM vocabularies ORDER BY name ASC",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, bool>(3)?,
+                row.get::<_, String>(4)?,
+            ))
+        })?;
+
+        let mut vocabularies = Vec::new();
+        for row in rows {
+            let (id, name, description, is_controlled, created_at) = row?;
+            vocabularies.push(Vocabulary {
+                id,
+                name,
+                description,
+                is_controlled,
+                created_at: parse_timestamp(&created_at)?,
+            });
+        }
+        Ok(vocabularies)
+    }
+
+    /// Insert a new tag or vocabulary term
+    pub fn insert_tag(&self, tag: &Tag) -> Result<()> {
+        self.conn().execute(
+            "INSERT INTO tags (id, name, vocabulary_id, usage_count, created_at) VALUES (?, ?, ?, 0, ?)",
+            params![tag.id, tag.name, tag.vocabulary_id, tag.created_at.to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// Attach a tag to a project or one of its files; attaching twice is a no-op
+    pub fn insert_tag_assignment(&self, assignment: &TagAssignment) -> Result<()> {
+        self.conn().execute(
+            "INSERT OR IGNORE INTO tag_assignments (tag_id, archived_project_id, file_path, created_at)
+             VALUES (?, ?, ?, ?)",
+            params![
+                assignment.tag_id,
+                assignment.archived_project_id,
+                assignment.file_path.clone().unwrap_or_default(),
+                assignment.created_at.to_rfc3339(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Detach a tag from a project or one of its files, returning whether it was attached
+    pub fn delete_tag_assignment(
+        &self,
+        tag_id: &str,
+        archived_project_id: &str,
+        file_path: Option<&str>,
+    ) -> Result<bool> {
+        let deleted = self.conn().execute(
+            "DELETE FROM tag_assignments WHERE tag_id = ? AND archived_project_id = ? AND file_path = ?",
+            params![tag_id, archived_project_id, file_path.unwrap_or_default()],
+        )?;
+        Ok(deleted > 0)
+    }
+
+    /// Insert a new environment profile together with its components
+    pub fn insert_environment_profile(&self, profile: &EnvironmentProfile) -> Result<()> {
+        let conn = self.conn();
+        let tx = conn.unchecked_transaction()?;
+        tx.execute(
+            "INSERT INTO environment_profiles (id, name, description, os_name, os_version, created_at)
+             VALUES (?, ?, ?, ?, ?, ?)",
+            params![
+                profile.id,
+                profile.name,
+                profile.description,
+                profile.os_name,
+                profile.os_version,
+                profile.created_at.to_rfc3339(),
+            ],
+        )?;
+        for (position, component) in profile.components.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO environment_components (profile_id, position, component_type, name, version)
+                 VALUES (?, ?, ?, ?, ?)",
+                params![
+                    profile.id,
+                    position as i64,
+                    component.component_type,
+                    component.name,
+                    component.version,
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Get all environment profiles with their components, by name
+    pub fn get_all_environment_profiles(&self) -> Result<Vec<EnvironmentProfile>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(
+            "SELECT id, name, description, os_name, os_version, created_at
+             FROM environment_profiles ORDER BY name ASC",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, Option<String>>(4)?,
+                row.get::<_, String>(5)?,
+            ))
+        })?;
+
+        let mut profiles = Vec::new();
+        for row in rows {
+            let (id, name, description, os_name, os_version, created_at) = row?;
+            profiles.push(EnvironmentProfile {
+                id,
+                name,
+                description,
+                os_name,
+                os_version,
+                components: Vec::new(),
+                created_at: parse_timestamp(&created_at)?,
+            });
+        }
+
+        let mut statement = conn.prepare(
+            "SELECT profile_id, component_type, name, version
+             FROM environment_components ORDER BY profile_id, position",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                EnvironmentComponent {
+                    component_type: row.get(1)?,
+                    name: row.get(2)?,
+                    version: row.get(3)?,
+                },
+            ))
+        })?;
+        for row in rows {
+            let (profile_id, component) = row?;
+            if let Some(profile) = profiles.iter_mut().find(|p| p.id == profile_id) {
+                profile.components.push(component);
+            }
+        }
+        Ok(profiles)
+    }
+
+    /// Attach an environment profile to a project; attaching twice is a no-op
+    pub fn insert_project_environment(&self, attachment: &ProjectEnvironment) -> Result<()> {
+        insert_environment_row(&self.conn(), attachment)
+    }
+
+    /// Detach an environment profile from a project, returning whether it was attached
+    pub fn delete_project_environment(&self, project_id: &str, profile_id: &str) -> Result<bool> {
+        let deleted = self.conn().execute(
+            "DELETE FROM project_environments WHERE archived_project_id = ? AND profile_id = ?",
+            params![project_id, profile_id],
+        )?;
+        Ok(deleted > 0)
+    }
+
+    /// Get every project/profile attachment, oldest first
+    pub fn get_all_project_environments(&self) -> Result<Vec<ProjectEnvironment>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(
+            "SELECT archived_project_id, profile_id, attached_at
+             FROM project_environments ORDER BY attached_at ASC, rowid",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?;
+
+        let mut attachments = Vec::new();
+        for row in rows {
+            let (archived_project_id, profile_id, attached_at) = row?;
+            attachments.push(ProjectEnvironment {
+                archived_project_id,
+                profile_id,
+                attached_at: parse_timestamp(&attached_at)?,
+            });
+        }
+        Ok(attachments)
+    }
+
+    /// Replace the full-text index rows of a project
+    pub fn replace_search_documents(&self, project_id: &str, documents: &[SearchDocument]) -> Result<()> {
+        let conn = self.conn();
+        let tx = conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM search_index WHERE archived_project_id = ?", [project_id])?;
+        for document in documents {
+            tx.execute(
+                "INSERT INTO search_index (
+                    archived_project_id, file_path, name, description, metadata, tags, content
+                ) VALUES (?, ?, ?, ?, ?, ?, ?)",
+                params![
+                    document.project_id,
+                    document.file_path.clone().unwrap_or_default(),
+                    document.name,
+                    document.description,
+                    document.metadata,
+                    document.tags,
+                    document.content,
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Run an FTS5 MATCH expression against the index, best matches first
+    ///
+    /// Weights and the exclusion of quarantined projects match the app's search.
+    pub fn search_index(&self, match_query: &str, limit: i64) -> Result<Vec<SearchHit>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(
+            "SELECT search_index.archived_project_id, search_index.file_path, archived_projects.name,
+                    snippet(search_index, -1, '[', ']', '...', 12),
+                    bm25(search_index, 0.0, 0.0, 10.0, 5.0, 3.0, 8.0, 1.0) AS rank
+             FROM search_index
+             JOIN archived_projects ON archived_projects.id = search_index.archived_project_id
+             WHERE search_index MATCH ? AND archived_projects.is_quarantined = FALSE
+             ORDER BY rank
+             LIMIT ?",
+        )?;
+        let rows = statement.query_map(params![match_query, limit], |row| {
+            let file_path: String = row.get(1)?;
+            let rank: f64 = row.get(4)?;
+            Ok(SearchHit {
+                project_id: row.get(0)?,
+                project_name: row.get(2)?,
+                file_path: (!file_path.is_empty()).then_some(file_path),
+                snippet: row.get(3)?,
+                // bm25() is lower for better matches; flip it so higher scores rank first
+                score: -rank,
+            })
+        })?;
+
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    /// Get a value from the settings table
+    pub fn get_setting(&self, key: &str) -> Result<Option<String>> {
+        Ok(self
+            .conn()
+            .query_row("SELECT value FROM settings WHERE key = ?", [key], |row| row.get(0))
+            .optional()?)
+    }
+
+    /// Set a value in the settings table
+    pub fn set_setting(&self, key: &str, value: &str) -> Result<()> {
+        self.conn().execute(
+            "INSERT INTO settings (key, value, updated_at) VALUES (?, ?, CURRENT_TIMESTAMP)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = CURRENT_TIMESTAMP",
+            params![key, value],
+        )?;
+        Ok(())
+    }
+
+    /// Record that a project's bag was moved into quarantine
+    pub fn insert_quarantine_entry(&self, entry: &QuarantineEntry) -> Result<()> {
+        self.conn().execute(
+            "INSERT INTO quarantine_entries (
+                id, archived_project_id, quarantined_at, original_bag_path, scheduled_for_deletion_at, reason
+            ) VALUES (?, ?, ?, ?, ?, ?)",
+            params![
+                entry.id,
+                entry.archived_project_id,
+                entry.quarantined_at.to_rfc3339(),
+                entry.original_bag_path,
+                entry.scheduled_for_deletion_at.map(|at| at.to_rfc3339()),
+                entry.reason,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// The most recent quarantine entry of a project
+    pub fn get_quarantine_entry(&self, project_id: &str) -> Result<Option<QuarantineEntry>> {
+        let row = self
+            .conn()
+            .query_row(
+                "SELECT id, archived_project_id, quarantined_at, original_bag_path, scheduled_for_deletion_at, reason
+                 FROM quarantine_entries WHERE archived_project_id = ?
+                 ORDER BY quarantined_at DESC LIMIT 1",
+                [project_id],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, String>(3)?,
+                        row.get::<_, Option<String>>(4)?,
+                        row.get::<_, Option<String>>(5)?,
+                    ))
+                },
+            )
+            .optional()?;
+
+        let Some((id, archived_project_id, quarantined_at, original_bag_path, scheduled, reason)) = row else {
+            return Ok(None);
+        };
+        Ok(Some(QuarantineEntry {
+            id,
+            archived_project_id,
+            quarantined_at: parse_timestamp(&quarantined_at)?,
+            original_bag_path,
+            scheduled_for_deletion_at: scheduled.as_deref().map(parse_timestamp).transpose()?,
+            reason,
+        }))
+    }
+
+    /// Remove a quarantine entry once its project has been restored
+    pub fn delete_quarantine_entry(&self, entry_id: &str) -> Result<()> {
+        self.conn().execute("DELETE FROM quarantine_entries WHERE id = ?", [entry_id])?;
+        Ok(())
+    }
+
+    /// Store the result of a vault integrity scan
+    pub fn insert_vault_snapshot(&self, snapshot: &VaultSnapshot) -> Result<()> {
+        let anomalies = snapshot
+            .anomalies_detected
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()?;
+        self.conn().execute(
+            "INSERT INTO vault_snapshots (
+                id, snapshot_at, user_layer_checksum, bags_layer_checksum, anomalies_detected, created_at
+            ) VALUES (?, ?, ?, ?, ?, ?)",
+            params![
+                snapshot.id,
+                snapshot.snapshot_at.to_rfc3339(),
+                snapshot.user_layer_checksum,
+                snapshot.bags_layer_checksum,
+                anomalies,
+                snapshot.created_at.to_rfc3339(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Link two archived projects in a lineage
+    pub fn insert_project_relation(&self, relation: &ProjectRelation) -> Result<()> {
+        insert_relation_row(&self.conn(), relation)
+    }
+
+    /// Get every lineage link between archived projects
+    pub fn get_all_project_relations(&self) -> Result<Vec<ProjectRelation>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(
+            "SELECT id, parent_project_id, child_project_id, relation_type, label, created_at
+             FROM project_relations ORDER BY created_at ASC",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, Option<String>>(4)?,
+                row.get::<_, String>(5)?,
+            ))
+        })?;
+
+        let mut relations = Vec::new();
+        for row in rows {
+            let (id, parent_project_id, child_project_id, relation_type, label, created_at) = row?;
+            relations.push(ProjectRelation {
+                id,
+                parent_project_id,
+                child_project_id,
+                relation_type,
+                label,
+                created_at: parse_timestamp(&created_at)?,
+            });
+        }
+        Ok(relations)
+    }
+
+    /// Remove the link between two projects, returning whether there was one
+    pub fn delete_project_relation(&self, parent_project_id: &str, child_project_id: &str) -> Result<bool> {
+        let deleted = self.conn().execute(
+            "DELETE FROM project_relations WHERE parent_project_id = ? AND child_project_id = ?",
+            params![parent_project_id, child_project_id],
+        )?;
+        Ok(deleted > 0)
+    }
+
+    /// Insert a new collection
+    pub fn insert_collection(&self, collection: &Collection) -> Result<()> {
+        self.conn().execute(
+            "INSERT INTO collections (
+                id, name, description, parent_collection_id, position, created_at, updated_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?)",
+            params![
+                collection.id,
+                collection.name,
+                collection.description,
+                collection.parent_collection_id,
+                collection.position,
+                collection.created_at.to_rfc3339(),
+                collection.updated_at.to_rfc3339(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Move a collection under a new parent and/or to a new position among its siblings
+    pub fn update_collection_placement(
+        &self,
+        collection_id: &str,
+        parent_collection_id: Option<&str>,
+        position: i32,
+    ) -> Result<()> {
+        self.conn().execute(
+            "UPDATE collections SET parent_collection_id = ?, position = ?, updated_at = ? WHERE id = ?",
+            params![parent_collection_id, position, Utc::now().to_rfc3339(), collection_id],
+        )?;
+        Ok(())
+    }
+
+    /// Get all collections in sibling order
+    pub fn get_all_collections(&self) -> Result<Vec<Collection>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(
+            "SELECT id, name, description, parent_collection_id, position, created_at, updated_at
+             FROM collections ORDER BY position ASC, name ASC",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, i32>(4)?,
+                row.get::<_, String>(5)?,
+                row.get::<_, String>(6)?,
+            ))
+        })?;
+
+        let mut collections = Vec::new();
+        for row in rows {
+            let (id, name, description, parent_collection_id, position, created_at, updated_at) = row?;
+            collections.push(Collection {
+                id,
+                name,
+                description,
+                parent_collection_id,
+                position,
+                created_at: parse_timestamp(&created_at)?,
+                updated_at: parse_timestamp(&updated_at)?,
+            });
+        }
+        Ok(collections)
+    }
+
+    /// Add a project to a collection, or update its position if it is already there
+    pub fn upsert_collection_member(&self, member: &CollectionMember) -> Result<()> {
+        self.conn().execute(
+            "INSERT INTO collection_members (collection_id, archived_project_id, position, added_at)
+             VALUES (?, ?, ?, ?)
+             ON CONFLICT (collection_id, archived_project_id) DO UPDATE SET position = excluded.position",
+            params![
+                member.collection_id,
+                member.archived_project_id,
+                member.position,
+                member.added_at.to_rfc3339(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Remove a project from a collection, returning whether it was a member
+    pub fn delete_collection_member(&self, collection_id: &str, archived_project_id: &str) -> Result<bool> {
+        let deleted = self.conn().execute(
+            "DELETE FROM collection_members WHERE collection_id = ? AND archived_project_id = ?",
+            params![collection_id, archived_project_id],
+        )?;
+        Ok(deleted > 0)
+    }
+
+    /// Get every project membership across all collections
+    pub fn get_all_collection_members(&self) -> Result<Vec<CollectionMember>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(
+            "SELECT collection_id, archived_project_id, position, added_at
+             FROM collection_members ORDER BY collection_id, position ASC",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i32>(2)?,
+                row.get::<_, String>(3)?,
+            ))
+        })?;
+
+        let mut members = Vec::new();
+        for row in rows {
+            let (collection_id, archived_project_id, position, added_at) = row?;
+            members.push(CollectionMember {
+                collection_id,
+                archived_project_id,
+                position,
+                added_at: parse_timestamp(&added_at)?,
+            });
+        }
+        Ok(members)
+    }
+
+    /// Start watching a folder
+    pub fn insert_watch_folder(&self, folder: &WatchFolder) -> Result<()> {
+        self.conn().execute(
+            "INSERT INTO watch_folders (id, path, settle_seconds, create_bag, enabled, created_at)
+             VALUES (?, ?, ?, ?, ?, ?)",
+            params![
+                folder.id,
+                folder.path,
+                folder.settle_seconds,
+                folder.create_bag,
+                folder.enabled,
+                folder.created_at.to_rfc3339(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Get all watch folders, oldest first
+    pub fn get_watch_folders(&self) -> Result<Vec<WatchFolder>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(
+            "SELECT id, path, settle_seconds, create_bag, enabled, created_at
+             FROM watch_folders ORDER BY created_at ASC",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, bool>(3)?,
+                row.get::<_, bool>(4)?,
+                row.get::<_, String>(5)?,
+            ))
+        })?;
+
+        let mut folders = Vec::new();
+        for row in rows {
+            let (id, path, settle_seconds, create_bag, enabled, created_at) = row?;
+            folders.push(WatchFolder {
+                id,
+                path,
+                settle_seconds,
+                create_bag,
+                enabled,
+                created_at: parse_timestamp(&created_at)?,
+            });
+        }
+        Ok(folders)
+    }
+
+    /// Stop watching a folder and forget what was ingested from it
+    pub fn delete_watch_folder(&self, watch_folder_id: &str) -> Result<bool> {
+        let conn = self.conn();
+        let tx = conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM watch_folder_entries WHERE watch_folder_id = ?", [watch_folder_id])?;
+        let deleted = tx.execute("DELETE FROM watch_folders WHERE id = ?", [watch_folder_id])?;
+        tx.commit()?;
+        Ok(deleted > 0)
+    }
+
+    /// The fingerprint and project of an entry's last ingestion, if it was ingested before
+    pub fn get_watch_folder_entry(&self, watch_folder_id: &str, entry_name: &str) -> Result<Option<(String, String)>> {
+        Ok(self
+            .conn()
+            .query_row(
+                "SELECT fingerprint, archived_project_id FROM watch_folder_entries
+                 WHERE watch_folder_id = ? AND entry_name = ?",
+                [watch_folder_id, entry_name],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?)
+    }
+
+    /// Remember the latest ingestion of a watch folder entry
+    pub fn upsert_watch_folder_entry(
+        &self,
+        watch_folder_id: &str,
+        entry_name: &str,
+        fingerprint: &str,
+        project_id: &str,
+    ) -> Result<()> {
+        self.conn().execute(
+            "INSERT INTO watch_folder_entries (watch_folder_id, entry_name, fingerprint, archived_project_id, ingested_at)
+             VALUES (?, ?, ?, ?, ?)
+             ON CONFLICT(watch_folder_id, entry_name) DO UPDATE SET
+                fingerprint = excluded.fingerprint,
+                archived_project_id = excluded.archived_project_id,
+                ingested_at = excluded.ingested_at",
+            params![watch_folder_id, entry_name, fingerprint, project_id, Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// Remember the folder a project was archived from
+    pub fn set_project_source(&self, project_id: &str, source_root: &str) -> Result<()> {
+        set_source_row(&self.conn(), project_id, source_root)
+    }
+
+    /// The folder a project was archived from, if it was recorded
+    pub fn get_project_source(&self, project_id: &str) -> Result<Option<Utf8PathBuf>> {
+        Ok(self
+            .conn()
+            .query_row(
+                "SELECT source_root FROM project_sources WHERE archived_project_id = ?",
+                [project_id],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?
+            .map(Utf8PathBuf::from))
+    }
+
+    /// Replace the recorded state of a project's source files
+    pub fn replace_source_file_states(&self, project_id: &str, states: &[SourceFileState]) -> Result<()> {
+        let conn = self.conn();
+        let tx = conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM source_file_states WHERE archived_project_id = ?", [project_id])?;
+        for state in states {
+            tx.execute(
+                "INSERT INTO source_file_states (archived_project_id, path, size, modified_at, sha256)
+                 VALUES (?, ?, ?, ?, ?)",
+                params![project_id, state.path, state.size as i64, state.modified_at, state.sha256],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Get the recorded state of a project's source files
+    pub fn get_source_file_states(&self, project_id: &str) -> Result<Vec<SourceFileState>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(
+            "SELECT path, size, modified_at, sha256 FROM source_file_states
+             WHERE archived_project_id = ? ORDER BY path",
+        )?;
+        let rows = statement.query_map([project_id], |row| {
+            Ok(SourceFileState {
+                path: row.get(0)?,
+                size: row.get::<_, i64>(1)? as u64,
+                modified_at: row.get(2)?,
+                sha256: row.get(3)?,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    /// Add a place bags are copied to
+    pub fn insert_replication_target(&self, target: &ReplicationTarget) -> Result<()> {
+        self.conn().execute(
+            "INSERT INTO replication_targets (id, name, path, kind, settings, encrypted, enabled, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            params![
+                target.id,
+                target.name,
+                target.path,
+                target.kind,
+                target.settings,
+                target.encrypted,
+                target.enabled,
+                target.created_at.to_rfc3339()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Get all replication targets, oldest first
+    pub fn get_replication_targets(&self) -> Result<Vec<ReplicationTarget>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(
+            "SELECT id, name, path, kind, settings, encrypted, enabled, created_at
+             FROM replication_targets ORDER BY created_at ASC",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, Option<String>>(4)?,
+                row.get::<_, bool>(5)?,
+                row.get::<_, bool>(6)?,
+                row.get::<_, String>(7)?,
+            ))
+        })?;
+
+        let mut targets = Vec::new();
+        for row in rows {
+            let (id, name, path, kind, settings, encrypted, enabled, created_at) = row?;
+            targets.push(ReplicationTarget {
+                id,
+                name,
+                path,
+                kind,
+                settings,
+                encrypted,
+                enabled,
+                created_at: parse_timestamp(&created_at)?,
+            });
+        }
+        Ok(targets)
+    }
+
+    /// Switch encryption of a target's replicas on or off, marking them for rewriting
+    pub fn set_replication_target_encrypted(&self, target_id: &str, encrypted: bool) -> Result<bool> {
+        let conn = self.conn();
+        let tx = conn.unchecked_transaction()?;
+        let updated =
+            tx.execute("UPDATE replication_targets SET encrypted = ? WHERE id = ?", params![encrypted, target_id])?;
+        tx.execute("UPDATE replicas SET status = 'pending' WHERE target_id = ?", [target_id])?;
+        tx.commit()?;
+        Ok(updated > 0)
+    }
+
+    /// Remove a replication target and its replica records; the copies themselves stay on disk
+    pub fn delete_replication_target(&self, target_id: &str) -> Result<bool> {
+        let conn = self.conn();
+        let tx = conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM replica_objects WHERE target_id = ?", [target_id])?;
+        tx.execute("DELETE FROM replicas WHERE target_id = ?", [target_id])?;
+        let deleted = tx.execute("DELETE FROM replication_targets WHERE id = ?", [target_id])?;
+        tx.commit()?;
+        Ok(deleted > 0)
+    }
+
+    /// Insert or update the status of one replica
+    pub fn upsert_replica(&self, replica: &ReplicaStatus) -> Result<()> {
+        self.conn().execute(
+            "INSERT INTO replicas (
+                archived_project_id, target_id, replica_path, status, last_copied_at, last_verified_at, last_error
+            ) VALUES (?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(archived_project_id, target_id) DO UPDATE SET
+                replica_path = excluded.replica_path,
+                status = excluded.status,
+                last_copied_at = excluded.last_copied_at,
+                last_verified_at = excluded.last_verified_at,
+                last_error = excluded.last_error",
+            params![
+                replica.archived_project_id,
+                replica.target_id,
+                replica.replica_path,
+                replica.status,
+                replica.last_copied_at.map(|at| at.to_rfc3339()),
+                replica.last_verified_at.map(|at| at.to_rfc3339()),
+                replica.last_error,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Get the replicas of one project, or of every project
+    pub fn get_replicas(&self, project_id: Option<&str>) -> Result<Vec<ReplicaStatus>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(
+            "SELECT archived_project_id, target_id, replica_path, status, last_copied_at, last_verified_at, last_error
+             FROM replicas WHERE ?1 IS NULL OR archived_project_id = ?1
+             ORDER BY archived_project_id, target_id",
+        )?;
+        let rows = statement.query_map([project_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, Option<String>>(4)?,
+                row.get::<_, Option<String>>(5)?,
+                row.get::<_, Option<String>>(6)?,
+            ))
+        })?;
+
+        let mut replicas = Vec::new();
+        for row in rows {
+            let (archived_project_id, target_id, replica_path, status, copied, verified, last_error) = row?;
+            replicas.push(ReplicaStatus {
+                archived_project_id,
+                target_id,
+                replica_path,
+                status,
+                last_copied_at: copied.as_deref().map(parse_timestamp).transpose()?,
+                last_verified_at: verified.as_deref().map(parse_timestamp).transpose()?,
+                last_error,
+            });
+        }
+        Ok(replicas)
+    }
+
+    /// Record a file uploaded to a replica, replacing any earlier record of it
+    pub fn upsert_replica_object(&self, object: &ReplicaObject) -> Result<()> {
+        self.conn().execute(
+            "INSERT INTO replica_objects (archived_project_id, target_id, path, size, sha256, etag, key_id, stored_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT(archived_project_id, target_id, path) DO UPDATE SET
+                size = excluded.size,
+                sha256 = excluded.sha256,
+                etag = excluded.etag,
+                key_id = excluded.key_id,
+                stored_at = excluded.stored_at",
+            params![
+                object.archived_project_id,
+                object.target_id,
+                object.path,
+                object.size,
+                object.sha256,
+                object.etag,
+                object.key_id,
+                object.stored_at.to_rfc3339(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Get the recorded files of one replica
+    pub fn get_replica_objects(&self, project_id: &str, target_id: &str) -> Result<Vec<ReplicaObject>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(
+            "SELECT path, size, sha256, etag, key_id, stored_at FROM replica_objects
+             WHERE archived_project_id = ? AND target_id = ? ORDER BY path",
+        )?;
+        let rows = statement.query_map([project_id, target_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, Option<String>>(4)?,
+                row.get::<_, String>(5)?,
+            ))
+        })?;
+
+        let mut objects = Vec::new();
+        for row in rows {
+            let (path, size, sha256, etag, key_id, stored_at) = row?;
+            objects.push(ReplicaObject {
+                archived_project_id: project_id.to_string(),
+                target_id: target_id.to_string(),
+                path,
+                size,
+                sha256,
+                etag,
+                key_id,
+                stored_at: parse_timestamp(&stored_at)?,
+            });
+        }
+        Ok(objects)
+    }
+
+    pub fn delete_replica_object(&self, project_id: &str, target_id: &str, path: &str) -> Result<()> {
+        self.conn().execute(
+            "DELETE FROM replica_objects WHERE archived_project_id = ? AND target_id = ? AND path = ?",
+            [project_id, target_id, path],
+        )?;
+        Ok(())
+    }
+
+    /// Forget what was uploaded for one replica, as when it is replaced wholesale
+    pub fn delete_replica_objects(&self, project_id: &str, target_id: &str) -> Result<()> {
+        self.conn().execute(
+            "DELETE FROM replica_objects WHERE archived_project_id = ? AND target_id = ?",
+            [project_id, target_id],
+        )?;
+        Ok(())
+    }
+
+    /// Add a data key for client-side encryption
+    pub fn insert_encryption_key(&self, key: &EncryptionKey) -> Result<()> {
+        self.conn().execute(
+            "INSERT INTO encryption_keys (id, sealed_key, created_at) VALUES (?, ?, ?)",
+            params![key.id, key.sealed_key, key.created_at.to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// Get every data key, oldest first
+    pub fn get_encryption_keys(&self) -> Result<Vec<EncryptionKey>> {
+        let conn = self.conn();
+        let mut statement =
+            conn.prepare("SELECT id, sealed_key, created_at FROM encryption_keys ORDER BY created_at ASC")?;
+        let rows = statement.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+        })?;
+
+        let mut keys = Vec::new();
+        for row in rows {
+            let (id, sealed_key, created_at) = row?;
+            keys.push(EncryptionKey {
+                id,
+                sealed_key,
+                created_at: parse_timestamp(&created_at)?,
+            });
+        }
+        Ok(keys)
+    }
+}
+
+fn insert_project_row(conn: &Connection, project: &ArchivedProject) -> Result<()> {
+    conn.execute(
+        "INSERT INTO archived_projects (
+            id, name, description, archived_at, bagit_package_id,
+            file_count, total_size, is_quarantined, created_at, updated_at
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        params![
+            project.id,
+            project.name,
+            project.description,
+            project.archived_at.to_rfc3339(),
+            project.bagit_package_id,
+            project.file_count,
+            project.total_size,
+            project.is_quarantined,
+            project.created_at.to_rfc3339(),
+            project.updated_at.to_rfc3339(),
+        ],
+    )?;
+    Ok(())
+}
+
+fn insert_event_row(conn: &Connection, event_type: &str, aggregate_id: &str, payload: &str) -> Result<()> {
+    conn.execute(
+        "INSERT INTO events (id, event_type, aggregate_id, payload, created_at)
+         VALUES (?, ?, ?, ?, CURRENT_TIMESTAMP)",
+        params![uuid::Uuid::new_v4().to_string(), event_type, aggregate_id, payload],
+    )?;
+    Ok(())
+}
+
+fn set_source_row(conn: &Connection, project_id: &str, source_root: &str) -> Result<()> {
+    conn.execute(
+        "INSERT INTO project_sources (archived_project_id, source_root, recorded_at) VALUES (?, ?, ?)
+         ON CONFLICT(archived_project_id) DO UPDATE SET
+            source_root = excluded.source_root,
+            recorded_at = excluded.recorded_at",
+        params![project_id, source_root, Utc::now().to_rfc3339()],
+    )?;
+    Ok(())
+}
+
+fn insert_environment_row(conn: &Connection, attachment: &ProjectEnvironment) -> Result<()> {
+    conn.execute(
+        "INSERT OR IGNORE INTO project_environments (archived_project_id, profile_id, attached_at) VALUES (?, ?, ?)",
+        params![
+            attachment.archived_project_id,
+            attachment.profile_id,
+            attachment.attached_at.to_rfc3339(),
+        ],
+    )?;
+    Ok(())
+}
+
+fn insert_relation_row(conn: &Connection, relation: &ProjectRelation) -> Result<()> {
+    conn.execute(
+        "INSERT INTO project_relations (
+            id, parent_project_id, child_project_id, relation_type, label, created_at
+        ) VALUES (?, ?, ?, ?, ?, ?)",
+        params![
+            relation.id,
+            relation.parent_project_id,
+            relation.child_project_id,
+            relation.relation_type,
+            relation.label,
+            relation.created_at.to_rfc3339(),
+        ],
+    )?;
+    Ok(())
+}
+
+fn project_from_row(row: &Row) -> Result<ArchivedProject> {
+    let id: String = row.get("id")?;
+    let archived_at: String = row.get("archived_at")?;
+    let created_at: String = row.get("created_at")?;
+    let updated_at: String = row.get("updated_at")?;
+
+    Ok(ArchivedProject {
+        name: row.get("name")?,
+        description: row.get("description")?,
+        archived_at: parse_timestamp(&archived_at).with_context(|| format!("Invalid archived_at for project {}", id))?,
+        bagit_package_id: row.get("bagit_package_id")?,
+        file_count: row.get("file_count")?,
+        total_size: row.get("total_size")?,
+        is_quarantined: row.get("is_quarantined")?,
+        created_at: parse_timestamp(&created_at).with_context(|| format!("Invalid created_at for project {}", id))?,
+        updated_at: parse_timestamp(&updated_at).with_context(|| format!("Invalid updated_at for project {}", id))?,
+        id,
+    })
+}
+
+/// Convert the JSON parameters built by `project_query` into SQLite values
+fn sql_values(params: &[serde_json::Value]) -> Vec<SqlValue> {
+    params
+        .iter()
+        .map(|param| match param {
+            serde_json::Value::Null => SqlValue::Null,
+            serde_json::Value::Bool(value) => SqlValue::Integer(*value as i64),
+            serde_json::Value::Number(number) => match number.as_i64() {
+                Some(value) => SqlValue::Integer(value),
+                None => SqlValue::Real(number.as_f64().unwrap_or_default()),
+            },
+            serde_json::Value::String(value) => SqlValue::Text(value.clone()),
+            other => SqlValue::Text(other.to_string()),
+        })
+        .collect()
+}
+
+/// Parse a stored timestamp, accepting both RFC 3339 and SQLite's CURRENT_TIMESTAMP format
+fn parse_timestamp(value: &str) -> Result<DateTime<Utc>> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(value) {
+        return Ok(timestamp.with_timezone(&Utc));
+    }
+
+    let naive = chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")?;
+    Ok(naive.and_utc())
+}
+
+/// Open a database with SQLCipher under a passphrase, or `None` if the passphrase is wrong
+#[cfg(feature = "sqlcipher")]
+fn open_keyed(path: &Utf8Path, passphrase: &str) -> Result<Option<Connection>> {
+    let conn = Connection::open(path).with_context(|| format!("Failed to open database {}", path))?;
+    conn.pragma_update(None, "key", passphrase)?;
+    // The key is only checked when the first page is read
+    match conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| row.get::<_, i64>(0)) {
+        Ok(_) => Ok(Some(conn)),
+        Err(rusqlite::Error::SqliteFailure(e, _)) if e.code == rusqlite::ErrorCode::NotADatabase => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Copy everything in a connection's database to a new file under a passphrase; an empty one leaves it unencrypted
+#[cfg(feature = "sqlcipher")]
+fn export_attached(conn: &Connection, output: &Utf8Path, passphrase: &str) -> Result<()> {
+    conn.execute("ATTACH DATABASE ? AS export KEY ?", params![output.as_str(), passphrase])?;
+    let exported = conn.query_row("SELECT sqlcipher_export('export')", [], |_| Ok(()));
+    conn.execute_batch("DETACH DATABASE export")?;
+    if let Err(e) = exported {
+        let _ = std::fs::remove_file(output);
+        return Err(e).with_context(|| format!("Failed to export the database to {}", output));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "sqlcipher")]
+fn remove_if_exists(path: &Utf8Path) -> Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        result => Ok(result?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_migrations_are_recorded_once() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = Utf8Path::from_path(temp_dir.path()).unwrap().join("preservation.db");
+
+        let store = Store::open(&path).unwrap();
+        let project = ArchivedProject::new("Thesis".to_string(), None, 2, 2048);
+        store.insert_archived_project(&project).unwrap();
+        store.update_quarantine_status(&project.id, true).unwrap();
+        drop(store);
+
+        // Reopening applies nothing new and keeps the data
+        let store = Store::open(&path).unwrap();
+        let applied: i64 = store
+            .conn()
+            .query_row("SELECT COUNT(*) FROM _sqlx_migrations", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(applied, MIGRATIONS.len() as i64);
+
+        let loaded = store.get_archived_project(&project.id).unwrap().unwrap();
+        assert!(loaded.is_quarantined);
+        assert_eq!(loaded.total_size, 2048);
+    }
+
+    #[cfg(feature = "sqlcipher")]
+    #[test]
+    fn test_encrypted_database() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let path = root.join("preservation.db");
+
+        let store = Store::open(&path).unwrap();
+        let project = ArchivedProject::new("Student Thesis".to_string(), None, 1, 64);
+        store.insert_archived_project(&project).unwrap();
+        store.encrypt("correct horse").unwrap();
+        assert!(store.is_encrypted().unwrap() && !store.is_locked());
+        assert!(store.get_archived_project(&project.id).unwrap().is_some());
+        assert!(!std::fs::read(&path).unwrap().windows(7).any(|w| w == b"Student"));
+        drop(store);
+
+        let store = Store::open(&path).unwrap();
+        assert!(store.is_locked());
+        assert!(store.get_archived_project(&project.id).is_err());
+        assert!(store.change_passphrase("correct horse", "battery staple").is_err());
+        assert!(!store.unlock("wrong horse").unwrap());
+        assert!(store.unlock("correct horse").unwrap());
+        assert!(store.get_archived_project(&project.id).unwrap().is_some());
+
+        assert!(!store.change_passphrase("wrong horse", "battery staple").unwrap());
+        assert!(store.change_passphrase("correct horse", "battery staple").unwrap());
+        store.export_unencrypted(&root.join("plain.db")).unwrap();
+        assert!(store.export_unencrypted(&root.join("plain.db")).is_err());
+        drop(store);
+
+        let store = Store::open(&path).unwrap();
+        assert!(!store.unlock("correct horse").unwrap());
+        assert!(store.unlock("battery staple").unwrap());
+        let plain = Store::open(&root.join("plain.db")).unwrap();
+        assert!(!plain.is_encrypted().unwrap());
+        assert_eq!(plain.get_archived_project(&project.id).unwrap().unwrap().name, "Student Thesis");
+    }
+}
@@ -0,0 +1,299 @@
+use anyhow::Result;
+
+/// Tag lines longer than this are folded onto indented continuation lines
+const MAX_LINE_LENGTH: usize = 79;
+
+/// Ordered multimap of bag-info.txt labels and values
+///
+/// Labels keep their original order and spelling, may repeat, and are
+/// matched case-insensitively as the BagIt spec allows.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BagInfo {
+    fields: Vec<(String, String)>,
+}
+
+/// A requirement on a single bag-info label, typically supplied by a profile
+#[derive(Debug, Clone)]
+pub struct BagInfoRule {
+    pub label: String,
+    pub required: bool,
+    pub repeatable: bool,
+    pub values: Option<Vec<String>>,
+}
+
+impl BagInfo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a value, keeping any existing values for the same label
+    pub fn add(&mut self, label: impl Into<String>, value: impl Into<String>) {
+        self.fields.push((label.into(), value.into()));
+    }
+
+    /// Replace every value for a label with a single value
+    ///
+    /// The new value takes the position of the first existing occurrence, or
+    /// is appended if the label was not present.
+    pub fn set(&mut self, label: impl Into<String>, value: impl Into<String>) {
+        let label = label.into();
+        let value = value.into();
+
+        match self.fields.iter().position(|(l, _)| l.eq_ignore_ascii_case(&label)) {
+            Some(index) => {
+                self.fields[index] = (label.clone(), value);
+                let mut position = 0;
+                self.fields.retain(|(l, _)| {
+                    let keep = position <= index || !l.eq_ignore_ascii_case(&label);
+                    position += 1;
+                    keep
+                });
+            }
+            None => self.fields.push((label, value)),
+        }
+    }
+
+    /// Get the first value for a label
+    pub fn get(&self, label: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(l, _)| l.eq_ignore_ascii_case(label))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Get every value for a label in file order
+    pub fn get_all(&self, label: &str) -> Vec<&str> {
+        self.fields
+            .iter()
+            .filter(|(l, _)| l.eq_ignore_ascii_case(label))
+            .map(|(_, v)| v.as_str())
+            .collect()
+    }
+
+    /// Remove every value for a label, returning the removed values
+    pub fn remove(&mut self, label: &str) -> Vec<String> {
+        let mut removed = Vec::new();
+        self.fields.retain(|(l, v)| {
+            if l.eq_ignore_ascii_case(label) {
+                removed.push(v.clone());
+                false
+            } else {
+                true
+            }
+        });
+        removed
+    }
+
+    pub fn contains(&self, label: &str) -> bool {
+        self.get(label).is_some()
+    }
+
+    /// Iterate over (label, value) pairs in file order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.fields.iter().map(|(l, v)| (l.as_str(), v.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Parse the contents of a bag-info.txt file
+    ///
+    /// Lines starting with whitespace continue the previous value and are
+    /// joined to it with a single space.
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut fields: Vec<(String, String)> = Vec::new();
+
+        for (line_number, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            if line.starts_with(' ') || line.starts_with('\t') {
+                let (_, value) = fields.last_mut().ok_or_else(|| {
+                    anyhow::anyhow!("Continuation line without a label on line {}", line_number + 1)
+                })?;
+                if !value.is_empty() {
+                    value.push(' ');
+                }
+                value.push_str(line.trim());
+                continue;
+            }
+
+            let (label, value) = line.split_once(':').ok_or_else(|| {
+                anyhow::anyhow!("Invalid bag-info line {}: {}", line_number + 1, line)
+            })?;
+
+            let label = label.trim();
+            if label.is_empty() {
+                return Err(anyhow::anyhow!("Empty label on bag-info line {}", line_number + 1));
+            }
+
+            fields.push((label.to_string(), value.trim().to_string()));
+        }
+
+        Ok(BagInfo { fields })
+    }
+
+    /// Serialize to bag-info.txt format, folding long values
+    pub fn to_tag_file(&self) -> String {
+        let mut output = String::new();
+        for (label, value) in &self.fields {
+            output.push_str(&fold_line(&format!("{}: {}", label, value)));
+            output.push('\n');
+        }
+        output
+    }
+
+    /// Check the fields against a set of rules, returning a message per violation
+    pub fn check_rules(&self, rules: &[BagInfoRule]) -> Vec<String> {
+        let mut issues = Vec::new();
+
+        for rule in rules {
+            let values = self.get_all(&rule.label);
+
+            if values.is_empty() {
+                if rule.required {
+                    issues.push(format!("Missing required bag-info field: {}", rule.label));
+                }
+                continue;
+            }
+
+            if !rule.repeatable && values.len() > 1 {
+                issues.push(format!(
+                    "Bag-info field {} is not repeatable but appears {} times",
+                    rule.label,
+                    values.len()
+                ));
+            }
+
+            if let Some(allowed) = &rule.values {
+                for value in values.iter().filter(|v| !allowed.iter().any(|a| a == *v)) {
+                    issues.push(format!(
+                        "Bag-info field {} has value '{}' which is not one of: {}",
+                        rule.label,
+                        value,
+                        allowed.join(", ")
+                    ));
+                }
+            }
+        }
+
+        issues
+    }
+}
+
+/// Fold a tag line at whitespace so no physical line exceeds the limit
+///
+/// Only single spaces are used as break points so that unfolding restores
+/// the original value exactly.
+fn fold_line(line: &str) -> String {
+    if line.chars().count() <= MAX_LINE_LENGTH {
+        return line.to_string();
+    }
+
+    let mut folded = String::new();
+    let mut remaining = line;
+    let mut limit = MAX_LINE_LENGTH;
+
+    while remaining.chars().count() > limit {
+        let bytes = remaining.as_bytes();
+        let break_at = remaining
+            .char_indices()
+            .take(limit + 1)
+            .filter(|(i, c)| {
+                *c == ' '
+                    && *i > 0
+                    && bytes[i - 1] != b' '
+                    && bytes.get(i + 1).is_some_and(|b| *b != b' ')
+            })
+            .map(|(i, _)| i)
+            .last();
+
+        match break_at {
+            Some(index) => {
+                folded.push_str(&remaining[..index]);
+                folded.push_str("\n  ");
+                remaining = &remaining[index + 1..];
+                // Continuation lines carry a two-space indent
+                limit = MAX_LINE_LENGTH - 2;
+            }
+            // A single unbreakable word is written as-is
+            None => break,
+        }
+    }
+
+    folded.push_str(remaining);
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeated_labels_keep_order() {
+        let mut info = BagInfo::new();
+        info.add("Contact-Name", "Ada");
+        info.add("Source-Organization", "Art Dept");
+        info.add("Contact-Name", "Grace");
+
+        assert_eq!(info.get("contact-name"), Some("Ada"));
+        assert_eq!(info.get_all("Contact-Name"), vec!["Ada", "Grace"]);
+
+        info.set("Contact-Name", "Lin");
+        let labels: Vec<_> = info.iter().collect();
+        assert_eq!(labels, vec![("Contact-Name", "Lin"), ("Source-Organization", "Art Dept")]);
+    }
+
+    #[test]
+    fn test_round_trip_with_folded_values() {
+        let long_value = "A long external description that certainly needs to be folded across more than one line of the tag file to stay readable";
+        let mut info = BagInfo::new();
+        info.add("External-Description", long_value);
+        info.add("X-Course", "ART 201");
+        info.add("X-Course", "ART 305");
+
+        let serialized = info.to_tag_file();
+        assert!(serialized.lines().all(|l| l.chars().count() <= MAX_LINE_LENGTH));
+        assert!(serialized.lines().nth(1).unwrap().starts_with("  "));
+
+        let parsed = BagInfo::parse(&serialized).unwrap();
+        assert_eq!(parsed, info);
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_lines() {
+        assert!(BagInfo::parse("  orphan continuation").is_err());
+        assert!(BagInfo::parse("No colon here").is_err());
+    }
+
+    #[test]
+    fn test_check_rules() {
+        let mut info = BagInfo::new();
+        info.add("Contact-Name", "Ada");
+        info.add("Contact-Name", "Grace");
+        info.add("Access", "Restricted");
+
+        let rules = vec![
+            BagInfoRule { label: "Source-Organization".to_string(), required: true, repeatable: false, values: None },
+            BagInfoRule { label: "Contact-Name".to_string(), required: true, repeatable: false, values: None },
+            BagInfoRule {
+                label: "Access".to_string(),
+                required: false,
+                repeatable: false,
+                values: Some(vec!["Open".to_string(), "Embargoed".to_string()]),
+            },
+        ];
+
+        let issues = info.check_rules(&rules);
+        assert_eq!(issues.len(), 3);
+        assert!(issues[0].contains("Source-Organization"));
+        assert!(issues[1].contains("not repeatable"));
+        assert!(issues[2].contains("Restricted"));
+    }
+}
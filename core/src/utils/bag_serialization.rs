@@ -0,0 +1,479 @@
+use crate::utils::bag_info::BagInfo;
+use crate::utils::bagit::{parse_bagit_version, BagItPackage};
+use crate::utils::bagit_profile::BagItProfile;
+use crate::utils::checksums::{calculate_checksums_from_reader, ChecksumAlgorithm};
+use anyhow::Result;
+use camino::{Utf8Path, Utf8PathBuf};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use zip::write::SimpleFileOptions;
+
+/// Every algorithm we can verify, hashed together while streaming entries
+const ALL_ALGORITHMS: [ChecksumAlgorithm; 3] = [
+    ChecksumAlgorithm::Md5,
+    ChecksumAlgorithm::Sha256,
+    ChecksumAlgorithm::Sha512,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SerializationFormat {
+    Tar,
+    TarGz,
+    Zip,
+}
+
+impl SerializationFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SerializationFormat::Tar => "tar",
+            SerializationFormat::TarGz => "tar.gz",
+            SerializationFormat::Zip => "zip",
+        }
+    }
+
+    /// MIME type as used in a BagIt profile's Accept-Serialization list
+    pub fn media_type(&self) -> &'static str {
+        match self {
+            SerializationFormat::Tar => "application/x-tar",
+            SerializationFormat::TarGz => "application/gzip",
+            SerializationFormat::Zip => "application/zip",
+        }
+    }
+
+    /// Detect the format from an archive's file name
+    pub fn from_path(path: &Utf8Path) -> Option<Self> {
+        let name = path.file_name()?.to_ascii_lowercase();
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(SerializationFormat::TarGz)
+        } else if name.ends_with(".tar") {
+            Some(SerializationFormat::Tar)
+        } else if name.ends_with(".zip") {
+            Some(SerializationFormat::Zip)
+        } else {
+            None
+        }
+    }
+}
+
+/// Result of validating a serialized bag in place
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SerializedBagReport {
+    pub format: SerializationFormat,
+    pub top_level_directory: Option<String>,
+    pub payload_file_count: usize,
+    pub payload_bytes: u64,
+    pub issues: Vec<String>,
+}
+
+impl SerializedBagReport {
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Write a bag to a single archive in `output_dir`
+///
+/// The archive holds one top-level directory named after the bag, as the
+/// BagIt spec requires. Files are streamed into the archive one at a time so
+/// no second copy of the bag is needed on disk.
+pub fn serialize_bag(
+    bag: &BagItPackage,
+    format: SerializationFormat,
+    output_dir: &Utf8Path,
+) -> Result<Utf8PathBuf> {
+    let bag_name = bag
+        .bag_root
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("Bag path has no directory name: {}", bag.bag_root))?;
+
+    fs::create_dir_all(output_dir)?;
+    let output_path = output_dir.join(format!("{}.{}", bag_name, format.extension()));
+    let file = BufWriter::new(File::create(&output_path)?);
+
+    match format {
+        SerializationFormat::Tar => {
+            let mut builder = tar::Builder::new(file);
+            builder.append_dir_all(bag_name, &bag.bag_root)?;
+            builder.into_inner()?.flush()?;
+        }
+        SerializationFormat::TarGz => {
+            let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+            builder.append_dir_all(bag_name, &bag.bag_root)?;
+            builder.into_inner()?.finish()?.flush()?;
+        }
+        SerializationFormat::Zip => write_zip(bag, bag_name, file)?,
+    }
+
+    Ok(output_path)
+}
+
+fn write_zip<W: Write + io::Seek>(bag: &BagItPackage, bag_name: &str, writer: W) -> Result<()> {
+    let mut zip = zip::ZipWriter::new(writer);
+    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+    for entry in ::walkdir::WalkDir::new(&bag.bag_root).sort_by_file_name() {
+        let entry = entry?;
+        let entry_path = Utf8Path::from_path(entry.path())
+            .ok_or_else(|| anyhow::anyhow!("Non-UTF8 path encountered"))?;
+        let relative_path = entry_path.strip_prefix(&bag.bag_root)?;
+        let archive_name = if relative_path.as_str().is_empty() {
+            bag_name.to_string()
+        } else {
+            format!("{}/{}", bag_name, relative_path)
+        };
+
+        if entry.file_type().is_dir() {
+            zip.add_directory(archive_name, options)?;
+        } else if entry.file_type().is_file() {
+            // Zip64 headers are only needed for files past the 4 GiB limit
+            let large_file = entry.metadata()?.len() >= u32::MAX as u64;
+            zip.start_file(archive_name, options.large_file(large_file))?;
+            io::copy(&mut File::open(entry_path)?, &mut zip)?;
+        }
+    }
+
+    zip.finish()?;
+    Ok(())
+}
+
+/// Hashes and sizes of every file seen while scanning an archive
+#[derive(Default)]
+struct ArchiveScan {
+    top_level_names: BTreeSet<String>,
+    stray_root_files: Vec<String>,
+    files: HashMap<String, (u64, Vec<(ChecksumAlgorithm, String)>)>,
+    tag_files: HashMap<String, Vec<u8>>,
+}
+
+impl ArchiveScan {
+    fn visit(&mut self, entry_path: &str, is_dir: bool, reader: &mut dyn Read) -> Result<()> {
+        let entry_path = entry_path.trim_start_matches("./").trim_end_matches('/');
+        if entry_path.is_empty() {
+            return Ok(());
+        }
+
+        let (top_level, relative_path) = match entry_path.split_once('/') {
+            Some((top_level, relative_path)) => (top_level, relative_path),
+            None if is_dir => (entry_path, ""),
+            None => {
+                self.stray_root_files.push(entry_path.to_string());
+                return Ok(());
+            }
+        };
+
+        self.top_level_names.insert(top_level.to_string());
+        if is_dir || relative_path.is_empty() {
+            return Ok(());
+        }
+
+        if relative_path.starts_with("data/") {
+            let mut counter = CountingReader { inner: reader, count: 0 };
+            let checksums = calculate_checksums_from_reader(&mut counter, &ALL_ALGORITHMS)?;
+            self.files.insert(relative_path.to_string(), (counter.count, checksums));
+        } else {
+            // Tag files are small and needed in full to read manifests
+            let mut content = Vec::new();
+            reader.read_to_end(&mut content)?;
+            let checksums = calculate_checksums_from_reader(&content[..], &ALL_ALGORITHMS)?;
+            self.files
+                .insert(relative_path.to_string(), (content.len() as u64, checksums));
+            self.tag_files.insert(relative_path.to_string(), content);
+        }
+
+        Ok(())
+    }
+
+    fn tag_file_text(&self, name: &str) -> Option<String> {
+        self.tag_files
+            .get(name)
+            .map(|content| String::from_utf8_lossy(content).to_string())
+    }
+
+    fn manifest_algorithms(&self, prefix: &str) -> Vec<String> {
+        let mut algorithms: Vec<String> = self
+            .tag_files
+            .keys()
+            .filter_map(|name| name.strip_prefix(prefix)?.strip_suffix(".txt"))
+            .filter(|name| !name.contains('/'))
+            .map(str::to_string)
+            .collect();
+        algorithms.sort();
+        algorithms
+    }
+}
+
+struct CountingReader<'a> {
+    inner: &'a mut dyn Read,
+    count: u64,
+}
+
+impl Read for CountingReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let bytes_read = self.inner.read(buf)?;
+        self.count += bytes_read as u64;
+        Ok(bytes_read)
+    }
+}
+
+fn scan_archive(path: &Utf8Path, format: SerializationFormat) -> Result<ArchiveScan> {
+    let mut scan = ArchiveScan::default();
+    let file = BufReader::new(File::open(path)?);
+
+    match format {
+        SerializationFormat::Tar => scan_tar(tar::Archive::new(file), &mut scan)?,
+        SerializationFormat::TarGz => scan_tar(tar::Archive::new(GzDecoder::new(file)), &mut scan)?,
+        SerializationFormat::Zip => {
+            let mut archive = zip::ZipArchive::new(file)?;
+            for index in 0..archive.len() {
+                let mut entry = archive.by_index(index)?;
+                let name = entry.name().to_string();
+                let is_dir = entry.is_dir();
+                scan.visit(&name, is_dir, &mut entry)?;
+            }
+        }
+    }
+
+    Ok(scan)
+}
+
+fn scan_tar<R: Read>(mut archive: tar::Archive<R>, scan: &mut ArchiveScan) -> Result<()> {
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().to_string();
+        let is_dir = entry.header().entry_type().is_dir();
+        scan.visit(&name, is_dir, &mut entry)?;
+    }
+    Ok(())
+}
+
+/// Validate a serialized bag without extracting it
+///
+/// Tar archives are read in a single streaming pass, hashing payload entries
+/// as they go by; zip archives are read entry by entry. Nothing is written
+/// to disk.
+pub fn validate_serialized_bag(
+    path: &Utf8Path,
+    profile: Option<&BagItProfile>,
+) -> Result<SerializedBagReport> {
+    let format = SerializationFormat::from_path(path)
+        .ok_or_else(|| anyhow::anyhow!("Unrecognized bag serialization: {}", path))?;
+    let scan = scan_archive(path, format)?;
+    let mut issues = Vec::new();
+
+    // Structure: exactly one top-level directory
+    if scan.top_level_names.len() != 1 {
+        issues.push(format!(
+            "Serialized bag must contain a single top-level directory (found {})",
+            scan.top_level_names.len()
+        ));
+    }
+    for stray in &scan.stray_root_files {
+        issues.push(format!("File outside the bag directory: {}", stray));
+    }
+
+    // bagit.txt
+    let bagit_version = match scan.tag_file_text("bagit.txt") {
+        Some(content) => {
+            if !content.contains("Tag-File-Character-Encoding: UTF-8") {
+                issues.push("Invalid character encoding declaration in bagit.txt".to_string());
+            }
+            parse_bagit_version(&content)
+        }
+        None => {
+            issues.push("Missing bagit.txt file".to_string());
+            None
+        }
+    };
+
+    // bag-info.txt
+    let bag_info = match scan.tag_file_text("bag-info.txt") {
+        Some(content) => BagInfo::parse(&content).unwrap_or_else(|e| {
+            issues.push(format!("Invalid bag-info.txt: {}", e));
+            BagInfo::new()
+        }),
+        None => BagInfo::new(),
+    };
+
+    // Payload and tag manifests
+    let manifest_algorithms = scan.manifest_algorithms("manifest-");
+    let tag_manifest_algorithms = scan.manifest_algorithms("tagmanifest-");
+    if manifest_algorithms.is_empty() {
+        issues.push("Missing payload manifest file".to_string());
+    }
+
+    let payload_files: Vec<&String> = scan.files.keys().filter(|p| p.starts_with("data/")).collect();
+    let manifests = manifest_algorithms
+        .iter()
+        .map(|name| ("manifest", name))
+        .chain(tag_manifest_algorithms.iter().map(|name| ("tagmanifest", name)));
+
+    for (kind, name) in manifests {
+        let manifest_file = format!("{}-{}.txt", kind, name);
+        let Some(algorithm) = ChecksumAlgorithm::from_bagit_name(name) else {
+            issues.push(format!("Unsupported checksum algorithm in {}", manifest_file));
+            continue;
+        };
+
+        let content = scan.tag_file_text(&manifest_file).unwrap_or_default();
+        let mut listed = BTreeSet::new();
+        for line in content.lines().filter(|line| !line.trim().is_empty()) {
+            let Some((expected_checksum, file_path)) = line.split_once("  ") else {
+                issues.push(format!("Invalid manifest line format: {}", line));
+                continue;
+            };
+            listed.insert(file_path.to_string());
+
+            let Some((_, checksums)) = scan.files.get(file_path) else {
+                issues.push(format!("File missing: {}", file_path));
+                continue;
+            };
+
+            let actual_checksum = checksums
+                .iter()
+                .find(|(a, _)| *a == algorithm)
+                .map(|(_, checksum)| checksum.as_str())
+                .unwrap_or_default();
+            if !actual_checksum.eq_ignore_ascii_case(expected_checksum) {
+                issues.push(format!("Checksum mismatch for file: {}", file_path));
+            }
+        }
+
+        if kind == "manifest" {
+            for payload_file in payload_files.iter().filter(|p| !listed.contains(p.as_str())) {
+                issues.push(format!("Payload file not listed in {}: {}", manifest_file, payload_file));
+            }
+        }
+    }
+
+    // Payload-Oxum
+    let payload_file_count = payload_files.len();
+    let payload_bytes: u64 = payload_files.iter().map(|p| scan.files[p.as_str()].0).sum();
+    if let Some(oxum) = bag_info.get("Payload-Oxum") {
+        let expected = format!("{}.{}", payload_bytes, payload_file_count);
+        if oxum != expected {
+            issues.push(format!("Payload-Oxum mismatch: declared {}, found {}", oxum, expected));
+        }
+    }
+
+    // Profile conformance
+    if let Some(profile) = profile {
+        issues.extend(profile.validate_serialization(Some(format.media_type())));
+        issues.extend(profile.check_declared_contents(
+            bagit_version.as_deref(),
+            &bag_info,
+            &manifest_algorithms,
+            &tag_manifest_algorithms,
+            scan.tag_files.contains_key("fetch.txt"),
+        ));
+    }
+
+    Ok(SerializedBagReport {
+        format,
+        top_level_directory: scan.top_level_names.into_iter().next(),
+        payload_file_count,
+        payload_bytes,
+        issues,
+    })
+}
+
+/// Validate a serialized bag and, if it is valid, extract it under `bags_root`
+pub fn import_serialized_bag(
+    path: &Utf8Path,
+    bags_root: &Utf8Path,
+    profile: Option<&BagItProfile>,
+) -> Result<(SerializedBagReport, Option<BagItPackage>)> {
+    let report = validate_serialized_bag(path, profile)?;
+    if !report.is_valid() {
+        return Ok((report, None));
+    }
+
+    let top_level_directory = report
+        .top_level_directory
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("Serialized bag has no top-level directory"))?;
+    let bag_root = bags_root.join(&top_level_directory);
+    if bag_root.exists() {
+        return Err(anyhow::anyhow!("A bag already exists at {}", bag_root));
+    }
+
+    fs::create_dir_all(bags_root)?;
+    let file = BufReader::new(File::open(path)?);
+
+    // Both extractors refuse entries that would escape the destination
+    match report.format {
+        SerializationFormat::Tar => tar::Archive::new(file).unpack(bags_root)?,
+        SerializationFormat::TarGz => tar::Archive::new(GzDecoder::new(file)).unpack(bags_root)?,
+        SerializationFormat::Zip => zip::ZipArchive::new(file)?.extract(bags_root)?,
+    }
+
+    let bag = BagItPackage::open(bag_root)?;
+    Ok((report, Some(bag)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    async fn make_bag(root: &Utf8Path) -> BagItPackage {
+        let bag = BagItPackage::new(root.join("studio-bag")).unwrap();
+        fs::create_dir_all(bag.data_dir.join("renders")).unwrap();
+        fs::write(bag.data_dir.join("renders/frame-001.png"), b"frame one").unwrap();
+        fs::write(bag.data_dir.join("notes.md"), b"# Notes").unwrap();
+        bag.create_bagit_declaration().unwrap();
+        bag.create_manifest().await.unwrap();
+
+        let (bytes, files) = bag.calculate_payload_oxum().unwrap();
+        let mut bag_info = BagInfo::new();
+        bag_info.add("Payload-Oxum", format!("{}.{}", bytes, files));
+        bag.create_bag_info(&bag_info).unwrap();
+        bag.create_tag_manifest(ChecksumAlgorithm::Sha256).await.unwrap();
+        bag
+    }
+
+    #[tokio::test]
+    async fn test_round_trip_all_formats() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let bag = make_bag(root).await;
+
+        for format in [SerializationFormat::Tar, SerializationFormat::TarGz, SerializationFormat::Zip] {
+            let archive = serialize_bag(&bag, format, &root.join("out")).unwrap();
+            assert_eq!(SerializationFormat::from_path(&archive), Some(format));
+
+            let report = validate_serialized_bag(&archive, None).unwrap();
+            assert!(report.is_valid(), "{:?}: {:?}", format, report.issues);
+            assert_eq!(report.top_level_directory.as_deref(), Some("studio-bag"));
+            assert_eq!(report.payload_file_count, 2);
+
+            let import_root = root.join(format!("import-{}", format.extension()));
+            let (_, imported) = import_serialized_bag(&archive, &import_root, None).unwrap();
+            let imported = imported.unwrap();
+            assert!(imported.validate().await.unwrap().is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_detects_tampered_payload() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let bag = make_bag(root).await;
+        fs::write(bag.data_dir.join("notes.md"), b"# Edited").unwrap();
+        fs::write(bag.data_dir.join("extra.txt"), b"unlisted").unwrap();
+
+        let archive = serialize_bag(&bag, SerializationFormat::Zip, &root.join("out")).unwrap();
+        let report = validate_serialized_bag(&archive, None).unwrap();
+        assert!(report.issues.iter().any(|i| i.contains("Checksum mismatch for file: data/notes.md")));
+        assert!(report.issues.iter().any(|i| i.contains("not listed in manifest-sha256.txt: data/extra.txt")));
+        assert!(report.issues.iter().any(|i| i.starts_with("Payload-Oxum mismatch")));
+
+        let (_, imported) = import_serialized_bag(&archive, &root.join("import"), None).unwrap();
+        assert!(imported.is_none());
+    }
+}
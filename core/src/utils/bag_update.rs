@@ -0,0 +1,408 @@
+use crate::utils::bagit::{read_manifest_file, BagItPackage};
+use crate::utils::checksums::{calculate_checksum, ChecksumAlgorithm};
+use anyhow::Result;
+use camino::{Utf8Path, Utf8PathBuf};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::Write;
+use uuid::Uuid;
+
+/// bag-info.txt label holding the number of updates applied to a bag
+pub const BAG_VERSION_LABEL: &str = "CWPT-Bag-Version";
+
+/// A single change to a bag's payload
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum PayloadChange {
+    Add { source_path: String, payload_path: String },
+    Remove { payload_path: String },
+    Replace { source_path: String, payload_path: String },
+}
+
+/// What an applied update changed
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BagUpdateSummary {
+    pub version: u32,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub replaced: Vec<String>,
+}
+
+/// Apply payload changes to an existing bag
+///
+/// Only added and replaced files are hashed; manifest entries for every other
+/// file are carried over as they are. Payload-Oxum, the bag version and any
+/// tag manifests are brought up to date afterwards.
+pub async fn apply_bag_update(bag: &BagItPackage, changes: &[PayloadChange]) -> Result<BagUpdateSummary> {
+    let planned = plan_changes(bag, changes)?;
+
+    let mut algorithms: Vec<ChecksumAlgorithm> = bag
+        .manifest_algorithms()?
+        .iter()
+        .filter_map(|name| ChecksumAlgorithm::from_bagit_name(name))
+        .collect();
+    if algorithms.is_empty() {
+        algorithms.push(ChecksumAlgorithm::Sha256);
+    }
+
+    let mut manifests = Vec::new();
+    for algorithm in &algorithms {
+        let manifest_path = bag.manifest_path_for(*algorithm);
+        let entries: BTreeMap<String, String> = if manifest_path.exists() {
+            read_manifest_file(&manifest_path)?
+                .into_iter()
+                .map(|(checksum, path)| (path, checksum))
+                .collect()
+        } else {
+            BTreeMap::new()
+        };
+        manifests.push((*algorithm, entries));
+    }
+
+    let staging = bag.bag_root.join(format!(".update-{}", Uuid::new_v4()));
+    fs::create_dir(&staging)?;
+    let applied = apply_payload_changes(bag, planned, &staging, &mut manifests).await;
+    let _ = fs::remove_dir_all(&staging);
+    let mut summary = applied?;
+
+    let mut fetch_entries = bag.read_fetch()?;
+    fetch_entries.retain(|entry| {
+        !summary.removed.contains(&entry.path)
+            && !summary.replaced.contains(&entry.path)
+            && !summary.added.contains(&entry.path)
+    });
+
+    for (algorithm, entries) in &manifests {
+        let mut file = fs::File::create(bag.manifest_path_for(*algorithm))?;
+        for (path, checksum) in entries {
+            writeln!(file, "{}  {}", checksum, path)?;
+        }
+    }
+    bag.write_fetch(&fetch_entries)?;
+
+    bag.refresh_bag_info_totals()?;
+    let mut bag_info = bag.read_bag_info()?;
+    summary.version = bag_info
+        .get(BAG_VERSION_LABEL)
+        .and_then(|v| v.parse::<u32>().ok())
+        .unwrap_or(1)
+        + 1;
+    bag_info.set(BAG_VERSION_LABEL, summary.version.to_string());
+    bag.create_bag_info(&bag_info)?;
+
+    bag.refresh_tag_manifests().await?;
+
+    Ok(summary)
+}
+
+/// Put the planned changes into the payload, or leave it as it was
+///
+/// New files are copied and hashed under `staging` first, so a failed copy
+/// never reaches the payload. Removed and replaced files are moved aside
+/// into `staging` rather than deleted, and every move is undone if a later
+/// one fails.
+async fn apply_payload_changes(
+    bag: &BagItPackage,
+    planned: Vec<PlannedChange>,
+    staging: &Utf8Path,
+    manifests: &mut [(ChecksumAlgorithm, BTreeMap<String, String>)],
+) -> Result<BagUpdateSummary> {
+    let mut staged = Vec::new();
+    for (index, change) in planned.iter().enumerate() {
+        if let PlannedChange::Write { source, .. } = change {
+            let staged_path = staging.join(format!("new-{}", index));
+            fs::copy(source, &staged_path)?;
+            let mut checksums = Vec::new();
+            for (algorithm, _) in manifests.iter() {
+                checksums.push(calculate_checksum(&staged_path, *algorithm).await?);
+            }
+            staged.push((staged_path, checksums));
+        }
+    }
+
+    let mut moves: Vec<(Utf8PathBuf, Utf8PathBuf)> = Vec::new();
+    if let Err(e) = place_staged_files(bag, &planned, &staged, staging, &mut moves) {
+        for (from, to) in moves.iter().rev() {
+            let _ = fs::rename(to, from);
+        }
+        for (from, to) in &moves {
+            if from.starts_with(staging) {
+                let _ = remove_empty_parents(&bag.data_dir, to);
+            }
+        }
+        return Err(e);
+    }
+
+    let mut summary = BagUpdateSummary::default();
+    let mut staged = staged.into_iter();
+    for change in planned {
+        match change {
+            PlannedChange::Remove { payload_path } => {
+                remove_empty_parents(&bag.data_dir, &bag.bag_root.join(&payload_path))?;
+                for (_, entries) in manifests.iter_mut() {
+                    entries.remove(&payload_path);
+                }
+                summary.removed.push(payload_path);
+            }
+            PlannedChange::Write { payload_path, is_replacement, .. } => {
+                let (_, checksums) = staged.next().unwrap_or_default();
+                for ((_, entries), checksum) in manifests.iter_mut().zip(checksums) {
+                    entries.insert(payload_path.clone(), checksum);
+                }
+                if is_replacement {
+                    summary.replaced.push(payload_path);
+                } else {
+                    summary.added.push(payload_path);
+                }
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Move removed files aside and staged files into place, noting each move in `moves`
+fn place_staged_files(
+    bag: &BagItPackage,
+    planned: &[PlannedChange],
+    staged: &[(Utf8PathBuf, Vec<String>)],
+    staging: &Utf8Path,
+    moves: &mut Vec<(Utf8PathBuf, Utf8PathBuf)>,
+) -> Result<()> {
+    let mut staged = staged.iter();
+    for (index, change) in planned.iter().enumerate() {
+        let payload_path = match change {
+            PlannedChange::Remove { payload_path } | PlannedChange::Write { payload_path, .. } => payload_path,
+        };
+        let file_path = bag.bag_root.join(payload_path);
+        if file_path.exists() {
+            let set_aside = staging.join(format!("old-{}", index));
+            fs::rename(&file_path, &set_aside)?;
+            moves.push((file_path.clone(), set_aside));
+        }
+
+        if let PlannedChange::Write { .. } = change {
+            let Some((staged_path, _)) = staged.next() else {
+                return Err(anyhow::anyhow!("No staged copy for {}", payload_path));
+            };
+            if let Some(parent) = file_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::rename(staged_path, &file_path)?;
+            moves.push((staged_path.clone(), file_path));
+        }
+    }
+    Ok(())
+}
+
+enum PlannedChange {
+    Write {
+        source: Utf8PathBuf,
+        payload_path: String,
+        is_replacement: bool,
+    },
+    Remove {
+        payload_path: String,
+    },
+}
+
+/// Check every change before touching the bag so a bad request leaves it intact
+fn plan_changes(bag: &BagItPackage, changes: &[PayloadChange]) -> Result<Vec<PlannedChange>> {
+    let unfetched: HashSet<String> = bag.unfetched_entries()?.into_iter().map(|e| e.path).collect();
+    let exists = |payload_path: &str| {
+        bag.bag_root.join(payload_path).is_file() || unfetched.contains(payload_path)
+    };
+
+    let mut seen = HashSet::new();
+    let mut planned = Vec::new();
+
+    for change in changes {
+        let (payload_path, source) = match change {
+            PayloadChange::Add { source_path, payload_path }
+            | PayloadChange::Replace { source_path, payload_path } => {
+                (normalize_payload_path(payload_path)?, Some(Utf8PathBuf::from(source_path)))
+            }
+            PayloadChange::Remove { payload_path } => (normalize_payload_path(payload_path)?, None),
+        };
+
+        if !seen.insert(payload_path.clone()) {
+            return Err(anyhow::anyhow!("Payload path changed more than once: {}", payload_path));
+        }
+
+        if let Some(source) = &source {
+            if !source.is_file() {
+                return Err(anyhow::anyhow!("Source file does not exist: {}", source));
+            }
+        }
+
+        planned.push(match change {
+            PayloadChange::Add { .. } => {
+                if exists(&payload_path) {
+                    return Err(anyhow::anyhow!("Payload file already exists: {}", payload_path));
+                }
+                PlannedChange::Write {
+                    source: source.unwrap_or_default(),
+                    payload_path,
+                    is_replacement: false,
+                }
+            }
+            PayloadChange::Replace { .. } => {
+                if !exists(&payload_path) {
+                    return Err(anyhow::anyhow!("Payload file does not exist: {}", payload_path));
+                }
+                PlannedChange::Write {
+                    source: source.unwrap_or_default(),
+                    payload_path,
+                    is_replacement: true,
+                }
+            }
+            PayloadChange::Remove { .. } => {
+                if !exists(&payload_path) {
+                    return Err(anyhow::anyhow!("Payload file does not exist: {}", payload_path));
+                }
+                PlannedChange::Remove { payload_path }
+            }
+        });
+    }
+
+    Ok(planned)
+}
+
+/// Turn a user-supplied path into a "data/..." path that stays inside the payload
+fn normalize_payload_path(payload_path: &str) -> Result<String> {
+    let trimmed = payload_path.trim_start_matches('/');
+    let payload_path = if trimmed.starts_with("data/") {
+        trimmed.to_string()
+    } else {
+        format!("data/{}", trimmed)
+    };
+
+    if payload_path == "data/"
+        || payload_path.ends_with('/')
+        || payload_path.split('/').any(|part| part == ".." || part == "." || part.is_empty())
+    {
+        return Err(anyhow::anyhow!("Invalid payload path: {}", payload_path));
+    }
+
+    Ok(payload_path)
+}
+
+fn remove_empty_parents(data_dir: &Utf8Path, file_path: &Utf8Path) -> Result<()> {
+    let mut current = file_path.parent();
+    while let Some(dir) = current {
+        if dir == data_dir || !dir.starts_with(data_dir) || fs::read_dir(dir)?.next().is_some() {
+            break;
+        }
+        fs::remove_dir(dir)?;
+        current = dir.parent();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::bag_info::BagInfo;
+    use tempfile::TempDir;
+
+    async fn make_bag(root: &Utf8Path) -> BagItPackage {
+        let bag = BagItPackage::new(root.join("bag")).unwrap();
+        fs::create_dir_all(bag.data_dir.join("drafts")).unwrap();
+        fs::write(bag.data_dir.join("drafts/draft-1.psd"), b"first draft").unwrap();
+        fs::write(bag.data_dir.join("sketch.png"), b"sketch").unwrap();
+        bag.create_bagit_declaration().unwrap();
+        bag.create_manifest().await.unwrap();
+        bag.create_bag_info(&BagInfo::new()).unwrap();
+        bag.refresh_bag_info_totals().unwrap();
+        bag.create_tag_manifest(ChecksumAlgorithm::Sha256).await.unwrap();
+        bag
+    }
+
+    #[tokio::test]
+    async fn test_add_remove_replace() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let bag = make_bag(root).await;
+        fs::write(root.join("final.png"), b"final artwork").unwrap();
+        fs::write(root.join("sketch-v2.png"), b"better sketch").unwrap();
+
+        let changes = vec![
+            PayloadChange::Add {
+                source_path: root.join("final.png").to_string(),
+                payload_path: "final/final.png".to_string(),
+            },
+            PayloadChange::Remove { payload_path: "data/drafts/draft-1.psd".to_string() },
+            PayloadChange::Replace {
+                source_path: root.join("sketch-v2.png").to_string(),
+                payload_path: "sketch.png".to_string(),
+            },
+        ];
+
+        let summary = apply_bag_update(&bag, &changes).await.unwrap();
+        assert_eq!(summary.version, 2);
+        assert_eq!(summary.added, vec!["data/final/final.png".to_string()]);
+        assert_eq!(summary.removed, vec!["data/drafts/draft-1.psd".to_string()]);
+        assert_eq!(summary.replaced, vec!["data/sketch.png".to_string()]);
+        assert!(!bag.data_dir.join("drafts").exists());
+
+        assert!(bag.validate().await.unwrap().is_empty());
+        let bag_info = bag.read_bag_info().unwrap();
+        assert_eq!(bag_info.get("Payload-Oxum"), Some("26.2"));
+        assert_eq!(bag_info.get(BAG_VERSION_LABEL), Some("2"));
+    }
+
+    #[tokio::test]
+    async fn test_failed_update_rolls_back_applied_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let bag = make_bag(root).await;
+        let manifest_before = fs::read_to_string(&bag.manifest_path).unwrap();
+        fs::write(root.join("final.png"), b"final artwork").unwrap();
+        fs::write(root.join("sketch-v2.png"), b"better sketch").unwrap();
+
+        // The last change can only fail once it is placed, since sketch.png is a file
+        let changes = vec![
+            PayloadChange::Remove { payload_path: "drafts/draft-1.psd".to_string() },
+            PayloadChange::Replace {
+                source_path: root.join("sketch-v2.png").to_string(),
+                payload_path: "sketch.png".to_string(),
+            },
+            PayloadChange::Add {
+                source_path: root.join("final.png").to_string(),
+                payload_path: "final/final.png".to_string(),
+            },
+            PayloadChange::Add {
+                source_path: root.join("final.png").to_string(),
+                payload_path: "sketch.png/final.png".to_string(),
+            },
+        ];
+        assert!(apply_bag_update(&bag, &changes).await.is_err());
+
+        assert_eq!(fs::read(bag.data_dir.join("drafts/draft-1.psd")).unwrap(), b"first draft");
+        assert_eq!(fs::read(bag.data_dir.join("sketch.png")).unwrap(), b"sketch");
+        assert!(!bag.data_dir.join("final").exists());
+        assert_eq!(fs::read_to_string(&bag.manifest_path).unwrap(), manifest_before);
+        assert_eq!(fs::read_dir(&bag.bag_root).unwrap().count(), 5);
+        assert!(bag.validate().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_update_leaves_bag_untouched() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let bag = make_bag(root).await;
+        let manifest_before = fs::read_to_string(&bag.manifest_path).unwrap();
+
+        let changes = vec![
+            PayloadChange::Remove { payload_path: "sketch.png".to_string() },
+            PayloadChange::Remove { payload_path: "missing.png".to_string() },
+        ];
+        assert!(apply_bag_update(&bag, &changes).await.is_err());
+        assert!(bag.data_dir.join("sketch.png").exists());
+        assert_eq!(fs::read_to_string(&bag.manifest_path).unwrap(), manifest_before);
+
+        assert!(normalize_payload_path("../escape.txt").is_err());
+        assert!(normalize_payload_path("data/").is_err());
+    }
+}
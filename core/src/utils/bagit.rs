@@ -0,0 +1,691 @@
+use crate::models::preservation::ArchivedProject;
+use crate::utils::bag_info::BagInfo;
+use crate::utils::checksums::{calculate_checksum, ChecksumAlgorithm};
+use crate::utils::fetch::{format_fetch, parse_fetch, FetchEntry};
+use crate::utils::file_operations::{sanitize_directory_name, FileInfo};
+use crate::utils::storage::{join_key, StorageBackend};
+use anyhow::Result;
+use camino::{Utf8Component, Utf8Path, Utf8PathBuf};
+use chrono::Utc;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+
+/// Directory bags are written to until the vault location is configurable
+pub const DEFAULT_BAGS_ROOT: &str = "/tmp/cwpt-bags";
+
+pub struct BagItPackage {
+    pub bag_root: Utf8PathBuf,
+    pub data_dir: Utf8PathBuf,
+    pub manifest_path: Utf8PathBuf,
+    pub bag_info_path: Utf8PathBuf,
+    pub bagit_txt_path: Utf8PathBuf,
+    pub fetch_txt_path: Utf8PathBuf,
+}
+
+impl BagItPackage {
+    /// Create a new BagIt package structure
+    pub fn new(bag_root: Utf8PathBuf) -> Result<Self> {
+        let data_dir = bag_root.join("data");
+        let manifest_path = bag_root.join("manifest-sha256.txt");
+        let bag_info_path = bag_root.join("bag-info.txt");
+        let bagit_txt_path = bag_root.join("bagit.txt");
+        let fetch_txt_path = bag_root.join("fetch.txt");
+
+        // Create the bag directory structure
+        fs::create_dir_all(&bag_root)?;
+        fs::create_dir_all(&data_dir)?;
+
+        Ok(BagItPackage {
+            bag_root,
+            data_dir,
+            manifest_path,
+            bag_info_path,
+            bagit_txt_path,
+            fetch_txt_path,
+        })
+    }
+
+    /// Open an existing BagIt package without creating any directories
+    pub fn open(bag_root: Utf8PathBuf) -> Result<Self> {
+        if !bag_root.is_dir() {
+            return Err(anyhow::anyhow!("Bag directory does not exist: {}", bag_root));
+        }
+
+        Ok(BagItPackage {
+            data_dir: bag_root.join("data"),
+            manifest_path: bag_root.join("manifest-sha256.txt"),
+            bag_info_path: bag_root.join("bag-info.txt"),
+            bagit_txt_path: bag_root.join("bagit.txt"),
+            fetch_txt_path: bag_root.join("fetch.txt"),
+            bag_root,
+        })
+    }
+
+    /// Create the bagit.txt declaration file
+    pub fn create_bagit_declaration(&self) -> Result<()> {
+        let mut file = fs::File::create(&self.bagit_txt_path)?;
+        writeln!(file, "BagIt-Version: 1.0")?;
+        writeln!(file, "Tag-File-Character-Encoding: UTF-8")?;
+        Ok(())
+    }
+
+    /// Copy files to the bag's data directory
+    pub fn add_files(&self, files: &[FileInfo], source_root: &Utf8Path) -> Result<()> {
+        for file_info in files {
+            if file_info.is_directory {
+                // Create directory structure
+                let relative_path = file_info.path.strip_prefix(source_root)?;
+                let dest_path = self.data_dir.join(relative_path);
+                fs::create_dir_all(&dest_path)?;
+            } else {
+                // Copy file
+                let relative_path = file_info.path.strip_prefix(source_root)?;
+                let dest_path = self.data_dir.join(relative_path);
+
+                // Create parent directory if needed
+                if let Some(parent) = dest_path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+
+                fs::copy(&file_info.path, &dest_path)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Generate the manifest file with SHA-256 checksums
+    pub async fn create_manifest(&self) -> Result<()> {
+        self.create_manifest_with(ChecksumAlgorithm::Sha256).await
+    }
+
+    /// Generate a payload manifest using the given checksum algorithm
+    pub async fn create_manifest_with(&self, algorithm: ChecksumAlgorithm) -> Result<()> {
+        let mut manifest_entries = Vec::new();
+
+        // Walk through all files in the data directory
+        for entry in ::walkdir::WalkDir::new(&self.data_dir) {
+            let entry = entry?;
+            if entry.file_type().is_file() {
+                let file_path = Utf8Path::from_path(entry.path())
+                    .ok_or_else(|| anyhow::anyhow!("Non-UTF8 path encountered"))?;
+
+                let checksum = calculate_checksum(file_path, algorithm).await?;
+
+                // Get relative path from bag root (include "data/" prefix)
+                let relative_path = file_path.strip_prefix(&self.bag_root)?;
+
+                manifest_entries.push(format!("{}  {}", checksum, relative_path));
+            }
+        }
+
+        // Keep existing entries for files listed in fetch.txt that have not
+        // been fetched yet, since they cannot be re-hashed
+        let manifest_path = self.manifest_path_for(algorithm);
+        let unfetched = self.unfetched_entries()?;
+        if !unfetched.is_empty() && manifest_path.exists() {
+            for (checksum, path) in read_manifest_file(&manifest_path)? {
+                if unfetched.iter().any(|e| e.path == path) {
+                    manifest_entries.push(format!("{}  {}", checksum, path));
+                }
+            }
+        }
+
+        // Sort entries for consistent output
+        manifest_entries.sort();
+
+        // Write manifest file
+        let mut file = fs::File::create(&manifest_path)?;
+        for entry in manifest_entries {
+            writeln!(file, "{}", entry)?;
+        }
+
+        Ok(())
+    }
+
+    /// Generate a tag manifest covering every tag file in the bag root
+    ///
+    /// Call this last: it hashes bagit.txt, bag-info.txt and the payload
+    /// manifests as they currently exist on disk.
+    pub async fn create_tag_manifest(&self, algorithm: ChecksumAlgorithm) -> Result<()> {
+        let mut manifest_entries = Vec::new();
+
+        for entry in fs::read_dir(&self.bag_root)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_file() {
+                continue;
+            }
+
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            if file_name.starts_with("tagmanifest-") {
+                continue;
+            }
+
+            let checksum = calculate_checksum(entry.path(), algorithm).await?;
+            manifest_entries.push(format!("{}  {}", checksum, file_name));
+        }
+
+        manifest_entries.sort();
+
+        let mut file = fs::File::create(self.tag_manifest_path_for(algorithm))?;
+        for entry in manifest_entries {
+            writeln!(file, "{}", entry)?;
+        }
+
+        Ok(())
+    }
+
+    /// Regenerate every tag manifest already present in the bag
+    ///
+    /// Call after changing bag-info.txt, fetch.txt or a payload manifest.
+    pub async fn refresh_tag_manifests(&self) -> Result<()> {
+        for name in self.tag_manifest_algorithms()? {
+            if let Some(algorithm) = ChecksumAlgorithm::from_bagit_name(&name) {
+                self.create_tag_manifest(algorithm).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Path of the payload manifest for an algorithm
+    pub fn manifest_path_for(&self, algorithm: ChecksumAlgorithm) -> Utf8PathBuf {
+        self.bag_root.join(format!("manifest-{}.txt", algorithm.bagit_name()))
+    }
+
+    /// Path of the tag manifest for an algorithm
+    pub fn tag_manifest_path_for(&self, algorithm: ChecksumAlgorithm) -> Utf8PathBuf {
+        self.bag_root.join(format!("tagmanifest-{}.txt", algorithm.bagit_name()))
+    }
+
+    /// Algorithm names of the payload manifests present in the bag
+    pub fn manifest_algorithms(&self) -> Result<Vec<String>> {
+        self.list_manifest_algorithms("manifest-")
+    }
+
+    /// Algorithm names of the tag manifests present in the bag
+    pub fn tag_manifest_algorithms(&self) -> Result<Vec<String>> {
+        self.list_manifest_algorithms("tagmanifest-")
+    }
+
+    fn list_manifest_algorithms(&self, prefix: &str) -> Result<Vec<String>> {
+        let mut algorithms = Vec::new();
+        if !self.bag_root.is_dir() {
+            return Ok(algorithms);
+        }
+
+        for entry in fs::read_dir(&self.bag_root)? {
+            let file_name = entry?.file_name().to_string_lossy().to_string();
+            if let Some(name) = file_name
+                .strip_prefix(prefix)
+                .and_then(|rest| rest.strip_suffix(".txt"))
+            {
+                algorithms.push(name.to_string());
+            }
+        }
+        algorithms.sort();
+        Ok(algorithms)
+    }
+
+    /// Create the bag-info.txt metadata file
+    pub fn create_bag_info(&self, bag_info: &BagInfo) -> Result<()> {
+        fs::write(&self.bag_info_path, bag_info.to_tag_file())?;
+        Ok(())
+    }
+
+    /// Read the manifest as (checksum, relative path) pairs
+    ///
+    /// The SHA-256 manifest is preferred; a bag made to a profile that does
+    /// not allow it is read from the first manifest it has instead.
+    pub fn read_manifest(&self) -> Result<Vec<(String, String)>> {
+        if self.manifest_path.exists() {
+            return read_manifest_file(&self.manifest_path);
+        }
+
+        let algorithm = self
+            .manifest_algorithms()?
+            .iter()
+            .find_map(|name| ChecksumAlgorithm::from_bagit_name(name))
+            .ok_or_else(|| anyhow::anyhow!("Bag has no payload manifest"))?;
+        read_manifest_file(&self.manifest_path_for(algorithm))
+    }
+
+    /// Read and parse the bag-info.txt file
+    pub fn read_bag_info(&self) -> Result<BagInfo> {
+        let content = fs::read_to_string(&self.bag_info_path)?;
+        BagInfo::parse(&content)
+    }
+
+    /// Recalculate Payload-Oxum and Bag-Size and rewrite them in bag-info.txt
+    pub fn refresh_bag_info_totals(&self) -> Result<()> {
+        let mut bag_info = if self.bag_info_path.exists() {
+            self.read_bag_info()?
+        } else {
+            BagInfo::new()
+        };
+
+        let (payload_bytes, payload_files) = self.calculate_payload_oxum()?;
+        bag_info.set("Payload-Oxum", format!("{}.{}", payload_bytes, payload_files));
+        if bag_info.contains("Bag-Size") {
+            bag_info.set("Bag-Size", self.format_bag_size()?);
+        }
+
+        self.create_bag_info(&bag_info)
+    }
+
+    /// Read fetch.txt, returning no entries when the bag has none
+    pub fn read_fetch(&self) -> Result<Vec<FetchEntry>> {
+        if !self.fetch_txt_path.exists() {
+            return Ok(Vec::new());
+        }
+        parse_fetch(&fs::read_to_string(&self.fetch_txt_path)?)
+    }
+
+    /// Write fetch.txt, removing it when there are no entries
+    pub fn write_fetch(&self, entries: &[FetchEntry]) -> Result<()> {
+        if entries.is_empty() {
+            if self.fetch_txt_path.exists() {
+                fs::remove_file(&self.fetch_txt_path)?;
+            }
+            return Ok(());
+        }
+        fs::write(&self.fetch_txt_path, format_fetch(entries))?;
+        Ok(())
+    }
+
+    /// Entries in fetch.txt whose files are not yet in the payload
+    pub fn unfetched_entries(&self) -> Result<Vec<FetchEntry>> {
+        Ok(self
+            .read_fetch()?
+            .into_iter()
+            .filter(|entry| !self.bag_root.join(&entry.path).exists())
+            .collect())
+    }
+
+    /// Calculate payload oxum (byte count and file count)
+    ///
+    /// Files listed in fetch.txt but not yet fetched are counted using their
+    /// declared length, so the oxum describes the complete bag.
+    pub fn calculate_payload_oxum(&self) -> Result<(u64, usize)> {
+        let mut total_bytes = 0u64;
+        let mut file_count = 0usize;
+
+        for entry in ::walkdir::WalkDir::new(&self.data_dir) {
+            let entry = entry?;
+            if entry.file_type().is_file() {
+                let metadata = entry.metadata()?;
+                total_bytes += metadata.len();
+                file_count += 1;
+            }
+        }
+
+        for entry in self.unfetched_entries()? {
+            total_bytes += entry.length.unwrap_or(0);
+            file_count += 1;
+        }
+
+        Ok((total_bytes, file_count))
+    }
+
+    /// Format bag size in human-readable format
+    pub fn format_bag_size(&self) -> Result<String> {
+        let total_size = self.calculate_bag_directory_size()?;
+        format_bytes(total_size)
+    }
+
+    /// Calculate total size of the entire bag directory
+    pub fn calculate_bag_directory_size(&self) -> Result<u64> {
+        let mut total_size = 0u64;
+
+        for entry in ::walkdir::WalkDir::new(&self.bag_root) {
+            let entry = entry?;
+            if entry.file_type().is_file() {
+                let metadata = entry.metadata()?;
+                total_size += metadata.len();
+            }
+        }
+
+        Ok(total_size)
+    }
+
+    /// Validate the bag structure and checksums
+    pub async fn validate(&self) -> Result<Vec<String>> {
+        let mut issues = Vec::new();
+
+        // Check required files exist
+        if !self.bagit_txt_path.exists() {
+            issues.push("Missing bagit.txt file".to_string());
+        }
+
+        let manifest_algorithms = self.manifest_algorithms()?;
+        if manifest_algorithms.is_empty() {
+            issues.push("Missing payload manifest file".to_string());
+        }
+
+        if !self.data_dir.exists() {
+            issues.push("Missing data directory".to_string());
+        }
+
+        // Validate bagit.txt content
+        if self.bagit_txt_path.exists() {
+            let content = fs::read_to_string(&self.bagit_txt_path)?;
+            if !content.contains("BagIt-Version: 1.0") {
+                issues.push("Invalid BagIt version in bagit.txt".to_string());
+            }
+            if !content.contains("Tag-File-Character-Encoding: UTF-8") {
+                issues.push("Invalid character encoding declaration in bagit.txt".to_string());
+            }
+        }
+
+        // Validate bag-info.txt syntax when present
+        if self.bag_info_path.exists() {
+            if let Err(e) = self.read_bag_info() {
+                issues.push(format!("Invalid bag-info.txt: {}", e));
+            }
+        }
+
+        // Validate fetch.txt; its files count as present-but-unfetched below
+        let fetch_entries = match self.read_fetch() {
+            Ok(entries) => entries,
+            Err(e) => {
+                issues.push(format!("Invalid fetch.txt: {}", e));
+                Vec::new()
+            }
+        };
+
+        // Validate payload and tag manifest checksums
+        let tag_manifest_algorithms = self.tag_manifest_algorithms()?;
+        let manifests = manifest_algorithms
+            .iter()
+            .map(|name| ("manifest", name))
+            .chain(tag_manifest_algorithms.iter().map(|name| ("tagmanifest", name)));
+
+        for (kind, name) in manifests {
+            let manifest_file = format!("{}-{}.txt", kind, name);
+            let Some(algorithm) = ChecksumAlgorithm::from_bagit_name(name) else {
+                issues.push(format!("Unsupported checksum algorithm in {}", manifest_file));
+                continue;
+            };
+
+            let manifest_content = fs::read_to_string(self.bag_root.join(&manifest_file))?;
+            for line in manifest_content.lines() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+
+                let parts: Vec<&str> = line.splitn(2, "  ").collect();
+                if parts.len() != 2 {
+                    issues.push(format!("Invalid manifest line format: {}", line));
+                    continue;
+                }
+
+                let expected_checksum = parts[0];
+                let file_path = self.bag_root.join(parts[1]);
+
+                if !file_path.exists() {
+                    if kind == "manifest" && fetch_entries.iter().any(|e| e.path == parts[1]) {
+                        issues.push(format!("File not yet fetched: {}", parts[1]));
+                    } else {
+                        issues.push(format!("File missing: {}", parts[1]));
+                    }
+                    continue;
+                }
+
+                let actual_checksum = calculate_checksum(&file_path, algorithm).await?;
+                if !actual_checksum.eq_ignore_ascii_case(expected_checksum) {
+                    issues.push(format!("Checksum mismatch for file: {}", parts[1]));
+                }
+            }
+        }
+
+        Ok(issues)
+    }
+
+    /// Bag-relative paths of every file, payload first and bagit.txt last
+    pub fn file_paths(&self) -> Result<Vec<String>> {
+        let mut paths = Vec::new();
+        for entry in ::walkdir::WalkDir::new(&self.bag_root).sort_by_file_name() {
+            let entry = entry?;
+            if entry.file_type().is_file() {
+                paths.push(entry.path().strip_prefix(&self.bag_root)?.to_string_lossy().replace('\\', "/"));
+            }
+        }
+        paths.sort_by_key(|path| (path == "bagit.txt", !path.starts_with("data/")));
+        Ok(paths)
+    }
+
+    /// Copy the bag to a storage backend under `prefix`
+    ///
+    /// Payload files go first and bagit.txt last, so an interrupted upload
+    /// never looks like a complete bag. Objects from an earlier copy that are
+    /// no longer part of the bag are removed. Returns the number of files stored.
+    pub async fn store(&self, storage: &dyn StorageBackend, prefix: &str) -> Result<usize> {
+        let paths = self.file_paths()?;
+        self.store_files(storage, prefix, &paths).await?;
+        for stale in self.stored_differences(storage, prefix).await?.unexpected {
+            storage.delete(&join_key(prefix, &stale)).await?;
+        }
+        Ok(paths.len())
+    }
+
+    /// Copy some of the bag's files to a storage backend under `prefix`
+    pub async fn store_files(&self, storage: &dyn StorageBackend, prefix: &str, paths: &[String]) -> Result<()> {
+        for path in paths {
+            storage.put_file(&join_key(prefix, path), &self.bag_root.join(path)).await?;
+        }
+        Ok(())
+    }
+
+    /// Compare the bag with its copy under `prefix` by file sizes
+    pub async fn stored_differences(&self, storage: &dyn StorageBackend, prefix: &str) -> Result<StoredDifferences> {
+        let stored: HashMap<String, u64> = storage
+            .list(&format!("{}/", prefix.trim_end_matches('/')))
+            .await?
+            .into_iter()
+            .filter_map(|object| {
+                let relative = object.key.strip_prefix(prefix)?.trim_start_matches('/');
+                Some((relative.to_string(), object.size))
+            })
+            .collect();
+
+        let mut differences = StoredDifferences::default();
+        let paths = self.file_paths()?;
+        for path in &paths {
+            match stored.get(path) {
+                None => differences.missing.push(path.clone()),
+                Some(size) if *size != fs::metadata(self.bag_root.join(path))?.len() => {
+                    differences.mismatched.push(path.clone())
+                }
+                Some(_) => {}
+            }
+        }
+        differences.unexpected = stored.into_keys().filter(|key| !paths.contains(key)).collect();
+        differences.unexpected.sort();
+        Ok(differences)
+    }
+
+    /// Download a bag stored under `prefix` into `bag_root`
+    pub async fn retrieve(storage: &dyn StorageBackend, prefix: &str, bag_root: Utf8PathBuf) -> Result<Self> {
+        let objects = storage.list(&format!("{}/", prefix.trim_end_matches('/'))).await?;
+        if objects.is_empty() {
+            return Err(anyhow::anyhow!("No bag stored at {}/{}", storage.location(), prefix));
+        }
+        for object in objects {
+            let relative = object.key.strip_prefix(prefix).unwrap_or(&object.key).trim_start_matches('/');
+            if Utf8Path::new(relative).components().any(|c| !matches!(c, Utf8Component::Normal(_))) {
+                return Err(anyhow::anyhow!("Invalid path in stored bag: {}", object.key));
+            }
+            storage.get_file(&object.key, &bag_root.join(relative)).await?;
+        }
+        Self::open(bag_root)
+    }
+}
+
+/// How a stored copy of a bag differs from the bag, by bag-relative path
+#[derive(Debug, Default, PartialEq)]
+pub struct StoredDifferences {
+    pub missing: Vec<String>,
+    pub mismatched: Vec<String>,
+    /// Stored files of the right size whose etag shows different content
+    pub changed: Vec<String>,
+    /// Stored files the bag does not have
+    pub unexpected: Vec<String>,
+}
+
+impl StoredDifferences {
+    pub fn is_empty(&self) -> bool {
+        [&self.missing, &self.mismatched, &self.changed, &self.unexpected]
+            .iter()
+            .all(|paths| paths.is_empty())
+    }
+
+    /// One line per difference, in the style of [`BagItPackage::validate`]
+    pub fn describe(&self) -> Vec<String> {
+        let missing = self.missing.iter().map(|path| format!("File missing from storage: {}", path));
+        let mismatched = self.mismatched.iter().map(|path| format!("Size mismatch for stored file: {}", path));
+        let changed = self.changed.iter().map(|path| format!("Stored file changed since upload: {}", path));
+        let unexpected = self.unexpected.iter().map(|path| format!("Stored file not in bag: {}", path));
+        missing.chain(mismatched).chain(changed).chain(unexpected).collect()
+    }
+}
+
+/// Read a manifest file as (checksum, relative path) pairs
+pub fn read_manifest_file(manifest_path: &Utf8Path) -> Result<Vec<(String, String)>> {
+    let manifest_content = fs::read_to_string(manifest_path)?;
+    let mut entries = Vec::new();
+
+    for line in manifest_content.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let parts: Vec<&str> = line.splitn(2, "  ").collect();
+        if parts.len() != 2 {
+            return Err(anyhow::anyhow!("Invalid manifest line format: {}", line));
+        }
+
+        entries.push((parts[0].to_string(), parts[1].to_string()));
+    }
+
+    Ok(entries)
+}
+
+/// Extract the BagIt-Version value from bagit.txt content
+pub fn parse_bagit_version(bagit_txt: &str) -> Option<String> {
+    bagit_txt
+        .lines()
+        .find_map(|line| line.strip_prefix("BagIt-Version:"))
+        .map(|version| version.trim().to_string())
+}
+
+/// Get the bag directory for a project under the given bags root
+pub fn project_bag_root(bags_root: &Utf8Path, project_name: &str, project_id: &str) -> Utf8PathBuf {
+    let short_id = &project_id[..project_id.len().min(8)];
+    bags_root.join(format!("{}-{}", sanitize_directory_name(project_name), short_id))
+}
+
+/// The bag-info.txt fields CWPT writes for every project bag
+///
+/// Payload totals are taken from the bag as it stands, so the payload must be
+/// in place before this is called.
+pub fn project_bag_info(project: &ArchivedProject, bag: &BagItPackage) -> Result<BagInfo> {
+    let (payload_bytes, payload_files) = bag.calculate_payload_oxum()?;
+    let bag_size = bag.format_bag_size()?;
+
+    let mut bag_info = BagInfo::new();
+    bag_info.add("Bag-Software-Agent", "Creative Work Preservation Toolkit v0.1.0");
+    bag_info.add("Bagging-Date", Utc::now().format("%Y-%m-%d").to_string());
+    bag_info.add("Payload-Oxum", format!("{}.{}", payload_bytes, payload_files));
+    bag_info.add("Bag-Size", bag_size);
+    bag_info.add("Source-Organization", "Creative Work Preservation Toolkit");
+    bag_info.add(
+        "External-Description",
+        project.description.clone().unwrap_or_else(|| format!("Archived project: {}", project.name)),
+    );
+    bag_info.add("Internal-Sender-Identifier", project.id.clone());
+    bag_info.add(
+        "Internal-Sender-Description",
+        format!("Creative work archived via CWPT on {}", project.archived_at.format("%Y-%m-%d")),
+    );
+    Ok(bag_info)
+}
+
+/// Format bytes in human-readable format
+fn format_bytes(bytes: u64) -> Result<String> {
+    const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
+    const THRESHOLD: f64 = 1024.0;
+
+    if bytes == 0 {
+        return Ok("0 B".to_string());
+    }
+
+    let bytes_f = bytes as f64;
+    let unit_index = (bytes_f.log(THRESHOLD).floor() as usize).min(UNITS.len() - 1);
+    let value = bytes_f / THRESHOLD.powi(unit_index as i32);
+
+    if unit_index == 0 {
+        Ok(format!("{} {}", bytes, UNITS[unit_index]))
+    } else {
+        Ok(format!("{:.1} {}", value, UNITS[unit_index]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(0).unwrap(), "0 B");
+        assert_eq!(format_bytes(512).unwrap(), "512 B");
+        assert_eq!(format_bytes(1024).unwrap(), "1.0 KB");
+        assert_eq!(format_bytes(1536).unwrap(), "1.5 KB");
+        assert_eq!(format_bytes(1048576).unwrap(), "1.0 MB");
+        assert_eq!(format_bytes(1073741824).unwrap(), "1.0 GB");
+    }
+
+    #[tokio::test]
+    async fn test_bagit_creation() {
+        let temp_dir = TempDir::new().unwrap();
+        let bag_path = Utf8PathBuf::from_path_buf(temp_dir.path().join("test-bag")).unwrap();
+
+        let bag = BagItPackage::new(bag_path).unwrap();
+
+        // Test bagit.txt creation
+        bag.create_bagit_declaration().unwrap();
+        assert!(bag.bagit_txt_path.exists());
+
+        let content = fs::read_to_string(&bag.bagit_txt_path).unwrap();
+        assert!(content.contains("BagIt-Version: 1.0"));
+        assert!(content.contains("Tag-File-Character-Encoding: UTF-8"));
+    }
+
+    #[tokio::test]
+    async fn test_store_and_retrieve() {
+        use crate::utils::storage::LocalStorage;
+
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let bag = BagItPackage::new(root.join("Zine-1234")).unwrap();
+        bag.create_bagit_declaration().unwrap();
+        fs::write(bag.data_dir.join("cover.txt"), b"cover").unwrap();
+        bag.create_manifest().await.unwrap();
+        assert_eq!(bag.file_paths().unwrap(), vec!["data/cover.txt", "manifest-sha256.txt", "bagit.txt"]);
+
+        fs::create_dir(root.join("bucket")).unwrap();
+        let storage = LocalStorage::new(root.join("bucket"));
+        storage.put_bytes("Zine-1234/data/old.txt", b"old".to_vec()).await.unwrap();
+        assert_eq!(bag.store(&storage, "Zine-1234").await.unwrap(), 3);
+        assert!(bag.stored_differences(&storage, "Zine-1234").await.unwrap().is_empty());
+
+        storage.put_bytes("Zine-1234/data/cover.txt", b"torn".to_vec()).await.unwrap();
+        storage.delete("Zine-1234/bagit.txt").await.unwrap();
+        let differences = bag.stored_differences(&storage, "Zine-1234").await.unwrap();
+        assert_eq!(differences.missing, vec!["bagit.txt"]);
+        assert_eq!(differences.mismatched, vec!["data/cover.txt"]);
+
+        bag.store(&storage, "Zine-1234").await.unwrap();
+        let retrieved = BagItPackage::retrieve(&storage, "Zine-1234", root.join("restored")).await.unwrap();
+        assert!(retrieved.validate().await.unwrap().is_empty());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,484 @@
+use crate::utils::bag_info::{BagInfo, BagInfoRule};
+use crate::utils::bagit::{parse_bagit_version, BagItPackage};
+use crate::utils::checksums::ChecksumAlgorithm;
+use anyhow::Result;
+use camino::Utf8Path;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+
+/// A BagIt Profile (https://bagit-profiles.github.io/bagit-profiles-specification/)
+/// describing what a receiving repository accepts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BagItProfile {
+    #[serde(rename = "BagIt-Profile-Info")]
+    pub info: BagItProfileInfo,
+    #[serde(rename = "Bag-Info", default)]
+    pub bag_info: BTreeMap<String, BagInfoTagSpec>,
+    #[serde(rename = "Manifests-Required", default)]
+    pub manifests_required: Vec<String>,
+    #[serde(rename = "Manifests-Allowed", default)]
+    pub manifests_allowed: Option<Vec<String>>,
+    #[serde(rename = "Allow-Fetch.txt", default = "default_true")]
+    pub allow_fetch_txt: bool,
+    #[serde(rename = "Fetch.txt-Required", default)]
+    pub fetch_txt_required: bool,
+    #[serde(rename = "Data-Empty", default)]
+    pub data_empty: bool,
+    #[serde(rename = "Serialization", default)]
+    pub serialization: SerializationRequirement,
+    #[serde(rename = "Accept-Serialization", default)]
+    pub accept_serialization: Vec<String>,
+    #[serde(rename = "Accept-BagIt-Version", default)]
+    pub accept_bagit_version: Vec<String>,
+    #[serde(rename = "Tag-Manifests-Required", default)]
+    pub tag_manifests_required: Vec<String>,
+    #[serde(rename = "Tag-Manifests-Allowed", default)]
+    pub tag_manifests_allowed: Option<Vec<String>>,
+    #[serde(rename = "Tag-Files-Required", default)]
+    pub tag_files_required: Vec<String>,
+    #[serde(rename = "Tag-Files-Allowed", default)]
+    pub tag_files_allowed: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BagItProfileInfo {
+    #[serde(rename = "BagIt-Profile-Identifier")]
+    pub identifier: String,
+    #[serde(rename = "BagIt-Profile-Version", default)]
+    pub profile_version: Option<String>,
+    #[serde(rename = "Source-Organization", default)]
+    pub source_organization: Option<String>,
+    #[serde(rename = "External-Description", default)]
+    pub external_description: Option<String>,
+    #[serde(rename = "Contact-Name", default)]
+    pub contact_name: Option<String>,
+    #[serde(rename = "Contact-Email", default)]
+    pub contact_email: Option<String>,
+    #[serde(rename = "Version", default)]
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BagInfoTagSpec {
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default)]
+    pub values: Option<Vec<String>>,
+    #[serde(default = "default_true")]
+    pub repeatable: bool,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SerializationRequirement {
+    Forbidden,
+    Required,
+    #[default]
+    Optional,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl BagItProfile {
+    /// Load a profile from a local JSON file
+    pub fn load(path: &Utf8Path) -> Result<Self> {
+        let content = fs::read_to_string(path)?;
+        Self::from_json(&content)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        let profile: BagItProfile = serde_json::from_str(json)?;
+        if profile.info.identifier.trim().is_empty() {
+            return Err(anyhow::anyhow!("BagIt profile is missing BagIt-Profile-Identifier"));
+        }
+        Ok(profile)
+    }
+
+    pub fn identifier(&self) -> &str {
+        &self.info.identifier
+    }
+
+    /// The profile's Bag-Info section as rules for `BagInfo::check_rules`
+    pub fn bag_info_rules(&self) -> Vec<BagInfoRule> {
+        self.bag_info
+            .iter()
+            .map(|(label, spec)| BagInfoRule {
+                label: label.clone(),
+                required: spec.required,
+                repeatable: spec.repeatable,
+                values: spec.values.clone(),
+            })
+            .collect()
+    }
+
+    /// Payload manifest algorithms a conforming bag should be created with
+    ///
+    /// All required algorithms, plus SHA-256 when the profile allows it.
+    pub fn manifest_algorithms(&self) -> Result<Vec<ChecksumAlgorithm>> {
+        let mut algorithms = parse_algorithms(&self.manifests_required)?;
+        let sha256_allowed = self
+            .manifests_allowed
+            .as_ref()
+            .is_none_or(|allowed| allowed.iter().any(|a| a.eq_ignore_ascii_case("sha256")));
+
+        if sha256_allowed && !algorithms.contains(&ChecksumAlgorithm::Sha256) {
+            algorithms.push(ChecksumAlgorithm::Sha256);
+        }
+
+        if algorithms.is_empty() {
+            return Err(anyhow::anyhow!(
+                "BagIt profile {} allows no supported manifest algorithm",
+                self.identifier()
+            ));
+        }
+
+        Ok(algorithms)
+    }
+
+    /// Tag manifest algorithms a conforming bag should be created with
+    pub fn tag_manifest_algorithms(&self) -> Result<Vec<ChecksumAlgorithm>> {
+        parse_algorithms(&self.tag_manifests_required)
+    }
+
+    /// Add the profile identifier to the bag-info fields and set any required
+    /// tag that has exactly one permitted value, replacing values it does not allow
+    pub fn apply_to_bag_info(&self, bag_info: &mut BagInfo) {
+        bag_info.set("BagIt-Profile-Identifier", self.identifier());
+
+        for (label, spec) in &self.bag_info {
+            let Some([value]) = spec.values.as_deref() else {
+                continue;
+            };
+            // A value the profile does not allow, such as a default the vault filled in, gives way to the required one
+            let present = bag_info.get_all(label);
+            if spec.required && (present.is_empty() || present.iter().any(|v| *v != value.as_str())) {
+                bag_info.set(label.clone(), value.clone());
+            }
+        }
+    }
+
+    /// Check a directory bag against the profile
+    ///
+    /// Only the profile's requirements are checked; use `BagItPackage::validate`
+    /// for structural and checksum validation.
+    pub fn validate_bag(&self, bag: &BagItPackage) -> Result<Vec<String>> {
+        let bagit_version = fs::read_to_string(&bag.bagit_txt_path)
+            .ok()
+            .and_then(|content| parse_bagit_version(&content));
+
+        let bag_info = if bag.bag_info_path.exists() {
+            bag.read_bag_info()?
+        } else {
+            BagInfo::new()
+        };
+
+        let mut issues = self.validate_serialization(None);
+        issues.extend(self.check_declared_contents(
+            bagit_version.as_deref(),
+            &bag_info,
+            &bag.manifest_algorithms()?,
+            &bag.tag_manifest_algorithms()?,
+            bag.fetch_txt_path.exists(),
+        ));
+
+        // Data-Empty allows no payload or a single zero-length file
+        if self.data_empty {
+            let mut payload_files = Vec::new();
+            for entry in ::walkdir::WalkDir::new(&bag.data_dir) {
+                let entry = entry?;
+                if entry.file_type().is_file() {
+                    payload_files.push(entry.metadata()?.len());
+                }
+            }
+            if payload_files.len() > 1 || payload_files.iter().any(|size| *size > 0) {
+                issues.push("Profile requires an empty payload directory".to_string());
+            }
+        }
+
+        // Tag files
+        for required in &self.tag_files_required {
+            if !bag.bag_root.join(required).is_file() {
+                issues.push(format!("Required tag file missing: {}", required));
+            }
+        }
+
+        if let Some(allowed) = &self.tag_files_allowed {
+            for tag_file in list_tag_files(bag)? {
+                if !allowed.iter().any(|pattern| glob_match(pattern, &tag_file)) {
+                    issues.push(format!("Tag file not allowed by profile: {}", tag_file));
+                }
+            }
+        }
+
+        Ok(issues)
+    }
+
+    /// Check the parts of a bag that can be read from its tag files alone
+    ///
+    /// Shared by directory validation and in-archive validation of serialized
+    /// bags.
+    pub fn check_declared_contents(
+        &self,
+        bagit_version: Option<&str>,
+        bag_info: &BagInfo,
+        manifest_algorithms: &[String],
+        tag_manifest_algorithms: &[String],
+        has_fetch: bool,
+    ) -> Vec<String> {
+        let mut issues = Vec::new();
+
+        // BagIt version
+        if !self.accept_bagit_version.is_empty() {
+            match bagit_version {
+                Some(version) if self.accept_bagit_version.iter().any(|v| v == version) => {}
+                Some(version) => issues.push(format!(
+                    "BagIt version {} is not accepted by profile (accepts {})",
+                    version,
+                    self.accept_bagit_version.join(", ")
+                )),
+                None => issues.push("Cannot determine BagIt version from bagit.txt".to_string()),
+            }
+        }
+
+        // Bag-info fields
+        issues.extend(bag_info.check_rules(&self.bag_info_rules()));
+
+        // Payload and tag manifests
+        issues.extend(check_manifest_set(
+            "manifest",
+            manifest_algorithms,
+            &self.manifests_required,
+            self.manifests_allowed.as_deref(),
+        ));
+        issues.extend(check_manifest_set(
+            "tag manifest",
+            tag_manifest_algorithms,
+            &self.tag_manifests_required,
+            self.tag_manifests_allowed.as_deref(),
+        ));
+
+        // fetch.txt
+        if has_fetch && !self.allow_fetch_txt {
+            issues.push("fetch.txt is present but not allowed by profile".to_string());
+        }
+        if !has_fetch && self.fetch_txt_required {
+            issues.push("fetch.txt is required by profile but missing".to_string());
+        }
+
+        issues
+    }
+
+    /// Check the bag's serialization against the profile
+    ///
+    /// `media_type` is `None` for a directory bag, or the MIME type of the
+    /// serialized archive.
+    pub fn validate_serialization(&self, media_type: Option<&str>) -> Vec<String> {
+        let mut issues = Vec::new();
+
+        match (self.serialization, media_type) {
+            (SerializationRequirement::Required, None) => {
+                issues.push("Profile requires a serialized bag".to_string());
+            }
+            (SerializationRequirement::Forbidden, Some(_)) => {
+                issues.push("Profile forbids serialized bags".to_string());
+            }
+            (_, Some(media_type))
+                if !self.accept_serialization.is_empty()
+                    && !self.accept_serialization.iter().any(|a| a == media_type) =>
+            {
+                issues.push(format!(
+                    "Serialization {} is not accepted by profile (accepts {})",
+                    media_type,
+                    self.accept_serialization.join(", ")
+                ));
+            }
+            _ => {}
+        }
+
+        issues
+    }
+}
+
+fn parse_algorithms(names: &[String]) -> Result<Vec<ChecksumAlgorithm>> {
+    names
+        .iter()
+        .map(|name| {
+            ChecksumAlgorithm::from_bagit_name(name)
+                .ok_or_else(|| anyhow::anyhow!("Unsupported checksum algorithm required by profile: {}", name))
+        })
+        .collect()
+}
+
+fn check_manifest_set(
+    kind: &str,
+    present: &[String],
+    required: &[String],
+    allowed: Option<&[String]>,
+) -> Vec<String> {
+    let mut issues = Vec::new();
+
+    for algorithm in required {
+        if !present.iter().any(|p| p.eq_ignore_ascii_case(algorithm)) {
+            issues.push(format!("Required {} missing: {}", kind, algorithm));
+        }
+    }
+
+    if let Some(allowed) = allowed {
+        for algorithm in present {
+            if !allowed.iter().any(|a| a.eq_ignore_ascii_case(algorithm)) {
+                issues.push(format!("{} algorithm not allowed by profile: {}", kind, algorithm));
+            }
+        }
+    }
+
+    issues
+}
+
+/// Tag files in the bag other than the ones every bag carries
+fn list_tag_files(bag: &BagItPackage) -> Result<Vec<String>> {
+    let mut tag_files = Vec::new();
+
+    for entry in ::walkdir::WalkDir::new(&bag.bag_root)
+        .into_iter()
+        .filter_entry(|e| e.path() != bag.data_dir.as_std_path())
+    {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        let relative_path = Utf8Path::from_path(entry.path())
+            .ok_or_else(|| anyhow::anyhow!("Non-UTF8 path encountered"))?
+            .strip_prefix(&bag.bag_root)?
+            .to_string();
+
+        let is_standard = matches!(relative_path.as_str(), "bagit.txt" | "bag-info.txt" | "fetch.txt")
+            || relative_path.starts_with("manifest-")
+            || relative_path.starts_with("tagmanifest-");
+
+        if !is_standard {
+            tag_files.push(relative_path);
+        }
+    }
+
+    Ok(tag_files)
+}
+
+/// Match a path against a glob pattern where `*` matches any run of characters
+fn glob_match(pattern: &str, path: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = path.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+
+    rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use camino::Utf8PathBuf;
+    use tempfile::TempDir;
+
+    const PROFILE: &str = r#"{
+        "BagIt-Profile-Info": {
+            "BagIt-Profile-Identifier": "https://example.edu/profiles/art-deposit.json",
+            "Source-Organization": "Example Art School Library",
+            "Version": "1.0"
+        },
+        "Bag-Info": {
+            "Source-Organization": { "required": true, "values": ["Example Art School Library"] },
+            "Contact-Email": { "required": true, "repeatable": false }
+        },
+        "Manifests-Required": ["md5"],
+        "Manifests-Allowed": ["md5", "sha256"],
+        "Tag-Manifests-Required": ["sha256"],
+        "Allow-Fetch.txt": false,
+        "Serialization": "optional",
+        "Accept-Serialization": ["application/zip"],
+        "Accept-BagIt-Version": ["1.0"],
+        "Tag-Files-Allowed": ["metadata/*"]
+    }"#;
+
+    #[test]
+    fn test_parse_profile_defaults() {
+        let profile = BagItProfile::from_json(PROFILE).unwrap();
+        assert_eq!(profile.identifier(), "https://example.edu/profiles/art-deposit.json");
+        assert!(profile.bag_info["Source-Organization"].repeatable);
+        assert!(!profile.bag_info["Contact-Email"].repeatable);
+        assert_eq!(
+            profile.manifest_algorithms().unwrap(),
+            vec![ChecksumAlgorithm::Md5, ChecksumAlgorithm::Sha256]
+        );
+
+        assert!(BagItProfile::from_json(r#"{"BagIt-Profile-Info": {"BagIt-Profile-Identifier": ""}}"#).is_err());
+    }
+
+    #[test]
+    fn test_validate_serialization() {
+        let profile = BagItProfile::from_json(PROFILE).unwrap();
+        assert!(profile.validate_serialization(None).is_empty());
+        assert!(profile.validate_serialization(Some("application/zip")).is_empty());
+        assert_eq!(profile.validate_serialization(Some("application/x-tar")).len(), 1);
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("metadata/*", "metadata/vra.xml"));
+        assert!(glob_match("*.xml", "metadata/vra.xml"));
+        assert!(glob_match("notes.txt", "notes.txt"));
+        assert!(!glob_match("metadata/*", "other/vra.xml"));
+    }
+
+    #[tokio::test]
+    async fn test_create_and_validate_conforming_bag() {
+        let temp_dir = TempDir::new().unwrap();
+        let bag_path = Utf8PathBuf::from_path_buf(temp_dir.path().join("bag")).unwrap();
+        let bag = BagItPackage::new(bag_path).unwrap();
+        fs::write(bag.data_dir.join("final.png"), b"png bytes").unwrap();
+
+        let profile = BagItProfile::from_json(PROFILE).unwrap();
+        bag.create_bagit_declaration().unwrap();
+        for algorithm in profile.manifest_algorithms().unwrap() {
+            bag.create_manifest_with(algorithm).await.unwrap();
+        }
+
+        let mut bag_info = BagInfo::new();
+        profile.apply_to_bag_info(&mut bag_info);
+        bag.create_bag_info(&bag_info).unwrap();
+
+        for algorithm in profile.tag_manifest_algorithms().unwrap() {
+            bag.create_tag_manifest(algorithm).await.unwrap();
+        }
+
+        // Contact-Email has no single default value, so it must be supplied
+        let issues = profile.validate_bag(&bag).unwrap();
+        assert_eq!(issues, vec!["Missing required bag-info field: Contact-Email".to_string()]);
+        assert_eq!(bag_info.get("Source-Organization"), Some("Example Art School Library"));
+        assert!(bag.validate().await.unwrap().is_empty());
+
+        fs::write(bag.bag_root.join("fetch.txt"), "").unwrap();
+        fs::write(bag.bag_root.join("notes.txt"), "").unwrap();
+        let issues = profile.validate_bag(&bag).unwrap();
+        assert!(issues.iter().any(|i| i.contains("fetch.txt")));
+        assert!(issues.iter().any(|i| i.contains("notes.txt")));
+    }
+}
@@ -0,0 +1,242 @@
+use anyhow::Result;
+use blake3::Hasher as Blake3Hasher;
+use md5::{Digest as Md5Digest, Md5};
+use sha2::{Sha256, Sha512};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+/// Checksum algorithms usable in BagIt manifests
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChecksumAlgorithm {
+    Md5,
+    Sha256,
+    Sha512,
+}
+
+impl ChecksumAlgorithm {
+    /// Parse the algorithm name used in manifest file names (e.g. "sha256")
+    pub fn from_bagit_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "md5" => Some(ChecksumAlgorithm::Md5),
+            "sha256" => Some(ChecksumAlgorithm::Sha256),
+            "sha512" => Some(ChecksumAlgorithm::Sha512),
+            _ => None,
+        }
+    }
+
+    /// The algorithm name used in manifest file names
+    pub fn bagit_name(&self) -> &'static str {
+        match self {
+            ChecksumAlgorithm::Md5 => "md5",
+            ChecksumAlgorithm::Sha256 => "sha256",
+            ChecksumAlgorithm::Sha512 => "sha512",
+        }
+    }
+}
+
+pub struct FileChecksums {
+    pub blake3: String,
+    pub sha256: String,
+    pub md5: String,
+}
+
+/// Calculate multiple checksums for a file efficiently
+pub async fn calculate_file_checksums<P: AsRef<Path>>(file_path: P) -> Result<FileChecksums> {
+    let file = File::open(&file_path)?;
+    let mut reader = BufReader::new(file);
+
+    let mut blake3_hasher = Blake3Hasher::new();
+    let mut sha256_hasher = Sha256::new();
+    let mut md5_hasher = Md5::new();
+
+    let mut buffer = [0; 8192]; // 8KB buffer for efficient reading
+
+    loop {
+        let bytes_read = reader.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+
+        let chunk = &buffer[..bytes_read];
+        blake3_hasher.update(chunk);
+        sha256_hasher.update(chunk);
+        md5_hasher.update(chunk);
+    }
+
+    Ok(FileChecksums {
+        blake3: blake3_hasher.finalize().to_hex().to_string(),
+        sha256: format!("{:x}", sha256_hasher.finalize()),
+        md5: format!("{:x}", md5_hasher.finalize()),
+    })
+}
+
+/// Calculate SHA-256 checksum only (for BagIt compatibility)
+pub async fn calculate_sha256<P: AsRef<Path>>(file_path: P) -> Result<String> {
+    let file = File::open(&file_path)?;
+    let mut reader = BufReader::new(file);
+    let mut hasher = Sha256::new();
+
+    let mut buffer = [0; 8192];
+    loop {
+        let bytes_read = reader.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..bytes_read]);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Calculate a checksum with the given BagIt manifest algorithm
+pub async fn calculate_checksum<P: AsRef<Path>>(
+    file_path: P,
+    algorithm: ChecksumAlgorithm,
+) -> Result<String> {
+    match algorithm {
+        ChecksumAlgorithm::Md5 => calculate_md5(file_path).await,
+        ChecksumAlgorithm::Sha256 => calculate_sha256(file_path).await,
+        ChecksumAlgorithm::Sha512 => calculate_sha512(file_path).await,
+    }
+}
+
+/// Calculate checksums for several algorithms in a single pass over a reader
+///
+/// Used where the data can only be read once, such as entries streamed out
+/// of a tar archive.
+pub fn calculate_checksums_from_reader<R: Read>(
+    reader: R,
+    algorithms: &[ChecksumAlgorithm],
+) -> Result<Vec<(ChecksumAlgorithm, String)>> {
+    use sha2::digest::DynDigest;
+
+    let mut reader = BufReader::new(reader);
+    let mut hashers: Vec<(ChecksumAlgorithm, Box<dyn DynDigest>)> = algorithms
+        .iter()
+        .map(|algorithm| {
+            let hasher: Box<dyn DynDigest> = match algorithm {
+                ChecksumAlgorithm::Md5 => Box::new(Md5::new()),
+                ChecksumAlgorithm::Sha256 => Box::new(Sha256::new()),
+                ChecksumAlgorithm::Sha512 => Box::new(Sha512::new()),
+            };
+            (*algorithm, hasher)
+        })
+        .collect();
+
+    let mut buffer = [0; 8192];
+    loop {
+        let bytes_read = reader.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        for (_, hasher) in hashers.iter_mut() {
+            hasher.update(&buffer[..bytes_read]);
+        }
+    }
+
+    Ok(hashers
+        .into_iter()
+        .map(|(algorithm, hasher)| {
+            let digest = hasher.finalize();
+            let hex = digest.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+            (algorithm, hex)
+        })
+        .collect())
+}
+
+/// Calculate SHA-512 checksum (required by some repository profiles)
+pub async fn calculate_sha512<P: AsRef<Path>>(file_path: P) -> Result<String> {
+    let file = File::open(&file_path)?;
+    let mut reader = BufReader::new(file);
+    let mut hasher = Sha512::new();
+
+    let mut buffer = [0; 8192];
+    loop {
+        let bytes_read = reader.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..bytes_read]);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Calculate MD5 checksum (for compatibility with older systems)
+pub async fn calculate_md5<P: AsRef<Path>>(file_path: P) -> Result<String> {
+    let file = File::open(&file_path)?;
+    let mut reader = BufReader::new(file);
+    let mut hasher = Md5::new();
+
+    let mut buffer = [0; 8192];
+    loop {
+        let bytes_read = reader.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..bytes_read]);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Calculate BLAKE3 checksum (fastest option)
+pub async fn calculate_blake3<P: AsRef<Path>>(file_path: P) -> Result<String> {
+    let file = File::open(&file_path)?;
+    let mut reader = BufReader::new(file);
+    let mut hasher = Blake3Hasher::new();
+
+    let mut buffer = [0; 8192];
+    loop {
+        let bytes_read = reader.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..bytes_read]);
+    }
+
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[tokio::test]
+    async fn test_calculate_checksums() {
+        // Create a temporary file with known content
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, "Hello, World!").unwrap();
+        temp_file.flush().unwrap();
+
+        let checksums = calculate_file_checksums(temp_file.path()).await.unwrap();
+
+        // Verify we get some checksum values (actual values depend on content)
+        assert!(!checksums.blake3.is_empty());
+        assert!(!checksums.sha256.is_empty());
+        assert!(!checksums.md5.is_empty());
+
+        // Checksums should be hexadecimal
+        assert!(checksums.blake3.chars().all(|c| c.is_ascii_hexdigit()));
+        assert!(checksums.sha256.chars().all(|c| c.is_ascii_hexdigit()));
+        assert!(checksums.md5.chars().all(|c| c.is_ascii_hexdigit()));
+    }
+
+    #[tokio::test]
+    async fn test_reader_checksums_match_file_checksums() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, "Hello, World!").unwrap();
+        temp_file.flush().unwrap();
+
+        let algorithms = [ChecksumAlgorithm::Md5, ChecksumAlgorithm::Sha256, ChecksumAlgorithm::Sha512];
+        let from_reader =
+            calculate_checksums_from_reader(&b"Hello, World!"[..], &algorithms).unwrap();
+
+        for (algorithm, checksum) in from_reader {
+            assert_eq!(checksum, calculate_checksum(temp_file.path(), algorithm).await.unwrap());
+        }
+    }
+}
\ No newline at end of file
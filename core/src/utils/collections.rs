@@ -0,0 +1,242 @@
+use crate::models::preservation::{Collection, CollectionMember, CollectionTree};
+use crate::utils::bag_info::BagInfo;
+use anyhow::Result;
+use std::collections::HashSet;
+
+/// bag-info.txt label recording which collections a bag belongs to
+pub const BAG_GROUP_IDENTIFIER: &str = "Bag-Group-Identifier";
+
+/// Check that a collection can be placed under a new parent
+pub fn validate_collection_move(
+    collections: &[Collection],
+    collection_id: &str,
+    new_parent_id: Option<&str>,
+) -> Result<()> {
+    let Some(new_parent_id) = new_parent_id else {
+        return Ok(());
+    };
+
+    if !collections.iter().any(|c| c.id == new_parent_id) {
+        return Err(anyhow::anyhow!("Collection not found: {}", new_parent_id));
+    }
+    if subtree_ids(collections, collection_id).contains(new_parent_id) {
+        return Err(anyhow::anyhow!("A collection cannot be moved inside itself"));
+    }
+
+    Ok(())
+}
+
+/// Ids of a collection and every collection nested below it
+pub fn subtree_ids(collections: &[Collection], collection_id: &str) -> HashSet<String> {
+    let mut found = HashSet::from([collection_id.to_string()]);
+    let mut pending = vec![collection_id.to_string()];
+
+    while let Some(current) = pending.pop() {
+        for child in collections
+            .iter()
+            .filter(|c| c.parent_collection_id.as_deref() == Some(current.as_str()))
+        {
+            if found.insert(child.id.clone()) {
+                pending.push(child.id.clone());
+            }
+        }
+    }
+
+    found
+}
+
+/// Build ordered collection trees, starting below `parent_id` (None for the top level)
+pub fn build_collection_trees(
+    collections: &[Collection],
+    members: &[CollectionMember],
+    parent_id: Option<&str>,
+) -> Vec<CollectionTree> {
+    let mut visited = HashSet::new();
+    build_level(collections, members, parent_id, &mut visited)
+}
+
+fn build_level(
+    collections: &[Collection],
+    members: &[CollectionMember],
+    parent_id: Option<&str>,
+    visited: &mut HashSet<String>,
+) -> Vec<CollectionTree> {
+    let mut level: Vec<&Collection> = collections
+        .iter()
+        .filter(|c| c.parent_collection_id.as_deref() == parent_id)
+        .collect();
+    level.sort_by(|a, b| a.position.cmp(&b.position).then_with(|| a.name.cmp(&b.name)));
+
+    let mut trees = Vec::new();
+    for collection in level {
+        if !visited.insert(collection.id.clone()) {
+            continue;
+        }
+
+        let mut collection_members: Vec<&CollectionMember> = members
+            .iter()
+            .filter(|m| m.collection_id == collection.id)
+            .collect();
+        collection_members.sort_by_key(|m| (m.position, m.added_at));
+
+        trees.push(CollectionTree {
+            collection: collection.clone(),
+            project_ids: collection_members
+                .into_iter()
+                .map(|m| m.archived_project_id.clone())
+                .collect(),
+            children: build_level(collections, members, Some(&collection.id), visited),
+        });
+    }
+
+    trees
+}
+
+/// Slash-separated names from the top-level collection down to this one
+pub fn collection_path(collections: &[Collection], collection_id: &str) -> Option<String> {
+    let mut names = Vec::new();
+    let mut visited = HashSet::new();
+    let mut current = collections.iter().find(|c| c.id == collection_id)?;
+
+    loop {
+        names.push(current.name.clone());
+        if !visited.insert(current.id.clone()) {
+            break;
+        }
+        match current
+            .parent_collection_id
+            .as_deref()
+            .and_then(|parent_id| collections.iter().find(|c| c.id == parent_id))
+        {
+            Some(parent) => current = parent,
+            None => break,
+        }
+    }
+
+    names.reverse();
+    Some(names.join("/"))
+}
+
+/// Bag-Group-Identifier values for every collection a project belongs to
+pub fn bag_group_identifiers(
+    collections: &[Collection],
+    members: &[CollectionMember],
+    project_id: &str,
+) -> Vec<String> {
+    let mut identifiers: Vec<String> = members
+        .iter()
+        .filter(|m| m.archived_project_id == project_id)
+        .filter_map(|m| collection_path(collections, &m.collection_id))
+        .collect();
+    identifiers.sort();
+    identifiers.dedup();
+    identifiers
+}
+
+/// Replace the Bag-Group-Identifier entries in bag-info.txt, returning whether anything changed
+pub fn apply_bag_group_identifiers(bag_info: &mut BagInfo, identifiers: &[String]) -> bool {
+    let current: Vec<String> = bag_info
+        .get_all(BAG_GROUP_IDENTIFIER)
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    if current == identifiers {
+        return false;
+    }
+
+    bag_info.remove(BAG_GROUP_IDENTIFIER);
+    for identifier in identifiers {
+        bag_info.add(BAG_GROUP_IDENTIFIER, identifier.clone());
+    }
+    true
+}
+
+/// Move `item_id` to `position` in an ordered list of ids, appending when out of range
+pub fn reorder(ids: &[String], item_id: &str, position: Option<usize>) -> Vec<String> {
+    let mut ordered: Vec<String> = ids.iter().filter(|id| *id != item_id).cloned().collect();
+    let index = position.unwrap_or(ordered.len()).min(ordered.len());
+    ordered.insert(index, item_id.to_string());
+    ordered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn collection(id: &str, name: &str, parent: Option<&str>, position: i32) -> Collection {
+        let mut collection = Collection::new(name.to_string(), None, parent.map(str::to_string), position);
+        collection.id = id.to_string();
+        collection
+    }
+
+    fn member(collection_id: &str, project_id: &str, position: i32) -> CollectionMember {
+        CollectionMember {
+            collection_id: collection_id.to_string(),
+            archived_project_id: project_id.to_string(),
+            position,
+            added_at: Utc::now(),
+        }
+    }
+
+    fn sample() -> (Vec<Collection>, Vec<CollectionMember>) {
+        let collections = vec![
+            collection("fall", "Fall 2025", None, 0),
+            collection("drawing", "Drawing I", Some("fall"), 1),
+            collection("color", "Color Theory", Some("fall"), 0),
+            collection("portfolio", "Portfolio", None, 1),
+        ];
+        let members = vec![
+            member("drawing", "p2", 1),
+            member("drawing", "p1", 0),
+            member("portfolio", "p1", 0),
+        ];
+        (collections, members)
+    }
+
+    #[test]
+    fn test_trees_and_paths() {
+        let (collections, members) = sample();
+
+        let trees = build_collection_trees(&collections, &members, None);
+        assert_eq!(trees.len(), 2);
+        assert_eq!(trees[0].collection.name, "Fall 2025");
+        assert_eq!(trees[0].children[0].collection.name, "Color Theory");
+        assert_eq!(trees[0].children[1].project_ids, vec!["p1", "p2"]);
+
+        assert_eq!(
+            bag_group_identifiers(&collections, &members, "p1"),
+            vec!["Fall 2025/Drawing I", "Portfolio"]
+        );
+    }
+
+    #[test]
+    fn test_move_and_reorder() {
+        let (collections, _) = sample();
+
+        assert!(validate_collection_move(&collections, "fall", Some("drawing")).is_err());
+        assert!(validate_collection_move(&collections, "fall", Some("missing")).is_err());
+        assert!(validate_collection_move(&collections, "drawing", Some("portfolio")).is_ok());
+        assert!(validate_collection_move(&collections, "drawing", None).is_ok());
+
+        let ids: Vec<String> = ["a", "b", "c"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(reorder(&ids, "c", Some(0)), vec!["c", "a", "b"]);
+        assert_eq!(reorder(&ids, "a", None), vec!["b", "c", "a"]);
+        assert_eq!(reorder(&ids, "d", Some(9)), vec!["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn test_apply_bag_group_identifiers() {
+        let mut bag_info = BagInfo::new();
+        bag_info.add("Source-Organization", "Art Dept");
+        bag_info.add(BAG_GROUP_IDENTIFIER, "Old");
+
+        let identifiers = vec!["Fall 2025/Drawing I".to_string(), "Portfolio".to_string()];
+        assert!(apply_bag_group_identifiers(&mut bag_info, &identifiers));
+        assert_eq!(bag_info.get_all(BAG_GROUP_IDENTIFIER), vec!["Fall 2025/Drawing I", "Portfolio"]);
+        assert!(!apply_bag_group_identifiers(&mut bag_info, &identifiers));
+
+        assert!(apply_bag_group_identifiers(&mut bag_info, &[]));
+        assert!(!bag_info.contains(BAG_GROUP_IDENTIFIER));
+    }
+}
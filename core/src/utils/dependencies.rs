@@ -0,0 +1,721 @@
+use crate::models::preservation::FileDependency;
+use crate::utils::bag_update::PayloadChange;
+use crate::utils::bagit::BagItPackage;
+use anyhow::Result;
+use camino::{Utf8Path, Utf8PathBuf};
+use std::collections::HashSet;
+use std::fs;
+use std::io::Read;
+
+pub const DEPENDENCY_FONT: &str = "font";
+pub const DEPENDENCY_LINKED_FILE: &str = "linked_file";
+
+/// Payload directory that linked files found outside the bag are copied into
+pub const LINKED_FILES_DIR: &str = "data/linked";
+
+/// Files larger than this are not analyzed
+const MAX_SOURCE_BYTES: u64 = 200 * 1024 * 1024;
+
+const FONT_EXTENSIONS: &[&str] = &["ttf", "otf", "ttc", "otc", "woff", "woff2", "pfb", "pfm", "dfont"];
+
+/// Extensions recognised as asset paths when scanning plain-text project files
+const ASSET_EXTENSIONS: &[&str] = &[
+    "png", "jpg", "jpeg", "tif", "tiff", "gif", "bmp", "webp", "psd", "psb", "exr", "hdr", "tga", "dds", "svg",
+    "eps", "ai", "pdf", "wav", "aif", "aiff", "mp3", "flac", "ogg", "m4a", "mov", "mp4", "m4v", "mkv", "avi",
+    "mxf", "obj", "mtl", "fbx", "abc", "usd", "usda", "usdc", "gltf", "glb", "ma", "mb", "ttf", "otf", "woff",
+    "woff2",
+];
+
+/// Plain-text scene and project formats scanned for asset paths
+const TEXT_PROJECT_EXTENSIONS: &[&str] = &[
+    "ma", "mtl", "obj", "usda", "nk", "fcpxml", "mlt", "kdenlive", "html", "htm", "css",
+];
+
+/// CSS generic families and keywords that never name an installable font
+const GENERIC_FONT_FAMILIES: &[&str] = &[
+    "serif", "sans-serif", "monospace", "cursive", "fantasy", "system-ui", "ui-serif", "ui-sans-serif",
+    "ui-monospace", "ui-rounded", "emoji", "math", "inherit", "initial", "unset",
+];
+
+/// The PDF base fonts every conforming reader provides
+const STANDARD_PDF_FONTS: &[&str] = &[
+    "Times-Roman", "Times-Bold", "Times-Italic", "Times-BoldItalic", "Helvetica", "Helvetica-Bold",
+    "Helvetica-Oblique", "Helvetica-BoldOblique", "Courier", "Courier-Bold", "Courier-Oblique",
+    "Courier-BoldOblique", "Symbol", "ZapfDingbats",
+];
+
+/// A font or file named inside a payload file, before it is matched against the payload
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Reference {
+    pub dependency_type: &'static str,
+    pub value: String,
+    pub embedded: bool,
+}
+
+impl Reference {
+    fn font(value: impl Into<String>, embedded: bool) -> Self {
+        Reference { dependency_type: DEPENDENCY_FONT, value: value.into(), embedded }
+    }
+
+    fn linked_file(value: impl Into<String>) -> Self {
+        Reference { dependency_type: DEPENDENCY_LINKED_FILE, value: value.into(), embedded: false }
+    }
+}
+
+/// Find the fonts and linked files a payload file needs, returning nothing for unsupported formats
+pub fn analyze_file(path: &Utf8Path) -> Result<Vec<Reference>> {
+    let extension = path.extension().map(|e| e.to_ascii_lowercase()).unwrap_or_default();
+    if fs::metadata(path)?.len() > MAX_SOURCE_BYTES {
+        return Ok(Vec::new());
+    }
+
+    let references = match extension.as_str() {
+        "svg" => analyze_svg(&String::from_utf8_lossy(&fs::read(path)?)),
+        "pdf" => analyze_pdf(&fs::read(path)?),
+        "docx" | "xlsx" | "pptx" => analyze_ooxml(path)?,
+        "odt" | "ods" | "odp" | "odg" => analyze_odf(path)?,
+        ext if TEXT_PROJECT_EXTENSIONS.contains(&ext) => {
+            scan_text_for_paths(&String::from_utf8_lossy(&fs::read(path)?))
+        }
+        _ => Vec::new(),
+    };
+
+    Ok(dedupe(references))
+}
+
+/// Analyze every payload file of a bag and match the references against its payload
+pub fn analyze_payload(bag: &BagItPackage) -> Result<Vec<FileDependency>> {
+    let payload: Vec<String> = bag.read_manifest()?.into_iter().map(|(_, path)| path).collect();
+    let mut dependencies = Vec::new();
+
+    for file_path in &payload {
+        let full_path = bag.bag_root.join(file_path);
+        // Files still listed in fetch.txt are analyzed once they arrive
+        if !full_path.is_file() {
+            continue;
+        }
+
+        for reference in analyze_file(&full_path)? {
+            let included_as = if reference.embedded {
+                None
+            } else if reference.dependency_type == DEPENDENCY_FONT {
+                resolve_font(&reference.value, &payload)
+            } else {
+                resolve_linked_file(file_path, &reference.value, &payload)
+            };
+
+            dependencies.push(FileDependency {
+                file_path: file_path.clone(),
+                dependency_type: reference.dependency_type.to_string(),
+                reference: reference.value,
+                embedded: reference.embedded,
+                included_as,
+            });
+        }
+    }
+
+    Ok(dependencies)
+}
+
+/// Plan payload additions for missing linked files that can be found on disk
+///
+/// Absolute references are tried as they are; relative ones are looked up under
+/// each search root, first by their relative path and then by file name. Files
+/// keep their relative position when that stays inside the payload and otherwise
+/// go to `data/linked/`.
+pub fn plan_linked_file_imports(
+    dependencies: &[FileDependency],
+    payload: &[String],
+    search_roots: &[Utf8PathBuf],
+) -> Vec<PayloadChange> {
+    let mut taken: HashSet<String> = payload.iter().map(|p| p.to_lowercase()).collect();
+    let mut sources = HashSet::new();
+    let mut changes = Vec::new();
+
+    for dependency in dependencies
+        .iter()
+        .filter(|d| d.dependency_type == DEPENDENCY_LINKED_FILE && d.is_missing())
+    {
+        let reference = normalize_reference(&dependency.reference);
+        let Some(file_name) = reference.rsplit('/').next().filter(|name| !name.is_empty()) else {
+            continue;
+        };
+
+        let mut candidates = Vec::new();
+        if is_absolute_reference(&reference) {
+            candidates.push(Utf8PathBuf::from(&reference));
+        } else {
+            candidates.extend(search_roots.iter().map(|root| root.join(reference.trim_start_matches("./"))));
+        }
+        candidates.extend(search_roots.iter().map(|root| root.join(file_name)));
+
+        let Some(source) = candidates.into_iter().find(|candidate| candidate.is_file()) else {
+            continue;
+        };
+        if !sources.insert(source.clone()) {
+            continue;
+        }
+
+        let payload_path = relative_payload_path(&dependency.file_path, &reference)
+            .filter(|path| !taken.contains(&path.to_lowercase()))
+            .unwrap_or_else(|| format!("{}/{}", LINKED_FILES_DIR, file_name));
+        if !taken.insert(payload_path.to_lowercase()) {
+            continue;
+        }
+
+        changes.push(PayloadChange::Add { source_path: source.to_string(), payload_path });
+    }
+
+    changes
+}
+
+/// Match a font name against font files in the payload, e.g. "Minion Pro" to "MinionPro-Regular.otf"
+fn resolve_font(name: &str, payload: &[String]) -> Option<String> {
+    let key = font_key(name);
+    if key.is_empty() {
+        return None;
+    }
+
+    payload
+        .iter()
+        .filter(|path| {
+            let path = Utf8Path::new(path.as_str());
+            path.extension()
+                .is_some_and(|e| FONT_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+                && path.file_stem().is_some_and(|stem| font_key(stem).starts_with(&key))
+        })
+        .min()
+        .cloned()
+}
+
+/// Match a linked path against the payload, relative to the referencing file or else by file name
+fn resolve_linked_file(source: &str, reference: &str, payload: &[String]) -> Option<String> {
+    let reference = normalize_reference(reference);
+
+    if let Some(path) = relative_payload_path(source, &reference) {
+        if let Some(found) = payload.iter().find(|p| p.eq_ignore_ascii_case(&path)) {
+            return Some(found.clone());
+        }
+    }
+
+    // Linked files are often moved next to the document when a project is collected
+    let file_name = reference.rsplit('/').next()?.to_lowercase();
+    payload
+        .iter()
+        .filter(|p| p.as_str() != source)
+        .find(|p| p.rsplit('/').next().is_some_and(|name| name.to_lowercase() == file_name))
+        .cloned()
+}
+
+/// Resolve a relative reference against the referencing file's directory, if it stays inside data/
+fn relative_payload_path(source: &str, reference: &str) -> Option<String> {
+    if is_absolute_reference(reference) {
+        return None;
+    }
+
+    let mut segments: Vec<&str> = source.split('/').collect();
+    segments.pop();
+    for segment in reference.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop()?;
+            }
+            segment => segments.push(segment),
+        }
+    }
+
+    let path = segments.join("/");
+    (path.starts_with("data/") && segments.len() > 1).then_some(path)
+}
+
+fn normalize_reference(reference: &str) -> String {
+    let reference = percent_decode(&reference.trim().replace('\\', "/"));
+    match reference
+        .strip_prefix("file://localhost")
+        .or_else(|| reference.strip_prefix("file://"))
+    {
+        // file:///C:/... names a Windows drive
+        Some(path) => match path.strip_prefix('/') {
+            Some(rest) if rest.as_bytes().get(1) == Some(&b':') => rest.to_string(),
+            _ => path.to_string(),
+        },
+        None => reference,
+    }
+}
+
+fn is_absolute_reference(reference: &str) -> bool {
+    reference.starts_with('/') || reference.as_bytes().get(1) == Some(&b':')
+}
+
+/// True for references that point inside the document or to the network
+fn is_external_or_internal_only(reference: &str) -> bool {
+    let reference = reference.trim();
+    if reference.is_empty() || reference.starts_with('#') {
+        return true;
+    }
+
+    // A URI scheme, but not a Windows drive letter
+    match reference.split_once(':') {
+        Some((scheme, _)) if scheme.len() > 1 => {
+            !scheme.eq_ignore_ascii_case("file")
+                && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+        }
+        _ => false,
+    }
+}
+
+fn font_key(name: &str) -> String {
+    name.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+fn dedupe(references: Vec<Reference>) -> Vec<Reference> {
+    let mut seen = HashSet::new();
+    references.into_iter().filter(|r| seen.insert(r.clone())).collect()
+}
+
+fn analyze_svg(svg: &str) -> Vec<Reference> {
+    let mut references = Vec::new();
+
+    for family in attribute_values(svg, "font-family").iter().chain(&css_property_values(svg, "font-family")) {
+        if let Some(font) = primary_font_family(family) {
+            references.push(Reference::font(font, false));
+        }
+    }
+
+    let links = attribute_values(svg, "href")
+        .into_iter()
+        .chain(attribute_values(svg, "xlink:href"))
+        .chain(css_urls(svg));
+    for link in links {
+        if !is_external_or_internal_only(&link) {
+            references.push(Reference::linked_file(link));
+        }
+    }
+
+    references
+}
+
+fn analyze_pdf(bytes: &[u8]) -> Vec<Reference> {
+    // lopdf panics on some malformed files; treat those as having no references
+    let document = match std::panic::catch_unwind(|| lopdf::Document::load_mem(bytes)) {
+        Ok(Ok(document)) => document,
+        Ok(Err(_)) | Err(_) => return Vec::new(),
+    };
+
+    let mut references = Vec::new();
+    for object in document.objects.values() {
+        let Ok(dict) = object.as_dict() else {
+            continue;
+        };
+        let name_of = |key: &[u8]| dict.get(key).and_then(|v| v.as_name_str()).ok();
+
+        if name_of(b"Type") == Some("Font") {
+            // Composite fonts are reported through their descendant font
+            let Some(base_font) = name_of(b"BaseFont").filter(|_| name_of(b"Subtype") != Some("Type0")) else {
+                continue;
+            };
+            // Subset fonts carry a six letter prefix such as "ABCDEF+"
+            let base_font = match base_font.split_once('+') {
+                Some((prefix, name)) if prefix.len() == 6 => name,
+                _ => base_font,
+            };
+            let embedded = dict
+                .get_deref(b"FontDescriptor", &document)
+                .and_then(|d| d.as_dict())
+                .is_ok_and(|d| [&b"FontFile"[..], b"FontFile2", b"FontFile3"].iter().any(|k| d.has(k)));
+
+            if embedded || !STANDARD_PDF_FONTS.contains(&base_font) {
+                references.push(Reference::font(base_font, embedded));
+            }
+            continue;
+        }
+
+        // File specifications, either on their own or inline in launch and remote go-to actions
+        let is_filespec = name_of(b"Type") == Some("Filespec") && !dict.has(b"EF");
+        let is_action = matches!(name_of(b"S"), Some("Launch" | "GoToR" | "ImportData"));
+        if !is_filespec && !is_action {
+            continue;
+        }
+        let target = if is_filespec { dict.get(b"UF").or_else(|_| dict.get(b"F")) } else { dict.get(b"F") };
+        if let Ok(target) = target.and_then(|t| t.as_string()) {
+            if !is_external_or_internal_only(&target) {
+                references.push(Reference::linked_file(target.into_owned()));
+            }
+        }
+    }
+
+    references
+}
+
+fn analyze_ooxml(path: &Utf8Path) -> Result<Vec<Reference>> {
+    let mut archive = zip::ZipArchive::new(fs::File::open(path)?)?;
+    let names: Vec<String> = archive.file_names().map(str::to_string).collect();
+    let mut references = Vec::new();
+
+    let embeds_all_fonts = read_zip_text(&mut archive, "word/settings.xml")?
+        .is_some_and(|settings| settings.contains("embedTrueTypeFonts"));
+    let embedded_fonts: HashSet<String> = match read_zip_text(&mut archive, "ppt/presentation.xml")? {
+        Some(presentation) => section(&presentation, "embeddedFontLst")
+            .map(|list| attribute_values(list, "typeface").into_iter().collect())
+            .unwrap_or_default(),
+        None => HashSet::new(),
+    };
+
+    for name in &names {
+        let is_font_part = name == "word/fontTable.xml" || name.contains("/theme/") || name.starts_with("ppt/slides/");
+        if is_font_part && name.ends_with(".xml") {
+            let xml = read_zip_text(&mut archive, name)?.unwrap_or_default();
+            let fonts = attribute_values(&xml, "w:name").into_iter().chain(attribute_values(&xml, "typeface"));
+            for font in fonts {
+                // "+mj-lt" style values point at the theme's fonts
+                if !font.is_empty() && !font.starts_with('+') {
+                    let embedded = embeds_all_fonts || embedded_fonts.contains(&font);
+                    references.push(Reference::font(font, embedded));
+                }
+            }
+        }
+
+        if name.ends_with(".rels") {
+            let xml = read_zip_text(&mut archive, name)?.unwrap_or_default();
+            for element in xml.split("<Relationship ").skip(1) {
+                let is_external = attribute_values(element, "TargetMode").first().map(String::as_str) == Some("External");
+                if let Some(target) = attribute_values(element, "Target").into_iter().next() {
+                    if is_external && !is_external_or_internal_only(&target) {
+                        references.push(Reference::linked_file(target));
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(references)
+}
+
+fn analyze_odf(path: &Utf8Path) -> Result<Vec<Reference>> {
+    let mut archive = zip::ZipArchive::new(fs::File::open(path)?)?;
+    let names: HashSet<String> = archive.file_names().map(str::to_string).collect();
+    let packaged_fonts: Vec<String> = names
+        .iter()
+        .filter(|name| name.starts_with("Fonts/"))
+        .filter_map(|name| Utf8Path::new(name.as_str()).file_stem().map(font_key))
+        .collect();
+    let mut references = Vec::new();
+
+    for part in ["content.xml", "styles.xml"] {
+        let Some(xml) = read_zip_text(&mut archive, part)? else {
+            continue;
+        };
+
+        for family in attribute_values(&xml, "svg:font-family") {
+            if let Some(font) = primary_font_family(&family) {
+                let key = font_key(&font);
+                let embedded = packaged_fonts.iter().any(|packaged| packaged.starts_with(&key));
+                references.push(Reference::font(font, embedded));
+            }
+        }
+
+        for link in attribute_values(&xml, "xlink:href") {
+            let inside_package = names.contains(link.trim_start_matches("./"));
+            if inside_package || is_external_or_internal_only(&link) {
+                continue;
+            }
+            // ODF resolves relative links against the package as if it were a directory
+            let link = link.strip_prefix("../").map(str::to_string).unwrap_or(link);
+            references.push(Reference::linked_file(link));
+        }
+    }
+
+    Ok(references)
+}
+
+/// Pick out quoted strings and bare words that look like paths to assets
+fn scan_text_for_paths(text: &str) -> Vec<Reference> {
+    let looks_like_asset = |candidate: &str| {
+        Utf8Path::new(candidate)
+            .extension()
+            .is_some_and(|e| ASSET_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+            && !is_external_or_internal_only(candidate)
+    };
+
+    let quoted = text
+        .split('"')
+        .skip(1)
+        .step_by(2)
+        .chain(text.split('\'').skip(1).step_by(2))
+        .map(|s| s.trim().trim_start_matches("url(").trim_end_matches(')'));
+    // Bare words come from outside double quotes so a quoted path with spaces is not split up
+    let bare = text
+        .split('"')
+        .step_by(2)
+        .flat_map(|segment| segment.split(|c: char| c.is_whitespace() || "'()<>=,;".contains(c)))
+        .filter(|token| !token.is_empty());
+
+    quoted
+        .chain(bare)
+        .filter(|candidate| !candidate.contains('\n') && looks_like_asset(candidate))
+        .map(Reference::linked_file)
+        .collect()
+}
+
+/// Values of an XML attribute, with character entities decoded
+pub(crate) fn attribute_values(xml: &str, name: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    let mut rest = xml;
+
+    while let Some(index) = rest.find(name) {
+        let preceded_by_space = rest[..index].chars().next_back().is_some_and(char::is_whitespace);
+        let after = rest[index + name.len()..].trim_start();
+        rest = &rest[index + name.len()..];
+
+        let Some(after) = after.strip_prefix('=').map(str::trim_start) else {
+            continue;
+        };
+        let Some(quote) = after.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+            continue;
+        };
+        if let Some(end) = after[1..].find(quote) {
+            if preceded_by_space {
+                values.push(decode_entities(&after[1..1 + end]));
+            }
+        }
+    }
+
+    values
+}
+
+/// Values of a CSS property inside style attributes and style elements
+fn css_property_values(text: &str, property: &str) -> Vec<String> {
+    text.match_indices(property)
+        .filter_map(|(index, _)| {
+            let after = text[index + property.len()..].trim_start().strip_prefix(':')?.trim_start();
+            // Skip past a quoted first family so its quotes are not read as the end of an attribute
+            let quoted_len = match after.chars().next() {
+                Some(quote @ ('"' | '\'')) => after[1..].find(quote).map_or(0, |end| end + 2),
+                _ => 0,
+            };
+            let end = after[quoted_len..]
+                .find([';', '}', '"', '\'', '\n'])
+                .map_or(after.len(), |end| quoted_len + end);
+            Some(decode_entities(after[..end].trim()))
+        })
+        .collect()
+}
+
+/// Targets of CSS url(...) references
+fn css_urls(text: &str) -> Vec<String> {
+    text.match_indices("url(")
+        .filter_map(|(index, _)| {
+            let after = &text[index + 4..];
+            let end = after.find(')')?;
+            Some(decode_entities(after[..end].trim().trim_matches(['"', '\''])))
+        })
+        .collect()
+}
+
+/// The first named family of a font-family list, skipping generic keywords
+fn primary_font_family(families: &str) -> Option<String> {
+    let family = families.split(',').next()?.trim().trim_matches(['"', '\'']).trim();
+    if family.is_empty() || GENERIC_FONT_FAMILIES.contains(&family.to_ascii_lowercase().as_str()) {
+        return None;
+    }
+    Some(family.to_string())
+}
+
+/// The body of the first element with the given local name
+fn section<'a>(xml: &'a str, local_name: &str) -> Option<&'a str> {
+    let start = xml.find(&format!("{}>", local_name))?;
+    let end = xml[start..].find(&format!("/{}", local_name)).map(|end| start + end)?;
+    Some(&xml[start..end])
+}
+
+fn read_zip_text(archive: &mut zip::ZipArchive<fs::File>, name: &str) -> Result<Option<String>> {
+    let mut file = match archive.by_name(name) {
+        Ok(file) => file,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    Ok(Some(String::from_utf8_lossy(&bytes).into_owned()))
+}
+
+pub(crate) fn decode_entities(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok());
+        match (bytes[i], hex.and_then(|h| u8::from_str_radix(h, 16).ok())) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{dictionary, Document, Object};
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_analyze_svg() {
+        let svg = r##"<svg xmlns:xlink="http://www.w3.org/1999/xlink">
+            <style>.title { font-family: 'Playfair Display', serif; }</style>
+            <text font-family="sans-serif">Fallback</text>
+            <image xlink:href="../scans/plate%201.tif"/>
+            <image href="data:image/png;base64,AAAA"/>
+            <use href="#logo"/>
+            <a href="https://example.com">link</a>
+        </svg>"##;
+
+        let references = dedupe(analyze_svg(svg));
+        assert_eq!(
+            references,
+            vec![Reference::font("Playfair Display", false), Reference::linked_file("../scans/plate%201.tif")]
+        );
+    }
+
+    #[test]
+    fn test_analyze_pdf_fonts_and_links() {
+        let mut document = Document::with_version("1.5");
+        let descriptor = document.add_object(dictionary! {
+            "Type" => "FontDescriptor",
+            "FontName" => "ABCDEF+MinionPro-Regular",
+            "FontFile3" => Object::Null,
+        });
+        document.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "ABCDEF+MinionPro-Regular",
+            "FontDescriptor" => descriptor,
+        });
+        document.add_object(dictionary! { "Type" => "Font", "Subtype" => "Type1", "BaseFont" => "Helvetica" });
+        document.add_object(dictionary! { "Type" => "Font", "Subtype" => "TrueType", "BaseFont" => "Futura-Bold" });
+        document.add_object(dictionary! { "S" => "Launch", "F" => Object::string_literal("cover.indd") });
+
+        let mut bytes = Vec::new();
+        document.save_to(&mut bytes).unwrap();
+
+        let mut references = analyze_pdf(&bytes);
+        references.sort_by(|a, b| a.value.cmp(&b.value));
+        assert_eq!(
+            references,
+            vec![
+                Reference::font("Futura-Bold", false),
+                Reference::font("MinionPro-Regular", true),
+                Reference::linked_file("cover.indd"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_analyze_ooxml() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let path = root.join("essay.docx");
+
+        let mut writer = zip::ZipWriter::new(fs::File::create(&path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        writer.start_file("word/fontTable.xml", options).unwrap();
+        writer.write_all(br#"<w:fonts><w:font w:name="Garamond Premier"/></w:fonts>"#).unwrap();
+        writer.start_file("word/_rels/document.xml.rels", options).unwrap();
+        writer
+            .write_all(
+                br#"<Relationships><Relationship Id="rId4" Target="media/image1.png"/>
+                <Relationship Id="rId5" Target="file:///C:/Work/Plates/map.tif" TargetMode="External"/>
+                <Relationship Id="rId6" Target="https://example.com" TargetMode="External"/></Relationships>"#,
+            )
+            .unwrap();
+        writer.finish().unwrap();
+
+        assert_eq!(
+            analyze_file(&path).unwrap(),
+            vec![Reference::font("Garamond Premier", false), Reference::linked_file("file:///C:/Work/Plates/map.tif")]
+        );
+    }
+
+    #[test]
+    fn test_scan_text_for_paths() {
+        let scene = "file -r -ns \"tree\" \"/Volumes/Assets/tree model.ma\";\n\
+                     setAttr \".ftn\" -type \"string\" \"sourceimages/bark.exr\";\n\
+                     map_Kd textures\\leaf.png\n";
+        let mut values: Vec<String> = dedupe(scan_text_for_paths(scene)).into_iter().map(|r| r.value).collect();
+        values.sort();
+        assert_eq!(values, vec!["/Volumes/Assets/tree model.ma", "sourceimages/bark.exr", "textures\\leaf.png"]);
+    }
+
+    #[test]
+    fn test_resolution_and_import_planning() {
+        let payload = vec![
+            "data/layout.svg".to_string(),
+            "data/images/plate 1.tif".to_string(),
+            "data/fonts/PlayfairDisplay-Regular.ttf".to_string(),
+        ];
+
+        assert_eq!(
+            resolve_linked_file("data/layout.svg", "images/plate%201.tif", &payload),
+            Some("data/images/plate 1.tif".to_string())
+        );
+        assert_eq!(
+            resolve_linked_file("data/layout.svg", "file:///Users/ada/plate%201.tif", &payload),
+            Some("data/images/plate 1.tif".to_string())
+        );
+        assert_eq!(resolve_font("Playfair Display", &payload), Some("data/fonts/PlayfairDisplay-Regular.ttf".to_string()));
+        assert_eq!(resolve_font("Futura", &payload), None);
+        assert_eq!(relative_payload_path("data/a/b.svg", "../../x.png"), None);
+
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap().to_path_buf();
+        fs::create_dir_all(root.join("textures")).unwrap();
+        fs::write(root.join("textures/bark.png"), b"png").unwrap();
+        fs::write(root.join("overlay.png"), b"png").unwrap();
+
+        let missing = |reference: &str| FileDependency {
+            file_path: "data/scene/tree.ma".to_string(),
+            dependency_type: DEPENDENCY_LINKED_FILE.to_string(),
+            reference: reference.to_string(),
+            embedded: false,
+            included_as: None,
+        };
+        let dependencies = vec![
+            missing("textures/bark.png"),
+            missing("../../elsewhere/overlay.png"),
+            missing("textures/absent.png"),
+        ];
+
+        let changes = plan_linked_file_imports(&dependencies, &payload, std::slice::from_ref(&root));
+        let planned: Vec<(String, String)> = changes
+            .into_iter()
+            .map(|change| match change {
+                PayloadChange::Add { source_path, payload_path } => (source_path, payload_path),
+                other => panic!("unexpected change {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            planned,
+            vec![
+                (root.join("textures/bark.png").to_string(), "data/scene/textures/bark.png".to_string()),
+                (root.join("overlay.png").to_string(), "data/linked/overlay.png".to_string()),
+            ]
+        );
+    }
+}
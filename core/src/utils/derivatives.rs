@@ -0,0 +1,212 @@
+use crate::models::preservation::{ProjectPreview, ProjectPreviews, SkippedPreview};
+use crate::utils::bagit::BagItPackage;
+use anyhow::Result;
+use camino::{Utf8Path, Utf8PathBuf};
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageDecoder, ImageReader};
+use std::fs;
+
+/// Directory derivatives are written to, outside any bag so they never enter the payload
+pub const DEFAULT_DERIVATIVES_ROOT: &str = "/tmp/cwpt-derivatives";
+
+/// Raster formats the derivative generator can decode
+const PREVIEWABLE_MEDIA_TYPES: &[&str] = &[
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "image/webp",
+    "image/tiff",
+    "image/bmp",
+];
+
+const JPEG_QUALITY: u8 = 85;
+
+/// The sizes of web-friendly version generated for each image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DerivativeKind {
+    Thumbnail,
+    Preview,
+}
+
+impl DerivativeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DerivativeKind::Thumbnail => "thumbnail",
+            DerivativeKind::Preview => "preview",
+        }
+    }
+
+    /// Longest edge in pixels; smaller images are never upscaled
+    pub fn max_dimension(&self) -> u32 {
+        match self {
+            DerivativeKind::Thumbnail => 256,
+            DerivativeKind::Preview => 1600,
+        }
+    }
+}
+
+/// True for media types the generator can make previews of
+pub fn is_previewable(media_type: &str) -> bool {
+    PREVIEWABLE_MEDIA_TYPES.contains(&media_type)
+}
+
+/// Where the derivative of a file with the given checksum is stored
+///
+/// Derivatives are keyed by content, so identical files share them and a
+/// replaced file gets new ones.
+pub fn derivative_path(root: &Utf8Path, checksum: &str, kind: DerivativeKind) -> Utf8PathBuf {
+    let prefix = &checksum[..checksum.len().min(2)];
+    root.join(prefix).join(format!("{}-{}.jpg", checksum, kind.as_str()))
+}
+
+/// Generate any missing thumbnail and preview for one image, returning where they are
+pub fn generate_derivatives(root: &Utf8Path, source: &Utf8Path, checksum: &str) -> Result<(Utf8PathBuf, Utf8PathBuf)> {
+    let thumbnail_path = derivative_path(root, checksum, DerivativeKind::Thumbnail);
+    let preview_path = derivative_path(root, checksum, DerivativeKind::Preview);
+
+    if !thumbnail_path.exists() || !preview_path.exists() {
+        let image = decode_upright(source)?;
+        write_derivative(&image, DerivativeKind::Preview, &preview_path)?;
+        write_derivative(&image, DerivativeKind::Thumbnail, &thumbnail_path)?;
+    }
+
+    Ok((thumbnail_path, preview_path))
+}
+
+/// Generate derivatives for the previewable payload files of a bag
+///
+/// `media_types` pairs payload paths with their detected media type, as in the
+/// project file catalog. Files that fail to decode are skipped and listed
+/// with the reason.
+pub fn generate_bag_previews(
+    root: &Utf8Path,
+    bag: &BagItPackage,
+    media_types: &[(String, String)],
+) -> Result<ProjectPreviews> {
+    let mut result = ProjectPreviews::default();
+
+    for (checksum, path) in bag.read_manifest()? {
+        let Some((_, media_type)) = media_types.iter().find(|(p, _)| *p == path) else {
+            continue;
+        };
+        let source = bag.bag_root.join(&path);
+        if !is_previewable(media_type) || !source.is_file() {
+            continue;
+        }
+
+        let (thumbnail_path, preview_path) = match generate_derivatives(root, &source, &checksum) {
+            Ok(paths) => paths,
+            Err(e) => {
+                result.skipped.push(SkippedPreview {
+                    path,
+                    error: format!("{:#}", e),
+                });
+                continue;
+            }
+        };
+        let (width, height) = image::image_dimensions(&preview_path)?;
+
+        result.previews.push(ProjectPreview {
+            path,
+            checksum,
+            thumbnail_path: thumbnail_path.to_string(),
+            preview_path: preview_path.to_string(),
+            width,
+            height,
+        });
+    }
+
+    Ok(result)
+}
+
+/// Decode an image and turn it the way its EXIF orientation says it should be viewed
+fn decode_upright(source: &Utf8Path) -> Result<DynamicImage> {
+    let mut decoder = ImageReader::open(source)?.with_guessed_format()?.into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+    Ok(image)
+}
+
+fn write_derivative(image: &DynamicImage, kind: DerivativeKind, path: &Utf8Path) -> Result<()> {
+    let max = kind.max_dimension();
+    let resized = if image.width() <= max && image.height() <= max {
+        image.clone()
+    } else if kind == DerivativeKind::Thumbnail {
+        image.thumbnail(max, max)
+    } else {
+        image.resize(max, max, FilterType::Lanczos3)
+    };
+
+    // JPEG has no alpha channel, so transparent areas are flattened onto white
+    let mut rgb = image::RgbImage::from_pixel(resized.width(), resized.height(), image::Rgb([255, 255, 255]));
+    for (x, y, pixel) in resized.to_rgba8().enumerate_pixels() {
+        let alpha = pixel[3] as u32;
+        let blend = |channel: u8| ((channel as u32 * alpha + 255 * (255 - alpha)) / 255) as u8;
+        rgb.put_pixel(x, y, image::Rgb([blend(pixel[0]), blend(pixel[1]), blend(pixel[2])]));
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // Write under a temporary name so an interrupted run never leaves a truncated derivative
+    let partial_path = path.with_extension("jpg.partial");
+    let mut file = fs::File::create(&partial_path)?;
+    JpegEncoder::new_with_quality(&mut file, JPEG_QUALITY).encode_image(&rgb)?;
+    fs::rename(&partial_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_generate_bag_previews() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let derivatives_root = root.join("derivatives");
+
+        let bag = BagItPackage::new(root.join("bag")).unwrap();
+        image::RgbaImage::from_pixel(1700, 850, image::Rgba([200, 40, 40, 128]))
+            .save(bag.data_dir.join("large.png"))
+            .unwrap();
+        image::RgbImage::new(100, 50).save(bag.data_dir.join("small.jpg")).unwrap();
+        fs::write(bag.data_dir.join("notes.txt"), "not an image").unwrap();
+        fs::write(bag.data_dir.join("broken.png"), "truncated upload").unwrap();
+        bag.create_manifest().await.unwrap();
+
+        let media_types = vec![
+            ("data/large.png".to_string(), "image/png".to_string()),
+            ("data/small.jpg".to_string(), "image/jpeg".to_string()),
+            ("data/notes.txt".to_string(), "text/plain".to_string()),
+            ("data/broken.png".to_string(), "image/png".to_string()),
+        ];
+        let result = generate_bag_previews(&derivatives_root, &bag, &media_types).unwrap();
+        assert_eq!(result.skipped.len(), 1);
+        assert_eq!(result.skipped[0].path, "data/broken.png");
+        let previews = result.previews;
+        assert_eq!(previews.len(), 2);
+
+        let large = previews.iter().find(|p| p.path == "data/large.png").unwrap();
+        assert_eq!((large.width, large.height), (1600, 800));
+        assert_eq!(image::image_dimensions(&large.thumbnail_path).unwrap(), (256, 128));
+        assert!(!large.preview_path.starts_with(bag.bag_root.as_str()));
+
+        let small = previews.iter().find(|p| p.path == "data/small.jpg").unwrap();
+        assert_eq!((small.width, small.height), (100, 50));
+
+        // Cached derivatives are reused rather than rewritten
+        let modified = fs::metadata(&large.preview_path).unwrap().modified().unwrap();
+        generate_bag_previews(&derivatives_root, &bag, &media_types).unwrap();
+        assert_eq!(fs::metadata(&large.preview_path).unwrap().modified().unwrap(), modified);
+    }
+
+    #[test]
+    fn test_derivative_path() {
+        let path = derivative_path(Utf8Path::new("/vault/derivatives"), "ab12cd", DerivativeKind::Thumbnail);
+        assert_eq!(path, Utf8Path::new("/vault/derivatives/ab/ab12cd-thumbnail.jpg"));
+    }
+}
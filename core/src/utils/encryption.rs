@@ -0,0 +1,512 @@
+use crate::utils::storage::{StorageBackend, StoredObject};
+use anyhow::{Context, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
+use async_trait::async_trait;
+use camino::{Utf8Path, Utf8PathBuf};
+use chacha20poly1305::aead::generic_array::GenericArray;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::stream::{DecryptorBE32, EncryptorBE32};
+use chacha20poly1305::aead::{Aead, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Nonce};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::sync::Arc;
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+/// Extension of encrypted exports, appended to the archive's own
+pub const ENCRYPTED_EXTENSION: &str = "cwptenc";
+
+/// Passphrases shorter than this are refused
+pub const MIN_PASSPHRASE_LENGTH: usize = 8;
+
+/// Start of every encrypted file: format magic, data key id, nonce prefix
+const MAGIC: &[u8; 8] = b"CWPTENC1";
+const KEY_ID_LENGTH: usize = 16;
+const NONCE_PREFIX_LENGTH: usize = 7;
+const HEADER_LENGTH: usize = MAGIC.len() + KEY_ID_LENGTH + NONCE_PREFIX_LENGTH;
+
+/// Plaintext is sealed in chunks this size, each with its own tag
+const CHUNK_LENGTH: usize = 64 * 1024;
+const TAG_LENGTH: usize = 16;
+
+/// Argon2id cost for deriving keys from passphrases; tests use a cheap one
+const KDF_MEMORY_KIB: u32 = if cfg!(test) { 1024 } else { 64 * 1024 };
+const KDF_ITERATIONS: u32 = 3;
+const KDF_PARALLELISM: u32 = 1;
+
+pub type SecretKey = Zeroizing<[u8; 32]>;
+
+/// A key file contents are encrypted with
+#[derive(Clone)]
+pub struct DataKey {
+    pub id: Uuid,
+    key: SecretKey,
+}
+
+impl DataKey {
+    pub fn generate() -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            key: random_key(),
+        }
+    }
+
+    fn cipher(&self) -> ChaCha20Poly1305 {
+        ChaCha20Poly1305::new(self.key.as_ref().into())
+    }
+}
+
+/// The master key and every data key, held in memory while encryption is unlocked
+///
+/// The newest data key encrypts; older ones are kept to read what was
+/// written before a rotation.
+#[derive(Clone)]
+pub struct Keyring {
+    master: SecretKey,
+    keys: Vec<DataKey>,
+}
+
+impl Keyring {
+    /// A keyring from a master key and its data keys, oldest first
+    pub fn new(master: SecretKey, keys: Vec<DataKey>) -> Self {
+        Self { master, keys }
+    }
+
+    /// Start a keyring with a fresh master key and a first data key
+    pub fn generate() -> Self {
+        Self::new(random_key(), vec![DataKey::generate()])
+    }
+
+    pub fn master(&self) -> &[u8; 32] {
+        &self.master
+    }
+
+    /// The key new content is encrypted with
+    pub fn current(&self) -> &DataKey {
+        self.keys.last().expect("a keyring always holds a data key")
+    }
+
+    pub fn get(&self, id: &Uuid) -> Option<&DataKey> {
+        self.keys.iter().find(|key| &key.id == id)
+    }
+
+    /// Make a key the current one
+    pub fn add(&mut self, key: DataKey) {
+        self.keys.push(key);
+    }
+
+    /// Encrypt a data key under the master key, for storing in the database
+    pub fn seal(&self, key: &DataKey) -> Result<String> {
+        let cipher = ChaCha20Poly1305::new(self.master.as_ref().into());
+        let mut nonce = [0u8; 12];
+        OsRng.fill_bytes(&mut nonce);
+        let sealed = cipher
+            .encrypt(Nonce::from_slice(&nonce), Payload { msg: key.key.as_ref(), aad: key.id.as_bytes() })
+            .map_err(|_| anyhow::anyhow!("Failed to seal data key"))?;
+        Ok(format!("{}{}", hex::encode(nonce), hex::encode(sealed)))
+    }
+
+    /// Decrypt a data key sealed with [`Keyring::seal`] under `master`
+    pub fn open(master: &[u8; 32], id: Uuid, sealed: &str) -> Result<DataKey> {
+        let bytes = hex::decode(sealed).context("Invalid sealed data key")?;
+        if bytes.len() < 12 {
+            return Err(anyhow::anyhow!("Invalid sealed data key"));
+        }
+        let (nonce, sealed) = bytes.split_at(12);
+        let cipher = ChaCha20Poly1305::new(master.into());
+        let key = Zeroizing::new(
+            cipher
+                .decrypt(Nonce::from_slice(nonce), Payload { msg: sealed, aad: id.as_bytes() })
+                .map_err(|_| anyhow::anyhow!("Data key {} does not belong to this master key", id))?,
+        );
+        let key: [u8; 32] = key.as_slice().try_into().context("Invalid data key length")?;
+        Ok(DataKey { id, key: Zeroizing::new(key) })
+    }
+}
+
+/// The master key encrypted under a key derived from a passphrase or recovery key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WrappedKey {
+    pub salt: String,
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+impl WrappedKey {
+    /// Wrap the master key under a secret
+    pub fn wrap(secret: &str, master: &[u8; 32]) -> Result<Self> {
+        let mut salt = [0u8; 16];
+        let mut nonce = [0u8; 12];
+        OsRng.fill_bytes(&mut salt);
+        OsRng.fill_bytes(&mut nonce);
+        let mut wrapped = Self {
+            salt: hex::encode(salt),
+            memory_kib: KDF_MEMORY_KIB,
+            iterations: KDF_ITERATIONS,
+            parallelism: KDF_PARALLELISM,
+            nonce: hex::encode(nonce),
+            ciphertext: String::new(),
+        };
+        let cipher = ChaCha20Poly1305::new(wrapped.derive(secret)?.as_ref().into());
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), master.as_slice())
+            .map_err(|_| anyhow::anyhow!("Failed to wrap the master key"))?;
+        wrapped.ciphertext = hex::encode(ciphertext);
+        Ok(wrapped)
+    }
+
+    /// The master key, or `None` if the secret is wrong
+    pub fn unwrap(&self, secret: &str) -> Result<Option<SecretKey>> {
+        let nonce = hex::decode(&self.nonce).context("Invalid wrapped key nonce")?;
+        let ciphertext = hex::decode(&self.ciphertext).context("Invalid wrapped key")?;
+        if nonce.len() != 12 {
+            return Err(anyhow::anyhow!("Invalid wrapped key nonce"));
+        }
+        let cipher = ChaCha20Poly1305::new(self.derive(secret)?.as_ref().into());
+        let Ok(master) = cipher.decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice()).map(Zeroizing::new) else {
+            return Ok(None);
+        };
+        let master: [u8; 32] = master.as_slice().try_into().context("Invalid master key length")?;
+        Ok(Some(Zeroizing::new(master)))
+    }
+
+    fn derive(&self, secret: &str) -> Result<SecretKey> {
+        let salt = hex::decode(&self.salt).context("Invalid wrapped key salt")?;
+        let params = Params::new(self.memory_kib, self.iterations, self.parallelism, Some(32))
+            .map_err(|e| anyhow::anyhow!("Invalid key derivation parameters: {}", e))?;
+        let mut key = Zeroizing::new([0u8; 32]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(secret.as_bytes(), &salt, key.as_mut())
+            .map_err(|e| anyhow::anyhow!("Key derivation failed: {}", e))?;
+        Ok(key)
+    }
+}
+
+/// A new recovery key, as 64 hex digits in groups of eight
+pub fn generate_recovery_key() -> Zeroizing<String> {
+    let key = random_key();
+    let digits = Zeroizing::new(hex::encode(key.as_ref()));
+    let groups: Vec<&str> = (0..digits.len()).step_by(8).map(|i| &digits[i..i + 8]).collect();
+    Zeroizing::new(groups.join("-"))
+}
+
+/// A recovery key as typed back in, without its separators
+pub fn normalize_recovery_key(recovery_key: &str) -> Zeroizing<String> {
+    Zeroizing::new(
+        recovery_key
+            .chars()
+            .filter(|c| c.is_ascii_hexdigit())
+            .map(|c| c.to_ascii_lowercase())
+            .collect(),
+    )
+}
+
+/// Size of the encryption of `plaintext` bytes
+pub fn encrypted_size(plaintext: u64) -> u64 {
+    let chunks = plaintext.div_ceil(CHUNK_LENGTH as u64).max(1);
+    HEADER_LENGTH as u64 + plaintext + chunks * TAG_LENGTH as u64
+}
+
+/// Size of the plaintext of an encrypted file, or `None` if no encryption is that size
+pub fn plaintext_size(encrypted: u64) -> Option<u64> {
+    let sealed = encrypted.checked_sub(HEADER_LENGTH as u64).filter(|sealed| *sealed >= TAG_LENGTH as u64)?;
+    let chunks = sealed.div_ceil((CHUNK_LENGTH + TAG_LENGTH) as u64);
+    sealed.checked_sub(chunks * TAG_LENGTH as u64)
+}
+
+/// Encrypt everything `reader` yields into `writer`
+///
+/// Chunks are sealed with the STREAM construction and the header as
+/// associated data, so reordering, truncating or swapping the key id is
+/// detected on decryption.
+pub fn encrypt_stream(key: &DataKey, mut reader: impl Read, mut writer: impl Write) -> Result<()> {
+    let mut header = [0u8; HEADER_LENGTH];
+    header[..MAGIC.len()].copy_from_slice(MAGIC);
+    header[MAGIC.len()..MAGIC.len() + KEY_ID_LENGTH].copy_from_slice(key.id.as_bytes());
+    OsRng.fill_bytes(&mut header[MAGIC.len() + KEY_ID_LENGTH..]);
+    writer.write_all(&header)?;
+
+    let nonce_prefix = GenericArray::from_slice(&header[MAGIC.len() + KEY_ID_LENGTH..]);
+    let mut encryptor = EncryptorBE32::from_aead(key.cipher(), nonce_prefix);
+    let mut chunk = read_chunk(&mut reader, CHUNK_LENGTH)?;
+    loop {
+        let next = read_chunk(&mut reader, CHUNK_LENGTH)?;
+        let payload = Payload { msg: chunk.as_slice(), aad: &header };
+        if next.is_empty() {
+            let sealed = encryptor.encrypt_last(payload).map_err(|_| anyhow::anyhow!("Encryption failed"))?;
+            writer.write_all(&sealed)?;
+            break;
+        }
+        let sealed = encryptor.encrypt_next(payload).map_err(|_| anyhow::anyhow!("Encryption failed"))?;
+        writer.write_all(&sealed)?;
+        chunk = next;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Decrypt what [`encrypt_stream`] wrote, with whichever key of the keyring it names
+pub fn decrypt_stream(keyring: &Keyring, mut reader: impl Read, mut writer: impl Write) -> Result<()> {
+    let mut header = [0u8; HEADER_LENGTH];
+    reader.read_exact(&mut header).context("Not an encrypted file")?;
+    if &header[..MAGIC.len()] != MAGIC {
+        return Err(anyhow::anyhow!("Not an encrypted file"));
+    }
+    let key_id = Uuid::from_slice(&header[MAGIC.len()..MAGIC.len() + KEY_ID_LENGTH])?;
+    let key = keyring
+        .get(&key_id)
+        .ok_or_else(|| anyhow::anyhow!("Encrypted with key {}, which is not in the keyring", key_id))?;
+
+    let nonce_prefix = GenericArray::from_slice(&header[MAGIC.len() + KEY_ID_LENGTH..]);
+    let mut decryptor = DecryptorBE32::from_aead(key.cipher(), nonce_prefix);
+    let tampered = || anyhow::anyhow!("Encrypted file is damaged or was tampered with");
+    let mut chunk = read_chunk(&mut reader, CHUNK_LENGTH + TAG_LENGTH)?;
+    loop {
+        let next = read_chunk(&mut reader, CHUNK_LENGTH + TAG_LENGTH)?;
+        let payload = Payload { msg: chunk.as_slice(), aad: &header };
+        if next.is_empty() {
+            writer.write_all(&decryptor.decrypt_last(payload).map_err(|_| tampered())?)?;
+            break;
+        }
+        writer.write_all(&decryptor.decrypt_next(payload).map_err(|_| tampered())?)?;
+        chunk = next;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn encrypt_bytes(key: &DataKey, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let mut encrypted = Vec::with_capacity(encrypted_size(plaintext.len() as u64) as usize);
+    encrypt_stream(key, plaintext, &mut encrypted)?;
+    Ok(encrypted)
+}
+
+pub fn decrypt_bytes(keyring: &Keyring, encrypted: &[u8]) -> Result<Vec<u8>> {
+    let mut plaintext = Vec::with_capacity(encrypted.len());
+    decrypt_stream(keyring, encrypted, &mut plaintext)?;
+    Ok(plaintext)
+}
+
+/// Encrypt a file, writing through a temporary file next to `to`
+pub fn encrypt_file(key: &DataKey, from: &Utf8Path, to: &Utf8Path) -> Result<()> {
+    let reader = BufReader::new(File::open(from).with_context(|| format!("Failed to open {}", from))?);
+    write_through_partial(to, |writer| encrypt_stream(key, reader, writer))
+}
+
+/// Decrypt a file, writing through a temporary file next to `to` so a damaged
+/// file never leaves partial plaintext behind
+pub fn decrypt_file(keyring: &Keyring, from: &Utf8Path, to: &Utf8Path) -> Result<()> {
+    let reader = BufReader::new(File::open(from).with_context(|| format!("Failed to open {}", from))?);
+    write_through_partial(to, |writer| decrypt_stream(keyring, reader, writer))
+}
+
+fn write_through_partial(to: &Utf8Path, write: impl FnOnce(&mut BufWriter<File>) -> Result<()>) -> Result<()> {
+    if let Some(parent) = to.parent().filter(|parent| !parent.as_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    let partial = Utf8PathBuf::from(format!("{}.partial", to));
+    let mut writer = BufWriter::new(File::create(&partial)?);
+    if let Err(e) = write(&mut writer) {
+        drop(writer);
+        let _ = fs::remove_file(&partial);
+        return Err(e);
+    }
+    drop(writer);
+    fs::rename(&partial, to)?;
+    Ok(())
+}
+
+/// Fill a buffer of up to `length` bytes, short only at the end of the input
+fn read_chunk(reader: &mut impl Read, length: usize) -> Result<Vec<u8>> {
+    let mut chunk = Vec::with_capacity(length);
+    reader.take(length as u64).read_to_end(&mut chunk)?;
+    Ok(chunk)
+}
+
+fn random_key() -> SecretKey {
+    let mut key = Zeroizing::new([0u8; 32]);
+    OsRng.fill_bytes(key.as_mut());
+    key
+}
+
+/// Encrypts objects on their way into another backend and decrypts them on the way out
+///
+/// Keys stay as they are, so a replica keeps its bag layout, and sizes are
+/// reported as the plaintext's, so a stored copy compares directly with the
+/// bag and its manifests.
+pub struct EncryptedStorage {
+    inner: Box<dyn StorageBackend>,
+    keyring: Option<Arc<Keyring>>,
+}
+
+impl EncryptedStorage {
+    /// Without a keyring, as while encryption is locked, the storage is unavailable
+    pub fn new(inner: Box<dyn StorageBackend>, keyring: Option<Arc<Keyring>>) -> Self {
+        Self { inner, keyring }
+    }
+
+    fn keyring(&self) -> Result<Arc<Keyring>> {
+        self.keyring.clone().ok_or_else(|| anyhow::anyhow!("Encryption is locked"))
+    }
+
+    fn plaintext_object(object: StoredObject) -> StoredObject {
+        StoredObject {
+            size: plaintext_size(object.size).unwrap_or(object.size),
+            ..object
+        }
+    }
+}
+
+/// A temporary file for one encrypted object
+fn scratch_path() -> Result<Utf8PathBuf> {
+    let name = format!("cwpt-{}.{}", Uuid::new_v4(), ENCRYPTED_EXTENSION);
+    Utf8PathBuf::from_path_buf(std::env::temp_dir().join(name))
+        .map_err(|_| anyhow::anyhow!("Non-UTF8 temporary directory"))
+}
+
+#[async_trait]
+impl StorageBackend for EncryptedStorage {
+    fn location(&self) -> String {
+        self.inner.location()
+    }
+
+    async fn is_available(&self) -> bool {
+        self.keyring.is_some() && self.inner.is_available().await
+    }
+
+    async fn put_file(&self, key: &str, source: &Utf8Path) -> Result<()> {
+        let keyring = self.keyring()?;
+        let (source, scratch) = (source.to_path_buf(), scratch_path()?);
+        let encrypted = scratch.clone();
+        tokio::task::spawn_blocking(move || encrypt_file(keyring.current(), &source, &encrypted)).await??;
+        let stored = self.inner.put_file(key, &scratch).await;
+        let _ = fs::remove_file(&scratch);
+        stored
+    }
+
+    async fn put_bytes(&self, key: &str, bytes: Vec<u8>) -> Result<()> {
+        let encrypted = encrypt_bytes(self.keyring()?.current(), &bytes)?;
+        self.inner.put_bytes(key, encrypted).await
+    }
+
+    async fn get_file(&self, key: &str, destination: &Utf8Path) -> Result<()> {
+        let keyring = self.keyring()?;
+        let scratch = scratch_path()?;
+        let fetched = self.inner.get_file(key, &scratch).await;
+        let decrypted = match fetched {
+            Ok(()) => {
+                let (from, to) = (scratch.clone(), destination.to_path_buf());
+                tokio::task::spawn_blocking(move || decrypt_file(&keyring, &from, &to))
+                    .await
+                    .map_err(anyhow::Error::from)
+                    .and_then(|result| result.with_context(|| format!("Failed to decrypt {}", key)))
+            }
+            Err(e) => Err(e),
+        };
+        let _ = fs::remove_file(&scratch);
+        decrypted
+    }
+
+    async fn get_bytes(&self, key: &str) -> Result<Vec<u8>> {
+        let keyring = self.keyring()?;
+        decrypt_bytes(&keyring, &self.inner.get_bytes(key).await?).with_context(|| format!("Failed to decrypt {}", key))
+    }
+
+    async fn head(&self, key: &str) -> Result<Option<StoredObject>> {
+        Ok(self.inner.head(key).await?.map(Self::plaintext_object))
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredObject>> {
+        Ok(self.inner.list(prefix).await?.into_iter().map(Self::plaintext_object).collect())
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.inner.delete(key).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::storage::LocalStorage;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_stream_round_trip() {
+        let mut keyring = Keyring::generate();
+        let old = keyring.current().clone();
+        keyring.add(DataKey::generate());
+
+        for length in [0, 1, CHUNK_LENGTH - 1, CHUNK_LENGTH, CHUNK_LENGTH + 1, 3 * CHUNK_LENGTH + 17] {
+            let plaintext: Vec<u8> = (0..length).map(|i| (i % 251) as u8).collect();
+            let encrypted = encrypt_bytes(keyring.current(), &plaintext).unwrap();
+            assert_eq!(encrypted.len() as u64, encrypted_size(length as u64));
+            assert_eq!(plaintext_size(encrypted.len() as u64), Some(length as u64));
+            assert_eq!(decrypt_bytes(&keyring, &encrypted).unwrap(), plaintext);
+        }
+
+        // Content written before a rotation still decrypts
+        let encrypted = encrypt_bytes(&old, b"before rotation").unwrap();
+        assert_eq!(decrypt_bytes(&keyring, &encrypted).unwrap(), b"before rotation");
+        assert!(decrypt_bytes(&Keyring::generate(), &encrypted).is_err());
+
+        // Flipped bits and dropped chunks are caught
+        let plaintext = vec![7u8; 2 * CHUNK_LENGTH];
+        let encrypted = encrypt_bytes(keyring.current(), &plaintext).unwrap();
+        let mut flipped = encrypted.clone();
+        flipped[HEADER_LENGTH + 5] ^= 1;
+        assert!(decrypt_bytes(&keyring, &flipped).is_err());
+        assert!(decrypt_bytes(&keyring, &encrypted[..HEADER_LENGTH + CHUNK_LENGTH + TAG_LENGTH]).is_err());
+        assert!(decrypt_bytes(&keyring, b"plain text").is_err());
+    }
+
+    #[test]
+    fn test_wrapped_keys() {
+        let mut keyring = Keyring::generate();
+        let wrapped = WrappedKey::wrap("correct horse", keyring.master()).unwrap();
+        assert!(wrapped.unwrap("wrong horse").unwrap().is_none());
+        let master = wrapped.unwrap("correct horse").unwrap().unwrap();
+        assert_eq!(&*master, keyring.master());
+
+        let key = DataKey::generate();
+        let sealed = keyring.seal(&key).unwrap();
+        keyring.add(Keyring::open(&master, key.id, &sealed).unwrap());
+        assert_eq!(keyring.current().key, key.key);
+        assert!(Keyring::open(&master, Uuid::new_v4(), &sealed).is_err());
+
+        let recovery_key = generate_recovery_key();
+        assert_eq!(recovery_key.len(), 64 + 7);
+        let wrapped = WrappedKey::wrap(&normalize_recovery_key(&recovery_key), &master).unwrap();
+        let typed = recovery_key.to_uppercase().replace('-', " ");
+        assert!(wrapped.unwrap(&normalize_recovery_key(&typed)).unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_storage() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        fs::create_dir(root.join("store")).unwrap();
+        let keyring = Arc::new(Keyring::generate());
+        let storage = EncryptedStorage::new(Box::new(LocalStorage::new(root.join("store"))), Some(keyring));
+
+        fs::write(root.join("score.txt"), b"movement one").unwrap();
+        storage.put_file("Score/data/score.txt", &root.join("score.txt")).await.unwrap();
+        let raw = fs::read(root.join("store/Score/data/score.txt")).unwrap();
+        assert!(!raw.windows(8).any(|window| window == b"movement"));
+        assert_eq!(storage.head("Score/data/score.txt").await.unwrap().unwrap().size, 12);
+        assert_eq!(storage.list("Score/").await.unwrap()[0].size, 12);
+
+        storage.get_file("Score/data/score.txt", &root.join("back/score.txt")).await.unwrap();
+        assert_eq!(fs::read(root.join("back/score.txt")).unwrap(), b"movement one");
+        assert_eq!(storage.get_bytes("Score/data/score.txt").await.unwrap(), b"movement one");
+
+        let locked = EncryptedStorage::new(Box::new(LocalStorage::new(root.join("store"))), None);
+        assert!(!locked.is_available().await);
+        assert!(locked.get_bytes("Score/data/score.txt").await.is_err());
+    }
+}
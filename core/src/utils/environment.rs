@@ -0,0 +1,201 @@
+use crate::models::preservation::EnvironmentProfile;
+use crate::utils::bagit::BagItPackage;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+/// Tag file in the bag root that records the environment needed to open the work
+pub const ENVIRONMENT_TAG_FILE: &str = "environment.json";
+
+/// Kinds of component an environment profile can list
+pub const COMPONENT_TYPES: &[&str] = &["software", "plugin", "font", "library"];
+
+/// Contents of the environment tag file
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct EnvironmentTagFile {
+    pub generated_by: String,
+    pub profiles: Vec<EnvironmentEntry>,
+}
+
+/// One attached profile as written into the bag, without database identifiers
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct EnvironmentEntry {
+    pub name: String,
+    pub description: Option<String>,
+    pub operating_system: Option<OperatingSystem>,
+    pub software: Vec<ComponentEntry>,
+    pub plugins: Vec<ComponentEntry>,
+    pub fonts: Vec<ComponentEntry>,
+    pub libraries: Vec<ComponentEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct OperatingSystem {
+    pub name: String,
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ComponentEntry {
+    pub name: String,
+    pub version: Option<String>,
+}
+
+/// Trim a profile's fields and check its components before it is stored
+pub fn normalize_profile(profile: &mut EnvironmentProfile) -> Result<()> {
+    profile.name = profile.name.trim().to_string();
+    if profile.name.is_empty() {
+        return Err(anyhow::anyhow!("Environment profile name cannot be empty"));
+    }
+
+    profile.os_name = non_empty(profile.os_name.take());
+    profile.os_version = non_empty(profile.os_version.take());
+    if profile.os_name.is_none() && profile.os_version.is_some() {
+        return Err(anyhow::anyhow!("An operating system version needs an operating system name"));
+    }
+
+    for component in &mut profile.components {
+        component.component_type = component.component_type.trim().to_lowercase();
+        component.name = component.name.trim().to_string();
+        component.version = non_empty(component.version.take());
+
+        if !COMPONENT_TYPES.contains(&component.component_type.as_str()) {
+            return Err(anyhow::anyhow!(
+                "Unknown component type '{}', expected one of: {}",
+                component.component_type,
+                COMPONENT_TYPES.join(", ")
+            ));
+        }
+        if component.name.is_empty() {
+            return Err(anyhow::anyhow!("Component name cannot be empty"));
+        }
+    }
+
+    Ok(())
+}
+
+/// Build the tag file contents for the profiles attached to a project
+pub fn environment_tag_file(profiles: &[EnvironmentProfile]) -> EnvironmentTagFile {
+    let components_of = |profile: &EnvironmentProfile, component_type: &str| {
+        profile
+            .components
+            .iter()
+            .filter(|c| c.component_type == component_type)
+            .map(|c| ComponentEntry { name: c.name.clone(), version: c.version.clone() })
+            .collect::<Vec<_>>()
+    };
+
+    EnvironmentTagFile {
+        generated_by: "Creative Work Preservation Toolkit v0.1.0".to_string(),
+        profiles: profiles
+            .iter()
+            .map(|profile| EnvironmentEntry {
+                name: profile.name.clone(),
+                description: profile.description.clone(),
+                operating_system: profile.os_name.clone().map(|name| OperatingSystem {
+                    name,
+                    version: profile.os_version.clone(),
+                }),
+                software: components_of(profile, "software"),
+                plugins: components_of(profile, "plugin"),
+                fonts: components_of(profile, "font"),
+                libraries: components_of(profile, "library"),
+            })
+            .collect(),
+    }
+}
+
+/// Write the environment tag file into a bag, or remove it when no profiles are attached
+///
+/// Tag manifests are not touched; refresh them afterwards.
+pub fn write_environment_tag_file(bag: &BagItPackage, profiles: &[EnvironmentProfile]) -> Result<()> {
+    let path = bag.bag_root.join(ENVIRONMENT_TAG_FILE);
+
+    if profiles.is_empty() {
+        if path.exists() {
+            fs::remove_file(&path)?;
+        }
+        return Ok(());
+    }
+
+    let contents = serde_json::to_string_pretty(&environment_tag_file(profiles))?;
+    fs::write(&path, contents)?;
+    Ok(())
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::preservation::EnvironmentComponent;
+    use crate::utils::checksums::ChecksumAlgorithm;
+    use camino::Utf8Path;
+    use tempfile::TempDir;
+
+    fn component(component_type: &str, name: &str, version: Option<&str>) -> EnvironmentComponent {
+        EnvironmentComponent {
+            component_type: component_type.to_string(),
+            name: name.to_string(),
+            version: version.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_normalize_profile() {
+        let mut profile = EnvironmentProfile::new(
+            " Studio Mac ".to_string(),
+            None,
+            Some("macOS".to_string()),
+            Some(" ".to_string()),
+            vec![component(" Plugin", "Nik Collection ", Some("6.0"))],
+        );
+        normalize_profile(&mut profile).unwrap();
+        assert_eq!(profile.name, "Studio Mac");
+        assert_eq!(profile.os_version, None);
+        assert_eq!(profile.components[0], component("plugin", "Nik Collection", Some("6.0")));
+
+        let mut bad_type = EnvironmentProfile::new("Laptop".to_string(), None, None, None, vec![component("driver", "x", None)]);
+        assert!(normalize_profile(&mut bad_type).is_err());
+
+        let mut version_only = EnvironmentProfile::new("Laptop".to_string(), None, None, Some("14".to_string()), vec![]);
+        assert!(normalize_profile(&mut version_only).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_write_environment_tag_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let bag = BagItPackage::new(root.join("bag")).unwrap();
+        bag.create_bagit_declaration().unwrap();
+
+        let profile = EnvironmentProfile::new(
+            "Print studio".to_string(),
+            None,
+            Some("Windows".to_string()),
+            Some("11".to_string()),
+            vec![
+                component("software", "InDesign", Some("2024")),
+                component("font", "Minion Pro", None),
+            ],
+        );
+
+        write_environment_tag_file(&bag, &[profile]).unwrap();
+        bag.create_tag_manifest(ChecksumAlgorithm::Sha256).await.unwrap();
+
+        let written: EnvironmentTagFile =
+            serde_json::from_str(&fs::read_to_string(bag.bag_root.join(ENVIRONMENT_TAG_FILE)).unwrap()).unwrap();
+        assert_eq!(written.profiles[0].software[0].name, "InDesign");
+        assert_eq!(written.profiles[0].fonts[0].version, None);
+        assert!(written.profiles[0].plugins.is_empty());
+        assert_eq!(written.profiles[0].operating_system.as_ref().unwrap().version.as_deref(), Some("11"));
+
+        let tag_manifest = fs::read_to_string(bag.tag_manifest_path_for(ChecksumAlgorithm::Sha256)).unwrap();
+        assert!(tag_manifest.contains(ENVIRONMENT_TAG_FILE));
+
+        write_environment_tag_file(&bag, &[]).unwrap();
+        assert!(!bag.bag_root.join(ENVIRONMENT_TAG_FILE).exists());
+    }
+}
@@ -0,0 +1,364 @@
+use crate::utils::bagit::{read_manifest_file, BagItPackage};
+use crate::utils::checksums::{calculate_checksum, ChecksumAlgorithm};
+use anyhow::Result;
+use camino::{Utf8Path, Utf8PathBuf};
+use std::fs;
+use std::io::Write;
+
+/// One line of fetch.txt: a payload file to be retrieved from elsewhere
+#[derive(Debug, Clone, PartialEq)]
+pub struct FetchEntry {
+    pub url: String,
+    /// Size in bytes, or `None` when written as "-"
+    pub length: Option<u64>,
+    /// Path relative to the bag root, always under "data/"
+    pub path: String,
+}
+
+/// Outcome of completing a holey bag
+#[derive(Debug, Default)]
+pub struct FetchReport {
+    pub fetched: Vec<String>,
+    pub already_present: Vec<String>,
+    pub failed: Vec<(String, String)>,
+}
+
+impl FetchReport {
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// Parse fetch.txt content
+pub fn parse_fetch(content: &str) -> Result<Vec<FetchEntry>> {
+    let mut entries = Vec::new();
+
+    for (line_number, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let mut parts = line.splitn(3, [' ', '\t']);
+        let (Some(url), Some(length), Some(path)) = (parts.next(), parts.next(), parts.next()) else {
+            return Err(anyhow::anyhow!("Invalid fetch.txt line {}: {}", line_number + 1, line));
+        };
+
+        let length = match length {
+            "-" => None,
+            length => Some(length.parse::<u64>().map_err(|_| {
+                anyhow::anyhow!("Invalid length on fetch.txt line {}: {}", line_number + 1, length)
+            })?),
+        };
+
+        let path = percent_decode(path.trim_start());
+        if !path.starts_with("data/") || path.split('/').any(|part| part == "..") {
+            return Err(anyhow::anyhow!(
+                "fetch.txt line {} points outside the payload: {}",
+                line_number + 1,
+                path
+            ));
+        }
+
+        entries.push(FetchEntry {
+            url: url.to_string(),
+            length,
+            path,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Format entries as fetch.txt content
+pub fn format_fetch(entries: &[FetchEntry]) -> String {
+    let mut output = String::new();
+    for entry in entries {
+        let length = entry
+            .length
+            .map(|l| l.to_string())
+            .unwrap_or_else(|| "-".to_string());
+        output.push_str(&format!("{} {} {}\n", entry.url, length, encode_fetch_path(&entry.path)));
+    }
+    output
+}
+
+/// Build a file:// URL for a local path
+pub fn file_url(path: &Utf8Path) -> String {
+    let mut url = String::from("file://");
+    for byte in path.as_str().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                url.push(byte as char)
+            }
+            _ => url.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    url
+}
+
+/// The local path a file:// URL refers to, if it is one
+pub fn local_path_from_url(url: &str) -> Option<Utf8PathBuf> {
+    let path = url.strip_prefix("file://")?;
+    // Accept both file:///path and file://localhost/path
+    let path = path.strip_prefix("localhost").unwrap_or(path);
+    Some(Utf8PathBuf::from(percent_decode(path)))
+}
+
+/// Register a file on external storage as part of the payload without copying it
+///
+/// The file is hashed now so that the bag's manifests cover it; it is listed
+/// in fetch.txt with a file:// URL so `complete_fetch` can pull it in later.
+pub async fn add_fetch_reference(
+    bag: &BagItPackage,
+    source_path: &Utf8Path,
+    payload_path: &str,
+) -> Result<FetchEntry> {
+    let payload_path = if payload_path.starts_with("data/") {
+        payload_path.to_string()
+    } else {
+        format!("data/{}", payload_path.trim_start_matches('/'))
+    };
+    if payload_path.split('/').any(|part| part == "..") {
+        return Err(anyhow::anyhow!("Payload path escapes the bag: {}", payload_path));
+    }
+
+    let source_path = source_path.canonicalize_utf8()?;
+    let length = fs::metadata(&source_path)?.len();
+
+    let mut entries = bag.read_fetch()?;
+    if entries.iter().any(|e| e.path == payload_path) || bag.bag_root.join(&payload_path).exists() {
+        return Err(anyhow::anyhow!("Payload path already in use: {}", payload_path));
+    }
+
+    let mut algorithms: Vec<ChecksumAlgorithm> = bag
+        .manifest_algorithms()?
+        .iter()
+        .filter_map(|name| ChecksumAlgorithm::from_bagit_name(name))
+        .collect();
+    if algorithms.is_empty() {
+        algorithms.push(ChecksumAlgorithm::Sha256);
+    }
+
+    for algorithm in algorithms {
+        let checksum = calculate_checksum(&source_path, algorithm).await?;
+        let mut manifest = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(bag.manifest_path_for(algorithm))?;
+        writeln!(manifest, "{}  {}", checksum, payload_path)?;
+    }
+
+    let entry = FetchEntry {
+        url: file_url(&source_path),
+        length: Some(length),
+        path: payload_path,
+    };
+    entries.push(entry.clone());
+    bag.write_fetch(&entries)?;
+
+    Ok(entry)
+}
+
+/// Fetch every missing payload file listed in fetch.txt from its local source
+///
+/// Each file is copied next to its destination, checked against every
+/// payload manifest, and only then moved into place. Files that are not on
+/// a file:// URL are reported as failures rather than downloaded.
+pub async fn complete_fetch(bag: &BagItPackage) -> Result<FetchReport> {
+    let mut report = FetchReport::default();
+
+    let mut manifests = Vec::new();
+    for name in bag.manifest_algorithms()? {
+        if let Some(algorithm) = ChecksumAlgorithm::from_bagit_name(&name) {
+            manifests.push((algorithm, read_manifest_file(&bag.manifest_path_for(algorithm))?));
+        }
+    }
+
+    for entry in bag.read_fetch()? {
+        let destination = bag.bag_root.join(&entry.path);
+        if destination.exists() {
+            report.already_present.push(entry.path);
+            continue;
+        }
+
+        match fetch_entry(&entry, &destination, &manifests).await {
+            Ok(()) => report.fetched.push(entry.path),
+            Err(e) => report.failed.push((entry.path, e.to_string())),
+        }
+    }
+
+    Ok(report)
+}
+
+async fn fetch_entry(
+    entry: &FetchEntry,
+    destination: &Utf8Path,
+    manifests: &[(ChecksumAlgorithm, Vec<(String, String)>)],
+) -> Result<()> {
+    let source = local_path_from_url(&entry.url)
+        .ok_or_else(|| anyhow::anyhow!("Only file:// URLs can be fetched: {}", entry.url))?;
+    if !source.is_file() {
+        return Err(anyhow::anyhow!("Source is not available: {}", source));
+    }
+
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let partial = Utf8PathBuf::from(format!("{}.cwpt-partial", destination));
+    fs::copy(&source, &partial)?;
+
+    let verified = verify_fetched_file(entry, &partial, manifests).await;
+    match verified {
+        Ok(()) => {
+            fs::rename(&partial, destination)?;
+            Ok(())
+        }
+        Err(e) => {
+            let _ = fs::remove_file(&partial);
+            Err(e)
+        }
+    }
+}
+
+async fn verify_fetched_file(
+    entry: &FetchEntry,
+    path: &Utf8Path,
+    manifests: &[(ChecksumAlgorithm, Vec<(String, String)>)],
+) -> Result<()> {
+    if let Some(expected_length) = entry.length {
+        let actual_length = fs::metadata(path)?.len();
+        if actual_length != expected_length {
+            return Err(anyhow::anyhow!(
+                "Size mismatch: expected {} bytes, got {}",
+                expected_length,
+                actual_length
+            ));
+        }
+    }
+
+    let mut checked = false;
+    for (algorithm, entries) in manifests {
+        let Some((expected, _)) = entries.iter().find(|(_, p)| *p == entry.path) else {
+            continue;
+        };
+        let actual = calculate_checksum(path, *algorithm).await?;
+        if !actual.eq_ignore_ascii_case(expected) {
+            return Err(anyhow::anyhow!("Checksum mismatch ({})", algorithm.bagit_name()));
+        }
+        checked = true;
+    }
+
+    if !checked {
+        return Err(anyhow::anyhow!("File is not listed in any payload manifest"));
+    }
+
+    Ok(())
+}
+
+// fetch.txt paths must not contain line breaks, and "%" introduces an escape
+fn encode_fetch_path(path: &str) -> String {
+    path.replace('%', "%25").replace('\n', "%0A").replace('\r', "%0D")
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+
+    while index < bytes.len() {
+        if bytes[index] == b'%' && index + 2 < bytes.len() {
+            let hex = &bytes[index + 1..index + 3];
+            if hex.iter().all(u8::is_ascii_hexdigit) {
+                let hex = std::str::from_utf8(hex).unwrap_or_default();
+                decoded.push(u8::from_str_radix(hex, 16).unwrap_or_default());
+                index += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[index]);
+        index += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_fetch_round_trip() {
+        let entries = vec![
+            FetchEntry {
+                url: "file:///mnt/footage/take%201.mov".to_string(),
+                length: Some(1024),
+                path: "data/footage/take 1.mov".to_string(),
+            },
+            FetchEntry {
+                url: "https://example.edu/cache.bin".to_string(),
+                length: None,
+                path: "data/cache.bin".to_string(),
+            },
+        ];
+
+        let content = format_fetch(&entries);
+        assert_eq!(parse_fetch(&content).unwrap(), entries);
+        assert!(parse_fetch("file:///x 10 ../escape").is_err());
+        assert!(parse_fetch("file:///x ten data/x").is_err());
+    }
+
+    #[test]
+    fn test_file_urls() {
+        let url = file_url(Utf8Path::new("/mnt/drive/take 1.mov"));
+        assert_eq!(url, "file:///mnt/drive/take%201.mov");
+        assert_eq!(local_path_from_url(&url).unwrap(), "/mnt/drive/take 1.mov");
+        assert!(local_path_from_url("https://example.edu/x").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_holey_bag_completes_from_local_source() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let external = root.join("external-drive");
+        fs::create_dir_all(&external).unwrap();
+        fs::write(external.join("render.exr"), b"render cache").unwrap();
+
+        let bag = BagItPackage::new(root.join("bag")).unwrap();
+        fs::write(bag.data_dir.join("scene.blend"), b"scene").unwrap();
+        bag.create_bagit_declaration().unwrap();
+        bag.create_manifest().await.unwrap();
+
+        add_fetch_reference(&bag, &external.join("render.exr"), "renders/render.exr")
+            .await
+            .unwrap();
+        assert_eq!(bag.calculate_payload_oxum().unwrap(), (17, 2));
+
+        // Regenerating the manifest keeps the entry for the unfetched file
+        bag.create_manifest().await.unwrap();
+        let issues = bag.validate().await.unwrap();
+        assert_eq!(issues, vec!["File not yet fetched: data/renders/render.exr".to_string()]);
+
+        let report = complete_fetch(&bag).await.unwrap();
+        assert!(report.is_complete());
+        assert_eq!(report.fetched, vec!["data/renders/render.exr".to_string()]);
+        assert!(bag.validate().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_corrupted_source_is_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        fs::write(root.join("source.bin"), b"original").unwrap();
+
+        let bag = BagItPackage::new(root.join("bag")).unwrap();
+        bag.create_bagit_declaration().unwrap();
+        bag.create_manifest().await.unwrap();
+        add_fetch_reference(&bag, &root.join("source.bin"), "source.bin").await.unwrap();
+
+        fs::write(root.join("source.bin"), b"modified").unwrap();
+        let report = complete_fetch(&bag).await.unwrap();
+        assert_eq!(report.failed.len(), 1);
+        assert!(!bag.data_dir.join("source.bin").exists());
+    }
+}
@@ -121,7 +121,7 @@ pub fn find_common_root(paths: &[String]) -> Result<Utf8PathBuf> {
 
     if paths.len() == 1 {
         let path = Utf8Path::new(&paths[0]);
-        return Ok(if path.is_file() {
+        return Ok(if !path.is_dir() {
             path.parent().unwrap_or(path).to_path_buf()
         } else {
             path.to_path_buf()
@@ -0,0 +1,196 @@
+use crate::models::preservation::ProjectFile;
+use crate::utils::bagit::BagItPackage;
+use anyhow::Result;
+use camino::Utf8Path;
+use file_format::FileFormat;
+use std::fs;
+
+/// How likely a file format is to become unreadable over time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RiskLevel {
+    Low,
+    Medium,
+    High,
+}
+
+impl RiskLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RiskLevel::Low => "low",
+            RiskLevel::Medium => "medium",
+            RiskLevel::High => "high",
+        }
+    }
+
+    /// Numeric rank stored alongside the level so the highest risk can be found in SQL
+    pub fn rank(&self) -> i64 {
+        match self {
+            RiskLevel::Low => 1,
+            RiskLevel::Medium => 2,
+            RiskLevel::High => 3,
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "low" => Some(RiskLevel::Low),
+            "medium" => Some(RiskLevel::Medium),
+            "high" => Some(RiskLevel::High),
+            _ => None,
+        }
+    }
+}
+
+/// Open, well documented formats with broad tool support
+const LOW_RISK_MEDIA_TYPES: &[&str] = &[
+    "application/pdf",
+    "application/xml",
+    "application/json",
+    "image/jpeg",
+    "image/png",
+    "image/tiff",
+    "image/jp2",
+    "image/svg+xml",
+    "image/x-adobe-dng",
+    "audio/wav",
+    "audio/x-wav",
+    "audio/flac",
+    "audio/mpeg",
+    "video/mp4",
+    "video/x-matroska",
+];
+
+/// Proprietary or container formats that current software still opens reliably
+const MEDIUM_RISK_MEDIA_TYPES: &[&str] = &[
+    "image/vnd.adobe.photoshop",
+    "application/postscript",
+    "application/zip",
+    "image/gif",
+    "image/webp",
+    "image/heic",
+    "image/heif",
+    "video/quicktime",
+    "audio/aac",
+    "audio/mp4",
+];
+
+/// Application project files that depend on one vendor's software
+const HIGH_RISK_EXTENSIONS: &[&str] = &[
+    "indd", "aep", "prproj", "fla", "sketch", "xd", "fig", "procreate", "clip", "sai", "ppj", "veg",
+];
+
+/// Assess the preservation risk of a file from its extension and detected media type
+pub fn assess_format(path: &Utf8Path, media_type: &str) -> RiskLevel {
+    let extension = path.extension().map(|e| e.to_ascii_lowercase()).unwrap_or_default();
+    if HIGH_RISK_EXTENSIONS.contains(&extension.as_str()) {
+        return RiskLevel::High;
+    }
+
+    if LOW_RISK_MEDIA_TYPES.contains(&media_type) {
+        RiskLevel::Low
+    } else if MEDIUM_RISK_MEDIA_TYPES.contains(&media_type)
+        || media_type.starts_with("application/vnd.openxmlformats-officedocument.")
+        || media_type.starts_with("application/vnd.oasis.opendocument.")
+    {
+        RiskLevel::Medium
+    } else if media_type.starts_with("text/") {
+        RiskLevel::Low
+    } else if ["image/", "audio/", "video/"].iter().any(|prefix| media_type.starts_with(prefix)) {
+        RiskLevel::Medium
+    } else {
+        RiskLevel::High
+    }
+}
+
+/// Detect a file's media type from its content, falling back to the extension for text formats
+pub fn detect_media_type(path: &Utf8Path) -> Result<String> {
+    let media_type = FileFormat::from_file(path)?.media_type().to_string();
+    if media_type != "application/octet-stream" {
+        return Ok(media_type);
+    }
+
+    // Plain text formats carry no signature for content detection to find
+    let by_extension = match path.extension().map(|e| e.to_ascii_lowercase()).as_deref() {
+        Some("txt") | Some("text") => "text/plain",
+        Some("md") | Some("markdown") => "text/markdown",
+        Some("csv") => "text/csv",
+        Some("html") | Some("htm") => "text/html",
+        Some("css") => "text/css",
+        Some("json") => "application/json",
+        Some("xml") => "application/xml",
+        Some("svg") => "image/svg+xml",
+        _ => return Ok(media_type),
+    };
+    Ok(by_extension.to_string())
+}
+
+/// List a bag's payload files with their size, media type and risk
+///
+/// Files still listed in fetch.txt are catalogued from their fetch.txt length with an unknown type.
+pub fn catalog_payload(bag: &BagItPackage, project_id: &str) -> Result<Vec<ProjectFile>> {
+    let unfetched = bag.unfetched_entries()?;
+    let mut files = Vec::new();
+
+    for (_, path) in bag.read_manifest()? {
+        let file_path = bag.bag_root.join(&path);
+        let (size, media_type) = if file_path.is_file() {
+            (fs::metadata(&file_path)?.len(), detect_media_type(&file_path)?)
+        } else {
+            let length = unfetched
+                .iter()
+                .find(|entry| entry.path == path)
+                .and_then(|entry| entry.length)
+                .unwrap_or(0);
+            (length, "application/octet-stream".to_string())
+        };
+
+        let risk = assess_format(Utf8Path::new(&path), &media_type);
+        files.push(ProjectFile {
+            archived_project_id: project_id.to_string(),
+            path,
+            size: size as i64,
+            media_type,
+            risk_level: risk.as_str().to_string(),
+        });
+    }
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_assess_format() {
+        assert_eq!(assess_format(Utf8Path::new("data/scan.tif"), "image/tiff"), RiskLevel::Low);
+        assert_eq!(assess_format(Utf8Path::new("data/notes.md"), "text/markdown"), RiskLevel::Low);
+        assert_eq!(
+            assess_format(Utf8Path::new("data/layers.psd"), "image/vnd.adobe.photoshop"),
+            RiskLevel::Medium
+        );
+        assert_eq!(
+            assess_format(Utf8Path::new("data/book.indd"), "application/octet-stream"),
+            RiskLevel::High
+        );
+        assert_eq!(assess_format(Utf8Path::new("data/blob.bin"), "application/octet-stream"), RiskLevel::High);
+        assert!(RiskLevel::High > RiskLevel::Low);
+        assert_eq!(RiskLevel::parse("medium"), Some(RiskLevel::Medium));
+    }
+
+    #[tokio::test]
+    async fn test_catalog_payload() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let bag = BagItPackage::new(root.join("bag")).unwrap();
+        fs::write(bag.data_dir.join("statement.txt"), "Artist statement").unwrap();
+        bag.create_manifest().await.unwrap();
+
+        let files = catalog_payload(&bag, "p1").unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, "data/statement.txt");
+        assert_eq!(files[0].size, 16);
+        assert_eq!(files[0].risk_level, "low");
+    }
+}
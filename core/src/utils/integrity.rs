@@ -0,0 +1,158 @@
+use crate::models::preservation::IntegrityIssue;
+use crate::utils::bagit::BagItPackage;
+use crate::utils::fetch::local_path_from_url;
+use anyhow::Result;
+use camino::{Utf8Path, Utf8PathBuf};
+use std::fs;
+use walkdir::WalkDir;
+
+/// A bag the vault should hold, as recorded for an archived project
+pub struct ExpectedBag {
+    pub project_id: String,
+    pub bag_root: Utf8PathBuf,
+}
+
+/// Validate every expected bag and look for directories no project accounts for
+///
+/// Missing and invalid bags are critical; unknown directories under the bags
+/// root are reported as warnings since they may be copies made by hand.
+pub async fn check_vault(bags_root: &Utf8Path, expected: &[ExpectedBag]) -> Result<Vec<IntegrityIssue>> {
+    let mut issues = Vec::new();
+
+    for bag in expected {
+        if !bag.bag_root.is_dir() {
+            issues.push(IntegrityIssue {
+                issue_type: "missing_file".to_string(),
+                severity: "critical".to_string(),
+                message: format!("Bag for project {} is missing", bag.project_id),
+                affected_files: vec![bag.bag_root.to_string()],
+            });
+            continue;
+        }
+
+        let problems = bag_problems(&bag.bag_root).await?;
+        if !problems.is_empty() {
+            issues.push(IntegrityIssue {
+                issue_type: "corrupted_file".to_string(),
+                severity: "critical".to_string(),
+                message: format!("Bag for project {} failed validation: {}", bag.project_id, problems.join("; ")),
+                affected_files: vec![bag.bag_root.to_string()],
+            });
+        }
+    }
+
+    if bags_root.is_dir() {
+        for entry in fs::read_dir(bags_root)? {
+            let path = Utf8PathBuf::from_path_buf(entry?.path())
+                .map_err(|p| anyhow::anyhow!("Non-UTF8 path encountered: {}", p.display()))?;
+            if path.is_dir() && !expected.iter().any(|bag| bag.bag_root == path) {
+                issues.push(IntegrityIssue {
+                    issue_type: "external_modification".to_string(),
+                    severity: "warning".to_string(),
+                    message: format!("Directory in the bags layer belongs to no archived project: {}", path),
+                    affected_files: vec![path.to_string()],
+                });
+            }
+        }
+    }
+
+    Ok(issues)
+}
+
+/// Validation problems that make a bag untrustworthy
+///
+/// Files a bag references in fetch.txt are fine as long as their local
+/// source is still there.
+pub async fn bag_problems(bag_root: &Utf8Path) -> Result<Vec<String>> {
+    let package = BagItPackage::open(bag_root.to_path_buf())?;
+    let fetch = package.read_fetch().unwrap_or_default();
+    Ok(package
+        .validate()
+        .await?
+        .into_iter()
+        .filter(|problem| {
+            let Some(path) = problem.strip_prefix("File not yet fetched: ") else {
+                return true;
+            };
+            !fetch
+                .iter()
+                .find(|e| e.path == path)
+                .and_then(|e| local_path_from_url(&e.url))
+                .is_some_and(|source| source.is_file())
+        })
+        .collect())
+}
+
+/// BLAKE3 hash of a directory tree's structure: every file's relative path and size
+///
+/// Snapshots store this per vault layer so a later scan can tell whether
+/// anything was added, removed or resized since. A missing directory hashes
+/// as an empty one.
+pub fn layer_checksum(root: &Utf8Path) -> Result<String> {
+    let mut entries = Vec::new();
+    if root.is_dir() {
+        for entry in WalkDir::new(root).sort_by_file_name() {
+            let entry = entry?;
+            if entry.file_type().is_file() {
+                let relative = entry.path().strip_prefix(root)?.to_string_lossy().replace('\\', "/");
+                entries.push(format!("{}\t{}\n", relative, entry.metadata()?.len()));
+            }
+        }
+    }
+
+    let mut hasher = blake3::Hasher::new();
+    for entry in &entries {
+        hasher.update(entry.as_bytes());
+    }
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_check_vault() {
+        let temp_dir = TempDir::new().unwrap();
+        let bags_root = Utf8Path::from_path(temp_dir.path()).unwrap();
+
+        let healthy = BagItPackage::new(bags_root.join("healthy")).unwrap();
+        healthy.create_bagit_declaration().unwrap();
+        fs::write(healthy.data_dir.join("a.txt"), b"original").unwrap();
+        healthy.create_manifest().await.unwrap();
+
+        let corrupted = BagItPackage::new(bags_root.join("corrupted")).unwrap();
+        corrupted.create_bagit_declaration().unwrap();
+        fs::write(corrupted.data_dir.join("b.txt"), b"original").unwrap();
+        corrupted.create_manifest().await.unwrap();
+        fs::write(corrupted.data_dir.join("b.txt"), b"modified").unwrap();
+
+        fs::create_dir_all(bags_root.join("stray")).unwrap();
+
+        let expected = vec![
+            ExpectedBag { project_id: "p1".to_string(), bag_root: healthy.bag_root.clone() },
+            ExpectedBag { project_id: "p2".to_string(), bag_root: corrupted.bag_root.clone() },
+            ExpectedBag { project_id: "p3".to_string(), bag_root: bags_root.join("gone") },
+        ];
+        let issues = check_vault(bags_root, &expected).await.unwrap();
+
+        let types: Vec<&str> = issues.iter().map(|i| i.issue_type.as_str()).collect();
+        assert_eq!(types, vec!["corrupted_file", "missing_file", "external_modification"]);
+        assert!(issues[2].affected_files[0].ends_with("stray"));
+    }
+
+    #[test]
+    fn test_layer_checksum_tracks_structure() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        fs::write(root.join("a.txt"), b"one").unwrap();
+
+        let before = layer_checksum(root).unwrap();
+        assert_eq!(layer_checksum(root).unwrap(), before);
+
+        fs::write(root.join("b.txt"), b"two").unwrap();
+        assert_ne!(layer_checksum(root).unwrap(), before);
+        assert_ne!(layer_checksum(&root.join("missing")).unwrap(), before);
+    }
+}
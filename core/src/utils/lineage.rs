@@ -0,0 +1,206 @@
+use crate::models::preservation::{LineageNode, ProjectRelation, VersionDiff};
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Relation types a project link may have
+pub const RELATION_TYPES: &[&str] = &["version", "branch"];
+
+/// Check a proposed parent → child link before it is stored
+pub fn validate_new_relation(
+    relations: &[ProjectRelation],
+    parent_id: &str,
+    child_id: &str,
+    relation_type: &str,
+) -> Result<()> {
+    if !RELATION_TYPES.contains(&relation_type) {
+        return Err(anyhow::anyhow!("Unknown relation type: {}", relation_type));
+    }
+    if parent_id == child_id {
+        return Err(anyhow::anyhow!("A project cannot be its own version"));
+    }
+    if relations
+        .iter()
+        .any(|r| r.parent_project_id == parent_id && r.child_project_id == child_id)
+    {
+        return Err(anyhow::anyhow!("Projects are already linked"));
+    }
+    if descendants(relations, child_id).contains(parent_id) {
+        return Err(anyhow::anyhow!("Link would create a cycle in the project history"));
+    }
+    Ok(())
+}
+
+/// Follow parent links up to the first project of a lineage
+///
+/// When a project has several parents, version links are followed before
+/// branch links so the root is the start of the main line of work.
+pub fn find_lineage_root(relations: &[ProjectRelation], project_id: &str) -> String {
+    let mut current = project_id.to_string();
+    let mut visited = HashSet::new();
+
+    while visited.insert(current.clone()) {
+        let parent = relations
+            .iter()
+            .filter(|r| r.child_project_id == current)
+            .min_by_key(|r| (r.relation_type != "version", r.created_at));
+        match parent {
+            Some(relation) => current = relation.parent_project_id.clone(),
+            None => break,
+        }
+    }
+
+    current
+}
+
+/// Build the history tree below a project
+///
+/// A project reachable along several paths appears under each parent, but
+/// its own subtree is only expanded once per path to keep cycles out.
+pub fn build_history_tree(
+    relations: &[ProjectRelation],
+    names: &HashMap<String, String>,
+    root_id: &str,
+) -> LineageNode {
+    let mut path = HashSet::new();
+    build_node(relations, names, root_id, None, &mut path)
+}
+
+fn build_node(
+    relations: &[ProjectRelation],
+    names: &HashMap<String, String>,
+    project_id: &str,
+    via: Option<&ProjectRelation>,
+    path: &mut HashSet<String>,
+) -> LineageNode {
+    path.insert(project_id.to_string());
+
+    let mut child_relations: Vec<&ProjectRelation> = relations
+        .iter()
+        .filter(|r| r.parent_project_id == project_id && !path.contains(&r.child_project_id))
+        .collect();
+    child_relations.sort_by_key(|r| r.created_at);
+
+    let children = child_relations
+        .into_iter()
+        .map(|r| build_node(relations, names, &r.child_project_id, Some(r), path))
+        .collect();
+
+    path.remove(project_id);
+
+    LineageNode {
+        project_id: project_id.to_string(),
+        name: names.get(project_id).cloned().unwrap_or_default(),
+        relation_type: via.map(|r| r.relation_type.clone()),
+        label: via.and_then(|r| r.label.clone()),
+        children,
+    }
+}
+
+fn descendants(relations: &[ProjectRelation], project_id: &str) -> HashSet<String> {
+    let mut found = HashSet::new();
+    let mut pending = vec![project_id.to_string()];
+
+    while let Some(current) = pending.pop() {
+        for relation in relations.iter().filter(|r| r.parent_project_id == current) {
+            if found.insert(relation.child_project_id.clone()) {
+                pending.push(relation.child_project_id.clone());
+            }
+        }
+    }
+
+    found
+}
+
+/// Compare two manifests of (checksum, path) pairs file by file
+pub fn diff_manifests(from: &[(String, String)], to: &[(String, String)]) -> VersionDiff {
+    let from: BTreeMap<&str, String> = from
+        .iter()
+        .map(|(checksum, path)| (path.as_str(), checksum.to_lowercase()))
+        .collect();
+    let to: BTreeMap<&str, String> = to
+        .iter()
+        .map(|(checksum, path)| (path.as_str(), checksum.to_lowercase()))
+        .collect();
+
+    let mut diff = VersionDiff::default();
+
+    for (path, checksum) in &to {
+        match from.get(path) {
+            None => diff.added.push(path.to_string()),
+            Some(previous) if previous != checksum => diff.changed.push(path.to_string()),
+            Some(_) => diff.unchanged_count += 1,
+        }
+    }
+    diff.removed = from
+        .keys()
+        .filter(|path| !to.contains_key(*path))
+        .map(|path| path.to_string())
+        .collect();
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn relation(parent: &str, child: &str, relation_type: &str) -> ProjectRelation {
+        ProjectRelation::new(parent.to_string(), child.to_string(), relation_type.to_string(), None)
+    }
+
+    #[test]
+    fn test_history_tree_and_root() {
+        let relations = vec![
+            relation("sketch", "draft", "version"),
+            relation("draft", "final", "version"),
+            relation("draft", "alt", "branch"),
+        ];
+        let names: HashMap<String, String> = [("sketch", "Sketch"), ("final", "Final")]
+            .iter()
+            .map(|(id, name)| (id.to_string(), name.to_string()))
+            .collect();
+
+        assert_eq!(find_lineage_root(&relations, "alt"), "sketch");
+
+        let tree = build_history_tree(&relations, &names, "sketch");
+        assert_eq!(tree.name, "Sketch");
+        assert!(tree.relation_type.is_none());
+        let draft = &tree.children[0];
+        assert_eq!(draft.project_id, "draft");
+        assert_eq!(draft.children.len(), 2);
+        assert_eq!(draft.children[0].name, "Final");
+        assert_eq!(draft.children[1].relation_type.as_deref(), Some("branch"));
+    }
+
+    #[test]
+    fn test_rejects_cycles_and_duplicates() {
+        let relations = vec![relation("a", "b", "version"), relation("b", "c", "version")];
+
+        assert!(validate_new_relation(&relations, "c", "a", "version").is_err());
+        assert!(validate_new_relation(&relations, "a", "b", "branch").is_err());
+        assert!(validate_new_relation(&relations, "a", "a", "version").is_err());
+        assert!(validate_new_relation(&relations, "a", "c", "remix").is_err());
+        assert!(validate_new_relation(&relations, "a", "c", "branch").is_ok());
+    }
+
+    #[test]
+    fn test_diff_manifests() {
+        let entry = |checksum: &str, path: &str| (checksum.to_string(), path.to_string());
+        let from = vec![
+            entry("aa", "data/keep.txt"),
+            entry("bb", "data/edit.txt"),
+            entry("cc", "data/gone.txt"),
+        ];
+        let to = vec![
+            entry("AA", "data/keep.txt"),
+            entry("b2", "data/edit.txt"),
+            entry("dd", "data/new.txt"),
+        ];
+
+        let diff = diff_manifests(&from, &to);
+        assert_eq!(diff.added, vec!["data/new.txt"]);
+        assert_eq!(diff.removed, vec!["data/gone.txt"]);
+        assert_eq!(diff.changed, vec!["data/edit.txt"]);
+        assert_eq!(diff.unchanged_count, 1);
+    }
+}
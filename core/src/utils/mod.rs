@@ -0,0 +1,32 @@
+pub mod checksums;
+pub mod file_operations;
+pub mod format_risk;
+pub mod bag_info;
+pub mod bag_serialization;
+pub mod bag_update;
+pub mod bagit;
+pub mod bagit_profile;
+pub mod collections;
+pub mod dependencies;
+pub mod derivatives;
+pub mod environment;
+pub mod fetch;
+pub mod integrity;
+pub mod lineage;
+pub mod premis;
+pub mod project_query;
+pub mod quarantine;
+pub mod search;
+pub mod tags;
+pub mod technical_metadata;
+pub mod text_extraction;
+pub mod vra;
+pub mod watch;
+pub mod source_changes;
+pub mod replication;
+pub mod storage;
+#[cfg(feature = "s3")]
+pub mod s3;
+#[cfg(feature = "webdav")]
+pub mod webdav;
+pub mod encryption;
\ No newline at end of file
@@ -0,0 +1,195 @@
+use crate::models::preservation::{ArchivedProject, FileTechnicalMetadata, ProjectFile};
+use crate::utils::bagit::{read_manifest_file, BagItPackage};
+use crate::utils::checksums::ChecksumAlgorithm;
+use crate::utils::vra::escape_xml;
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+const PREMIS_NAMESPACE: &str = "http://www.loc.gov/premis/v3";
+const PREMIS_SCHEMA_LOCATION: &str = "https://www.loc.gov/standards/premis/premis.xsd";
+
+/// Namespace of the technical metadata carried in objectCharacteristicsExtension
+const TECHNICAL_METADATA_NAMESPACE: &str = "urn:cwpt:technical-metadata";
+
+/// A PREMIS file object: one payload file with its fixity, format and technical metadata
+pub struct PremisFile {
+    pub path: String,
+    pub size: i64,
+    pub media_type: String,
+    pub fixity: Vec<(String, String)>, // (PREMIS algorithm name, digest)
+    pub technical: BTreeMap<String, String>,
+}
+
+/// Collect the file objects of a bagged project from its catalog, manifests and technical metadata
+pub fn premis_files(
+    bag: &BagItPackage,
+    files: &[ProjectFile],
+    technical: &[FileTechnicalMetadata],
+) -> Result<Vec<PremisFile>> {
+    let mut manifests = Vec::new();
+    for name in bag.manifest_algorithms()? {
+        if let Some(algorithm) = ChecksumAlgorithm::from_bagit_name(&name) {
+            let entries: BTreeMap<String, String> = read_manifest_file(&bag.manifest_path_for(algorithm))?
+                .into_iter()
+                .map(|(checksum, path)| (path, checksum))
+                .collect();
+            manifests.push((premis_algorithm_name(algorithm), entries));
+        }
+    }
+
+    Ok(files
+        .iter()
+        .map(|file| PremisFile {
+            path: file.path.clone(),
+            size: file.size,
+            media_type: file.media_type.clone(),
+            fixity: manifests
+                .iter()
+                .filter_map(|(algorithm, entries)| {
+                    entries.get(&file.path).map(|digest| (algorithm.to_string(), digest.clone()))
+                })
+                .collect(),
+            technical: technical
+                .iter()
+                .find(|t| t.path == file.path)
+                .map(|t| t.properties.clone())
+                .unwrap_or_default(),
+        })
+        .collect())
+}
+
+/// Render a project as a PREMIS 3.0 document: an intellectual entity and its file objects
+pub fn build_premis_document(project: &ArchivedProject, files: &[PremisFile]) -> String {
+    let mut xml = String::new();
+
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<premis:premis xmlns:premis=\"{}\" xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" xsi:schemaLocation=\"{} {}\" version=\"3.0\">",
+        PREMIS_NAMESPACE, PREMIS_NAMESPACE, PREMIS_SCHEMA_LOCATION
+    );
+
+    let _ = writeln!(xml, "  <premis:object xsi:type=\"premis:intellectualEntity\">");
+    write_identifier(&mut xml, "UUID", &project.id);
+    let _ = writeln!(xml, "    <premis:originalName>{}</premis:originalName>", escape_xml(&project.name));
+    let _ = writeln!(xml, "  </premis:object>");
+
+    for file in files {
+        write_file(&mut xml, project, file);
+    }
+
+    xml.push_str("</premis:premis>\n");
+    xml
+}
+
+// PREMIS requires the elements of an object in schema order
+fn write_file(xml: &mut String, project: &ArchivedProject, file: &PremisFile) {
+    let _ = writeln!(xml, "  <premis:object xsi:type=\"premis:file\">");
+    write_identifier(xml, "local", &format!("{}/{}", project.id, file.path));
+
+    let _ = writeln!(xml, "    <premis:objectCharacteristics>");
+    let _ = writeln!(xml, "      <premis:compositionLevel>0</premis:compositionLevel>");
+    for (algorithm, digest) in &file.fixity {
+        let _ = writeln!(xml, "      <premis:fixity>");
+        let _ = writeln!(xml, "        <premis:messageDigestAlgorithm>{}</premis:messageDigestAlgorithm>", algorithm);
+        let _ = writeln!(xml, "        <premis:messageDigest>{}</premis:messageDigest>", escape_xml(digest));
+        let _ = writeln!(xml, "      </premis:fixity>");
+    }
+    let _ = writeln!(xml, "      <premis:size>{}</premis:size>", file.size);
+    let _ = writeln!(xml, "      <premis:format>");
+    let _ = writeln!(xml, "        <premis:formatDesignation>");
+    let _ = writeln!(xml, "          <premis:formatName>{}</premis:formatName>", escape_xml(&file.media_type));
+    let _ = writeln!(xml, "        </premis:formatDesignation>");
+    let _ = writeln!(xml, "      </premis:format>");
+
+    if !file.technical.is_empty() {
+        let _ = writeln!(xml, "      <premis:objectCharacteristicsExtension>");
+        let _ = writeln!(xml, "        <technicalMetadata xmlns=\"{}\">", TECHNICAL_METADATA_NAMESPACE);
+        for (key, value) in &file.technical {
+            let _ = writeln!(
+                xml,
+                "          <property key=\"{}\">{}</property>",
+                escape_xml(key),
+                escape_xml(value)
+            );
+        }
+        let _ = writeln!(xml, "        </technicalMetadata>");
+        let _ = writeln!(xml, "      </premis:objectCharacteristicsExtension>");
+    }
+    let _ = writeln!(xml, "    </premis:objectCharacteristics>");
+
+    let original_name = file.path.strip_prefix("data/").unwrap_or(&file.path);
+    let _ = writeln!(xml, "    <premis:originalName>{}</premis:originalName>", escape_xml(original_name));
+
+    let _ = writeln!(xml, "    <premis:relationship>");
+    let _ = writeln!(xml, "      <premis:relationshipType>structural</premis:relationshipType>");
+    let _ = writeln!(xml, "      <premis:relationshipSubType>is included in</premis:relationshipSubType>");
+    let _ = writeln!(xml, "      <premis:relatedObjectIdentifier>");
+    let _ = writeln!(xml, "        <premis:relatedObjectIdentifierType>UUID</premis:relatedObjectIdentifierType>");
+    let _ = writeln!(
+        xml,
+        "        <premis:relatedObjectIdentifierValue>{}</premis:relatedObjectIdentifierValue>",
+        escape_xml(&project.id)
+    );
+    let _ = writeln!(xml, "      </premis:relatedObjectIdentifier>");
+    let _ = writeln!(xml, "    </premis:relationship>");
+
+    let _ = writeln!(xml, "  </premis:object>");
+}
+
+fn write_identifier(xml: &mut String, identifier_type: &str, value: &str) {
+    let _ = writeln!(xml, "    <premis:objectIdentifier>");
+    let _ = writeln!(xml, "      <premis:objectIdentifierType>{}</premis:objectIdentifierType>", identifier_type);
+    let _ = writeln!(xml, "      <premis:objectIdentifierValue>{}</premis:objectIdentifierValue>", escape_xml(value));
+    let _ = writeln!(xml, "    </premis:objectIdentifier>");
+}
+
+/// Algorithm names as registered in the Library of Congress cryptographic hash vocabulary
+fn premis_algorithm_name(algorithm: ChecksumAlgorithm) -> &'static str {
+    match algorithm {
+        ChecksumAlgorithm::Md5 => "MD5",
+        ChecksumAlgorithm::Sha256 => "SHA-256",
+        ChecksumAlgorithm::Sha512 => "SHA-512",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use camino::Utf8Path;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_premis_document() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let bag = BagItPackage::new(root.join("bag")).unwrap();
+        fs::write(bag.data_dir.join("photo.jpg"), b"not really a jpeg").unwrap();
+        bag.create_manifest().await.unwrap();
+
+        let project = ArchivedProject::new("Street <Series>".to_string(), None, 1, 17);
+        let files = vec![ProjectFile {
+            archived_project_id: project.id.clone(),
+            path: "data/photo.jpg".to_string(),
+            size: 17,
+            media_type: "image/jpeg".to_string(),
+            risk_level: "low".to_string(),
+        }];
+        let technical = vec![FileTechnicalMetadata {
+            path: "data/photo.jpg".to_string(),
+            properties: [("exif.model".to_string(), "X100V".to_string())].into_iter().collect(),
+        }];
+
+        let premis = premis_files(&bag, &files, &technical).unwrap();
+        assert_eq!(premis[0].fixity.len(), 1);
+        assert_eq!(premis[0].fixity[0].0, "SHA-256");
+
+        let xml = build_premis_document(&project, &premis);
+        assert!(xml.contains("<premis:originalName>Street &lt;Series&gt;</premis:originalName>"));
+        assert!(xml.contains("<premis:messageDigestAlgorithm>SHA-256</premis:messageDigestAlgorithm>"));
+        assert!(xml.contains("<property key=\"exif.model\">X100V</property>"));
+        assert!(xml.contains("<premis:originalName>photo.jpg</premis:originalName>"));
+    }
+}
@@ -0,0 +1,324 @@
+use crate::models::preservation::{ProjectQuery, ProjectSort};
+use crate::utils::format_risk::RiskLevel;
+use serde_json::Value;
+
+pub const DEFAULT_PAGE_SIZE: u32 = 50;
+pub const MAX_PAGE_SIZE: u32 = 500;
+
+/// Columns selected for every archived project row
+pub const PROJECT_COLUMNS: &str = "p.id, p.name, p.description, p.archived_at, p.bagit_package_id, \
+     p.file_count, p.total_size, p.is_quarantined, p.created_at, p.updated_at";
+
+/// Highest file risk of a project; NULL when none of its files have been catalogued
+const PROJECT_RISK_RANK: &str =
+    "(SELECT MAX(pf.risk_rank) FROM project_files pf WHERE pf.archived_project_id = p.id)";
+
+/// Size buckets shown as the size range facet: (value, label, lower bound, upper bound)
+pub const SIZE_RANGES: &[(&str, &str, i64, i64)] = &[
+    ("under_10mb", "Under 10 MB", 0, 10 * 1024 * 1024),
+    ("10mb_100mb", "10 MB to 100 MB", 10 * 1024 * 1024, 100 * 1024 * 1024),
+    ("100mb_1gb", "100 MB to 1 GB", 100 * 1024 * 1024, 1024 * 1024 * 1024),
+    ("over_1gb", "Over 1 GB", 1024 * 1024 * 1024, i64::MAX),
+];
+
+/// Facets counted alongside a page of results
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Facet {
+    Format,
+    ArchivedYear,
+    SizeRange,
+    Quarantined,
+    Collection,
+    Tag,
+    RiskLevel,
+}
+
+/// A WHERE clause and its positional parameters
+#[derive(Debug, Default)]
+pub struct SqlFilter {
+    pub clause: String,
+    pub params: Vec<Value>,
+}
+
+/// Build the WHERE clause for a query
+///
+/// `exclude` leaves out the filter belonging to one facet, so that facet's
+/// counts show what selecting a different value would return.
+pub fn build_filter(query: &ProjectQuery, exclude: Option<Facet>) -> SqlFilter {
+    let mut conditions: Vec<String> = Vec::new();
+    let mut params: Vec<Value> = Vec::new();
+
+    if exclude != Some(Facet::Format) && !query.formats.is_empty() {
+        conditions.push(format!(
+            "EXISTS (SELECT 1 FROM project_files pf WHERE pf.archived_project_id = p.id AND pf.media_type IN ({}))",
+            placeholders(query.formats.len())
+        ));
+        params.extend(query.formats.iter().map(|f| Value::from(f.as_str())));
+    }
+
+    if exclude != Some(Facet::ArchivedYear) {
+        if let Some(after) = query.archived_after {
+            conditions.push("p.archived_at >= ?".to_string());
+            params.push(Value::from(after.to_rfc3339()));
+        }
+        if let Some(before) = query.archived_before {
+            conditions.push("p.archived_at <= ?".to_string());
+            params.push(Value::from(before.to_rfc3339()));
+        }
+    }
+
+    if exclude != Some(Facet::SizeRange) {
+        if let Some(min_size) = query.min_size {
+            conditions.push("p.total_size >= ?".to_string());
+            params.push(Value::from(min_size));
+        }
+        if let Some(max_size) = query.max_size {
+            conditions.push("p.total_size <= ?".to_string());
+            params.push(Value::from(max_size));
+        }
+    }
+
+    if exclude != Some(Facet::Quarantined) {
+        if let Some(quarantined) = query.quarantined {
+            conditions.push("p.is_quarantined = ?".to_string());
+            params.push(Value::from(quarantined));
+        }
+    }
+
+    if exclude != Some(Facet::Collection) {
+        if let Some(collection_id) = &query.collection_id {
+            conditions.push(
+                "EXISTS (SELECT 1 FROM collection_members cm WHERE cm.archived_project_id = p.id \
+                 AND cm.collection_id IN (\
+                 WITH RECURSIVE sub(id) AS (\
+                 SELECT ? UNION SELECT c.id FROM collections c JOIN sub ON c.parent_collection_id = sub.id\
+                 ) SELECT id FROM sub))"
+                    .to_string(),
+            );
+            params.push(Value::from(collection_id.as_str()));
+        }
+    }
+
+    if exclude != Some(Facet::Tag) {
+        for tag_id in &query.tag_ids {
+            conditions.push(
+                "EXISTS (SELECT 1 FROM tag_assignments ta WHERE ta.archived_project_id = p.id AND ta.tag_id = ?)"
+                    .to_string(),
+            );
+            params.push(Value::from(tag_id.as_str()));
+        }
+    }
+
+    if exclude != Some(Facet::RiskLevel) && !query.risk_levels.is_empty() {
+        let ranks: Vec<i64> = query
+            .risk_levels
+            .iter()
+            .filter_map(|level| RiskLevel::parse(level))
+            .map(|level| level.rank())
+            .collect();
+        // Unknown levels match nothing rather than silently dropping the filter
+        if ranks.is_empty() {
+            conditions.push("0".to_string());
+        } else {
+            conditions.push(format!("{} IN ({})", PROJECT_RISK_RANK, placeholders(ranks.len())));
+            params.extend(ranks.into_iter().map(Value::from));
+        }
+    }
+
+    SqlFilter {
+        clause: if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        },
+        params,
+    }
+}
+
+/// ORDER BY clause for a sort option; ties fall back to the id for stable pages
+pub fn order_by(sort: ProjectSort) -> &'static str {
+    match sort {
+        ProjectSort::NewestFirst => "ORDER BY p.archived_at DESC, p.id",
+        ProjectSort::OldestFirst => "ORDER BY p.archived_at ASC, p.id",
+        ProjectSort::NameAsc => "ORDER BY p.name COLLATE NOCASE ASC, p.id",
+        ProjectSort::NameDesc => "ORDER BY p.name COLLATE NOCASE DESC, p.id",
+        ProjectSort::LargestFirst => "ORDER BY p.total_size DESC, p.id",
+        ProjectSort::SmallestFirst => "ORDER BY p.total_size ASC, p.id",
+    }
+}
+
+/// Normalized 1-based page number, page size and row offset
+pub fn page_bounds(query: &ProjectQuery) -> (u32, u32, i64) {
+    let page = query.page.max(1);
+    let page_size = match query.page_size {
+        0 => DEFAULT_PAGE_SIZE,
+        size => size.min(MAX_PAGE_SIZE),
+    };
+    (page, page_size, (page as i64 - 1) * page_size as i64)
+}
+
+/// SELECT for one page of projects matching the query
+pub fn page_sql(query: &ProjectQuery) -> (String, Vec<Value>) {
+    let filter = build_filter(query, None);
+    let (_, page_size, offset) = page_bounds(query);
+
+    let sql = format!(
+        "SELECT {} FROM archived_projects p {} {} LIMIT ? OFFSET ?",
+        PROJECT_COLUMNS,
+        filter.clause,
+        order_by(query.sort)
+    );
+    let mut params = filter.params;
+    params.push(Value::from(page_size));
+    params.push(Value::from(offset));
+
+    (sql, params)
+}
+
+/// SELECT counting every project matching the query
+pub fn count_sql(query: &ProjectQuery) -> (String, Vec<Value>) {
+    let filter = build_filter(query, None);
+    (
+        format!("SELECT COUNT(*) AS count FROM archived_projects p {}", filter.clause),
+        filter.params,
+    )
+}
+
+/// SELECT returning `value`, `label` and `count` rows for one facet
+pub fn facet_sql(query: &ProjectQuery, facet: Facet) -> (String, Vec<Value>) {
+    let filter = build_filter(query, Some(facet));
+    let where_clause = &filter.clause;
+
+    let sql = match facet {
+        Facet::Format => format!(
+            "SELECT pf.media_type AS value, pf.media_type AS label, COUNT(DISTINCT p.id) AS count \
+             FROM archived_projects p JOIN project_files pf ON pf.archived_project_id = p.id \
+             {} GROUP BY pf.media_type ORDER BY count DESC, value",
+            where_clause
+        ),
+        Facet::ArchivedYear => format!(
+            "SELECT substr(p.archived_at, 1, 4) AS value, substr(p.archived_at, 1, 4) AS label, COUNT(*) AS count \
+             FROM archived_projects p {} GROUP BY value ORDER BY value DESC",
+            where_clause
+        ),
+        Facet::SizeRange => {
+            let cases: Vec<String> = SIZE_RANGES
+                .iter()
+                .map(|(value, _, _, upper)| format!("WHEN p.total_size < {} THEN '{}'", upper, value))
+                .collect();
+            let labels: Vec<String> = SIZE_RANGES
+                .iter()
+                .map(|(value, label, _, _)| format!("WHEN '{}' THEN '{}'", value, label))
+                .collect();
+            format!(
+                "SELECT bucket AS value, CASE bucket {} END AS label, COUNT(*) AS count \
+                 FROM (SELECT CASE {} ELSE '{}' END AS bucket, p.total_size AS size FROM archived_projects p {}) \
+                 GROUP BY bucket ORDER BY MIN(size)",
+                labels.join(" "),
+                cases.join(" "),
+                SIZE_RANGES[SIZE_RANGES.len() - 1].0,
+                where_clause
+            )
+        }
+        Facet::Quarantined => format!(
+            "SELECT CASE WHEN p.is_quarantined THEN 'true' ELSE 'false' END AS value, \
+             CASE WHEN p.is_quarantined THEN 'Quarantined' ELSE 'Active' END AS label, COUNT(*) AS count \
+             FROM archived_projects p {} GROUP BY value ORDER BY value",
+            where_clause
+        ),
+        Facet::Collection => format!(
+            "SELECT c.id AS value, c.name AS label, COUNT(DISTINCT p.id) AS count \
+             FROM archived_projects p JOIN collection_members cm ON cm.archived_project_id = p.id \
+             JOIN collections c ON c.id = cm.collection_id \
+             {} GROUP BY c.id ORDER BY count DESC, label",
+            where_clause
+        ),
+        Facet::Tag => format!(
+            "SELECT t.id AS value, t.name AS label, COUNT(DISTINCT p.id) AS count \
+             FROM archived_projects p JOIN tag_assignments ta ON ta.archived_project_id = p.id \
+             JOIN tags t ON t.id = ta.tag_id \
+             {} GROUP BY t.id ORDER BY count DESC, label",
+            where_clause
+        ),
+        Facet::RiskLevel => format!(
+            "SELECT CASE rank WHEN 1 THEN 'low' WHEN 2 THEN 'medium' WHEN 3 THEN 'high' ELSE 'unknown' END AS value, \
+             CASE rank WHEN 1 THEN 'Low' WHEN 2 THEN 'Medium' WHEN 3 THEN 'High' ELSE 'Not assessed' END AS label, \
+             COUNT(*) AS count \
+             FROM (SELECT {} AS rank FROM archived_projects p {}) \
+             GROUP BY rank ORDER BY rank DESC",
+            PROJECT_RISK_RANK, where_clause
+        ),
+    };
+
+    (sql, filter.params)
+}
+
+fn placeholders(count: usize) -> String {
+    vec!["?"; count].join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_filter() {
+        let query = ProjectQuery {
+            formats: vec!["image/png".to_string(), "image/tiff".to_string()],
+            min_size: Some(10),
+            quarantined: Some(false),
+            tag_ids: vec!["ink".to_string(), "portrait".to_string()],
+            risk_levels: vec!["high".to_string()],
+            ..Default::default()
+        };
+
+        let filter = build_filter(&query, None);
+        assert!(filter.clause.starts_with("WHERE EXISTS"));
+        assert_eq!(filter.clause.matches('?').count(), filter.params.len());
+        assert_eq!(
+            filter.params,
+            vec![
+                Value::from("image/png"),
+                Value::from("image/tiff"),
+                Value::from(10),
+                Value::from(false),
+                Value::from("ink"),
+                Value::from("portrait"),
+                Value::from(3),
+            ]
+        );
+
+        // A facet's own filter is left out of its counts
+        let without_formats = build_filter(&query, Some(Facet::Format));
+        assert!(!without_formats.clause.contains("media_type"));
+        assert_eq!(without_formats.params.len(), 5);
+
+        assert!(build_filter(&ProjectQuery::default(), None).clause.is_empty());
+    }
+
+    #[test]
+    fn test_unknown_risk_level_matches_nothing() {
+        let query = ProjectQuery {
+            risk_levels: vec!["extreme".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(build_filter(&query, None).clause, "WHERE 0");
+    }
+
+    #[test]
+    fn test_page_bounds() {
+        let mut query = ProjectQuery::default();
+        assert_eq!(page_bounds(&query), (1, DEFAULT_PAGE_SIZE, 0));
+
+        query.page = 3;
+        query.page_size = 20;
+        assert_eq!(page_bounds(&query), (3, 20, 40));
+
+        query.page_size = 10_000;
+        assert_eq!(page_bounds(&query).1, MAX_PAGE_SIZE);
+
+        let (sql, params) = page_sql(&query);
+        assert!(sql.ends_with("LIMIT ? OFFSET ?"));
+        assert_eq!(params, vec![Value::from(MAX_PAGE_SIZE), Value::from(1000)]);
+    }
+}
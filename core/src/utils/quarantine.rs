@@ -0,0 +1,87 @@
+use anyhow::Result;
+use camino::{Utf8Path, Utf8PathBuf};
+use chrono::{DateTime, Duration, Utc};
+use std::fs;
+use walkdir::WalkDir;
+
+/// Directory quarantined bags are moved to until the vault location is configurable
+pub const DEFAULT_QUARANTINE_ROOT: &str = "/tmp/cwpt-quarantine";
+
+/// How long a quarantined project is kept before it may be deleted
+pub const QUARANTINE_RETENTION_DAYS: i64 = 30;
+
+/// When a project quarantined at the given time becomes eligible for deletion
+pub fn scheduled_deletion(quarantined_at: DateTime<Utc>) -> DateTime<Utc> {
+    quarantined_at + Duration::days(QUARANTINE_RETENTION_DAYS)
+}
+
+/// Where a bag is kept while quarantined; the bag keeps its directory name
+pub fn quarantine_path(quarantine_root: &Utf8Path, bag_root: &Utf8Path) -> Result<Utf8PathBuf> {
+    let bag_name = bag_root
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("Bag path has no directory name: {}", bag_root))?;
+    Ok(quarantine_root.join(bag_name))
+}
+
+/// Move a bag directory, refusing to overwrite anything at the destination
+///
+/// A rename is tried first; when the quarantine is on another filesystem the
+/// bag is copied and the original removed only once the copy is complete.
+pub fn move_bag(from: &Utf8Path, to: &Utf8Path) -> Result<()> {
+    if !from.is_dir() {
+        return Err(anyhow::anyhow!("Bag not found: {}", from));
+    }
+    if to.exists() {
+        return Err(anyhow::anyhow!("Destination already exists: {}", to));
+    }
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    for entry in WalkDir::new(from) {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(from)?;
+        let destination = to.as_std_path().join(relative);
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&destination)?;
+        } else {
+            fs::copy(entry.path(), &destination)?;
+        }
+    }
+    fs::remove_dir_all(from)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_move_bag_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let bag_root = root.join("bags/Thesis-1a2b3c4d");
+        fs::create_dir_all(bag_root.join("data")).unwrap();
+        fs::write(bag_root.join("data/film.mov"), b"frames").unwrap();
+
+        let quarantined = quarantine_path(&root.join("quarantine"), &bag_root).unwrap();
+        assert_eq!(quarantined, root.join("quarantine/Thesis-1a2b3c4d"));
+
+        move_bag(&bag_root, &quarantined).unwrap();
+        assert!(!bag_root.exists());
+        assert_eq!(fs::read(quarantined.join("data/film.mov")).unwrap(), b"frames");
+
+        // Restoring onto an existing bag is refused rather than merged
+        fs::create_dir_all(&bag_root).unwrap();
+        assert!(move_bag(&quarantined, &bag_root).is_err());
+        fs::remove_dir(&bag_root).unwrap();
+
+        move_bag(&quarantined, &bag_root).unwrap();
+        assert!(bag_root.join("data/film.mov").is_file());
+    }
+}
@@ -0,0 +1,304 @@
+use crate::models::preservation::ReplicaObject;
+use crate::utils::bagit::{read_manifest_file, BagItPackage, StoredDifferences};
+use crate::utils::checksums::{calculate_checksum, calculate_sha256, ChecksumAlgorithm};
+use crate::utils::storage::StoredObject;
+use anyhow::Result;
+use camino::{Utf8Path, Utf8PathBuf};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use walkdir::WalkDir;
+
+/// Replicas not verified for this long are due for verification, unless configured
+pub const DEFAULT_VERIFY_INTERVAL_HOURS: i64 = 24 * 7;
+
+/// Copy a whole bag, replacing any earlier copy only once the new one is complete
+///
+/// The copy is written next to the destination first, so an unplugged drive
+/// leaves a partial directory behind rather than a half-written replica.
+pub fn copy_bag(from: &Utf8Path, to: &Utf8Path) -> Result<()> {
+    if !from.is_dir() {
+        return Err(anyhow::anyhow!("Bag not found: {}", from));
+    }
+    let partial = Utf8PathBuf::from(format!("{}.partial", to));
+    if partial.exists() {
+        fs::remove_dir_all(&partial)?;
+    }
+
+    for entry in WalkDir::new(from) {
+        let entry = entry?;
+        let destination = partial.as_std_path().join(entry.path().strip_prefix(from)?);
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&destination)?;
+        } else {
+            fs::copy(entry.path(), &destination)?;
+        }
+    }
+
+    if to.exists() {
+        fs::remove_dir_all(to)?;
+    }
+    fs::rename(&partial, to)?;
+    Ok(())
+}
+
+/// Bring a damaged copy of a bag back in line with a healthy one
+///
+/// Only payload files that are missing or fail the healthy bag's manifest are
+/// copied, along with any tag file that differs; payload files the healthy
+/// bag does not list are removed. Returns the bag-relative paths touched.
+pub async fn repair_bag(damaged_root: &Utf8Path, healthy: &BagItPackage) -> Result<Vec<String>> {
+    let algorithm = healthy
+        .manifest_algorithms()?
+        .iter()
+        .filter_map(|name| ChecksumAlgorithm::from_bagit_name(name))
+        .max_by_key(|algorithm| *algorithm == ChecksumAlgorithm::Sha256)
+        .ok_or_else(|| anyhow::anyhow!("Healthy bag has no usable payload manifest"))?;
+    let manifest = read_manifest_file(&healthy.manifest_path_for(algorithm))?;
+    let mut repaired = Vec::new();
+
+    for (checksum, path) in &manifest {
+        let source = healthy.bag_root.join(path);
+        // Files the healthy bag only references in fetch.txt have nothing to copy
+        if !source.is_file() {
+            continue;
+        }
+        let target = damaged_root.join(path);
+        let intact = target.is_file() && calculate_checksum(&target, algorithm).await?.eq_ignore_ascii_case(checksum);
+        if !intact {
+            replace_file(&source, &target)?;
+            repaired.push(path.clone());
+        }
+    }
+
+    for entry in fs::read_dir(&healthy.bag_root)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_file() {
+            continue;
+        }
+        let Ok(name) = entry.file_name().into_string() else {
+            continue;
+        };
+        let source = healthy.bag_root.join(&name);
+        let target = damaged_root.join(&name);
+        if !target.is_file() || fs::read(&source)? != fs::read(&target)? {
+            replace_file(&source, &target)?;
+            repaired.push(name);
+        }
+    }
+
+    let listed: HashSet<&str> = manifest.iter().map(|(_, path)| path.as_str()).collect();
+    let damaged_data = damaged_root.join("data");
+    if damaged_data.is_dir() {
+        for entry in WalkDir::new(&damaged_data) {
+            let entry = entry?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let relative = entry.path().strip_prefix(damaged_root)?.to_string_lossy().replace('\\', "/");
+            if !listed.contains(relative.as_str()) {
+                fs::remove_file(entry.path())?;
+                repaired.push(relative);
+            }
+        }
+    }
+
+    Ok(repaired)
+}
+
+/// A file of a bag as a replica should hold it
+#[derive(Debug, Clone, PartialEq)]
+pub struct BagFile {
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+    /// From the bag's MD5 manifests, when it has them
+    pub md5: Option<String>,
+}
+
+/// Every file of a bag, payload first and bagit.txt last
+///
+/// Checksums are taken from the bag's manifests where they list the file,
+/// so the payload is not read again; other files are hashed.
+pub async fn bag_files(bag: &BagItPackage) -> Result<Vec<BagFile>> {
+    let mut sha256 = HashMap::new();
+    let mut md5 = HashMap::new();
+    for (algorithm, checksums) in [(ChecksumAlgorithm::Sha256, &mut sha256), (ChecksumAlgorithm::Md5, &mut md5)] {
+        for manifest in [bag.manifest_path_for(algorithm), bag.tag_manifest_path_for(algorithm)] {
+            if manifest.is_file() {
+                let entries = read_manifest_file(&manifest)?;
+                checksums.extend(entries.into_iter().map(|(checksum, path)| (path, checksum.to_lowercase())));
+            }
+        }
+    }
+
+    let mut files = Vec::new();
+    for path in bag.file_paths()? {
+        let absolute = bag.bag_root.join(&path);
+        let sha256 = match sha256.remove(&path) {
+            Some(checksum) => checksum,
+            None => calculate_sha256(&absolute).await?,
+        };
+        files.push(BagFile {
+            size: fs::metadata(&absolute)?.len(),
+            md5: md5.remove(&path),
+            sha256,
+            path,
+        });
+    }
+    Ok(files)
+}
+
+/// Compare a replica on a remote target with the bag
+///
+/// `stored` holds the replica's objects with keys relative to the bag root.
+/// Sizes are compared with the bag's files. Etags are compared with the MD5
+/// manifest when the backend's etags are MD5s and the object was uploaded in
+/// one part, and otherwise with the etag
+/// recorded right after each upload, so content replaced in place is noticed
+/// even when its size stayed the same.
+pub fn compare_stored(
+    files: &[BagFile],
+    stored: &[StoredObject],
+    recorded: &[ReplicaObject],
+    etags_are_md5: bool,
+) -> StoredDifferences {
+    let stored: HashMap<&str, &StoredObject> = stored.iter().map(|o| (o.key.as_str(), o)).collect();
+    let recorded: HashMap<&str, &ReplicaObject> = recorded.iter().map(|o| (o.path.as_str(), o)).collect();
+    let mut differences = StoredDifferences::default();
+
+    for file in files {
+        let Some(object) = stored.get(file.path.as_str()) else {
+            differences.missing.push(file.path.clone());
+            continue;
+        };
+        if object.size != file.size {
+            differences.mismatched.push(file.path.clone());
+            continue;
+        }
+        let md5_etag = object.md5_etag().filter(|_| etags_are_md5);
+        let changed = match (md5_etag, &file.md5, &object.etag, recorded.get(file.path.as_str())) {
+            (Some(etag), Some(md5), _, _) => !etag.eq_ignore_ascii_case(md5),
+            (_, _, etag, Some(record)) => {
+                record.sha256 != file.sha256 || (record.etag.is_some() && etag.is_some() && &record.etag != etag)
+            }
+            _ => false,
+        };
+        if changed {
+            differences.changed.push(file.path.clone());
+        }
+    }
+
+    let paths: HashSet<&str> = files.iter().map(|f| f.path.as_str()).collect();
+    differences.unexpected = stored.keys().filter(|key| !paths.contains(*key)).map(|key| key.to_string()).collect();
+    differences.unexpected.sort();
+    differences
+}
+
+/// Copy a file over another through a temporary file in the same directory
+fn replace_file(source: &Utf8Path, target: &Utf8Path) -> Result<()> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let temporary = Utf8PathBuf::from(format!("{}.repair", target));
+    fs::copy(source, &temporary)?;
+    fs::rename(&temporary, target)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_copy_and_repair_bag() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+
+        let bag = BagItPackage::new(root.join("bags/Mural-1234")).unwrap();
+        bag.create_bagit_declaration().unwrap();
+        fs::create_dir_all(bag.data_dir.join("panels")).unwrap();
+        fs::write(bag.data_dir.join("panels/north.txt"), b"north wall").unwrap();
+        fs::write(bag.data_dir.join("statement.txt"), b"statement").unwrap();
+        bag.create_manifest().await.unwrap();
+
+        let replica = root.join("drive/Mural-1234");
+        fs::create_dir_all(root.join("drive")).unwrap();
+        copy_bag(&bag.bag_root, &replica).unwrap();
+        assert!(BagItPackage::open(replica.clone()).unwrap().validate().await.unwrap().is_empty());
+
+        fs::write(replica.join("data/panels/north.txt"), b"bit rot").unwrap();
+        fs::remove_file(replica.join("data/statement.txt")).unwrap();
+        fs::write(replica.join("data/stray.txt"), b"stray").unwrap();
+        fs::write(replica.join("bagit.txt"), b"BagIt-Version: 0.97\n").unwrap();
+
+        let mut repaired = repair_bag(&replica, &bag).await.unwrap();
+        repaired.sort();
+        assert_eq!(
+            repaired,
+            vec!["bagit.txt", "data/panels/north.txt", "data/statement.txt", "data/stray.txt"]
+        );
+        assert!(BagItPackage::open(replica).unwrap().validate().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_compare_stored() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let bag = BagItPackage::new(root.join("Zine-1234")).unwrap();
+        bag.create_bagit_declaration().unwrap();
+        fs::write(bag.data_dir.join("cover.txt"), b"cover").unwrap();
+        fs::write(bag.data_dir.join("spread.txt"), b"spread").unwrap();
+        bag.create_manifest().await.unwrap();
+        bag.create_manifest_with(ChecksumAlgorithm::Md5).await.unwrap();
+
+        let files = bag_files(&bag).await.unwrap();
+        let paths: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec!["data/cover.txt", "data/spread.txt", "manifest-md5.txt", "manifest-sha256.txt", "bagit.txt"]
+        );
+        let cover = &files[0];
+        assert_eq!(cover.size, 5);
+        assert!(cover.md5.is_some());
+        assert_eq!(files[4].md5, None);
+
+        let object = |path: &str, size: u64, etag: &str| StoredObject {
+            key: path.to_string(),
+            size,
+            etag: Some(etag.to_string()),
+        };
+        let record = |path: &str, sha256: &str, etag: &str| ReplicaObject {
+            archived_project_id: "p".to_string(),
+            target_id: "t".to_string(),
+            path: path.to_string(),
+            size: 0,
+            sha256: sha256.to_string(),
+            etag: Some(etag.to_string()),
+            key_id: None,
+            stored_at: chrono::Utc::now(),
+        };
+        let stored = vec![
+            object("data/cover.txt", 5, "e1"),
+            object("data/spread.txt", 6, "e2-edited"),
+            object("manifest-md5.txt", 1, "e3"),
+            object("data/stray.txt", 5, "e4"),
+        ];
+        let recorded = vec![
+            record("data/cover.txt", &cover.sha256, "e1"),
+            record("data/spread.txt", &files[1].sha256, "e2"),
+        ];
+
+        let differences = compare_stored(&files, &stored, &recorded, false);
+        assert_eq!(differences.missing, vec!["manifest-sha256.txt", "bagit.txt"]);
+        assert_eq!(differences.mismatched, vec!["manifest-md5.txt"]);
+        assert_eq!(differences.changed, vec!["data/spread.txt"]);
+        assert_eq!(differences.unexpected, vec!["data/stray.txt"]);
+
+        // MD5 etags are checked against the manifest itself
+        let stored = vec![object("data/cover.txt", 5, cover.md5.as_deref().unwrap())];
+        assert!(compare_stored(&files[..1], &stored, &[], true).is_empty());
+        let stored = vec![object("data/cover.txt", 5, "0123456789abcdef0123456789abcdef")];
+        assert_eq!(compare_stored(&files[..1], &stored, &[], true).changed, vec!["data/cover.txt"]);
+    }
+}
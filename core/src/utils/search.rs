@@ -0,0 +1,190 @@
+use crate::models::preservation::{ArchivedProject, FileTechnicalMetadata, Tag, TagAssignment};
+use crate::utils::bagit::BagItPackage;
+use crate::utils::text_extraction::extract_text;
+use anyhow::Result;
+use std::collections::HashMap;
+
+/// bag-info.txt labels that describe the bag itself rather than the work
+const STRUCTURAL_BAG_INFO_LABELS: &[&str] = &[
+    "Payload-Oxum",
+    "Bag-Size",
+    "Bagging-Date",
+    "Bag-Software-Agent",
+    "BagIt-Profile-Identifier",
+    "Internal-Sender-Identifier",
+];
+
+/// One row of the full-text index: a project record or one of its payload files
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchDocument {
+    pub project_id: String,
+    pub file_path: Option<String>,
+    pub name: String,
+    pub description: String,
+    pub metadata: String,
+    pub tags: String,
+    pub content: String,
+}
+
+/// Build the index documents for a project and, when it has a bag, its payload files
+pub fn project_documents(
+    project: &ArchivedProject,
+    bag: Option<&BagItPackage>,
+    tags: &[Tag],
+    assignments: &[TagAssignment],
+    technical: &[FileTechnicalMetadata],
+) -> Result<Vec<SearchDocument>> {
+    let tag_names: HashMap<&str, &str> = tags.iter().map(|t| (t.id.as_str(), t.name.as_str())).collect();
+    let tags_for = |file_path: Option<&str>| {
+        assignments
+            .iter()
+            .filter(|a| a.archived_project_id == project.id && a.file_path.as_deref() == file_path)
+            .filter_map(|a| tag_names.get(a.tag_id.as_str()).copied())
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    let metadata = match bag {
+        Some(bag) if bag.bag_info_path.exists() => bag
+            .read_bag_info()?
+            .iter()
+            .filter(|(label, _)| {
+                !STRUCTURAL_BAG_INFO_LABELS
+                    .iter()
+                    .any(|structural| structural.eq_ignore_ascii_case(label))
+            })
+            .map(|(_, value)| value)
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    };
+
+    let mut documents = vec![SearchDocument {
+        project_id: project.id.clone(),
+        file_path: None,
+        name: project.name.clone(),
+        description: project.description.clone().unwrap_or_default(),
+        metadata,
+        tags: tags_for(None),
+        content: String::new(),
+    }];
+
+    if let Some(bag) = bag.filter(|b| b.manifest_algorithms().is_ok_and(|algorithms| !algorithms.is_empty())) {
+        for (_, path) in bag.read_manifest()? {
+            let file_path = bag.bag_root.join(&path);
+            // Files still listed in fetch.txt are indexed by name until they arrive
+            let content = if file_path.is_file() {
+                extract_text(&file_path)?.unwrap_or_default()
+            } else {
+                String::new()
+            };
+
+            // Embedded metadata such as camera model or PDF author makes the file findable
+            let metadata = technical
+                .iter()
+                .find(|file| file.path == path)
+                .map(|file| file.properties.values().cloned().collect::<Vec<_>>().join("\n"))
+                .unwrap_or_default();
+
+            documents.push(SearchDocument {
+                project_id: project.id.clone(),
+                name: filename_terms(&path),
+                description: String::new(),
+                metadata,
+                tags: tags_for(Some(&path)),
+                content,
+                file_path: Some(path),
+            });
+        }
+    }
+
+    Ok(documents)
+}
+
+/// Turn free-form user input into an FTS5 MATCH expression
+///
+/// Every word becomes a quoted prefix term so punctuation in the input cannot
+/// be read as query syntax. Returns `None` when nothing searchable remains.
+pub fn build_match_query(input: &str) -> Option<String> {
+    let terms: Vec<String> = input
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("\"{}\"*", word.to_lowercase()))
+        .collect();
+
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+/// The payload path plus its words split on separators, so "final_v2.psd" matches "final"
+fn filename_terms(path: &str) -> String {
+    let display_path = path.strip_prefix("data/").unwrap_or(path);
+    let words = display_path
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!("{} {}", display_path, words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::bag_info::BagInfo;
+    use camino::Utf8Path;
+    use chrono::Utc;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_build_match_query() {
+        assert_eq!(build_match_query("Charcoal  study"), Some("\"charcoal\"* \"study\"*".to_string()));
+        assert_eq!(build_match_query("self-portrait \"OR\""), Some("\"self\"* \"portrait\"* \"or\"*".to_string()));
+        assert_eq!(build_match_query(" *:- "), None);
+    }
+
+    #[tokio::test]
+    async fn test_project_documents() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+
+        let bag = BagItPackage::new(root.join("bag")).unwrap();
+        fs::write(bag.data_dir.join("statement.md"), "Exploring *negative space*").unwrap();
+        fs::write(bag.data_dir.join("final_v2.psd"), b"8BPS").unwrap();
+        bag.create_manifest().await.unwrap();
+        let mut bag_info = BagInfo::new();
+        bag_info.add("Contact-Name", "Ada Student");
+        bag_info.add("Payload-Oxum", "30.2");
+        bag.create_bag_info(&bag_info).unwrap();
+
+        let project = ArchivedProject::new("Figure Drawing".to_string(), Some("Week 3".to_string()), 2, 30);
+        let tag = Tag::new("charcoal".to_string(), None);
+        let assignments = vec![TagAssignment {
+            tag_id: tag.id.clone(),
+            archived_project_id: project.id.clone(),
+            file_path: Some("data/final_v2.psd".to_string()),
+            created_at: Utc::now(),
+        }];
+
+        let technical = vec![FileTechnicalMetadata {
+            path: "data/final_v2.psd".to_string(),
+            properties: [("xmp.creator_tool".to_string(), "Photoshop 25.0".to_string())].into_iter().collect(),
+        }];
+
+        let documents = project_documents(&project, Some(&bag), &[tag], &assignments, &technical).unwrap();
+        assert_eq!(documents.len(), 3);
+        assert_eq!(documents[0].metadata, "Ada Student");
+        assert!(documents[0].tags.is_empty());
+
+        let psd = documents.iter().find(|d| d.file_path.as_deref() == Some("data/final_v2.psd")).unwrap();
+        assert_eq!(psd.name, "final_v2.psd final v2 psd");
+        assert_eq!(psd.tags, "charcoal");
+        assert_eq!(psd.metadata, "Photoshop 25.0");
+
+        let statement = documents.iter().find(|d| d.file_path.as_deref() == Some("data/statement.md")).unwrap();
+        assert_eq!(statement.content, "Exploring *negative space*");
+    }
+}
@@ -0,0 +1,171 @@
+use crate::utils::checksums::calculate_sha256;
+use anyhow::Result;
+use camino::{Utf8Path, Utf8PathBuf};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::time::UNIX_EPOCH;
+use walkdir::WalkDir;
+
+/// bag-info.txt label naming the project a delta version was made from
+pub const PREVIOUS_VERSION_LABEL: &str = "CWPT-Previous-Version";
+
+/// A source file as it was when its project's bag was written
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceFileState {
+    /// Relative to the source root, i.e. the payload path without "data/"
+    pub path: String,
+    pub size: u64,
+    /// Nanoseconds since the Unix epoch; 0 when unknown, which forces a checksum comparison
+    pub modified_at: i64,
+    pub sha256: String,
+}
+
+/// A source file as it is now
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    pub path: String,
+    pub absolute_path: Utf8PathBuf,
+    pub size: u64,
+    pub modified_at: i64,
+}
+
+/// How a project's sources differ from its last archived state
+#[derive(Debug, Default, PartialEq)]
+pub struct SourceDelta {
+    pub added: Vec<String>,
+    pub changed: Vec<String>,
+    pub removed: Vec<String>,
+    pub unchanged: Vec<String>,
+    /// Files whose modification time moved and had to be hashed to compare
+    pub hashed: usize,
+}
+
+impl SourceDelta {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Every file under the archived paths, relative to the source root
+///
+/// Only the paths that were archived are walked, so unrelated files next to
+/// them under the common root are not picked up.
+pub fn list_source_files(sources: &[String], source_root: &Utf8Path) -> Result<Vec<SourceFile>> {
+    let mut files = Vec::new();
+    for source in sources {
+        if !Utf8Path::new(source).exists() {
+            continue;
+        }
+        for entry in WalkDir::new(source).sort_by_file_name() {
+            let entry = entry?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let absolute_path = Utf8Path::from_path(entry.path())
+                .ok_or_else(|| anyhow::anyhow!("Non-UTF8 path encountered"))?
+                .to_path_buf();
+            let metadata = entry.metadata()?;
+            files.push(SourceFile {
+                path: absolute_path.strip_prefix(source_root)?.as_str().replace('\\', "/"),
+                size: metadata.len(),
+                modified_at: modified_nanos(&metadata),
+                absolute_path,
+            });
+        }
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    files.dedup_by(|a, b| a.path == b.path);
+    Ok(files)
+}
+
+pub fn modified_nanos(metadata: &fs::Metadata) -> i64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|since| since.as_nanos() as i64)
+        .unwrap_or(0)
+}
+
+/// Compare current source files with the recorded state
+///
+/// A different size means changed. The same size and modification time
+/// means unchanged. Otherwise the file is hashed, so a file that was only
+/// touched or copied back is not reported as changed.
+pub async fn compare_sources(current: &[SourceFile], baseline: &[SourceFileState]) -> Result<SourceDelta> {
+    let recorded: HashMap<&str, &SourceFileState> = baseline.iter().map(|s| (s.path.as_str(), s)).collect();
+    let mut delta = SourceDelta::default();
+
+    for file in current {
+        let Some(state) = recorded.get(file.path.as_str()) else {
+            delta.added.push(file.path.clone());
+            continue;
+        };
+        if file.size != state.size {
+            delta.changed.push(file.path.clone());
+        } else if file.modified_at == state.modified_at && state.modified_at != 0 {
+            delta.unchanged.push(file.path.clone());
+        } else {
+            delta.hashed += 1;
+            if calculate_sha256(&file.absolute_path).await?.eq_ignore_ascii_case(&state.sha256) {
+                delta.unchanged.push(file.path.clone());
+            } else {
+                delta.changed.push(file.path.clone());
+            }
+        }
+    }
+
+    let present: HashSet<&str> = current.iter().map(|f| f.path.as_str()).collect();
+    delta.removed = baseline
+        .iter()
+        .filter(|s| !present.contains(s.path.as_str()))
+        .map(|s| s.path.clone())
+        .collect();
+    delta.removed.sort();
+
+    Ok(delta)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::Digest;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_compare_sources() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        fs::create_dir_all(root.join("work/layers")).unwrap();
+        fs::write(root.join("work/same.txt"), b"same").unwrap();
+        fs::write(root.join("work/touched.txt"), b"touched").unwrap();
+        fs::write(root.join("work/edited.txt"), b"edit").unwrap();
+        fs::write(root.join("work/layers/new.txt"), b"new").unwrap();
+        fs::write(root.join("unrelated.txt"), b"not archived").unwrap();
+
+        let current = list_source_files(&[root.join("work").to_string()], root).unwrap();
+        let paths: Vec<&str> = current.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, vec!["work/edited.txt", "work/layers/new.txt", "work/same.txt", "work/touched.txt"]);
+
+        let state = |path: &str, size: u64, modified_at: i64, content: &[u8]| SourceFileState {
+            path: path.to_string(),
+            size,
+            modified_at,
+            sha256: format!("{:x}", sha2::Sha256::digest(content)),
+        };
+        let same = current.iter().find(|f| f.path == "work/same.txt").unwrap();
+        let baseline = vec![
+            state("work/same.txt", 4, same.modified_at, b"same"),
+            state("work/touched.txt", 7, 1, b"touched"),
+            state("work/edited.txt", 4, 1, b"orig"),
+            state("work/gone.txt", 3, 1, b"old"),
+        ];
+
+        let delta = compare_sources(&current, &baseline).await.unwrap();
+        assert_eq!(delta.added, vec!["work/layers/new.txt"]);
+        assert_eq!(delta.changed, vec!["work/edited.txt"]);
+        assert_eq!(delta.removed, vec!["work/gone.txt"]);
+        assert_eq!(delta.unchanged, vec!["work/same.txt", "work/touched.txt"]);
+        assert_eq!(delta.hashed, 2);
+    }
+}
@@ -0,0 +1,257 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use camino::{Utf8Component, Utf8Path, Utf8PathBuf};
+use std::fs;
+use walkdir::WalkDir;
+
+/// An object as a storage backend reports it
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredObject {
+    pub key: String,
+    pub size: u64,
+    /// Opaque version tag, when the backend has one; for S3 single-part uploads it is the MD5
+    pub etag: Option<String>,
+}
+
+impl StoredObject {
+    /// The etag, if it can stand for the content's MD5 on a backend whose etags are MD5s
+    ///
+    /// S3 gives objects uploaded in parts an etag of the form `<hash>-<parts>`,
+    /// which is not the MD5 of their content.
+    pub fn md5_etag(&self) -> Option<&str> {
+        self.etag.as_deref().filter(|etag| !etag.contains('-'))
+    }
+}
+
+/// Somewhere bags can be kept: a local folder or an object store
+///
+/// Keys are `/`-separated paths relative to the backend's root, such as
+/// `Mural-1234/data/north.txt`.
+#[async_trait]
+pub trait StorageBackend: Send + Sync {
+    /// Where the backend stores things, e.g. a path or `s3://bucket/prefix`
+    fn location(&self) -> String;
+
+    /// The local path of a key, for backends that are a filesystem
+    fn local_path(&self, _key: &str) -> Option<Utf8PathBuf> {
+        None
+    }
+
+    /// Whether the etags this backend reports are the MD5 of the content, for
+    /// objects it stored in a single part
+    fn etags_are_md5(&self) -> bool {
+        false
+    }
+
+    /// Whether the backend can be reached right now
+    async fn is_available(&self) -> bool;
+
+    async fn put_file(&self, key: &str, source: &Utf8Path) -> Result<()>;
+
+    async fn put_bytes(&self, key: &str, bytes: Vec<u8>) -> Result<()>;
+
+    async fn get_file(&self, key: &str, destination: &Utf8Path) -> Result<()>;
+
+    async fn get_bytes(&self, key: &str) -> Result<Vec<u8>>;
+
+    /// The object stored under a key, or `None` if there is none
+    async fn head(&self, key: &str) -> Result<Option<StoredObject>>;
+
+    /// Every object whose key starts with `prefix`
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredObject>>;
+
+    /// Remove an object; removing one that does not exist is not an error
+    async fn delete(&self, key: &str) -> Result<()>;
+}
+
+/// Join key segments with `/`, ignoring empty ones
+pub fn join_key(prefix: &str, key: &str) -> String {
+    let (prefix, key) = (prefix.trim_matches('/'), key.trim_matches('/'));
+    match (prefix.is_empty(), key.is_empty()) {
+        (true, _) => key.to_string(),
+        (_, true) => prefix.to_string(),
+        _ => format!("{}/{}", prefix, key),
+    }
+}
+
+/// Percent-encode everything but unreserved characters, and `/` only if asked
+pub fn percent_encode(value: &str, encode_slash: bool) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            b'/' if !encode_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Undo percent-encoding; malformed escapes are kept as they are
+pub fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| value.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Stores objects as files under a root directory
+#[derive(Debug, Clone)]
+pub struct LocalStorage {
+    root: Utf8PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: impl Into<Utf8PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Resolve a key under the root, refusing keys that would escape it
+    fn resolve(&self, key: &str) -> Result<Utf8PathBuf> {
+        let relative = Utf8Path::new(key.trim_start_matches('/'));
+        if relative.components().any(|c| !matches!(c, Utf8Component::Normal(_))) {
+            return Err(anyhow::anyhow!("Invalid storage key: {}", key));
+        }
+        Ok(self.root.join(relative))
+    }
+
+    /// Write through a temporary file so a reader never sees half an object
+    fn write_with(&self, key: &str, write: impl FnOnce(&Utf8Path) -> std::io::Result<()>) -> Result<()> {
+        let path = self.resolve(key)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let temporary = Utf8PathBuf::from(format!("{}.partial", path));
+        write(&temporary)?;
+        fs::rename(&temporary, &path)?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl StorageBackend for LocalStorage {
+    fn location(&self) -> String {
+        self.root.to_string()
+    }
+
+    fn local_path(&self, key: &str) -> Option<Utf8PathBuf> {
+        self.resolve(key).ok()
+    }
+
+    async fn is_available(&self) -> bool {
+        self.root.is_dir()
+    }
+
+    async fn put_file(&self, key: &str, source: &Utf8Path) -> Result<()> {
+        self.write_with(key, |path| fs::copy(source, path).map(|_| ()))
+    }
+
+    async fn put_bytes(&self, key: &str, bytes: Vec<u8>) -> Result<()> {
+        self.write_with(key, |path| fs::write(path, bytes))
+    }
+
+    async fn get_file(&self, key: &str, destination: &Utf8Path) -> Result<()> {
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(self.resolve(key)?, destination)?;
+        Ok(())
+    }
+
+    async fn get_bytes(&self, key: &str) -> Result<Vec<u8>> {
+        Ok(fs::read(self.resolve(key)?)?)
+    }
+
+    async fn head(&self, key: &str) -> Result<Option<StoredObject>> {
+        let path = self.resolve(key)?;
+        if !path.is_file() {
+            return Ok(None);
+        }
+        Ok(Some(StoredObject {
+            key: key.to_string(),
+            size: fs::metadata(&path)?.len(),
+            etag: None,
+        }))
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredObject>> {
+        if !self.root.is_dir() {
+            return Err(anyhow::anyhow!("Storage folder not found: {}", self.root));
+        }
+        let mut objects = Vec::new();
+        for entry in WalkDir::new(&self.root).sort_by_file_name() {
+            let entry = entry?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let key = entry.path().strip_prefix(&self.root)?.to_string_lossy().replace('\\', "/");
+            if !key.starts_with(prefix) || key.ends_with(".partial") {
+                continue;
+            }
+            objects.push(StoredObject {
+                key,
+                size: entry.metadata()?.len(),
+                etag: None,
+            });
+        }
+        Ok(objects)
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        match fs::remove_file(self.resolve(key)?) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            result => Ok(result?),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_local_storage() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let storage = LocalStorage::new(root.join("store"));
+        assert!(!storage.is_available().await);
+        fs::create_dir(root.join("store")).unwrap();
+        assert!(storage.is_available().await);
+
+        fs::write(root.join("panel.txt"), b"panel").unwrap();
+        storage.put_file("Mural/data/panel.txt", &root.join("panel.txt")).await.unwrap();
+        storage.put_bytes("Mural/bagit.txt", b"BagIt-Version: 1.0\n".to_vec()).await.unwrap();
+        storage.put_bytes("Other/bagit.txt", Vec::new()).await.unwrap();
+
+        let keys: Vec<String> = storage.list("Mural/").await.unwrap().into_iter().map(|o| o.key).collect();
+        assert_eq!(keys, vec!["Mural/bagit.txt", "Mural/data/panel.txt"]);
+        assert_eq!(storage.head("Mural/data/panel.txt").await.unwrap().unwrap().size, 5);
+        assert_eq!(storage.get_bytes("Mural/data/panel.txt").await.unwrap(), b"panel");
+        assert!(storage.put_bytes("../escape.txt", Vec::new()).await.is_err());
+
+        storage.delete("Mural/data/panel.txt").await.unwrap();
+        storage.delete("Mural/data/panel.txt").await.unwrap();
+        assert!(storage.head("Mural/data/panel.txt").await.unwrap().is_none());
+        assert_eq!(join_key("/bags/", "Mural/bagit.txt"), "bags/Mural/bagit.txt");
+        assert_eq!(join_key("", "Mural"), "Mural");
+        assert_eq!(percent_encode("Mural/north wall#2.txt", false), "Mural/north%20wall%232.txt");
+        assert_eq!(percent_decode("Mural/north%20wall%232.txt%"), "Mural/north wall#2.txt%");
+    }
+}
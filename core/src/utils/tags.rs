@@ -0,0 +1,83 @@
+use crate::models::preservation::{Tag, Vocabulary};
+use anyhow::Result;
+
+const MAX_TAG_LENGTH: usize = 100;
+
+/// Trim a tag and collapse runs of whitespace so "Oil  paint " and "oil paint" match
+pub fn normalize_tag_name(name: &str) -> Result<String> {
+    let normalized = name.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    if normalized.is_empty() {
+        return Err(anyhow::anyhow!("Tag name cannot be empty"));
+    }
+    if normalized.chars().count() > MAX_TAG_LENGTH {
+        return Err(anyhow::anyhow!("Tag name is longer than {} characters", MAX_TAG_LENGTH));
+    }
+
+    Ok(normalized)
+}
+
+/// Find a tag by name (case-insensitive) within a vocabulary, or among free-form tags
+pub fn find_tag<'a>(tags: &'a [Tag], vocabulary_id: Option<&str>, name: &str) -> Option<&'a Tag> {
+    tags.iter()
+        .find(|t| t.vocabulary_id.as_deref() == vocabulary_id && t.name.eq_ignore_ascii_case(name))
+}
+
+/// Look up a tag, or prepare a new one when the vocabulary allows it
+///
+/// Returns the tag and whether it still has to be stored.
+pub fn resolve_tag(tags: &[Tag], vocabulary: Option<&Vocabulary>, name: &str) -> Result<(Tag, bool)> {
+    let name = normalize_tag_name(name)?;
+    let vocabulary_id = vocabulary.map(|v| v.id.as_str());
+
+    if let Some(tag) = find_tag(tags, vocabulary_id, &name) {
+        return Ok((tag.clone(), false));
+    }
+
+    if let Some(vocabulary) = vocabulary.filter(|v| v.is_controlled) {
+        return Err(anyhow::anyhow!(
+            "'{}' is not a term in the controlled vocabulary '{}'",
+            name,
+            vocabulary.name
+        ));
+    }
+
+    Ok((Tag::new(name, vocabulary_id.map(str::to_string)), true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_tag_name() {
+        assert_eq!(normalize_tag_name("  Oil \t paint ").unwrap(), "Oil paint");
+        assert!(normalize_tag_name("   ").is_err());
+        assert!(normalize_tag_name(&"x".repeat(101)).is_err());
+    }
+
+    #[test]
+    fn test_resolve_tag_respects_controlled_vocabularies() {
+        let medium = Vocabulary::new("medium".to_string(), None, true);
+        let technique = Vocabulary::new("technique".to_string(), None, false);
+        let tags = vec![
+            Tag::new("Ink".to_string(), Some(medium.id.clone())),
+            Tag::new("ink".to_string(), None),
+        ];
+
+        let (tag, is_new) = resolve_tag(&tags, Some(&medium), "ink").unwrap();
+        assert_eq!(tag.id, tags[0].id);
+        assert!(!is_new);
+
+        assert!(resolve_tag(&tags, Some(&medium), "Charcoal").is_err());
+
+        let (tag, is_new) = resolve_tag(&tags, Some(&technique), "Cross  hatching").unwrap();
+        assert!(is_new);
+        assert_eq!(tag.name, "Cross hatching");
+        assert_eq!(tag.vocabulary_id.as_deref(), Some(technique.id.as_str()));
+
+        let (tag, is_new) = resolve_tag(&tags, None, "INK").unwrap();
+        assert_eq!(tag.id, tags[1].id);
+        assert!(!is_new);
+    }
+}
@@ -0,0 +1,392 @@
+use crate::models::preservation::{FileTechnicalMetadata, ProjectFile};
+use crate::utils::bagit::BagItPackage;
+use crate::utils::dependencies::{attribute_values, decode_entities};
+use anyhow::Result;
+use camino::Utf8Path;
+use lofty::prelude::*;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{BufReader, Read};
+
+/// How far into an image file to look for an XMP packet
+const XMP_SCAN_BYTES: u64 = 4 * 1024 * 1024;
+
+/// PDFs larger than this are described by their XMP packet alone
+const MAX_PDF_BYTES: u64 = 200 * 1024 * 1024;
+
+/// EXIF tags worth keeping, with the normalized key they are stored under
+const EXIF_KEYS: &[(exif::Tag, &str)] = &[
+    (exif::Tag::Make, "exif.make"),
+    (exif::Tag::Model, "exif.model"),
+    (exif::Tag::LensModel, "exif.lens_model"),
+    (exif::Tag::Software, "exif.software"),
+    (exif::Tag::Artist, "exif.artist"),
+    (exif::Tag::Copyright, "exif.copyright"),
+    (exif::Tag::DateTimeOriginal, "exif.date_time_original"),
+    (exif::Tag::DateTime, "exif.date_time"),
+    (exif::Tag::ExposureTime, "exif.exposure_time"),
+    (exif::Tag::FNumber, "exif.f_number"),
+    (exif::Tag::PhotographicSensitivity, "exif.iso"),
+    (exif::Tag::FocalLength, "exif.focal_length"),
+    (exif::Tag::Orientation, "exif.orientation"),
+    (exif::Tag::ColorSpace, "exif.color_space"),
+    (exif::Tag::GPSLatitude, "exif.gps_latitude"),
+    (exif::Tag::GPSLongitude, "exif.gps_longitude"),
+];
+
+/// XMP properties worth keeping, with the normalized key they are stored under
+const XMP_KEYS: &[(&str, &str)] = &[
+    ("dc:title", "xmp.title"),
+    ("dc:creator", "xmp.creator"),
+    ("dc:description", "xmp.description"),
+    ("dc:rights", "xmp.rights"),
+    ("dc:subject", "xmp.subject"),
+    ("xmp:CreatorTool", "xmp.creator_tool"),
+    ("xmp:CreateDate", "xmp.create_date"),
+    ("xmp:ModifyDate", "xmp.modify_date"),
+    ("xmpMM:DocumentID", "xmp.document_id"),
+];
+
+/// PDF document information entries, with the normalized key they are stored under
+const PDF_INFO_KEYS: &[(&[u8], &str)] = &[
+    (b"Title", "pdf.title"),
+    (b"Author", "pdf.author"),
+    (b"Subject", "pdf.subject"),
+    (b"Keywords", "pdf.keywords"),
+    (b"Creator", "pdf.creator"),
+    (b"Producer", "pdf.producer"),
+    (b"CreationDate", "pdf.created"),
+    (b"ModDate", "pdf.modified"),
+];
+
+/// Pull embedded technical metadata out of a file as normalized key-value pairs
+///
+/// Keys are namespaced by source (`image.`, `exif.`, `xmp.`, `pdf.`, `audio.`,
+/// `tag.`). Unreadable or unsupported files give an empty map rather than an error.
+pub fn extract_technical_metadata(path: &Utf8Path, media_type: &str) -> Result<BTreeMap<String, String>> {
+    let mut properties = BTreeMap::new();
+
+    if media_type.starts_with("image/") {
+        if let Ok((width, height)) = image::ImageReader::open(path)?.with_guessed_format()?.into_dimensions() {
+            properties.insert("image.width".to_string(), width.to_string());
+            properties.insert("image.height".to_string(), height.to_string());
+        }
+        properties.extend(exif_properties(path));
+
+        let mut head = Vec::new();
+        fs::File::open(path)?.take(XMP_SCAN_BYTES).read_to_end(&mut head)?;
+        properties.extend(xmp_properties(&String::from_utf8_lossy(&head)));
+    } else if media_type == "application/pdf" {
+        if fs::metadata(path)?.len() <= MAX_PDF_BYTES {
+            properties.extend(pdf_properties(&fs::read(path)?));
+        }
+    } else if media_type.starts_with("audio/") || media_type.starts_with("video/") {
+        properties.extend(audio_properties(path));
+    }
+
+    Ok(properties)
+}
+
+/// Extract technical metadata for every catalogued payload file that is on disk
+pub fn catalog_technical_metadata(bag: &BagItPackage, files: &[ProjectFile]) -> Result<Vec<FileTechnicalMetadata>> {
+    let mut catalog = Vec::new();
+
+    for file in files {
+        let file_path = bag.bag_root.join(&file.path);
+        if !file_path.is_file() {
+            continue;
+        }
+
+        let properties = extract_technical_metadata(&file_path, &file.media_type)?;
+        if !properties.is_empty() {
+            catalog.push(FileTechnicalMetadata { path: file.path.clone(), properties });
+        }
+    }
+
+    Ok(catalog)
+}
+
+fn exif_properties(path: &Utf8Path) -> BTreeMap<String, String> {
+    let mut properties = BTreeMap::new();
+    let Ok(file) = fs::File::open(path) else {
+        return properties;
+    };
+    let Ok(exif) = exif::Reader::new().read_from_container(&mut BufReader::new(file)) else {
+        return properties;
+    };
+
+    for (tag, key) in EXIF_KEYS {
+        let Some(field) = exif.get_field(*tag, exif::In::PRIMARY) else {
+            continue;
+        };
+        let value = match &field.value {
+            // Ascii values display with quotes; keep the bare text
+            exif::Value::Ascii(parts) => parts
+                .iter()
+                .map(|part| String::from_utf8_lossy(part).trim().to_string())
+                .collect::<Vec<_>>()
+                .join(" "),
+            _ => field.display_value().with_unit(&exif).to_string(),
+        };
+        if !value.is_empty() {
+            properties.insert(key.to_string(), value);
+        }
+    }
+
+    properties
+}
+
+/// Read the properties of the first XMP packet found in some text
+fn xmp_properties(text: &str) -> BTreeMap<String, String> {
+    let mut properties = BTreeMap::new();
+    let Some(start) = text.find("<x:xmpmeta") else {
+        return properties;
+    };
+    let end = text[start..].find("</x:xmpmeta>").map_or(text.len(), |end| start + end);
+    let packet = &text[start..end];
+
+    for (name, key) in XMP_KEYS {
+        // Simple properties may be written as attributes of rdf:Description
+        let value = attribute_values(packet, name)
+            .into_iter()
+            .next()
+            .or_else(|| xmp_element_value(packet, name));
+        if let Some(value) = value.filter(|v| !v.is_empty()) {
+            properties.insert(key.to_string(), value);
+        }
+    }
+
+    properties
+}
+
+/// Text of an XMP property element, joining the items of rdf:Alt, rdf:Bag and rdf:Seq lists
+fn xmp_element_value(packet: &str, name: &str) -> Option<String> {
+    let open = packet.find(&format!("<{}>", name))? + name.len() + 2;
+    let close = packet[open..].find(&format!("</{}>", name))? + open;
+    let body = &packet[open..close];
+
+    let items: Vec<String> = body
+        .split("<rdf:li")
+        .skip(1)
+        .filter_map(|item| {
+            let text_start = item.find('>')? + 1;
+            let text_end = item.find("</rdf:li>")?;
+            Some(decode_entities(item.get(text_start..text_end)?.trim()))
+        })
+        .collect();
+
+    if items.is_empty() {
+        Some(decode_entities(body.trim())).filter(|value| !value.contains('<'))
+    } else {
+        Some(items.join("; "))
+    }
+}
+
+fn pdf_properties(bytes: &[u8]) -> BTreeMap<String, String> {
+    let mut properties = BTreeMap::new();
+    // lopdf panics on some malformed files; treat those as having no properties
+    let document = match std::panic::catch_unwind(|| lopdf::Document::load_mem(bytes)) {
+        Ok(Ok(document)) => document,
+        Ok(Err(_)) | Err(_) => return properties,
+    };
+
+    properties.insert("pdf.version".to_string(), document.version.clone());
+    properties.insert("pdf.page_count".to_string(), document.get_pages().len().to_string());
+
+    if let Ok(info) = document.trailer.get_deref(b"Info", &document).and_then(|info| info.as_dict()) {
+        for (name, key) in PDF_INFO_KEYS {
+            let Ok(value) = info.get(name).and_then(|value| value.as_string()) else {
+                continue;
+            };
+            let value = if key.ends_with("created") || key.ends_with("modified") {
+                normalize_pdf_date(&value)
+            } else {
+                value.trim().to_string()
+            };
+            if !value.is_empty() {
+                properties.insert(key.to_string(), value);
+            }
+        }
+    }
+
+    // The catalog's metadata stream holds an XMP packet
+    let metadata = document
+        .catalog()
+        .and_then(|catalog| catalog.get_deref(b"Metadata", &document))
+        .and_then(|metadata| metadata.as_stream());
+    if let Ok(stream) = metadata {
+        let content = stream.decompressed_content().unwrap_or_else(|_| stream.content.clone());
+        properties.extend(xmp_properties(&String::from_utf8_lossy(&content)));
+    }
+
+    properties
+}
+
+/// Turn a PDF date such as "D:20240301143000+01'00'" into "2024-03-01T14:30:00"
+fn normalize_pdf_date(value: &str) -> String {
+    let digits: String = value
+        .trim()
+        .trim_start_matches("D:")
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect();
+
+    let part = |range: std::ops::Range<usize>, default: &'static str| digits.get(range).unwrap_or(default).to_string();
+    if digits.len() < 4 {
+        return value.trim().to_string();
+    }
+
+    format!(
+        "{}-{}-{}T{}:{}:{}",
+        part(0..4, ""),
+        part(4..6, "01"),
+        part(6..8, "01"),
+        part(8..10, "00"),
+        part(10..12, "00"),
+        part(12..14, "00")
+    )
+}
+
+fn audio_properties(path: &Utf8Path) -> BTreeMap<String, String> {
+    let mut properties = BTreeMap::new();
+    let Ok(tagged_file) = lofty::read_from_path(path) else {
+        return properties;
+    };
+
+    let audio = tagged_file.properties();
+    let duration = audio.duration();
+    if !duration.is_zero() {
+        properties.insert("audio.duration_seconds".to_string(), format!("{:.3}", duration.as_secs_f64()));
+    }
+    let numbers = [
+        ("audio.sample_rate", audio.sample_rate().map(u64::from)),
+        ("audio.channels", audio.channels().map(u64::from)),
+        ("audio.bit_depth", audio.bit_depth().map(u64::from)),
+        ("audio.bitrate_kbps", audio.audio_bitrate().map(u64::from)),
+    ];
+    for (key, value) in numbers {
+        if let Some(value) = value.filter(|v| *v > 0) {
+            properties.insert(key.to_string(), value.to_string());
+        }
+    }
+
+    if let Some(tag) = tagged_file.primary_tag().or_else(|| tagged_file.first_tag()) {
+        let text = [
+            ("tag.title", tag.title()),
+            ("tag.artist", tag.artist()),
+            ("tag.album", tag.album()),
+            ("tag.genre", tag.genre()),
+            ("tag.comment", tag.comment()),
+        ];
+        for (key, value) in text {
+            if let Some(value) = value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty()) {
+                properties.insert(key.to_string(), value);
+            }
+        }
+        if let Some(year) = tag.year() {
+            properties.insert("tag.year".to_string(), year.to_string());
+        }
+        if let Some(track) = tag.track() {
+            properties.insert("tag.track".to_string(), track.to_string());
+        }
+    }
+
+    properties
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{dictionary, Document, Object, Stream};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_image_dimensions_and_xmp() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let path = root.join("swatch.png");
+        image::RgbImage::new(12, 8).save(&path).unwrap();
+
+        // Append an XMP packet the way some tools leave one after the image data
+        let mut bytes = fs::read(&path).unwrap();
+        bytes.extend_from_slice(
+            br#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF><rdf:Description xmp:CreatorTool="Krita 5.2">
+            <dc:creator><rdf:Seq><rdf:li>Ada Student</rdf:li><rdf:li>Lin &amp; Co</rdf:li></rdf:Seq></dc:creator>
+            </rdf:Description></rdf:RDF></x:xmpmeta>"#,
+        );
+        fs::write(&path, bytes).unwrap();
+
+        let properties = extract_technical_metadata(&path, "image/png").unwrap();
+        assert_eq!(properties["image.width"], "12");
+        assert_eq!(properties["image.height"], "8");
+        assert_eq!(properties["xmp.creator_tool"], "Krita 5.2");
+        assert_eq!(properties["xmp.creator"], "Ada Student; Lin & Co");
+    }
+
+    #[test]
+    fn test_pdf_properties() {
+        let mut document = Document::with_version("1.7");
+        let pages_id = document.new_object_id();
+        let page_id = document.add_object(dictionary! { "Type" => "Page", "Parent" => pages_id });
+        document.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! { "Type" => "Pages", "Kids" => vec![page_id.into()], "Count" => 1 }),
+        );
+        let metadata_id = document.add_object(Stream::new(
+            dictionary! { "Type" => "Metadata", "Subtype" => "XML" },
+            br#"<x:xmpmeta><xmp:CreatorTool>InDesign 19.0</xmp:CreatorTool></x:xmpmeta>"#.to_vec(),
+        ));
+        let catalog_id = document.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+            "Metadata" => metadata_id,
+        });
+        let info_id = document.add_object(dictionary! {
+            "Title" => Object::string_literal("Exhibition catalogue"),
+            "CreationDate" => Object::string_literal("D:20240301143000+01'00'"),
+        });
+        document.trailer.set("Root", catalog_id);
+        document.trailer.set("Info", info_id);
+
+        let mut bytes = Vec::new();
+        document.save_to(&mut bytes).unwrap();
+
+        let properties = pdf_properties(&bytes);
+        assert_eq!(properties["pdf.version"], "1.7");
+        assert_eq!(properties["pdf.page_count"], "1");
+        assert_eq!(properties["pdf.title"], "Exhibition catalogue");
+        assert_eq!(properties["pdf.created"], "2024-03-01T14:30:00");
+        assert_eq!(properties["xmp.creator_tool"], "InDesign 19.0");
+    }
+
+    #[test]
+    fn test_wav_duration() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let path = root.join("tone.wav");
+
+        // One second of 16-bit mono silence at 8 kHz
+        let (sample_rate, data_len) = (8000u32, 16000u32);
+        let mut wav = Vec::new();
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&sample_rate.to_le_bytes());
+        wav.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+        wav.extend_from_slice(&2u16.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_len.to_le_bytes());
+        wav.resize(wav.len() + data_len as usize, 0);
+        fs::write(&path, wav).unwrap();
+
+        let properties = extract_technical_metadata(&path, "audio/wav").unwrap();
+        assert_eq!(properties["audio.duration_seconds"], "1.000");
+        assert_eq!(properties["audio.sample_rate"], "8000");
+        assert_eq!(properties["audio.channels"], "1");
+        assert_eq!(properties["audio.bit_depth"], "16");
+    }
+}
@@ -0,0 +1,96 @@
+use anyhow::Result;
+use camino::Utf8Path;
+use std::fs;
+
+/// Upper bound on text kept per file so one large document cannot dominate the index
+pub const MAX_EXTRACTED_CHARS: usize = 200_000;
+
+/// Files larger than this are indexed by name only
+const MAX_SOURCE_BYTES: u64 = 50 * 1024 * 1024;
+
+/// Payload formats whose text can be extracted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextSource {
+    Plain,
+    Markdown,
+    Pdf,
+}
+
+impl TextSource {
+    /// Pick a text source from a file's extension
+    pub fn for_path(path: &Utf8Path) -> Option<Self> {
+        match path.extension()?.to_ascii_lowercase().as_str() {
+            "txt" | "text" => Some(TextSource::Plain),
+            "md" | "markdown" => Some(TextSource::Markdown),
+            "pdf" => Some(TextSource::Pdf),
+            _ => None,
+        }
+    }
+}
+
+/// Extract searchable text from a plain text, markdown or PDF file
+///
+/// Returns `None` for other formats, oversized files and PDFs without a text layer.
+pub fn extract_text(path: &Utf8Path) -> Result<Option<String>> {
+    let Some(source) = TextSource::for_path(path) else {
+        return Ok(None);
+    };
+    if fs::metadata(path)?.len() > MAX_SOURCE_BYTES {
+        return Ok(None);
+    }
+
+    let bytes = fs::read(path)?;
+    let text = match source {
+        TextSource::Plain | TextSource::Markdown => String::from_utf8_lossy(&bytes).into_owned(),
+        TextSource::Pdf => {
+            // The PDF parser panics on some malformed files; treat those as having no text
+            match std::panic::catch_unwind(|| pdf_extract::extract_text_from_mem(&bytes)) {
+                Ok(Ok(text)) => text,
+                Ok(Err(_)) | Err(_) => return Ok(None),
+            }
+        }
+    };
+
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(truncate_chars(text, MAX_EXTRACTED_CHARS)))
+}
+
+fn truncate_chars(mut text: String, max_chars: usize) -> String {
+    if let Some((index, _)) = text.char_indices().nth(max_chars) {
+        text.truncate(index);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_extract_plain_and_markdown() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+
+        fs::write(root.join("notes.md"), "# Artist statement\n\nWorking  with\tcharcoal").unwrap();
+        fs::write(root.join("image.png"), [0x89, 0x50, 0x4e, 0x47]).unwrap();
+        fs::write(root.join("empty.txt"), "  \n ").unwrap();
+
+        assert_eq!(
+            extract_text(&root.join("notes.md")).unwrap().as_deref(),
+            Some("# Artist statement Working with charcoal")
+        );
+        assert!(extract_text(&root.join("image.png")).unwrap().is_none());
+        assert!(extract_text(&root.join("empty.txt")).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_truncate_chars_respects_char_boundaries() {
+        assert_eq!(truncate_chars("éèêë".to_string(), 2), "éè");
+        assert_eq!(truncate_chars("abc".to_string(), 10), "abc");
+    }
+}
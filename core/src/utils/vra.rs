@@ -0,0 +1,340 @@
+use crate::models::preservation::{ArchivedProject, FileTechnicalMetadata};
+use crate::utils::bag_info::BagInfo;
+use crate::utils::bagit::BagItPackage;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use file_format::FileFormat;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+
+const VRA_NAMESPACE: &str = "http://www.vraweb.org/vracore4.htm";
+const VRA_SCHEMA_LOCATION: &str = "http://www.loc.gov/standards/vracore/vra.xsd";
+const VRA_SOURCE: &str = "Creative Work Preservation Toolkit";
+
+/// A VRA Core image record describing one payload file of a work
+pub struct VraImage {
+    pub id: String,
+    pub path: String,
+    pub name: String,
+    pub size: u64,
+    pub media_type: String,
+    pub sha256: String,
+    pub technical: BTreeMap<String, String>,
+}
+
+/// A VRA Core work record describing an archived project
+pub struct VraWork {
+    pub id: String,
+    pub project_id: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub creator: Option<String>,
+    pub source_organization: Option<String>,
+    pub created_on: DateTime<Utc>,
+    pub images: Vec<VraImage>,
+}
+
+impl VraWork {
+    /// Build a work record from a project and the inventory of its bag
+    pub fn from_project(project: &ArchivedProject, bag: &BagItPackage) -> Result<Self> {
+        let bag_info = if bag.bag_info_path.exists() {
+            bag.read_bag_info()?
+        } else {
+            BagInfo::new()
+        };
+
+        let mut images = Vec::new();
+        for (index, (checksum, relative_path)) in bag.read_manifest()?.into_iter().enumerate() {
+            let file_path = bag.bag_root.join(&relative_path);
+            let size = fs::metadata(&file_path)?.len();
+            let media_type = FileFormat::from_file(&file_path)?.media_type().to_string();
+
+            images.push(VraImage {
+                id: format!("i_{}-{}", project.id, index + 1),
+                name: file_path.file_name().unwrap_or("Unknown").to_string(),
+                path: relative_path,
+                size,
+                media_type,
+                sha256: checksum,
+                technical: BTreeMap::new(),
+            });
+        }
+
+        Ok(VraWork {
+            id: format!("w_{}", project.id),
+            project_id: project.id.clone(),
+            title: project.name.clone(),
+            description: project.description.clone(),
+            creator: bag_info.get("Contact-Name").map(str::to_string),
+            source_organization: bag_info.get("Source-Organization").map(str::to_string),
+            created_on: project.created_at,
+            images,
+        })
+    }
+
+    /// Attach extracted technical metadata to the matching image records
+    pub fn with_technical_metadata(mut self, technical: &[FileTechnicalMetadata]) -> Self {
+        for image in &mut self.images {
+            if let Some(file) = technical.iter().find(|t| t.path == image.path) {
+                image.technical = file.properties.clone();
+            }
+        }
+        self
+    }
+}
+
+/// Render works and their image records as a VRA Core 4.0 XML document
+pub fn build_vra_document(works: &[VraWork]) -> String {
+    let mut xml = String::new();
+
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<vra xmlns=\"{}\" xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" xsi:schemaLocation=\"{} {}\">",
+        VRA_NAMESPACE, VRA_NAMESPACE, VRA_SCHEMA_LOCATION
+    );
+
+    for work in works {
+        write_work(&mut xml, work);
+        for image in &work.images {
+            write_image(&mut xml, work, image);
+        }
+    }
+
+    xml.push_str("</vra>\n");
+    xml
+}
+
+// VRA Core requires the sets inside a record to appear in alphabetical order
+fn write_work(xml: &mut String, work: &VraWork) {
+    let _ = writeln!(
+        xml,
+        "  <work id=\"{}\" source=\"{}\" refid=\"{}\">",
+        work.id,
+        VRA_SOURCE,
+        escape_xml(&work.project_id)
+    );
+
+    if let Some(creator) = &work.creator {
+        let creator = escape_xml(creator);
+        let _ = writeln!(xml, "    <agentSet>");
+        let _ = writeln!(xml, "      <display>{}</display>", creator);
+        let _ = writeln!(xml, "      <agent>");
+        let _ = writeln!(xml, "        <name type=\"personal\">{}</name>", creator);
+        let _ = writeln!(xml, "        <role>creator</role>");
+        let _ = writeln!(xml, "      </agent>");
+        let _ = writeln!(xml, "    </agentSet>");
+    }
+
+    let date = work.created_on.format("%Y-%m-%d").to_string();
+    let _ = writeln!(xml, "    <dateSet>");
+    let _ = writeln!(xml, "      <display>{}</display>", date);
+    let _ = writeln!(xml, "      <date type=\"creation\">");
+    let _ = writeln!(xml, "        <earliestDate>{}</earliestDate>", date);
+    let _ = writeln!(xml, "        <latestDate>{}</latestDate>", date);
+    let _ = writeln!(xml, "      </date>");
+    let _ = writeln!(xml, "    </dateSet>");
+
+    if let Some(description) = &work.description {
+        let _ = writeln!(xml, "    <descriptionSet>");
+        let _ = writeln!(xml, "      <description>{}</description>", escape_xml(description));
+        let _ = writeln!(xml, "    </descriptionSet>");
+    }
+
+    if let Some(organization) = &work.source_organization {
+        let _ = writeln!(xml, "    <locationSet>");
+        let _ = writeln!(xml, "      <location type=\"repository\">");
+        let _ = writeln!(xml, "        <name type=\"corporate\">{}</name>", escape_xml(organization));
+        let _ = writeln!(xml, "      </location>");
+        let _ = writeln!(xml, "    </locationSet>");
+    }
+
+    let _ = writeln!(xml, "    <measurementsSet>");
+    let _ = writeln!(xml, "      <display>{} files</display>", work.images.len());
+    let _ = writeln!(
+        xml,
+        "      <measurements type=\"count\" unit=\"files\">{}</measurements>",
+        work.images.len()
+    );
+    let _ = writeln!(xml, "    </measurementsSet>");
+
+    if !work.images.is_empty() {
+        let _ = writeln!(xml, "    <relationSet>");
+        for (index, image) in work.images.iter().enumerate() {
+            let _ = writeln!(
+                xml,
+                "      <relation type=\"imageIs\" relids=\"{}\" pref=\"{}\">{}</relation>",
+                image.id,
+                index == 0,
+                escape_xml(&image.name)
+            );
+        }
+        let _ = writeln!(xml, "    </relationSet>");
+    }
+
+    let title = escape_xml(&work.title);
+    let _ = writeln!(xml, "    <titleSet>");
+    let _ = writeln!(xml, "      <display>{}</display>", title);
+    let _ = writeln!(xml, "      <title type=\"creator\" pref=\"true\">{}</title>", title);
+    let _ = writeln!(xml, "    </titleSet>");
+
+    let _ = writeln!(xml, "    <worktypeSet>");
+    let _ = writeln!(xml, "      <worktype>digital work</worktype>");
+    let _ = writeln!(xml, "    </worktypeSet>");
+
+    let _ = writeln!(xml, "  </work>");
+}
+
+fn write_image(xml: &mut String, work: &VraWork, image: &VraImage) {
+    let path = escape_xml(&image.path);
+    let _ = writeln!(
+        xml,
+        "  <image id=\"{}\" source=\"{}\" refid=\"{}\">",
+        image.id, VRA_SOURCE, path
+    );
+
+    let _ = writeln!(xml, "    <locationSet>");
+    let _ = writeln!(xml, "      <display>{}</display>", path);
+    let _ = writeln!(xml, "      <location type=\"repository\">");
+    let _ = writeln!(xml, "        <refid type=\"other\">{}</refid>", path);
+    let _ = writeln!(xml, "      </location>");
+    let _ = writeln!(xml, "    </locationSet>");
+
+    let _ = writeln!(xml, "    <measurementsSet>");
+    let _ = writeln!(xml, "      <display>{} bytes</display>", image.size);
+    let _ = writeln!(
+        xml,
+        "      <measurements type=\"fileSize\" unit=\"byte\">{}</measurements>",
+        image.size
+    );
+    let measurements = [
+        ("width", "px", "image.width"),
+        ("height", "px", "image.height"),
+        ("duration", "sec", "audio.duration_seconds"),
+    ];
+    for (measurement_type, unit, key) in measurements {
+        if let Some(value) = image.technical.get(key) {
+            let _ = writeln!(
+                xml,
+                "      <measurements type=\"{}\" unit=\"{}\">{}</measurements>",
+                measurement_type,
+                unit,
+                escape_xml(value)
+            );
+        }
+    }
+    let _ = writeln!(xml, "    </measurementsSet>");
+
+    let _ = writeln!(xml, "    <relationSet>");
+    let _ = writeln!(
+        xml,
+        "      <relation type=\"imageOf\" relids=\"{}\" pref=\"true\">{}</relation>",
+        work.id,
+        escape_xml(&work.title)
+    );
+    let _ = writeln!(xml, "    </relationSet>");
+
+    let _ = writeln!(xml, "    <sourceSet>");
+    let _ = writeln!(xml, "      <source>");
+    let _ = writeln!(xml, "        <refid type=\"other\">sha256:{}</refid>", image.sha256);
+    let _ = writeln!(xml, "      </source>");
+    let _ = writeln!(xml, "    </sourceSet>");
+
+    let _ = writeln!(xml, "    <techniqueSet>");
+    let _ = writeln!(xml, "      <technique>{}</technique>", escape_xml(&image.media_type));
+    let _ = writeln!(xml, "    </techniqueSet>");
+
+    let _ = writeln!(xml, "    <titleSet>");
+    let _ = writeln!(
+        xml,
+        "      <title type=\"generalView\" pref=\"true\">{}</title>",
+        escape_xml(&image.name)
+    );
+    let _ = writeln!(xml, "    </titleSet>");
+
+    let _ = writeln!(xml, "    <worktypeSet>");
+    let _ = writeln!(xml, "      <worktype>{}</worktype>", image_worktype(&image.media_type));
+    let _ = writeln!(xml, "    </worktypeSet>");
+
+    let _ = writeln!(xml, "  </image>");
+}
+
+/// Map a media type onto a VRA worktype term for image records
+fn image_worktype(media_type: &str) -> &'static str {
+    match media_type.split('/').next().unwrap_or("") {
+        "image" => "digital image",
+        "video" => "moving image",
+        "audio" => "sound recording",
+        _ if media_type == "application/pdf" => "digital document",
+        _ => "digital file",
+    }
+}
+
+/// Escape the XML special characters in text and attribute values
+pub(crate) fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use camino::Utf8PathBuf;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_escape_xml() {
+        assert_eq!(escape_xml("Sketch & <Draft>"), "Sketch &amp; &lt;Draft&gt;");
+        assert_eq!(escape_xml("\"final\" 'cut'"), "&quot;final&quot; &apos;cut&apos;");
+    }
+
+    #[test]
+    fn test_image_worktype() {
+        assert_eq!(image_worktype("image/png"), "digital image");
+        assert_eq!(image_worktype("video/mp4"), "moving image");
+        assert_eq!(image_worktype("application/pdf"), "digital document");
+        assert_eq!(image_worktype("application/octet-stream"), "digital file");
+    }
+
+    #[tokio::test]
+    async fn test_work_from_bagged_project() {
+        let temp_dir = TempDir::new().unwrap();
+        let bag_path = Utf8PathBuf::from_path_buf(temp_dir.path().join("bag")).unwrap();
+        let bag = BagItPackage::new(bag_path).unwrap();
+        fs::write(bag.data_dir.join("poster.svg"), "<svg></svg>").unwrap();
+        bag.create_bagit_declaration().unwrap();
+        bag.create_manifest().await.unwrap();
+
+        let project = ArchivedProject::new(
+            "Poster <Series>".to_string(),
+            Some("Screenprint studies".to_string()),
+            1,
+            11,
+        );
+        let technical = vec![FileTechnicalMetadata {
+            path: "data/poster.svg".to_string(),
+            properties: [("image.width".to_string(), "600".to_string())].into_iter().collect(),
+        }];
+        let work = VraWork::from_project(&project, &bag).unwrap().with_technical_metadata(&technical);
+        assert_eq!(work.images.len(), 1);
+        assert_eq!(work.images[0].path, "data/poster.svg");
+
+        let xml = build_vra_document(&[work]);
+        assert!(xml.contains(&format!("<work id=\"w_{}\"", project.id)));
+        assert!(xml.contains("<title type=\"creator\" pref=\"true\">Poster &lt;Series&gt;</title>"));
+        assert!(xml.contains(&format!("relids=\"w_{}\"", project.id)));
+        assert!(xml.contains("<measurements type=\"fileSize\" unit=\"byte\">11</measurements>"));
+        assert!(xml.contains("<measurements type=\"width\" unit=\"px\">600</measurements>"));
+    }
+}
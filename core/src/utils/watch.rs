@@ -0,0 +1,178 @@
+use anyhow::Result;
+use camino::{Utf8Path, Utf8PathBuf};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use std::fs;
+use std::time::UNIX_EPOCH;
+use walkdir::WalkDir;
+
+/// How long an entry must stay unchanged before it is ingested, unless configured
+pub const DEFAULT_SETTLE_SECONDS: i64 = 30;
+
+/// Suffixes browsers and sync clients give files that are still being written
+const PARTIAL_FILE_SUFFIXES: &[&str] = &[".part", ".partial", ".crdownload", ".download", ".tmp"];
+
+/// A top-level file or folder of a watch folder as it is now
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatchEntry {
+    pub name: String,
+    pub path: Utf8PathBuf,
+    /// File count, total size and latest modification time; changes whenever the entry does
+    pub fingerprint: String,
+}
+
+/// Hidden files, Office lock files and system clutter are never ingested
+fn is_ignored(name: &str) -> bool {
+    name.starts_with('.') || name.starts_with("~$") || name == "Thumbs.db" || name == "desktop.ini"
+}
+
+fn is_partial(name: &str) -> bool {
+    let name = name.to_lowercase();
+    PARTIAL_FILE_SUFFIXES.iter().any(|suffix| name.ends_with(suffix))
+}
+
+/// List the entries of a watch folder that could be ingested
+///
+/// Entries with files still being downloaded, and folders with no files yet,
+/// are left out until they are complete.
+pub fn scan_watch_folder(folder: &Utf8Path) -> Result<Vec<WatchEntry>> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(folder)? {
+        let entry = entry?;
+        let Ok(name) = entry.file_name().into_string() else {
+            continue;
+        };
+        if is_ignored(&name) {
+            continue;
+        }
+
+        let path = folder.join(&name);
+        let mut file_count = 0u64;
+        let mut total_size = 0u64;
+        let mut latest_modified = 0u128;
+        let mut partial = false;
+        for file in WalkDir::new(&path).into_iter().filter_entry(|e| {
+            e.depth() == 0 || !e.file_name().to_str().map(is_ignored).unwrap_or(true)
+        }) {
+            let file = file?;
+            if !file.file_type().is_file() {
+                continue;
+            }
+            if file.file_name().to_str().map(is_partial).unwrap_or(false) {
+                partial = true;
+                break;
+            }
+            let metadata = file.metadata()?;
+            file_count += 1;
+            total_size += metadata.len();
+            let modified = metadata.modified()?.duration_since(UNIX_EPOCH).unwrap_or_default();
+            latest_modified = latest_modified.max(modified.as_nanos());
+        }
+
+        if partial || file_count == 0 {
+            continue;
+        }
+        entries.push(WatchEntry {
+            name,
+            path,
+            fingerprint: format!("{}:{}:{}", file_count, total_size, latest_modified),
+        });
+    }
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(entries)
+}
+
+#[derive(Debug)]
+struct Observation {
+    fingerprint: String,
+    since: DateTime<Utc>,
+    reported: bool,
+}
+
+/// Debounces watch folder entries across polls
+///
+/// An entry is reported once its fingerprint has stayed the same for the
+/// settle time, and again only after it changes and settles anew.
+#[derive(Debug, Default)]
+pub struct WatchTracker {
+    observed: HashMap<(String, String), Observation>,
+}
+
+impl WatchTracker {
+    /// Record a poll of one watch folder and return the entries that just settled
+    pub fn settled(
+        &mut self,
+        watch_folder_id: &str,
+        entries: Vec<WatchEntry>,
+        settle: Duration,
+        now: DateTime<Utc>,
+    ) -> Vec<WatchEntry> {
+        self.observed
+            .retain(|(folder, name), _| folder != watch_folder_id || entries.iter().any(|e| &e.name == name));
+
+        let mut settled = Vec::new();
+        for entry in entries {
+            let key = (watch_folder_id.to_string(), entry.name.clone());
+            let observation = self.observed.entry(key).or_insert_with(|| Observation {
+                fingerprint: entry.fingerprint.clone(),
+                since: now,
+                reported: false,
+            });
+            if observation.fingerprint != entry.fingerprint {
+                *observation = Observation {
+                    fingerprint: entry.fingerprint.clone(),
+                    since: now,
+                    reported: false,
+                };
+            }
+            if !observation.reported && now - observation.since >= settle {
+                observation.reported = true;
+                settled.push(entry);
+            }
+        }
+        settled
+    }
+
+    /// Forget an entry, so it is reported again once it has settled anew
+    pub fn forget(&mut self, watch_folder_id: &str, entry_name: &str) {
+        self.observed.remove(&(watch_folder_id.to_string(), entry_name.to_string()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_entries_settle_once_per_change() {
+        let temp_dir = TempDir::new().unwrap();
+        let folder = Utf8Path::from_path(temp_dir.path()).unwrap();
+        fs::create_dir(folder.join("Final Critique")).unwrap();
+        fs::write(folder.join("Final Critique/board.png"), b"png").unwrap();
+        fs::write(folder.join("Final Critique/.DS_Store"), b"clutter").unwrap();
+        fs::write(folder.join("render.mov.crdownload"), b"half a movie").unwrap();
+        fs::write(folder.join(".hidden"), b"skip").unwrap();
+        fs::create_dir(folder.join("empty")).unwrap();
+
+        let entries = scan_watch_folder(folder).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].fingerprint.starts_with("1:3:"));
+
+        let settle = Duration::seconds(30);
+        let start = Utc::now();
+        let mut tracker = WatchTracker::default();
+        assert!(tracker.settled("w", entries.clone(), settle, start).is_empty());
+        assert_eq!(tracker.settled("w", entries.clone(), settle, start + settle).len(), 1);
+        assert!(tracker
+            .settled("w", entries.clone(), settle, start + settle * 2)
+            .is_empty());
+
+        // A change restarts the settle time
+        let mut changed = entries;
+        changed[0].fingerprint = "2:10:1".to_string();
+        let later = start + settle * 3;
+        assert!(tracker.settled("w", changed.clone(), settle, later).is_empty());
+        assert_eq!(tracker.settled("w", changed, settle, later + settle).len(), 1);
+    }
+}
@@ -0,0 +1,501 @@
+use crate::utils::storage::{percent_decode, percent_encode, StorageBackend, StoredObject};
+use anyhow::Result;
+use async_trait::async_trait;
+use camino::Utf8Path;
+use quick_xml::events::Event;
+use reqwest::{header, Method, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Mutex;
+use tokio::io::AsyncWriteExt;
+use tokio_util::io::ReaderStream;
+
+/// Properties asked for when listing a collection
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:"><d:prop><d:resourcetype/><d:getcontentlength/><d:getetag/></d:prop></d:propfind>"#;
+
+/// Connection details of a WebDAV share, such as a Nextcloud or ownCloud folder
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WebDavConfig {
+    /// Collection bags are stored in, e.g. `https://cloud.example.edu/remote.php/dav/files/alice/CWPT`
+    pub url: String,
+    pub username: String,
+    /// Account password, or an app password where the server offers them
+    pub password: String,
+}
+
+impl std::fmt::Debug for WebDavConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebDavConfig")
+            .field("url", &self.url)
+            .field("username", &self.username)
+            .field("password", &"<redacted>")
+            .finish()
+    }
+}
+
+/// Stores objects as files in a WebDAV collection, creating sub-collections as needed
+pub struct WebDavStorage {
+    config: WebDavConfig,
+    base: Url,
+    client: reqwest::Client,
+    /// Collections known to exist, so each is only created once
+    collections: Mutex<HashSet<String>>,
+}
+
+impl WebDavStorage {
+    pub fn new(config: WebDavConfig) -> Result<Self> {
+        let mut base = Url::parse(&config.url)?;
+        if !matches!(base.scheme(), "http" | "https") {
+            return Err(anyhow::anyhow!("WebDAV URL must be http or https: {}", config.url));
+        }
+        if !base.path().ends_with('/') {
+            base.set_path(&format!("{}/", base.path()));
+        }
+        Ok(Self {
+            config,
+            base,
+            client: reqwest::Client::new(),
+            collections: Mutex::new(HashSet::new()),
+        })
+    }
+
+    fn url(&self, key: &str) -> Result<Url> {
+        Ok(self.base.join(&percent_encode(key.trim_start_matches('/'), false))?)
+    }
+
+    fn request(&self, method: Method, url: Url) -> reqwest::RequestBuilder {
+        self.client
+            .request(method, url)
+            .basic_auth(&self.config.username, Some(&self.config.password))
+    }
+
+    /// Create the collections a key lives in, from the top down
+    async fn create_parents(&self, key: &str) -> Result<()> {
+        let segments: Vec<&str> = key.trim_matches('/').split('/').collect();
+        for depth in 1..segments.len() {
+            let collection = format!("{}/", segments[..depth].join("/"));
+            if self.collections.lock().unwrap().contains(&collection) {
+                continue;
+            }
+            let response = self.request(Method::from_bytes(b"MKCOL")?, self.url(&collection)?).send().await?;
+            // 405 means the collection is already there
+            if !response.status().is_success() && response.status() != StatusCode::METHOD_NOT_ALLOWED {
+                check(response, "creating collection", &collection).await?;
+            }
+            self.collections.lock().unwrap().insert(collection);
+        }
+        Ok(())
+    }
+
+    async fn put(&self, key: &str, body: reqwest::Body, length: u64) -> Result<()> {
+        self.create_parents(key).await?;
+        let response = self
+            .request(Method::PUT, self.url(key)?)
+            .header(header::CONTENT_LENGTH, length)
+            .body(body)
+            .send()
+            .await?;
+        check(response, "upload", key).await?;
+        Ok(())
+    }
+
+    /// The members of one collection, one level deep
+    async fn propfind(&self, collection: &str) -> Result<Option<Vec<DavResource>>> {
+        let response = self
+            .request(Method::from_bytes(b"PROPFIND")?, self.url(collection)?)
+            .header("Depth", "1")
+            .header(header::CONTENT_TYPE, "application/xml; charset=utf-8")
+            .body(PROPFIND_BODY)
+            .send()
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response = check(response, "listing", collection).await?;
+        Ok(Some(parse_multistatus(&response.text().await?)?))
+    }
+
+    /// The key of a resource from the href a server reported for it
+    fn key_for_href(&self, href: &str) -> Option<String> {
+        let path = match Url::parse(href) {
+            Ok(url) => url.path().to_string(),
+            Err(_) => href.to_string(),
+        };
+        let key = percent_decode(&path).strip_prefix(&percent_decode(self.base.path()))?.to_string();
+        Some(key)
+    }
+}
+
+/// Turn an unsuccessful response into an error carrying the server's status
+async fn check(response: reqwest::Response, action: &str, key: &str) -> Result<reqwest::Response> {
+    if response.status().is_success() {
+        return Ok(response);
+    }
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    let detail: String = body.trim().chars().take(200).collect();
+    Err(anyhow::anyhow!("WebDAV {} of {} failed with {}: {}", action, key, status, detail))
+}
+
+/// Strip quotes, and the weak marker some servers add, from an etag
+fn clean_etag(etag: &str) -> String {
+    etag.trim().trim_start_matches("W/").trim_matches('"').to_string()
+}
+
+#[async_trait]
+impl StorageBackend for WebDavStorage {
+    fn location(&self) -> String {
+        self.base.as_str().trim_end_matches('/').to_string()
+    }
+
+    async fn is_available(&self) -> bool {
+        let Ok(method) = Method::from_bytes(b"PROPFIND") else {
+            return false;
+        };
+        self.request(method, self.base.clone())
+            .header("Depth", "0")
+            .send()
+            .await
+            .is_ok_and(|response| response.status() == StatusCode::MULTI_STATUS)
+    }
+
+    async fn put_file(&self, key: &str, source: &Utf8Path) -> Result<()> {
+        let file = tokio::fs::File::open(source).await?;
+        let length = file.metadata().await?.len();
+        self.put(key, reqwest::Body::wrap_stream(ReaderStream::new(file)), length).await
+    }
+
+    async fn put_bytes(&self, key: &str, bytes: Vec<u8>) -> Result<()> {
+        let length = bytes.len() as u64;
+        self.put(key, bytes.into(), length).await
+    }
+
+    async fn get_file(&self, key: &str, destination: &Utf8Path) -> Result<()> {
+        let response = self.request(Method::GET, self.url(key)?).send().await?;
+        let mut response = check(response, "download", key).await?;
+        if let Some(parent) = destination.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let mut file = tokio::fs::File::create(destination).await?;
+        while let Some(chunk) = response.chunk().await? {
+            file.write_all(&chunk).await?;
+        }
+        file.flush().await?;
+        Ok(())
+    }
+
+    async fn get_bytes(&self, key: &str) -> Result<Vec<u8>> {
+        let response = self.request(Method::GET, self.url(key)?).send().await?;
+        Ok(check(response, "download", key).await?.bytes().await?.to_vec())
+    }
+
+    async fn head(&self, key: &str) -> Result<Option<StoredObject>> {
+        let response = self.request(Method::HEAD, self.url(key)?).send().await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response = check(response, "lookup", key).await?;
+        let header_value = |name| response.headers().get(name).and_then(|value| value.to_str().ok());
+        Ok(Some(StoredObject {
+            key: key.to_string(),
+            size: header_value(header::CONTENT_LENGTH).and_then(|value| value.parse().ok()).unwrap_or(0),
+            etag: header_value(header::ETAG).map(clean_etag),
+        }))
+    }
+
+    /// Walks the collections one level at a time, as many servers refuse `Depth: infinity`
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredObject>> {
+        let start = match prefix.rfind('/') {
+            Some(end) => prefix[..=end].to_string(),
+            None => String::new(),
+        };
+        let mut pending = vec![start];
+        let mut objects = Vec::new();
+
+        while let Some(collection) = pending.pop() {
+            let Some(resources) = self.propfind(&collection).await? else {
+                continue;
+            };
+            for resource in resources {
+                let Some(key) = self.key_for_href(&resource.href) else {
+                    continue;
+                };
+                let key = key.trim_start_matches('/').to_string();
+                if resource.is_collection {
+                    let key = format!("{}/", key.trim_end_matches('/'));
+                    // A collection lists itself first
+                    if key != collection && key != "/" && (key.starts_with(prefix) || prefix.starts_with(&key)) {
+                        pending.push(key);
+                    }
+                } else if key.starts_with(prefix) {
+                    objects.push(StoredObject {
+                        key,
+                        size: resource.size,
+                        etag: resource.etag,
+                    });
+                }
+            }
+        }
+        objects.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(objects)
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        let response = self.request(Method::DELETE, self.url(key)?).send().await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(());
+        }
+        check(response, "deletion", key).await?;
+        Ok(())
+    }
+}
+
+/// One `<response>` of a PROPFIND multistatus body
+#[derive(Debug, Default, PartialEq)]
+struct DavResource {
+    href: String,
+    is_collection: bool,
+    size: u64,
+    etag: Option<String>,
+}
+
+/// Read a multistatus body by element names alone, as servers differ in namespace prefixes
+fn parse_multistatus(xml: &str) -> Result<Vec<DavResource>> {
+    let mut reader = quick_xml::Reader::from_str(xml);
+    let mut resources = Vec::new();
+    let mut current: Option<DavResource> = None;
+    let mut element = Vec::new();
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(start) => {
+                element = start.local_name().as_ref().to_vec();
+                match element.as_slice() {
+                    b"response" => current = Some(DavResource::default()),
+                    b"collection" => current.iter_mut().for_each(|r| r.is_collection = true),
+                    _ => {}
+                }
+            }
+            Event::Empty(empty) if empty.local_name().as_ref() == b"collection" => {
+                current.iter_mut().for_each(|r| r.is_collection = true);
+            }
+            Event::Text(text) => {
+                let (Some(resource), text) = (current.as_mut(), text.unescape()?) else {
+                    continue;
+                };
+                match element.as_slice() {
+                    b"href" => resource.href.push_str(text.trim()),
+                    b"getcontentlength" => resource.size = text.trim().parse().unwrap_or(0),
+                    b"getetag" => resource.etag = Some(clean_etag(&text)),
+                    _ => {}
+                }
+            }
+            Event::End(end) => {
+                if end.local_name().as_ref() == b"response" {
+                    resources.extend(current.take());
+                }
+                element.clear();
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(resources)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use axum::{
+        body::Bytes,
+        extract::State,
+        http::{HeaderMap, Uri},
+        response::{IntoResponse, Response},
+        Router,
+    };
+    use sha2::Digest;
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    /// Files by path below the share root, and paths whose next upload fails
+    #[derive(Clone, Default)]
+    pub(crate) struct Share {
+        pub files: Arc<Mutex<BTreeMap<String, Vec<u8>>>>,
+        pub uploads: Arc<Mutex<Vec<String>>>,
+        pub fail_next_upload: Arc<Mutex<HashSet<String>>>,
+    }
+
+    pub(crate) fn config(url: &str) -> WebDavConfig {
+        WebDavConfig {
+            url: format!("{}/remote.php/dav/files/alice/CWPT", url),
+            username: "alice".to_string(),
+            password: "app-password".to_string(),
+        }
+    }
+
+    /// Content-derived, like Nextcloud's, but deliberately not an MD5
+    fn etag(bytes: &[u8]) -> String {
+        let digest = format!("{:x}", sha2::Sha256::digest(bytes));
+        format!("\"{}\"", &digest[..16])
+    }
+
+    /// Enough of a Nextcloud share to exercise the client: PROPFIND, MKCOL, GET, HEAD, PUT and DELETE
+    async fn handle(State(share): State<Share>, method: Method, uri: Uri, headers: HeaderMap, body: Bytes) -> Response {
+        let expected = format!("Basic {}", "YWxpY2U6YXBwLXBhc3N3b3Jk");
+        if headers.get("authorization").and_then(|v| v.to_str().ok()) != Some(expected.as_str()) {
+            return StatusCode::UNAUTHORIZED.into_response();
+        }
+        let root = "/remote.php/dav/files/alice/CWPT/";
+        let path = percent_decode(uri.path());
+        let Some(key) = path.strip_prefix(root).or((path == root.trim_end_matches('/')).then_some("")) else {
+            return StatusCode::NOT_FOUND.into_response();
+        };
+        let mut files = share.files.lock().unwrap();
+        let is_collection = |key: &str| {
+            let prefix = format!("{}/", key.trim_end_matches('/'));
+            key.is_empty() || files.keys().any(|f| f.starts_with(&prefix))
+        };
+
+        match method.as_str() {
+            "PROPFIND" => {
+                if !is_collection(key) {
+                    return StatusCode::NOT_FOUND.into_response();
+                }
+                let collection = match key.trim_end_matches('/') {
+                    "" => String::new(),
+                    key => format!("{}/", key),
+                };
+                let mut members = BTreeMap::new();
+                for (file, bytes) in files.iter().filter(|(f, _)| f.starts_with(&collection)) {
+                    match file[collection.len()..].split_once('/') {
+                        Some((child, _)) => members.insert(format!("{}{}/", collection, child), None),
+                        None => members.insert(file.clone(), Some(bytes)),
+                    };
+                }
+                let entry = |key: &str, bytes: Option<&Vec<u8>>| {
+                    let prop = match bytes {
+                        Some(bytes) => format!(
+                            "<d:resourcetype/><d:getcontentlength>{}</d:getcontentlength><d:getetag>{}</d:getetag>",
+                            bytes.len(),
+                            etag(bytes)
+                        ),
+                        None => "<d:resourcetype><d:collection/></d:resourcetype>".to_string(),
+                    };
+                    format!(
+                        "<d:response><d:href>{}{}</d:href><d:propstat><d:prop>{}</d:prop></d:propstat></d:response>",
+                        root,
+                        percent_encode(key, false),
+                        prop
+                    )
+                };
+                let mut xml = String::from("<?xml version=\"1.0\"?><d:multistatus xmlns:d=\"DAV:\">");
+                xml.push_str(&entry(&collection, None));
+                if headers.get("depth").and_then(|v| v.to_str().ok()) != Some("0") {
+                    for (member, bytes) in &members {
+                        xml.push_str(&entry(member, *bytes));
+                    }
+                }
+                xml.push_str("</d:multistatus>");
+                (StatusCode::MULTI_STATUS, xml).into_response()
+            }
+            "MKCOL" if is_collection(key) => StatusCode::METHOD_NOT_ALLOWED.into_response(),
+            "MKCOL" => StatusCode::CREATED.into_response(),
+            "PUT" => {
+                if share.fail_next_upload.lock().unwrap().remove(key) {
+                    return StatusCode::SERVICE_UNAVAILABLE.into_response();
+                }
+                share.uploads.lock().unwrap().push(key.to_string());
+                files.insert(key.to_string(), body.to_vec());
+                StatusCode::CREATED.into_response()
+            }
+            "DELETE" => match files.remove(key) {
+                Some(_) => StatusCode::NO_CONTENT.into_response(),
+                None => StatusCode::NOT_FOUND.into_response(),
+            },
+            _ => match files.get(key) {
+                Some(bytes) => ([("etag", etag(bytes))], bytes.clone()).into_response(),
+                None => StatusCode::NOT_FOUND.into_response(),
+            },
+        }
+    }
+
+    /// Serve the stand-in share on a free port, returning its base URL
+    pub(crate) async fn serve_stand_in() -> (String, Share) {
+        let share = Share::default();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let app = Router::new().fallback(handle).with_state(share.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (url, share)
+    }
+
+    #[test]
+    fn test_parse_multistatus() {
+        // As Nextcloud answers, including a propstat for properties it does not have
+        let xml = r#"<?xml version="1.0"?>
+            <d:multistatus xmlns:d="DAV:" xmlns:oc="http://owncloud.org/ns">
+              <d:response>
+                <d:href>/remote.php/dav/files/alice/CWPT/</d:href>
+                <d:propstat><d:prop><d:resourcetype><d:collection/></d:resourcetype>
+                  <d:getetag>&quot;65f1a2&quot;</d:getetag></d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat>
+                <d:propstat><d:prop><d:getcontentlength/></d:prop><d:status>HTTP/1.1 404 Not Found</d:status></d:propstat>
+              </d:response>
+              <d:response>
+                <d:href>/remote.php/dav/files/alice/CWPT/north%20wall.txt</d:href>
+                <d:propstat><d:prop><d:resourcetype/><d:getcontentlength>10</d:getcontentlength>
+                  <d:getetag>W/"abc123"</d:getetag></d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat>
+              </d:response>
+            </d:multistatus>"#;
+        let resources = parse_multistatus(xml).unwrap();
+        assert_eq!(resources.len(), 2);
+        assert!(resources[0].is_collection);
+        assert_eq!(
+            resources[1],
+            DavResource {
+                href: "/remote.php/dav/files/alice/CWPT/north%20wall.txt".to_string(),
+                is_collection: false,
+                size: 10,
+                etag: Some("abc123".to_string()),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_round_trip_against_stand_in() {
+        let (url, share) = serve_stand_in().await;
+        let storage = WebDavStorage::new(config(&url)).unwrap();
+        assert!(storage.location().ends_with("/remote.php/dav/files/alice/CWPT"));
+        assert!(storage.is_available().await);
+
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        std::fs::write(root.join("north wall.txt"), b"north wall").unwrap();
+        storage.put_file("Mural/data/north wall.txt", &root.join("north wall.txt")).await.unwrap();
+        storage.put_bytes("Mural/bagit.txt", b"BagIt-Version: 1.0\n".to_vec()).await.unwrap();
+        storage.put_bytes("Mural-2/bagit.txt", Vec::new()).await.unwrap();
+        assert!(share.files.lock().unwrap().contains_key("Mural/data/north wall.txt"));
+
+        let listed = storage.list("Mural/").await.unwrap();
+        let keys: Vec<&str> = listed.iter().map(|o| o.key.as_str()).collect();
+        assert_eq!(keys, vec!["Mural/bagit.txt", "Mural/data/north wall.txt"]);
+        assert_eq!(storage.list("").await.unwrap().len(), 3);
+        let head = storage.head("Mural/data/north wall.txt").await.unwrap().unwrap();
+        assert_eq!(head.size, 10);
+        assert_eq!(head.etag, listed[1].etag);
+
+        storage.get_file("Mural/data/north wall.txt", &root.join("copy.txt")).await.unwrap();
+        assert_eq!(std::fs::read(root.join("copy.txt")).unwrap(), b"north wall");
+        storage.delete("Mural/bagit.txt").await.unwrap();
+        assert!(storage.head("Mural/bagit.txt").await.unwrap().is_none());
+        assert!(storage.list("Missing/").await.unwrap().is_empty());
+
+        let wrong_password = WebDavStorage::new(WebDavConfig {
+            password: "guess".to_string(),
+            ..config(&url)
+        })
+        .unwrap();
+        assert!(!wrong_password.is_available().await);
+    }
+}
@@ -0,0 +1,4143 @@
+use crate::error::{Error, Result};
+use crate::models::preservation::*;
+use crate::store::Store;
+use crate::utils::{
+    bag_update::{apply_bag_update, PayloadChange},
+    bag_serialization::{import_serialized_bag, serialize_bag, validate_serialized_bag, SerializationFormat},
+    bagit::{project_bag_info, project_bag_root, BagItPackage, DEFAULT_BAGS_ROOT},
+    bagit_profile::BagItProfile,
+    checksums::{calculate_sha256, ChecksumAlgorithm},
+    collections::{
+        apply_bag_group_identifiers, bag_group_identifiers, build_collection_trees, reorder, subtree_ids,
+        validate_collection_move,
+    },
+    dependencies::{analyze_payload, plan_linked_file_imports},
+    derivatives::{generate_bag_previews, DEFAULT_DERIVATIVES_ROOT},
+    encryption::{
+        decrypt_file, encrypt_file, generate_recovery_key, normalize_recovery_key, DataKey, EncryptedStorage, Keyring,
+        SecretKey, WrappedKey, ENCRYPTED_EXTENSION, MIN_PASSPHRASE_LENGTH,
+    },
+    environment::{normalize_profile, write_environment_tag_file},
+    fetch::{add_fetch_reference, complete_fetch, file_url, FetchEntry},
+    file_operations::{analyze_path, find_common_root, sanitize_directory_name, validate_paths},
+    format_risk::catalog_payload,
+    integrity::{bag_problems, check_vault, layer_checksum, ExpectedBag},
+    lineage::{build_history_tree, diff_manifests, find_lineage_root, validate_new_relation},
+    premis::{build_premis_document, premis_files},
+    quarantine::{move_bag, quarantine_path, scheduled_deletion, DEFAULT_QUARANTINE_ROOT},
+    replication::{bag_files, compare_stored, copy_bag, repair_bag, BagFile, DEFAULT_VERIFY_INTERVAL_HOURS},
+    search::{build_match_query, project_documents},
+    source_changes::{
+        compare_sources, list_source_files, SourceDelta, SourceFile, SourceFileState, PREVIOUS_VERSION_LABEL,
+    },
+    storage::{join_key, LocalStorage, StorageBackend, StoredObject},
+    tags::{normalize_tag_name, resolve_tag},
+    technical_metadata::catalog_technical_metadata,
+    vra::{build_vra_document, VraWork},
+    watch::{scan_watch_folder, WatchEntry, WatchTracker, DEFAULT_SETTLE_SECONDS},
+};
+#[cfg(any(feature = "s3", feature = "webdav"))]
+use crate::utils::encryption::{decrypt_bytes, encrypt_bytes};
+#[cfg(feature = "s3")]
+use crate::utils::s3::{S3Config, S3Storage};
+#[cfg(feature = "webdav")]
+use crate::utils::webdav::{WebDavConfig, WebDavStorage};
+use anyhow::Context;
+use camino::{Utf8Path, Utf8PathBuf};
+use chrono::{Duration, Utc};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::sync::{Arc, Mutex, PoisonError};
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+/// Hits returned by a search when the caller does not ask for a limit
+pub const DEFAULT_SEARCH_LIMIT: u32 = 50;
+
+const HTTP_API_TOKEN_SETTING: &str = "http_api_token";
+const REPLICA_VERIFY_INTERVAL_SETTING: &str = "replica_verify_interval_hours";
+const ENCRYPTION_PASSPHRASE_SETTING: &str = "encryption_passphrase_key";
+const ENCRYPTION_RECOVERY_SETTING: &str = "encryption_recovery_key";
+/// What events about the vault as a whole are recorded against
+const VAULT_AGGREGATE_ID: &str = "vault";
+
+/// Where a vault keeps its database and bags
+#[derive(Debug, Clone)]
+pub struct VaultConfig {
+    pub database_path: Utf8PathBuf,
+    pub bags_root: Utf8PathBuf,
+    pub quarantine_root: Utf8PathBuf,
+    /// Thumbnails and previews, keyed by payload checksum
+    pub derivatives_root: Utf8PathBuf,
+}
+
+impl VaultConfig {
+    /// A vault with the given database and the default bag locations
+    pub fn new(database_path: impl Into<Utf8PathBuf>) -> Self {
+        Self {
+            database_path: database_path.into(),
+            bags_root: Utf8PathBuf::from(DEFAULT_BAGS_ROOT),
+            quarantine_root: Utf8PathBuf::from(DEFAULT_QUARANTINE_ROOT),
+            derivatives_root: Utf8PathBuf::from(DEFAULT_DERIVATIVES_ROOT),
+        }
+    }
+
+    /// A vault with the given database and its bags, quarantine and derivatives under `data_dir`
+    pub fn with_data_dir(database_path: impl Into<Utf8PathBuf>, data_dir: &Utf8Path) -> Self {
+        Self {
+            database_path: database_path.into(),
+            bags_root: data_dir.join("bags"),
+            quarantine_root: data_dir.join("quarantine"),
+            derivatives_root: data_dir.join("derivatives"),
+        }
+    }
+}
+
+/// Options for writing a project's bag
+#[derive(Debug, Clone, Default)]
+pub struct BagOptions {
+    /// Extra bag-info.txt fields, appended in order after the standard ones
+    pub bag_info_fields: Vec<BagInfoField>,
+    /// BagIt profile the bag must conform to
+    pub profile_path: Option<Utf8PathBuf>,
+}
+
+/// What a project export writes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Tar,
+    TarGz,
+    Zip,
+    Vra,
+    Premis,
+}
+
+impl ExportFormat {
+    pub const ALL: &'static [ExportFormat] = &[
+        ExportFormat::Tar,
+        ExportFormat::TarGz,
+        ExportFormat::Zip,
+        ExportFormat::Vra,
+        ExportFormat::Premis,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportFormat::Tar => "tar",
+            ExportFormat::TarGz => "tar-gz",
+            ExportFormat::Zip => "zip",
+            ExportFormat::Vra => "vra",
+            ExportFormat::Premis => "premis",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|format| format.as_str() == value)
+    }
+
+    /// The bag serialization this format writes, if it writes the bag itself
+    pub(crate) fn serialization(&self) -> Option<SerializationFormat> {
+        match self {
+            ExportFormat::Tar => Some(SerializationFormat::Tar),
+            ExportFormat::TarGz => Some(SerializationFormat::TarGz),
+            ExportFormat::Zip => Some(SerializationFormat::Zip),
+            ExportFormat::Vra | ExportFormat::Premis => None,
+        }
+    }
+}
+
+/// A preservation vault: the project catalog, its bags and their audit trail
+///
+/// Cloning is cheap and clones share the same database connection.
+#[derive(Clone)]
+pub struct Vault {
+    store: Arc<Store>,
+    config: VaultConfig,
+    /// Encryption keys while unlocked; shared by clones like the store
+    keyring: Arc<Mutex<Option<Arc<Keyring>>>>,
+}
+
+impl Vault {
+    /// Open a vault, creating its database and applying migrations as needed
+    pub async fn open(config: VaultConfig) -> Result<Self> {
+        let database_path = config.database_path.clone();
+        let store = tokio::task::spawn_blocking(move || Store::open(&database_path))
+            .await
+            .map_err(anyhow::Error::from)??;
+        Ok(Self {
+            store: Arc::new(store),
+            config,
+            keyring: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    pub fn config(&self) -> &VaultConfig {
+        &self.config
+    }
+
+    /// Record a new archived project from files or folders
+    ///
+    /// Sources are stored as absolute paths so the project can be bagged from
+    /// any working directory later. Environment profiles in the request are
+    /// attached in the same transaction, and an unknown one stores nothing.
+    pub async fn archive_project(&self, request: &ArchiveRequest) -> Result<ArchivedProject> {
+        self.check_profiles_exist(&request.environment_profile_ids)?;
+        let files = request
+            .files
+            .iter()
+            .map(|path| {
+                let absolute = fs::canonicalize(path).map_err(|_| Error::NotFound(path.clone()))?;
+                absolute
+                    .into_os_string()
+                    .into_string()
+                    .map_err(|p| Error::Failed(anyhow::anyhow!("Non-UTF8 path encountered: {}", p.to_string_lossy())))
+            })
+            .collect::<Result<Vec<String>>>()?;
+
+        let stats_files = files.clone();
+        let (file_count, total_size, source_root) = blocking(move || {
+            let (file_count, total_size) = source_stats(&stats_files)?;
+            Ok((file_count, total_size, find_common_root(&stats_files)?))
+        })
+        .await?;
+        let project = ArchivedProject::new(
+            request.name.clone(),
+            request.description.clone(),
+            file_count as i32,
+            total_size as i64,
+        );
+        let event_payload = serde_json::json!({
+            "project_name": request.name,
+            "file_count": file_count,
+            "total_size": total_size,
+            "files": files,
+        });
+        let environments: Vec<ProjectEnvironment> = request
+            .environment_profile_ids
+            .iter()
+            .map(|profile_id| ProjectEnvironment {
+                archived_project_id: project.id.clone(),
+                profile_id: profile_id.clone(),
+                attached_at: Utc::now(),
+            })
+            .collect();
+        self.store.record_archived_project(
+            &project,
+            &event_payload.to_string(),
+            source_root.as_str(),
+            &environments,
+            None,
+        )?;
+        self.index_project(&project.id).await?;
+
+        Ok(project)
+    }
+
+    /// Get all archived projects, newest first
+    pub async fn list_projects(&self) -> Result<Vec<ArchivedProject>> {
+        Ok(self.store.get_all_archived_projects()?)
+    }
+
+    /// Get one page of archived projects matching a query, with a count for
+    /// each value of each facet
+    ///
+    /// A facet's counts ignore the query's own filter on that facet, so they
+    /// show what choosing a different value would return.
+    pub async fn query_projects(&self, query: &ProjectQuery) -> Result<ProjectPage> {
+        Ok(self.store.query_archived_projects(query)?)
+    }
+
+    /// Get one archived project
+    pub async fn get_project(&self, project_id: &str) -> Result<ArchivedProject> {
+        self.store
+            .get_archived_project(project_id)?
+            .ok_or_else(|| Error::ProjectNotFound(project_id.to_string()))
+    }
+
+    /// The audit trail of a project, oldest first
+    pub async fn project_events(&self, project_id: &str) -> Result<Vec<PreservationEvent>> {
+        Ok(self.store.get_events(project_id)?)
+    }
+
+    /// Where a project's bag is now: in the bags root, or in quarantine
+    pub fn bag_root(&self, project: &ArchivedProject) -> Result<Utf8PathBuf> {
+        let bag_root = project_bag_root(&self.config.bags_root, &project.name, &project.id);
+        if project.is_quarantined {
+            Ok(quarantine_path(&self.config.quarantine_root, &bag_root)?)
+        } else {
+            Ok(bag_root)
+        }
+    }
+
+    /// Copy a project's source files into a new bag and write its manifests and tag files
+    pub async fn create_bag(&self, project_id: &str, options: BagOptions) -> Result<BagResult> {
+        let profile = options
+            .profile_path
+            .as_deref()
+            .map(BagItProfile::load)
+            .transpose()?;
+        let project = self.get_project(project_id).await?;
+        if project.is_quarantined {
+            return Err(Error::InvalidState(format!("Project {} is quarantined", project_id)));
+        }
+
+        let bag_root = self.bag_root(&project)?;
+        if bag_root.join("bagit.txt").exists() {
+            return Err(Error::InvalidState(format!(
+                "Project {} is already bagged at {}",
+                project_id, bag_root
+            )));
+        }
+
+        let sources = self.store.archived_source_paths(project_id)?;
+        if sources.is_empty() {
+            return Err(Error::InvalidState(format!("Project {} has no recorded source files", project_id)));
+        }
+
+        // Copying the payload reads and writes every file, so keep it off the async runtime
+        let payload_root = bag_root.clone();
+        let (bag, source_root, source_files) = blocking(move || {
+            let mut payload = Vec::new();
+            for source in &sources {
+                payload.extend(analyze_path(source)?.files);
+            }
+            let source_root = find_common_root(&sources)?;
+            // Taken before copying so a file saved mid-copy shows up as changed later
+            let source_files = list_source_files(&sources, &source_root)?;
+
+            let bag = BagItPackage::new(payload_root)?;
+            bag.create_bagit_declaration()?;
+            bag.add_files(&payload, &source_root)?;
+            Ok((bag, source_root, source_files))
+        })
+        .await?;
+
+        let manifest_algorithms = match &profile {
+            Some(profile) => profile.manifest_algorithms()?,
+            None => vec![ChecksumAlgorithm::Sha256],
+        };
+        for algorithm in manifest_algorithms {
+            bag.create_manifest_with(algorithm).await?;
+        }
+
+        // The profile's required values replace the vault's defaults, and the caller's fields replace both
+        let mut bag_info = project_bag_info(&project, &bag)?;
+        if let Some(profile) = &profile {
+            profile.apply_to_bag_info(&mut bag_info);
+        }
+        for field in &options.bag_info_fields {
+            bag_info.remove(&field.label);
+        }
+        for field in options.bag_info_fields {
+            bag_info.add(field.label, field.value);
+        }
+        // Projects filed into collections before they were bagged
+        let identifiers = bag_group_identifiers(
+            &self.store.get_all_collections()?,
+            &self.store.get_all_collection_members()?,
+            project_id,
+        );
+        apply_bag_group_identifiers(&mut bag_info, &identifiers);
+        bag.create_bag_info(&bag_info)?;
+        write_environment_tag_file(&bag, &self.attached_profiles(project_id)?)?;
+
+        if let Some(profile) = &profile {
+            for algorithm in profile.tag_manifest_algorithms()? {
+                bag.create_tag_manifest(algorithm).await?;
+            }
+        }
+
+        let mut issues = bag.validate().await?;
+        if let Some(profile) = &profile {
+            issues.extend(profile.validate_bag(&bag)?);
+        }
+
+        let event_payload = serde_json::json!({
+            "project_id": project_id,
+            "bag_path": bag_root.as_str(),
+            "profile": profile.as_ref().map(|p| p.identifier()),
+            "validation_issues": issues.len(),
+        });
+        self.store
+            .insert_event("BagitPackageCreated", project_id, &event_payload.to_string())?;
+
+        self.store.set_project_source(project_id, source_root.as_str())?;
+        self.record_source_states(project_id, &bag, &source_files, &[]).await?;
+        self.refresh_catalog(project_id, &bag_root).await?;
+        self.index_project(project_id).await?;
+        self.replicate_changed_bag(project_id).await;
+
+        Ok(bag_result(&bag_root, issues, "BagIt package created and validated successfully"))
+    }
+
+    /// Validate a project's bag where it currently is
+    pub async fn validate_project(&self, project_id: &str) -> Result<BagResult> {
+        let project = self.get_project(project_id).await?;
+        let bag_root = self.bag_root(&project)?;
+        let bag = BagItPackage::open(bag_root).map_err(|e| Error::NotFound(e.to_string()))?;
+        let issues = bag.validate().await?;
+
+        let event_payload = serde_json::json!({
+            "bag_path": bag.bag_root.as_str(),
+            "validation_issues": issues.len(),
+        });
+        self.store.insert_event("BagValidated", project_id, &event_payload.to_string())?;
+
+        Ok(bag_result(&bag.bag_root, issues, "Bag is valid"))
+    }
+
+    /// Validate any bag directory, or a tar, tar.gz or zip serialized bag,
+    /// and check it against a BagIt profile if one is given
+    pub async fn validate_path(&self, path: &Utf8Path, profile_path: Option<&Utf8Path>) -> Result<BagResult> {
+        let profile = profile_path.map(BagItProfile::load).transpose()?;
+        let issues = if path.is_dir() {
+            let bag = BagItPackage::open(path.to_path_buf())?;
+            let mut issues = bag.validate().await?;
+            if let Some(profile) = &profile {
+                issues.extend(profile.validate_bag(&bag)?);
+            }
+            issues
+        } else if path.is_file() {
+            let archive_path = path.to_path_buf();
+            let archive_profile = profile.clone();
+            blocking(move || validate_serialized_bag(&archive_path, archive_profile.as_ref()))
+                .await?
+                .issues
+        } else {
+            return Err(Error::NotFound(path.to_string()));
+        };
+
+        let message = match &profile {
+            Some(profile) => format!("Bag conforms to profile {}", profile.identifier()),
+            None => "Bag is valid".to_string(),
+        };
+        Ok(bag_result(path, issues, &message))
+    }
+
+    /// Validate a tar, tar.gz or zip serialized bag and add it to the vault as a new project
+    ///
+    /// The project is named after the bag's top-level directory. An invalid
+    /// bag is reported in the result and nothing is imported.
+    pub async fn import_serialized_bag(
+        &self,
+        archive_path: &Utf8Path,
+        profile_path: Option<&Utf8Path>,
+    ) -> Result<BagResult> {
+        if !archive_path.is_file() {
+            return Err(Error::NotFound(archive_path.to_string()));
+        }
+        let profile = profile_path.map(BagItProfile::load).transpose()?;
+
+        // Unpacked beside the bags first, since the bag's final name depends on the new project ID
+        let staging = self.config.bags_root.join(format!(".import-{}", Uuid::new_v4()));
+        let (archive, unpack_root) = (archive_path.to_path_buf(), staging.clone());
+        let imported = blocking(move || import_serialized_bag(&archive, &unpack_root, profile.as_ref())).await;
+        let (report, bag) = match imported {
+            Ok(imported) => imported,
+            Err(e) => {
+                let _ = fs::remove_dir_all(&staging);
+                return Err(e);
+            }
+        };
+        let Some(bag) = bag else {
+            let _ = fs::remove_dir_all(&staging);
+            return Ok(bag_result(archive_path, report.issues, "Serialized bag is valid"));
+        };
+
+        let name = report.top_level_directory.clone().unwrap_or_else(|| "Imported bag".to_string());
+        let description = bag.read_bag_info()?.get("External-Description").map(str::to_string);
+        let project = ArchivedProject::new(
+            name,
+            description,
+            report.payload_file_count as i32,
+            report.payload_bytes as i64,
+        );
+        let bag_root = self.bag_root(&project)?;
+        let moved = fs::rename(&bag.bag_root, &bag_root);
+        let _ = fs::remove_dir_all(&staging);
+        moved?;
+
+        let event_payload = serde_json::json!({
+            "archive_path": archive_path.as_str(),
+            "format": report.format.media_type(),
+            "bag_path": bag_root.as_str(),
+            "file_count": report.payload_file_count,
+            "total_size": report.payload_bytes,
+        });
+        let event_payload = event_payload.to_string();
+        if let Err(e) = self.store.record_imported_project(&project, "SerializedBagImported", &event_payload) {
+            // A bag in the vault without a catalog entry would never be found again
+            let _ = fs::remove_dir_all(&bag_root);
+            return Err(e.into());
+        }
+        self.refresh_catalog(&project.id, &bag_root).await?;
+        self.index_project(&project.id).await?;
+        self.replicate_changed_bag(&project.id).await;
+
+        Ok(bag_result(&bag_root, Vec::new(), "Serialized bag imported"))
+    }
+
+    /// Add a file on external storage to a project's bag as a fetch.txt reference
+    ///
+    /// The file is hashed into the manifests now and copied in later by
+    /// [`Vault::complete_fetch`].
+    pub async fn add_external_reference(
+        &self,
+        project_id: &str,
+        source_path: &Utf8Path,
+        payload_path: &str,
+    ) -> Result<BagResult> {
+        let bag = self.project_bag(project_id).await?;
+        if !source_path.is_file() {
+            return Err(Error::NotFound(source_path.to_string()));
+        }
+        let entry = add_fetch_reference(&bag, source_path, payload_path).await?;
+        // The payload grew and fetch.txt changed, so the tag files must follow
+        bag.refresh_bag_info_totals()?;
+        bag.refresh_tag_manifests().await?;
+
+        let event_payload = serde_json::json!({
+            "url": entry.url,
+            "length": entry.length,
+            "path": entry.path,
+        });
+        self.store
+            .insert_event("ExternalReferenceAdded", project_id, &event_payload.to_string())?;
+        self.refresh_catalog(project_id, &bag.bag_root).await?;
+        self.replicate_changed_bag(project_id).await;
+
+        Ok(BagResult {
+            success: true,
+            bag_path: Some(bag.bag_root.to_string()),
+            validation_results: Some(vec![ValidationResult {
+                result_type: "info".to_string(),
+                message: format!("{} will be fetched from {}", entry.path, entry.url),
+                file: Some(entry.path),
+            }]),
+            error: None,
+        })
+    }
+
+    /// Copy in every file a project's fetch.txt lists, checking each against the manifests
+    pub async fn complete_fetch(&self, project_id: &str) -> Result<BagResult> {
+        let bag = self.project_bag(project_id).await?;
+        let report = complete_fetch(&bag).await?;
+
+        let mut validation_results = Vec::new();
+        for path in &report.fetched {
+            validation_results.push(ValidationResult {
+                result_type: "info".to_string(),
+                message: "Fetched and verified".to_string(),
+                file: Some(path.clone()),
+            });
+        }
+        for (path, reason) in &report.failed {
+            validation_results.push(ValidationResult {
+                result_type: "error".to_string(),
+                message: reason.clone(),
+                file: Some(path.clone()),
+            });
+        }
+
+        let event_payload = serde_json::json!({
+            "fetched": report.fetched,
+            "failed": report.failed.iter().map(|(path, _)| path).collect::<Vec<_>>(),
+        });
+        self.store.insert_event("HoleyBagCompleted", project_id, &event_payload.to_string())?;
+        // Fetched files now have a detectable format and text to index
+        self.refresh_catalog(project_id, &bag.bag_root).await?;
+        self.index_project(project_id).await?;
+        self.replicate_changed_bag(project_id).await;
+
+        Ok(BagResult {
+            success: report.is_complete(),
+            bag_path: Some(bag.bag_root.to_string()),
+            validation_results: Some(validation_results),
+            error: None,
+        })
+    }
+
+    /// Add, remove or replace files in a project's bag in place, as a new bag version
+    pub async fn update_bag(&self, project_id: &str, changes: &[PayloadChange]) -> Result<BagResult> {
+        if changes.is_empty() {
+            return Err(Error::InvalidState("No payload changes given".to_string()));
+        }
+        let bag = self.project_bag(project_id).await?;
+        let summary = apply_bag_update(&bag, changes).await?;
+        let issues = bag.validate().await?;
+
+        let event_payload = serde_json::json!({
+            "version": summary.version,
+            "added": summary.added,
+            "removed": summary.removed,
+            "replaced": summary.replaced,
+            "validation_issues": issues.len(),
+        });
+        self.store.insert_event("BagUpdated", project_id, &event_payload.to_string())?;
+        self.refresh_catalog(project_id, &bag.bag_root).await?;
+        self.index_project(project_id).await?;
+        self.replicate_changed_bag(project_id).await;
+
+        let message = format!(
+            "Bag version {}: {} added, {} removed, {} replaced",
+            summary.version,
+            summary.added.len(),
+            summary.removed.len(),
+            summary.replaced.len()
+        );
+        Ok(bag_result(&bag.bag_root, issues, &message))
+    }
+
+    /// Report the fonts and linked files a project's payload needs, flagging those the bag lacks
+    pub async fn analyze_dependencies(&self, project_id: &str) -> Result<DependencyReport> {
+        let bag = self.project_bag(project_id).await?;
+        // The analyzers read whole payload files, so keep them off the async runtime
+        let dependencies = blocking(move || analyze_payload(&bag)).await?;
+        let missing_count = dependencies.iter().filter(|d| d.is_missing()).count();
+
+        let event_payload = serde_json::json!({
+            "dependency_count": dependencies.len(),
+            "missing_count": missing_count,
+        });
+        self.store
+            .insert_event("DependenciesAnalyzed", project_id, &event_payload.to_string())?;
+
+        Ok(DependencyReport {
+            project_id: project_id.to_string(),
+            dependencies,
+            missing_count,
+        })
+    }
+
+    /// Copy missing linked files into a project's payload, as a new bag version
+    ///
+    /// Absolute references are copied from where they point when the file
+    /// still exists there; everything else is looked up under `search_roots`.
+    pub async fn include_linked_files(&self, project_id: &str, search_roots: &[Utf8PathBuf]) -> Result<BagResult> {
+        let bag = self.project_bag(project_id).await?;
+        let bag_root = bag.bag_root.clone();
+        let search_roots = search_roots.to_vec();
+        let changes = blocking(move || {
+            let payload: Vec<String> = bag.read_manifest()?.into_iter().map(|(_, path)| path).collect();
+            let dependencies = analyze_payload(&bag)?;
+            Ok(plan_linked_file_imports(&dependencies, &payload, &search_roots))
+        })
+        .await?;
+
+        if changes.is_empty() {
+            return Ok(bag_result(
+                &bag_root,
+                Vec::new(),
+                "No missing linked files were found in the search folders",
+            ));
+        }
+        self.update_bag(project_id, &changes).await
+    }
+
+    /// Thumbnails and previews of a project's raster images, generating any that are missing
+    ///
+    /// Derivatives are cached under the configured derivatives root by payload
+    /// checksum, so unchanged files are not decoded again.
+    pub async fn project_previews(&self, project_id: &str) -> Result<ProjectPreviews> {
+        let bag = self.project_bag(project_id).await?;
+        let media_types: Vec<(String, String)> = self
+            .store
+            .get_project_files(project_id)?
+            .into_iter()
+            .map(|file| (file.path, file.media_type))
+            .collect();
+
+        // Decoding and resizing full-size images is slow, so keep it off the async runtime
+        let derivatives_root = self.config.derivatives_root.clone();
+        blocking(move || generate_bag_previews(&derivatives_root, &bag, &media_types)).await
+    }
+
+    /// Move a project's bag into quarantine and schedule it for deletion
+    pub async fn quarantine_project(&self, project_id: &str, reason: &str) -> Result<QuarantineEntry> {
+        let project = self.get_project(project_id).await?;
+        if project.is_quarantined {
+            return Err(Error::InvalidState(format!("Project {} is already quarantined", project_id)));
+        }
+
+        let bag_root = self.bag_root(&project)?;
+        let quarantined_path = quarantine_path(&self.config.quarantine_root, &bag_root)?;
+        // Projects that were never bagged are quarantined in the catalog only
+        if bag_root.exists() {
+            let (from, to) = (bag_root.clone(), quarantined_path.clone());
+            blocking(move || move_bag(&from, &to)).await?;
+        }
+
+        let quarantined_at = Utc::now();
+        let entry = QuarantineEntry {
+            id: Uuid::new_v4().to_string(),
+            archived_project_id: project.id.clone(),
+            quarantined_at,
+            original_bag_path: bag_root.to_string(),
+            scheduled_for_deletion_at: Some(scheduled_deletion(quarantined_at)),
+            reason: Some(reason.to_string()),
+        };
+        self.store.insert_quarantine_entry(&entry)?;
+        self.store.update_quarantine_status(project_id, true)?;
+
+        let event_payload = serde_json::json!({
+            "reason": reason,
+            "original_bag_path": entry.original_bag_path,
+            "quarantine_path": quarantined_path.as_str(),
+            "scheduled_for_deletion_at": entry.scheduled_for_deletion_at,
+        });
+        self.store.insert_event("ItemQuarantined", project_id, &event_payload.to_string())?;
+
+        Ok(entry)
+    }
+
+    /// Move a quarantined project's bag back into the vault
+    pub async fn restore_project(&self, project_id: &str) -> Result<ArchivedProject> {
+        let project = self.get_project(project_id).await?;
+        if !project.is_quarantined {
+            return Err(Error::InvalidState(format!("Project {} is not quarantined", project_id)));
+        }
+
+        let entry = self.store.get_quarantine_entry(project_id)?;
+        if let Some(entry) = &entry {
+            let original = Utf8PathBuf::from(&entry.original_bag_path);
+            let quarantined_path = quarantine_path(&self.config.quarantine_root, &original)?;
+            if quarantined_path.exists() {
+                blocking(move || move_bag(&quarantined_path, &original)).await?;
+            }
+            self.store.delete_quarantine_entry(&entry.id)?;
+        }
+        self.store.update_quarantine_status(project_id, false)?;
+
+        let event_payload = serde_json::json!({
+            "bag_path": entry.as_ref().map(|e| e.original_bag_path.clone()),
+        });
+        self.store.insert_event("ProjectRestored", project_id, &event_payload.to_string())?;
+        self.index_project(project_id).await?;
+
+        self.get_project(project_id).await
+    }
+
+    /// Validate every project's bag and its replicas, and record a vault snapshot
+    pub async fn scan_integrity(&self) -> Result<IntegrityReport> {
+        let expected: Vec<ExpectedBag> = self
+            .store
+            .get_all_archived_projects()?
+            .into_iter()
+            .filter(|p| !p.is_quarantined)
+            .map(|p| ExpectedBag {
+                bag_root: project_bag_root(&self.config.bags_root, &p.name, &p.id),
+                project_id: p.id,
+            })
+            .collect();
+
+        let mut issues = check_vault(&self.config.bags_root, &expected).await?;
+        issues.extend(self.replica_issues().await?);
+        let bags_root = self.config.bags_root.clone();
+        let bags_layer_checksum = blocking(move || layer_checksum(&bags_root)).await?;
+        let scanned_at = Utc::now();
+
+        self.store.insert_vault_snapshot(&VaultSnapshot {
+            id: Uuid::new_v4().to_string(),
+            snapshot_at: scanned_at,
+            // There is no user layer outside the bags yet, so it is recorded as empty
+            user_layer_checksum: blake3::hash(b"").to_hex().to_string(),
+            bags_layer_checksum,
+            anomalies_detected: Some(issues.iter().map(|i| i.message.clone()).collect()),
+            created_at: scanned_at,
+        })?;
+
+        Ok(IntegrityReport {
+            is_healthy: issues.iter().all(|i| i.severity != "critical"),
+            issues,
+            last_scan_at: scanned_at,
+        })
+    }
+
+    /// Write a project's bag as an archive into the directory `output`, or its
+    /// VRA or PREMIS record to the file `output`
+    pub async fn export_project(&self, project_id: &str, format: ExportFormat, output: &Utf8Path) -> Result<ExportResult> {
+        let project = self.get_project(project_id).await?;
+        let bag_root = self.bag_root(&project)?;
+        if !bag_root.is_dir() {
+            return Err(Error::NotFound(format!("Bag for project {}", project_id)));
+        }
+
+        let output = output.to_path_buf();
+        let export_project_id = project_id.to_string();
+        let (event_type, event_payload, output_path, record_count) = blocking(move || {
+            let bag = BagItPackage::open(bag_root)?;
+
+            if let Some(serialization) = format.serialization() {
+                let archive_path = serialize_bag(&bag, serialization, &output)?;
+                let payload = serde_json::json!({
+                    "format": serialization.media_type(),
+                    "archive_path": archive_path.as_str(),
+                });
+                return Ok(("SerializedBagExported", payload, archive_path, 1));
+            }
+
+            let files = catalog_payload(&bag, &export_project_id)?;
+            let technical = catalog_technical_metadata(&bag, &files)?;
+            if format == ExportFormat::Vra {
+                let work = VraWork::from_project(&project, &bag)?.with_technical_metadata(&technical);
+                fs::write(&output, build_vra_document(&[work]))?;
+                let payload = serde_json::json!({
+                    "format": "vra-core-4.0",
+                    "output_path": output.as_str(),
+                    "work_count": 1,
+                });
+                Ok(("VraCoreExported", payload, output, 1))
+            } else {
+                let objects = premis_files(&bag, &files, &technical)?;
+                fs::write(&output, build_premis_document(&project, &objects))?;
+                let payload = serde_json::json!({
+                    "format": "premis-3.0",
+                    "output_path": output.as_str(),
+                    "object_count": objects.len() + 1,
+                });
+                Ok(("PremisExported", payload, output, objects.len() + 1))
+            }
+        })
+        .await?;
+
+        self.store.insert_event(event_type, project_id, &event_payload.to_string())?;
+
+        Ok(ExportResult {
+            success: true,
+            output_path: Some(output_path.to_string()),
+            record_count,
+            error: None,
+        })
+    }
+
+    /// Write every bagged project in the vault as one VRA Core 4.0 document
+    ///
+    /// Quarantined projects and projects without a bag yet are left out.
+    pub async fn export_vault_vra(&self, output: &Utf8Path) -> Result<ExportResult> {
+        let mut bagged = Vec::new();
+        for project in self.store.get_all_archived_projects()? {
+            let bag_root = self.bag_root(&project)?;
+            if !project.is_quarantined && bag_root.is_dir() {
+                bagged.push((project, bag_root));
+            }
+        }
+
+        let output_path = output.to_path_buf();
+        let (event_payload, work_count) = blocking(move || {
+            let mut works = Vec::new();
+            for (project, bag_root) in bagged {
+                let bag = BagItPackage::open(bag_root)?;
+                let files = catalog_payload(&bag, &project.id)?;
+                let technical = catalog_technical_metadata(&bag, &files)?;
+                works.push(VraWork::from_project(&project, &bag)?.with_technical_metadata(&technical));
+            }
+            fs::write(&output_path, build_vra_document(&works))?;
+            let payload = serde_json::json!({
+                "format": "vra-core-4.0",
+                "output_path": output_path.as_str(),
+                "work_count": works.len(),
+                "image_count": works.iter().map(|w| w.images.len()).sum::<usize>(),
+            });
+            Ok((payload, works.len()))
+        })
+        .await?;
+
+        self.store.insert_event("VraCoreExported", VAULT_AGGREGATE_ID, &event_payload.to_string())?;
+
+        Ok(ExportResult {
+            success: true,
+            output_path: Some(output.to_string()),
+            record_count: work_count,
+            error: None,
+        })
+    }
+
+    /// Record that one project is a later version or a branch of another
+    pub async fn link_projects(
+        &self,
+        parent_project_id: &str,
+        child_project_id: &str,
+        relation_type: &str,
+        label: Option<&str>,
+    ) -> Result<ProjectRelation> {
+        self.get_project(parent_project_id).await?;
+        self.get_project(child_project_id).await?;
+        let relations = self.store.get_all_project_relations()?;
+        validate_new_relation(&relations, parent_project_id, child_project_id, relation_type)
+            .map_err(|e| Error::InvalidState(e.to_string()))?;
+
+        let relation = ProjectRelation::new(
+            parent_project_id.to_string(),
+            child_project_id.to_string(),
+            relation_type.to_string(),
+            label.map(str::to_string),
+        );
+        self.store.insert_project_relation(&relation)?;
+        let event_payload = serde_json::json!({
+            "parent_project_id": relation.parent_project_id,
+            "child_project_id": relation.child_project_id,
+            "relation_type": relation.relation_type,
+            "label": relation.label,
+        });
+        self.store
+            .insert_event("ProjectVersionLinked", child_project_id, &event_payload.to_string())?;
+        Ok(relation)
+    }
+
+    /// Remove the lineage link between two projects
+    pub async fn unlink_projects(&self, parent_project_id: &str, child_project_id: &str) -> Result<()> {
+        if !self.store.delete_project_relation(parent_project_id, child_project_id)? {
+            return Err(Error::NotFound(format!(
+                "Link from {} to {}",
+                parent_project_id, child_project_id
+            )));
+        }
+        let event_payload = serde_json::json!({
+            "parent_project_id": parent_project_id,
+            "child_project_id": child_project_id,
+        });
+        self.store
+            .insert_event("ProjectVersionUnlinked", child_project_id, &event_payload.to_string())?;
+        Ok(())
+    }
+
+    /// The whole history tree a project belongs to, from the first project of its lineage
+    pub async fn project_history(&self, project_id: &str) -> Result<LineageNode> {
+        self.get_project(project_id).await?;
+        let relations = self.store.get_all_project_relations()?;
+        let names: HashMap<String, String> = self
+            .store
+            .get_all_archived_projects()?
+            .into_iter()
+            .map(|p| (p.id, p.name))
+            .collect();
+        let root_id = find_lineage_root(&relations, project_id);
+        Ok(build_history_tree(&relations, &names, &root_id))
+    }
+
+    /// Compare the payload of two project versions by checksum
+    pub async fn diff_versions(&self, from_project_id: &str, to_project_id: &str) -> Result<VersionDiff> {
+        let mut manifests = Vec::new();
+        for project_id in [from_project_id, to_project_id] {
+            let project = self.get_project(project_id).await?;
+            let bag = BagItPackage::open(self.bag_root(&project)?).map_err(|e| Error::NotFound(e.to_string()))?;
+            manifests.push(bag.read_manifest()?);
+        }
+        Ok(diff_manifests(&manifests[0], &manifests[1]))
+    }
+
+    /// Create a collection at the top level, or nested inside another one
+    pub async fn create_collection(
+        &self,
+        name: &str,
+        description: Option<&str>,
+        parent_collection_id: Option<&str>,
+    ) -> Result<Collection> {
+        let collections = self.store.get_all_collections()?;
+        if let Some(parent_id) = parent_collection_id {
+            if !collections.iter().any(|c| c.id == parent_id) {
+                return Err(Error::NotFound(format!("Collection {}", parent_id)));
+            }
+        }
+
+        let position = collections
+            .iter()
+            .filter(|c| c.parent_collection_id.as_deref() == parent_collection_id)
+            .map(|c| c.position + 1)
+            .max()
+            .unwrap_or(0);
+        let collection = Collection::new(
+            name.to_string(),
+            description.map(str::to_string),
+            parent_collection_id.map(str::to_string),
+            position,
+        );
+        self.store.insert_collection(&collection)?;
+
+        let event_payload = serde_json::json!({
+            "name": collection.name,
+            "parent_collection_id": collection.parent_collection_id,
+        });
+        self.store
+            .insert_event("CollectionCreated", &collection.id, &event_payload.to_string())?;
+        Ok(collection)
+    }
+
+    /// All collections as ordered trees with their member projects
+    pub async fn list_collections(&self) -> Result<Vec<CollectionTree>> {
+        let collections = self.store.get_all_collections()?;
+        let members = self.store.get_all_collection_members()?;
+        Ok(build_collection_trees(&collections, &members, None))
+    }
+
+    /// Add a project to a collection at a position, appending it when none is given
+    pub async fn add_project_to_collection(
+        &self,
+        collection_id: &str,
+        project_id: &str,
+        position: Option<usize>,
+    ) -> Result<()> {
+        self.get_project(project_id).await?;
+        self.place_project(collection_id, project_id, position)?;
+
+        let event_payload = serde_json::json!({ "collection_id": collection_id });
+        self.store
+            .insert_event("ProjectAddedToCollection", project_id, &event_payload.to_string())?;
+        self.sync_bag_group_identifiers(&[project_id.to_string()]).await
+    }
+
+    /// Remove a project from a collection
+    pub async fn remove_project_from_collection(&self, collection_id: &str, project_id: &str) -> Result<()> {
+        if !self.store.delete_collection_member(collection_id, project_id)? {
+            return Err(Error::NotFound(format!(
+                "Project {} in collection {}",
+                project_id, collection_id
+            )));
+        }
+
+        let event_payload = serde_json::json!({ "collection_id": collection_id });
+        self.store
+            .insert_event("ProjectRemovedFromCollection", project_id, &event_payload.to_string())?;
+        self.sync_bag_group_identifiers(&[project_id.to_string()]).await
+    }
+
+    /// Move a project from one collection to another, or to a new position in the same one
+    pub async fn move_project_between_collections(
+        &self,
+        project_id: &str,
+        from_collection_id: &str,
+        to_collection_id: &str,
+        position: Option<usize>,
+    ) -> Result<()> {
+        self.get_project(project_id).await?;
+        if !self.store.get_all_collections()?.iter().any(|c| c.id == to_collection_id) {
+            return Err(Error::NotFound(format!("Collection {}", to_collection_id)));
+        }
+        if from_collection_id != to_collection_id
+            && !self.store.delete_collection_member(from_collection_id, project_id)?
+        {
+            return Err(Error::NotFound(format!(
+                "Project {} in collection {}",
+                project_id, from_collection_id
+            )));
+        }
+        self.place_project(to_collection_id, project_id, position)?;
+
+        let event_payload = serde_json::json!({
+            "from_collection_id": from_collection_id,
+            "to_collection_id": to_collection_id,
+        });
+        self.store
+            .insert_event("ProjectMovedBetweenCollections", project_id, &event_payload.to_string())?;
+        self.sync_bag_group_identifiers(&[project_id.to_string()]).await
+    }
+
+    /// Move a collection under a new parent (None for the top level) and/or reorder it
+    pub async fn move_collection(
+        &self,
+        collection_id: &str,
+        new_parent_id: Option<&str>,
+        position: Option<usize>,
+    ) -> Result<()> {
+        let collections = self.store.get_all_collections()?;
+        if !collections.iter().any(|c| c.id == collection_id) {
+            return Err(Error::NotFound(format!("Collection {}", collection_id)));
+        }
+        validate_collection_move(&collections, collection_id, new_parent_id)
+            .map_err(|e| Error::InvalidState(e.to_string()))?;
+
+        let siblings: Vec<String> = collections
+            .iter()
+            .filter(|c| c.parent_collection_id.as_deref() == new_parent_id)
+            .map(|c| c.id.clone())
+            .collect();
+        for (index, id) in reorder(&siblings, collection_id, position).iter().enumerate() {
+            self.store.update_collection_placement(id, new_parent_id, index as i32)?;
+        }
+
+        let event_payload = serde_json::json!({ "new_parent_id": new_parent_id, "position": position });
+        self.store
+            .insert_event("CollectionMoved", collection_id, &event_payload.to_string())?;
+
+        // Every bag below the moved collection carries its old path in Bag-Group-Identifier
+        let moved = subtree_ids(&collections, collection_id);
+        let project_ids: Vec<String> = self
+            .store
+            .get_all_collection_members()?
+            .into_iter()
+            .filter(|m| moved.contains(&m.collection_id))
+            .map(|m| m.archived_project_id)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        self.sync_bag_group_identifiers(&project_ids).await
+    }
+
+    /// Write every bag in a collection and its sub-collections as serialized
+    /// archives into `output_dir`, nested the way the collections are
+    ///
+    /// The hierarchy and ordering are kept alongside the archives in
+    /// collection.json. Projects without a bag, or in quarantine, are skipped.
+    pub async fn export_collection(
+        &self,
+        collection_id: &str,
+        format: SerializationFormat,
+        output_dir: &Utf8Path,
+    ) -> Result<ExportResult> {
+        let collections = self.store.get_all_collections()?;
+        let members = self.store.get_all_collection_members()?;
+        let collection = collections
+            .iter()
+            .find(|c| c.id == collection_id)
+            .ok_or_else(|| Error::NotFound(format!("Collection {}", collection_id)))?;
+        let tree = build_collection_trees(&collections, &members, collection.parent_collection_id.as_deref())
+            .into_iter()
+            .find(|t| t.collection.id == collection_id)
+            .ok_or_else(|| Error::NotFound(format!("Collection {}", collection_id)))?;
+
+        let output_root = output_dir.join(sanitize_directory_name(&collection.name));
+        let mut bags = Vec::new();
+        let mut pending = vec![(&tree, output_root.clone())];
+        while let Some((node, dir)) = pending.pop() {
+            for project_id in &node.project_ids {
+                let project = self.get_project(project_id).await?;
+                let bag_root = self.bag_root(&project)?;
+                if !project.is_quarantined && bag_root.join("bagit.txt").exists() {
+                    bags.push((bag_root, dir.clone()));
+                }
+            }
+            for child in &node.children {
+                pending.push((child, dir.join(sanitize_directory_name(&child.collection.name))));
+            }
+        }
+
+        let structure = serde_json::to_string_pretty(&tree).context("Failed to serialize collection")?;
+        let (structure_path, output_path) = (output_root.join("collection.json"), output_root.clone());
+        let exported = blocking(move || {
+            fs::create_dir_all(&output_root)?;
+            for (bag_root, dir) in &bags {
+                fs::create_dir_all(dir)?;
+                serialize_bag(&BagItPackage::open(bag_root.clone())?, format, dir)?;
+            }
+            fs::write(&structure_path, structure)?;
+            Ok(bags.len())
+        })
+        .await?;
+
+        let event_payload = serde_json::json!({
+            "format": format.media_type(),
+            "output_path": output_path.as_str(),
+            "bag_count": exported,
+        });
+        self.store
+            .insert_event("CollectionExported", collection_id, &event_payload.to_string())?;
+
+        Ok(ExportResult {
+            success: true,
+            output_path: Some(output_path.to_string()),
+            record_count: exported,
+            error: None,
+        })
+    }
+
+    /// Put a project at a position in a collection, renumbering the other members
+    fn place_project(&self, collection_id: &str, project_id: &str, position: Option<usize>) -> Result<()> {
+        if !self.store.get_all_collections()?.iter().any(|c| c.id == collection_id) {
+            return Err(Error::NotFound(format!("Collection {}", collection_id)));
+        }
+
+        let mut current: Vec<CollectionMember> = self
+            .store
+            .get_all_collection_members()?
+            .into_iter()
+            .filter(|m| m.collection_id == collection_id)
+            .collect();
+        current.sort_by_key(|m| (m.position, m.added_at));
+        let ordered_ids: Vec<String> = current.iter().map(|m| m.archived_project_id.clone()).collect();
+
+        for (index, id) in reorder(&ordered_ids, project_id, position).iter().enumerate() {
+            let added_at = current
+                .iter()
+                .find(|m| &m.archived_project_id == id)
+                .map(|m| m.added_at)
+                .unwrap_or_else(Utc::now);
+            self.store.upsert_collection_member(&CollectionMember {
+                collection_id: collection_id.to_string(),
+                archived_project_id: id.clone(),
+                position: index as i32,
+                added_at,
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Rewrite Bag-Group-Identifier in each project's bag-info.txt to match its collections
+    ///
+    /// Projects that have not been bagged yet pick theirs up in `create_bag`.
+    async fn sync_bag_group_identifiers(&self, project_ids: &[String]) -> Result<()> {
+        let collections = self.store.get_all_collections()?;
+        let members = self.store.get_all_collection_members()?;
+
+        for project_id in project_ids {
+            let project = self.get_project(project_id).await?;
+            let bag_root = self.bag_root(&project)?;
+            if !bag_root.join("bagit.txt").exists() {
+                continue;
+            }
+
+            let bag = BagItPackage::open(bag_root)?;
+            let mut bag_info = bag.read_bag_info()?;
+            let identifiers = bag_group_identifiers(&collections, &members, project_id);
+            if !apply_bag_group_identifiers(&mut bag_info, &identifiers) {
+                continue;
+            }
+            bag.create_bag_info(&bag_info)?;
+            bag.refresh_tag_manifests().await?;
+
+            self.index_project(project_id).await?;
+            if !project.is_quarantined {
+                self.replicate_changed_bag(project_id).await;
+            }
+        }
+        Ok(())
+    }
+
+    /// Create a vocabulary such as "medium" or "technique", optionally
+    /// restricted to the given terms
+    pub async fn create_vocabulary(
+        &self,
+        name: &str,
+        description: Option<&str>,
+        is_controlled: bool,
+        terms: &[String],
+    ) -> Result<Vocabulary> {
+        let name = normalize_tag_name(name).map_err(|e| Error::InvalidState(e.to_string()))?;
+        if self
+            .store
+            .get_all_vocabularies()?
+            .iter()
+            .any(|v| v.name.eq_ignore_ascii_case(&name))
+        {
+            return Err(Error::InvalidState(format!("Vocabulary already exists: {}", name)));
+        }
+
+        let vocabulary = Vocabulary::new(name, description.map(str::to_string), is_controlled);
+        // Resolve every term before storing anything, so a bad one leaves no half-made vocabulary
+        let open_vocabulary = Vocabulary {
+            is_controlled: false,
+            ..vocabulary.clone()
+        };
+        let mut new_terms: Vec<Tag> = Vec::new();
+        for term in terms {
+            let (tag, is_new) =
+                resolve_tag(&new_terms, Some(&open_vocabulary), term).map_err(|e| Error::InvalidState(e.to_string()))?;
+            if is_new {
+                new_terms.push(tag);
+            }
+        }
+
+        self.store.insert_vocabulary(&vocabulary)?;
+        for tag in &new_terms {
+            self.store.insert_tag(tag)?;
+        }
+        let event_payload = serde_json::json!({
+            "name": vocabulary.name,
+            "is_controlled": vocabulary.is_controlled,
+            "term_count": new_terms.len(),
+        });
+        self.store
+            .insert_event("VocabularyCreated", &vocabulary.id, &event_payload.to_string())?;
+        Ok(vocabulary)
+    }
+
+    /// All vocabularies by name
+    pub async fn list_vocabularies(&self) -> Result<Vec<Vocabulary>> {
+        Ok(self.store.get_all_vocabularies()?)
+    }
+
+    /// Tags with their usage counts, most used first, optionally limited to one vocabulary
+    pub async fn list_tags(&self, vocabulary_id: Option<&str>) -> Result<Vec<Tag>> {
+        let tags = self.store.get_all_tags()?;
+        Ok(match vocabulary_id {
+            Some(vocabulary_id) => tags
+                .into_iter()
+                .filter(|t| t.vocabulary_id.as_deref() == Some(vocabulary_id))
+                .collect(),
+            None => tags,
+        })
+    }
+
+    /// The tags on a project and its payload files
+    pub async fn project_tags(&self, project_id: &str) -> Result<Vec<TagAssignment>> {
+        self.get_project(project_id).await?;
+        Ok(self
+            .store
+            .get_all_tag_assignments()?
+            .into_iter()
+            .filter(|a| a.archived_project_id == project_id)
+            .collect())
+    }
+
+    /// Tag a project, or one of its payload files when `file_path` is given
+    ///
+    /// The tag is created when it does not exist yet, unless the vocabulary is
+    /// controlled.
+    pub async fn tag_project(
+        &self,
+        project_id: &str,
+        tag_name: &str,
+        vocabulary_id: Option<&str>,
+        file_path: Option<&str>,
+    ) -> Result<Tag> {
+        self.get_project(project_id).await?;
+        if let Some(file_path) = file_path {
+            let bag = self.project_bag(project_id).await?;
+            if !bag.read_manifest()?.iter().any(|(_, path)| path == file_path) {
+                return Err(Error::NotFound(format!("{} in project {}", file_path, project_id)));
+            }
+        }
+
+        let vocabulary = match vocabulary_id {
+            Some(vocabulary_id) => Some(
+                self.store
+                    .get_all_vocabularies()?
+                    .into_iter()
+                    .find(|v| v.id == vocabulary_id)
+                    .ok_or_else(|| Error::NotFound(format!("Vocabulary {}", vocabulary_id)))?,
+            ),
+            None => None,
+        };
+        let (tag, is_new) = resolve_tag(&self.store.get_all_tags()?, vocabulary.as_ref(), tag_name)
+            .map_err(|e| Error::InvalidState(e.to_string()))?;
+        if is_new {
+            self.store.insert_tag(&tag)?;
+        }
+
+        self.store.insert_tag_assignment(&TagAssignment {
+            tag_id: tag.id.clone(),
+            archived_project_id: project_id.to_string(),
+            file_path: file_path.map(str::to_string),
+            created_at: Utc::now(),
+        })?;
+        let event_payload = serde_json::json!({
+            "tag_id": tag.id,
+            "tag": tag.name,
+            "vocabulary_id": tag.vocabulary_id,
+            "file_path": file_path,
+        });
+        self.store.insert_event("ProjectTagged", project_id, &event_payload.to_string())?;
+        self.index_project(project_id).await?;
+
+        // Pick up the count maintained by the database triggers
+        Ok(self
+            .store
+            .get_all_tags()?
+            .into_iter()
+            .find(|t| t.id == tag.id)
+            .unwrap_or(tag))
+    }
+
+    /// Remove a tag from a project, or from one of its payload files
+    pub async fn untag_project(&self, project_id: &str, tag_id: &str, file_path: Option<&str>) -> Result<()> {
+        if !self.store.delete_tag_assignment(tag_id, project_id, file_path)? {
+            return Err(Error::NotFound(format!("Tag {} on project {}", tag_id, project_id)));
+        }
+        let event_payload = serde_json::json!({ "tag_id": tag_id, "file_path": file_path });
+        self.store.insert_event("ProjectUntagged", project_id, &event_payload.to_string())?;
+        self.index_project(project_id).await
+    }
+
+    /// Create a reusable environment profile, e.g. "Studio Mac" with its
+    /// software, plugins and fonts
+    pub async fn create_environment_profile(
+        &self,
+        name: &str,
+        description: Option<&str>,
+        os_name: Option<&str>,
+        os_version: Option<&str>,
+        components: Vec<EnvironmentComponent>,
+    ) -> Result<EnvironmentProfile> {
+        let mut profile = EnvironmentProfile::new(
+            name.to_string(),
+            description.map(str::to_string),
+            os_name.map(str::to_string),
+            os_version.map(str::to_string),
+            components,
+        );
+        normalize_profile(&mut profile).map_err(|e| Error::InvalidState(e.to_string()))?;
+        if self
+            .store
+            .get_all_environment_profiles()?
+            .iter()
+            .any(|p| p.name.eq_ignore_ascii_case(&profile.name))
+        {
+            return Err(Error::InvalidState(format!(
+                "Environment profile already exists: {}",
+                profile.name
+            )));
+        }
+
+        self.store.insert_environment_profile(&profile)?;
+        let event_payload = serde_json::json!({
+            "name": profile.name,
+            "component_count": profile.components.len(),
+        });
+        self.store
+            .insert_event("EnvironmentProfileCreated", &profile.id, &event_payload.to_string())?;
+        Ok(profile)
+    }
+
+    /// All environment profiles by name
+    pub async fn list_environment_profiles(&self) -> Result<Vec<EnvironmentProfile>> {
+        Ok(self.store.get_all_environment_profiles()?)
+    }
+
+    /// The environment profiles attached to a project, in the order they were attached
+    pub async fn project_environment(&self, project_id: &str) -> Result<Vec<EnvironmentProfile>> {
+        self.get_project(project_id).await?;
+        self.attached_profiles(project_id)
+    }
+
+    /// Attach an environment profile to a project and record it in the project's bag
+    pub async fn attach_environment_profile(
+        &self,
+        project_id: &str,
+        profile_id: &str,
+    ) -> Result<Vec<EnvironmentProfile>> {
+        self.get_project(project_id).await?;
+        self.check_profiles_exist(&[profile_id.to_string()])?;
+        self.store.insert_project_environment(&ProjectEnvironment {
+            archived_project_id: project_id.to_string(),
+            profile_id: profile_id.to_string(),
+            attached_at: Utc::now(),
+        })?;
+        let event_payload = serde_json::json!({ "profile_id": profile_id });
+        self.store
+            .insert_event("EnvironmentProfileAttached", project_id, &event_payload.to_string())?;
+        self.sync_environment_tag_file(project_id).await?;
+        self.attached_profiles(project_id)
+    }
+
+    /// Detach an environment profile from a project and update the project's bag
+    pub async fn detach_environment_profile(
+        &self,
+        project_id: &str,
+        profile_id: &str,
+    ) -> Result<Vec<EnvironmentProfile>> {
+        if !self.store.delete_project_environment(project_id, profile_id)? {
+            return Err(Error::NotFound(format!(
+                "Environment profile {} on project {}",
+                profile_id, project_id
+            )));
+        }
+        let event_payload = serde_json::json!({ "profile_id": profile_id });
+        self.store
+            .insert_event("EnvironmentProfileDetached", project_id, &event_payload.to_string())?;
+        self.sync_environment_tag_file(project_id).await?;
+        self.attached_profiles(project_id)
+    }
+
+    /// Fail with the first profile id that does not exist
+    fn check_profiles_exist(&self, profile_ids: &[String]) -> Result<()> {
+        let profiles = self.store.get_all_environment_profiles()?;
+        match profile_ids.iter().find(|id| !profiles.iter().any(|p| &p.id == *id)) {
+            Some(unknown) => Err(Error::NotFound(format!("Environment profile {}", unknown))),
+            None => Ok(()),
+        }
+    }
+
+    fn attached_profiles(&self, project_id: &str) -> Result<Vec<EnvironmentProfile>> {
+        let profiles = self.store.get_all_environment_profiles()?;
+        Ok(self
+            .store
+            .get_all_project_environments()?
+            .iter()
+            .filter(|a| a.archived_project_id == project_id)
+            .filter_map(|a| profiles.iter().find(|p| p.id == a.profile_id).cloned())
+            .collect())
+    }
+
+    /// Rewrite the environment tag file of a project's bag
+    ///
+    /// Projects that have not been bagged yet pick theirs up in `create_bag`.
+    async fn sync_environment_tag_file(&self, project_id: &str) -> Result<()> {
+        let project = self.get_project(project_id).await?;
+        let bag_root = self.bag_root(&project)?;
+        if !bag_root.join("bagit.txt").exists() {
+            return Ok(());
+        }
+
+        let bag = BagItPackage::open(bag_root)?;
+        write_environment_tag_file(&bag, &self.attached_profiles(project_id)?)?;
+        bag.refresh_tag_manifests().await?;
+        if !project.is_quarantined {
+            self.replicate_changed_bag(project_id).await;
+        }
+        Ok(())
+    }
+
+    /// Search project names, descriptions, metadata, tags, filenames and extracted text
+    ///
+    /// Quarantined projects are left out of the results.
+    pub async fn search(&self, query: &str, limit: Option<u32>) -> Result<Vec<SearchHit>> {
+        let Some(match_query) = build_match_query(query) else {
+            return Ok(Vec::new());
+        };
+        Ok(self
+            .store
+            .search_index(&match_query, limit.unwrap_or(DEFAULT_SEARCH_LIMIT) as i64)?)
+    }
+
+    /// Re-index every archived project, e.g. after restoring a database backup
+    pub async fn rebuild_search_index(&self) -> Result<usize> {
+        let projects = self.store.get_all_archived_projects()?;
+        for project in &projects {
+            self.index_project(&project.id).await?;
+        }
+        Ok(projects.len())
+    }
+
+    /// Compare a project's working folder with the state it was archived in
+    pub async fn detect_source_changes(&self, project_id: &str) -> Result<SourceChanges> {
+        let project = self.get_project(project_id).await?;
+        let scan = self.scan_sources(&project).await?;
+        Ok(source_changes(project_id, &scan.source_root, &scan.delta))
+    }
+
+    /// Archive what changed in a project's working folder as a new version
+    ///
+    /// The new version's bag holds only added and changed files. Unchanged
+    /// files are listed in fetch.txt with file:// URLs into the previous
+    /// version's bag, and its manifest still covers every file, so the bag
+    /// can be completed into a full copy with `complete_fetch`.
+    pub async fn create_delta_version(&self, project_id: &str) -> Result<DeltaVersionResult> {
+        let parent = self.get_project(project_id).await?;
+        if parent.is_quarantined {
+            return Err(Error::InvalidState(format!("Project {} is quarantined", project_id)));
+        }
+        let parent_bag_root = self.bag_root(&parent)?;
+        if !parent_bag_root.join("bagit.txt").exists() {
+            return Err(Error::InvalidState(format!("Project {} has no bag to build on", project_id)));
+        }
+
+        let SourceScan {
+            source_root,
+            sources,
+            current,
+            baseline,
+            delta,
+        } = self.scan_sources(&parent).await?;
+        if delta.is_empty() {
+            return Err(Error::InvalidState(format!(
+                "Nothing changed in {} since project {} was archived",
+                source_root, project_id
+            )));
+        }
+
+        let project = ArchivedProject::new(
+            parent.name.clone(),
+            parent.description.clone(),
+            current.len() as i32,
+            current.iter().map(|f| f.size as i64).sum(),
+        );
+        let relations = self.store.get_all_project_relations()?;
+        validate_new_relation(&relations, project_id, &project.id, "version")?;
+        let relation = ProjectRelation::new(project_id.to_string(), project.id.clone(), "version".to_string(), None);
+
+        // Nothing is recorded until the bag is built and valid, so a failure leaves no half-made version behind
+        let bag_root = self.bag_root(&project)?;
+        let copied: Vec<String> = delta.added.iter().chain(&delta.changed).cloned().collect();
+        let (unchanged, copy_root, recorded_states) = (delta.unchanged.clone(), source_root.clone(), baseline.clone());
+        let payload_root = bag_root.clone();
+        let built: Result<(BagItPackage, Vec<String>)> = async {
+            let bag = blocking(move || {
+                let parent_bag = BagItPackage::open(parent_bag_root)?;
+                let parent_fetch = parent_bag.read_fetch()?;
+                let recorded: HashMap<&str, &SourceFileState> =
+                    recorded_states.iter().map(|s| (s.path.as_str(), s)).collect();
+
+                let bag = BagItPackage::new(payload_root)?;
+                bag.create_bagit_declaration()?;
+                for path in &copied {
+                    let destination = bag.data_dir.join(path);
+                    if let Some(parent) = destination.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    fs::copy(copy_root.join(path), &destination)?;
+                }
+
+                // Point at the copy the previous version holds, or wherever it fetches its own from
+                let mut fetch = Vec::new();
+                let mut manifest = String::new();
+                for path in &unchanged {
+                    let payload_path = format!("data/{}", path);
+                    let state = recorded
+                        .get(path.as_str())
+                        .ok_or_else(|| anyhow::anyhow!("No recorded state for {}", path))?;
+                    let parent_file = parent_bag.bag_root.join(&payload_path);
+                    let url = if parent_file.is_file() {
+                        file_url(&parent_file)
+                    } else {
+                        parent_fetch
+                            .iter()
+                            .find(|e| e.path == payload_path)
+                            .map(|e| e.url.clone())
+                            .ok_or_else(|| anyhow::anyhow!("Previous version has no copy of {}", path))?
+                    };
+                    fetch.push(FetchEntry {
+                        url,
+                        length: Some(state.size),
+                        path: payload_path.clone(),
+                    });
+                    manifest.push_str(&format!("{}  {}\n", state.sha256, payload_path));
+                }
+                bag.write_fetch(&fetch)?;
+                fs::write(bag.manifest_path_for(ChecksumAlgorithm::Sha256), manifest)?;
+                Ok(bag)
+            })
+            .await?;
+            // Hashes the copied files and keeps the lines written above for the referenced ones
+            bag.create_manifest_with(ChecksumAlgorithm::Sha256).await?;
+
+            let mut bag_info = project_bag_info(&project, &bag)?;
+            bag_info.add(PREVIOUS_VERSION_LABEL, project_id);
+            bag.create_bag_info(&bag_info)?;
+            let issues = bag.validate().await?;
+            Ok((bag, issues))
+        }
+        .await;
+        let (bag, issues) = match built {
+            Ok(built) => built,
+            Err(e) => {
+                let _ = fs::remove_dir_all(&bag_root);
+                return Err(e);
+            }
+        };
+        let result = bag_result(&bag_root, issues.clone(), "Delta version created and validated successfully");
+        if !result.success {
+            let _ = fs::remove_dir_all(&bag_root);
+            let errors: Vec<String> = result
+                .validation_results
+                .into_iter()
+                .flatten()
+                .filter(|r| r.result_type == "error")
+                .map(|r| r.message)
+                .collect();
+            return Err(Error::InvalidState(format!(
+                "Delta version of project {} is not a valid bag: {}",
+                project_id,
+                errors.join("; ")
+            )));
+        }
+
+        let event_payload = serde_json::json!({
+            "project_name": project.name,
+            "file_count": project.file_count,
+            "total_size": project.total_size,
+            "files": sources,
+            "delta_of": project_id,
+        });
+        let recorded = self.store.record_archived_project(
+            &project,
+            &event_payload.to_string(),
+            source_root.as_str(),
+            &[],
+            Some(&relation),
+        );
+        if let Err(e) = recorded {
+            let _ = fs::remove_dir_all(&bag_root);
+            return Err(e.into());
+        }
+
+        let changes = source_changes(&project.id, &source_root, &delta);
+        let event_payload = serde_json::json!({
+            "project_id": project.id,
+            "bag_path": bag.bag_root.as_str(),
+            "delta_of": project_id,
+            "added": delta.added,
+            "changed": delta.changed,
+            "removed": delta.removed,
+            "referenced": delta.unchanged.len(),
+            "validation_issues": issues.len(),
+        });
+        self.store
+            .insert_event("BagitPackageCreated", &project.id, &event_payload.to_string())?;
+
+        self.record_source_states(&project.id, &bag, &current, &baseline).await?;
+        self.refresh_catalog(&project.id, &bag.bag_root).await?;
+        self.index_project(&project.id).await?;
+        self.replicate_changed_bag(&project.id).await;
+
+        Ok(DeltaVersionResult {
+            project_id: project.id,
+            parent_project_id: project_id.to_string(),
+            changes,
+            bag: result,
+        })
+    }
+
+    /// Where a project was archived from, falling back to its recorded source paths
+    fn source_root(&self, project_id: &str) -> Result<(Utf8PathBuf, Vec<String>)> {
+        let sources = self.store.archived_source_paths(project_id)?;
+        let source_root = match self.store.get_project_source(project_id)? {
+            Some(source_root) => source_root,
+            None if !sources.is_empty() => find_common_root(&sources)?,
+            None => {
+                return Err(Error::InvalidState(format!(
+                    "Project {} has no recorded source folder",
+                    project_id
+                )))
+            }
+        };
+        Ok((source_root, sources))
+    }
+
+    /// Scan a project's sources and compare them with their archived state
+    async fn scan_sources(&self, project: &ArchivedProject) -> Result<SourceScan> {
+        let (source_root, sources) = self.source_root(&project.id)?;
+        if !source_root.is_dir() {
+            return Err(Error::NotFound(source_root.to_string()));
+        }
+        let baseline = self.baseline_states(project).await?;
+
+        let (scan_root, scan_sources) = (source_root.clone(), sources.clone());
+        let current = blocking(move || list_source_files(&scan_sources, &scan_root)).await?;
+        let delta = compare_sources(&current, &baseline).await?;
+        Ok(SourceScan {
+            source_root,
+            sources,
+            current,
+            baseline,
+            delta,
+        })
+    }
+
+    /// The recorded source state of a project, or one derived from its bag
+    ///
+    /// Projects bagged before source states were recorded get sizes and
+    /// checksums from the bag with an unknown modification time, so every
+    /// same-sized file is compared by checksum.
+    async fn baseline_states(&self, project: &ArchivedProject) -> Result<Vec<SourceFileState>> {
+        let states = self.store.get_source_file_states(&project.id)?;
+        if !states.is_empty() {
+            return Ok(states);
+        }
+
+        let bag_root = self.bag_root(project)?;
+        if !bag_root.join("bagit.txt").exists() {
+            return Err(Error::InvalidState(format!("Project {} has not been bagged yet", project.id)));
+        }
+        let bag = BagItPackage::open(bag_root)?;
+        let checksums = payload_sha256(&bag).await?;
+        let fetch = bag.read_fetch()?;
+
+        let mut states = Vec::new();
+        for (payload_path, sha256) in checksums {
+            let file_path = bag.bag_root.join(&payload_path);
+            let size = if file_path.is_file() {
+                fs::metadata(&file_path)?.len()
+            } else {
+                fetch
+                    .iter()
+                    .find(|e| e.path == payload_path)
+                    .and_then(|e| e.length)
+                    .unwrap_or(0)
+            };
+            states.push(SourceFileState {
+                path: payload_path.strip_prefix("data/").unwrap_or(&payload_path).to_string(),
+                size,
+                modified_at: 0,
+                sha256,
+            });
+        }
+        Ok(states)
+    }
+
+    /// Record the source files a bag was just written from
+    ///
+    /// Checksums come from the bag's manifest where possible; `known` supplies
+    /// them for files the bag only references.
+    async fn record_source_states(
+        &self,
+        project_id: &str,
+        bag: &BagItPackage,
+        files: &[SourceFile],
+        known: &[SourceFileState],
+    ) -> Result<()> {
+        let mut checksums = payload_sha256(bag).await?;
+        for state in known {
+            checksums
+                .entry(format!("data/{}", state.path))
+                .or_insert_with(|| state.sha256.clone());
+        }
+
+        let states = files
+            .iter()
+            .filter_map(|file| {
+                checksums.get(&format!("data/{}", file.path)).map(|sha256| SourceFileState {
+                    path: file.path.clone(),
+                    size: file.size,
+                    modified_at: file.modified_at,
+                    sha256: sha256.clone(),
+                })
+            })
+            .collect::<Vec<_>>();
+        self.store.replace_source_file_states(project_id, &states)?;
+        Ok(())
+    }
+
+    /// Add a folder, such as an external drive or NAS mount, that every bag is copied to
+    pub async fn add_replication_target(&self, name: &str, path: &Utf8Path) -> Result<ReplicationTarget> {
+        let path = path
+            .canonicalize_utf8()
+            .map_err(|_| Error::NotFound(path.to_string()))?;
+        if !path.is_dir() {
+            return Err(Error::InvalidState(format!("{} is not a folder", path)));
+        }
+        if let Ok(bags_root) = self.config.bags_root.canonicalize_utf8() {
+            if path.starts_with(&bags_root) || bags_root.starts_with(&path) {
+                return Err(Error::InvalidState(format!("{} overlaps the bags folder", path)));
+            }
+        }
+        if self.store.get_replication_targets()?.iter().any(|t| t.path == path.as_str()) {
+            return Err(Error::InvalidState(format!("{} is already a replication target", path)));
+        }
+
+        let target = ReplicationTarget::new(name.to_string(), path.to_string());
+        self.store.insert_replication_target(&target)?;
+        Ok(target)
+    }
+
+    /// Add an S3-compatible bucket, such as institutional object storage, that every bag is copied to
+    ///
+    /// The secret access key is stored encrypted, so encryption has to be unlocked.
+    #[cfg(feature = "s3")]
+    pub async fn add_s3_replication_target(&self, name: &str, config: S3Config) -> Result<ReplicationTarget> {
+        let sealed = S3Config {
+            secret_access_key: self.seal_secret(&config.secret_access_key)?,
+            ..config.clone()
+        };
+        let storage = S3Storage::new(config)?;
+        let settings = serde_json::to_string(&sealed).context("Failed to store S3 settings")?;
+        self.add_remote_replication_target(name, "s3", &storage, settings).await
+    }
+
+    /// Add a WebDAV share, such as a school's Nextcloud folder, that every bag is copied to
+    ///
+    /// The password is stored encrypted, so encryption has to be unlocked.
+    #[cfg(feature = "webdav")]
+    pub async fn add_webdav_replication_target(&self, name: &str, config: WebDavConfig) -> Result<ReplicationTarget> {
+        let sealed = WebDavConfig {
+            password: self.seal_secret(&config.password)?,
+            ..config.clone()
+        };
+        let storage = WebDavStorage::new(config)?;
+        let settings = serde_json::to_string(&sealed).context("Failed to store WebDAV settings")?;
+        self.add_remote_replication_target(name, "webdav", &storage, settings).await
+    }
+
+    /// All replication targets, oldest first
+    pub async fn list_replication_targets(&self) -> Result<Vec<ReplicationTarget>> {
+        Ok(self.store.get_replication_targets()?)
+    }
+
+    /// Stop replicating to a target; copies already there are left in place
+    pub async fn remove_replication_target(&self, target_id: &str) -> Result<()> {
+        if !self.store.delete_replication_target(target_id)? {
+            return Err(Error::NotFound(format!("Replication target {}", target_id)));
+        }
+        Ok(())
+    }
+
+    /// Replica statuses of one project, or of every project
+    pub async fn replica_statuses(&self, project_id: Option<&str>) -> Result<Vec<ReplicaStatus>> {
+        Ok(self.store.get_replicas(project_id)?)
+    }
+
+    /// Copy a project's bag to every enabled replication target
+    ///
+    /// A target that is not mounted, or that fails mid-copy, is recorded as
+    /// unavailable rather than failing the whole operation.
+    pub async fn replicate_project(&self, project_id: &str) -> Result<Vec<ReplicaStatus>> {
+        let project = self.get_project(project_id).await?;
+        if project.is_quarantined {
+            return Err(Error::InvalidState(format!("Project {} is quarantined", project_id)));
+        }
+        let bag_root = self.bag_root(&project)?;
+        if !bag_root.join("bagit.txt").exists() {
+            return Err(Error::InvalidState(format!("Project {} has not been bagged yet", project_id)));
+        }
+
+        let mut statuses = Vec::new();
+        for target in self.store.get_replication_targets()?.into_iter().filter(|t| t.enabled) {
+            let status = match self.copy_to_target(project_id, &bag_root, &target).await {
+                Ok(status) => status,
+                Err(e) => self.record_replica_failure(project_id, &target, &format!("{:#}", e))?,
+            };
+            statuses.push(status);
+        }
+
+        if !statuses.is_empty() {
+            let event_payload = serde_json::json!({
+                "bag_path": bag_root.as_str(),
+                "replicas": statuses.iter().map(|r| serde_json::json!({
+                    "target_id": r.target_id,
+                    "replica_path": r.replica_path,
+                    "status": r.status,
+                })).collect::<Vec<_>>(),
+            });
+            self.store.insert_event("BagReplicated", project_id, &event_payload.to_string())?;
+        }
+        Ok(statuses)
+    }
+
+    /// Replicate a project whose bag was just written or changed
+    ///
+    /// The change is already in the vault by then, so a failed copy shows up
+    /// in the project's replica statuses instead of failing the change.
+    async fn replicate_changed_bag(&self, project_id: &str) {
+        if let Err(e) = self.replicate_project(project_id).await {
+            let error = format!("{:#}", e);
+            let targets = self.store.get_replication_targets().unwrap_or_default();
+            for target in targets.iter().filter(|t| t.enabled) {
+                let _ = self.record_replica_failure(project_id, target, &error);
+            }
+        }
+    }
+
+    /// Mark a project's replica on one target as unavailable, keeping its earlier copy and check times
+    fn record_replica_failure(
+        &self,
+        project_id: &str,
+        target: &ReplicationTarget,
+        error: &str,
+    ) -> Result<ReplicaStatus> {
+        let previous = self
+            .store
+            .get_replicas(Some(project_id))?
+            .into_iter()
+            .find(|r| r.target_id == target.id);
+        let status = ReplicaStatus {
+            archived_project_id: project_id.to_string(),
+            target_id: target.id.clone(),
+            replica_path: previous.as_ref().map_or_else(|| target.path.clone(), |r| r.replica_path.clone()),
+            status: "unavailable".to_string(),
+            last_copied_at: previous.as_ref().and_then(|r| r.last_copied_at),
+            last_verified_at: previous.as_ref().and_then(|r| r.last_verified_at),
+            last_error: Some(error.to_string()),
+        };
+        self.store.upsert_replica(&status)?;
+        Ok(status)
+    }
+
+    /// Make sure every bagged project has a copy on every enabled target
+    ///
+    /// Only missing copies, targets that were unavailable and replicas still
+    /// encrypted under an older key are copied; existing replicas are left to
+    /// verification.
+    pub async fn replicate_all(&self) -> Result<Vec<ReplicaStatus>> {
+        let targets: Vec<ReplicationTarget> =
+            self.store.get_replication_targets()?.into_iter().filter(|t| t.enabled).collect();
+        let replicas = self.store.get_replicas(None)?;
+        let mut statuses = Vec::new();
+
+        for project in self.store.get_all_archived_projects()?.into_iter().filter(|p| !p.is_quarantined) {
+            let bag_root = self.bag_root(&project)?;
+            if !bag_root.join("bagit.txt").exists() {
+                continue;
+            }
+            for target in &targets {
+                let existing = replicas
+                    .iter()
+                    .find(|r| r.archived_project_id == project.id && r.target_id == target.id);
+                let settled = existing.is_some_and(|r| r.status != "unavailable" && r.status != "pending");
+                if settled && !self.has_stale_key(&project.id, target)? {
+                    continue;
+                }
+                let status = match self.copy_to_target(&project.id, &bag_root, target).await {
+                    Ok(status) => status,
+                    Err(e) => self.record_replica_failure(&project.id, target, &format!("{:#}", e))?,
+                };
+                statuses.push(status);
+            }
+        }
+        Ok(statuses)
+    }
+
+    /// Check replicas against their manifests
+    ///
+    /// With `due_only`, only replicas not verified within the configured
+    /// interval are checked, so this can run on a timer.
+    pub async fn verify_replicas(&self, due_only: bool) -> Result<Vec<ReplicaStatus>> {
+        let due_before = Utc::now() - Duration::hours(self.replica_verify_interval_hours().await?);
+        let targets = self.store.get_replication_targets()?;
+        let mut statuses = Vec::new();
+
+        for replica in self.store.get_replicas(None)? {
+            if due_only && replica.last_verified_at.is_some_and(|at| at > due_before) {
+                continue;
+            }
+            let Some(target) = targets.iter().find(|t| t.id == replica.target_id && t.enabled) else {
+                continue;
+            };
+            let status = self.check_replica(replica, target).await?;
+            self.store.upsert_replica(&status)?;
+            statuses.push(status);
+        }
+        Ok(statuses)
+    }
+
+    /// Repair damaged copies of a project's bag from a healthy one
+    ///
+    /// The vault's own copy is the source for the replicas, so if it is the
+    /// damaged one it is first rebuilt from an intact replica.
+    pub async fn repair_project(&self, project_id: &str) -> Result<Vec<BagRepair>> {
+        let project = self.get_project(project_id).await?;
+        if project.is_quarantined {
+            return Err(Error::InvalidState(format!("Project {} is quarantined", project_id)));
+        }
+        let primary = self.bag_root(&project)?;
+        let key = replica_key(&primary)?;
+        let targets = self.store.get_replication_targets()?;
+        let mut replicas = Vec::new();
+        for replica in self.store.get_replicas(Some(project_id))? {
+            let Some(target) = targets.iter().find(|t| t.id == replica.target_id) else {
+                continue;
+            };
+            let storage = self.target_storage(target)?;
+            if storage.is_available().await {
+                replicas.push((replica, storage));
+            }
+        }
+
+        let mut repairs = Vec::new();
+        if !copy_is_intact(&primary).await? {
+            let repair = self.restore_primary(project_id, &primary, &key, &replicas).await?;
+            let restored = repair.success;
+            self.record_repair(&repair, None)?;
+            repairs.push(repair);
+            if !restored {
+                return Ok(repairs);
+            }
+        }
+        let healthy = BagItPackage::open(primary.clone())?;
+
+        for (replica, storage) in &replicas {
+            let storage = storage.as_ref();
+            let problems = self.replica_problems(replica, storage, &key, &primary).await;
+            if matches!(problems, Ok(Some(problems)) if problems.is_empty()) {
+                continue;
+            }
+            let outcome = match storage.local_path(&key) {
+                Some(path) if path.is_dir() => repair_bag(&path, &healthy).await,
+                Some(path) => {
+                    let from = primary.clone();
+                    blocking(move || copy_bag(&from, &path).map(|_| vec![ENTIRE_BAG.to_string()]))
+                        .await
+                        .map_err(anyhow::Error::from)
+                }
+                None => match targets.iter().find(|t| t.id == replica.target_id) {
+                    Some(target) => self.store_replica(project_id, target, &healthy, storage, &key).await,
+                    None => continue,
+                },
+            };
+            let problems = match self.replica_problems(replica, storage, &key, &primary).await {
+                Ok(Some(problems)) => problems,
+                Ok(None) => vec!["Replica is missing".to_string()],
+                Err(e) => vec![format!("{:#}", e)],
+            };
+            let repair = bag_repair(project_id, &replica.replica_path, &primary, outcome, problems);
+            self.record_repair(&repair, Some(replica))?;
+            repairs.push(repair);
+        }
+        Ok(repairs)
+    }
+
+    /// Hours between scheduled replica verifications
+    pub async fn replica_verify_interval_hours(&self) -> Result<i64> {
+        Ok(self
+            .store
+            .get_setting(REPLICA_VERIFY_INTERVAL_SETTING)?
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_VERIFY_INTERVAL_HOURS))
+    }
+
+    pub async fn set_replica_verify_interval_hours(&self, hours: i64) -> Result<()> {
+        if hours < 1 {
+            return Err(Error::InvalidState("The verification interval must be at least one hour".to_string()));
+        }
+        self.store.set_setting(REPLICA_VERIFY_INTERVAL_SETTING, &hours.to_string())?;
+        Ok(())
+    }
+
+    /// Record a target reached over the network once it has been reached
+    #[cfg(any(feature = "s3", feature = "webdav"))]
+    async fn add_remote_replication_target(
+        &self,
+        name: &str,
+        kind: &str,
+        storage: &dyn StorageBackend,
+        settings: String,
+    ) -> Result<ReplicationTarget> {
+        let location = storage.location();
+        if !storage.is_available().await {
+            return Err(Error::InvalidState(format!("{} cannot be reached with these credentials", location)));
+        }
+        if self.store.get_replication_targets()?.iter().any(|t| t.path == location) {
+            return Err(Error::InvalidState(format!("{} is already a replication target", location)));
+        }
+
+        let target = ReplicationTarget {
+            kind: kind.to_string(),
+            settings: Some(settings),
+            ..ReplicationTarget::new(name.to_string(), location)
+        };
+        self.store.insert_replication_target(&target)?;
+        Ok(target)
+    }
+
+    /// The storage a replication target is reached through, encrypting if the target asks for it
+    fn target_storage(&self, target: &ReplicationTarget) -> Result<Box<dyn StorageBackend>> {
+        let storage = backend_storage(target, self.keyring().as_deref())?;
+        if !target.encrypted {
+            return Ok(storage);
+        }
+        Ok(Box::new(EncryptedStorage::new(storage, self.keyring())))
+    }
+
+    /// The data key new files on a target are encrypted with, if they are
+    fn replica_key_id(&self, target: &ReplicationTarget) -> Option<String> {
+        target.encrypted.then(|| self.keyring()).flatten().map(|keyring| keyring.current().id.to_string())
+    }
+
+    /// Whether a replica holds files stored under another key than its target now uses
+    ///
+    /// While encryption is locked nothing could be rewritten, so nothing counts as stale.
+    fn has_stale_key(&self, project_id: &str, target: &ReplicationTarget) -> Result<bool> {
+        if target.encrypted && self.keyring().is_none() {
+            return Ok(false);
+        }
+        let key_id = self.replica_key_id(target);
+        Ok(self.store.get_replica_objects(project_id, &target.id)?.iter().any(|o| o.key_id != key_id))
+    }
+
+    /// Copy a bag onto one target and verify the copy
+    async fn copy_to_target(
+        &self,
+        project_id: &str,
+        bag_root: &Utf8Path,
+        target: &ReplicationTarget,
+    ) -> Result<ReplicaStatus> {
+        let storage = self.target_storage(target)?;
+        let key = replica_key(bag_root)?;
+        let previous = self
+            .store
+            .get_replicas(Some(project_id))?
+            .into_iter()
+            .find(|r| r.target_id == target.id);
+
+        let mut status = ReplicaStatus {
+            archived_project_id: project_id.to_string(),
+            target_id: target.id.clone(),
+            replica_path: replica_location(storage.as_ref(), &key),
+            status: "pending".to_string(),
+            last_copied_at: previous.as_ref().and_then(|r| r.last_copied_at),
+            last_verified_at: previous.as_ref().and_then(|r| r.last_verified_at),
+            last_error: None,
+        };
+
+        if !storage.is_available().await {
+            status.status = "unavailable".to_string();
+            status.last_error = Some(format!("{} is not reachable", storage.location()));
+        } else {
+            let copied = match storage.local_path(&key) {
+                // A folder gets a complete new copy swapped in at once
+                Some(destination) => {
+                    let from = bag_root.to_path_buf();
+                    blocking(move || copy_bag(&from, &destination))
+                        .await
+                        .map_err(anyhow::Error::from)
+                        .and_then(|()| self.store.delete_replica_objects(project_id, &target.id))
+                }
+                None => match BagItPackage::open(bag_root.to_path_buf()) {
+                    Ok(bag) => {
+                        self.store_replica(project_id, target, &bag, storage.as_ref(), &key).await.map(|_| ())
+                    }
+                    Err(e) => Err(e),
+                },
+            };
+            match copied {
+                Ok(()) => {
+                    let problems = match self.replica_problems(&status, storage.as_ref(), &key, bag_root).await? {
+                        Some(problems) => problems,
+                        None => vec!["Replica is missing after copying".to_string()],
+                    };
+                    let now = Utc::now();
+                    status.last_copied_at = Some(now);
+                    status.last_verified_at = Some(now);
+                    status.status = if problems.is_empty() { "healthy" } else { "corrupted" }.to_string();
+                    status.last_error = (!problems.is_empty()).then(|| problems.join("; "));
+                }
+                Err(e) => {
+                    status.status = "unavailable".to_string();
+                    status.last_error = Some(format!("{:#}", e));
+                }
+            }
+        }
+
+        self.store.upsert_replica(&status)?;
+        Ok(status)
+    }
+
+    /// Check one replica where it is now
+    ///
+    /// A replica that cannot be checked, because its target is unreachable or
+    /// the vault's copy is needed for comparison and missing, keeps its last
+    /// verification time.
+    async fn check_replica(&self, replica: ReplicaStatus, target: &ReplicationTarget) -> Result<ReplicaStatus> {
+        let storage = self.target_storage(target)?;
+        let key = replica_key(Utf8Path::new(&replica.replica_path))?;
+        let primary = self.bag_root(&self.get_project(&replica.archived_project_id).await?)?;
+
+        let (status, last_error) = if !storage.is_available().await {
+            ("unavailable".to_string(), Some(format!("{} is not reachable", storage.location())))
+        } else {
+            match self.replica_problems(&replica, storage.as_ref(), &key, &primary).await {
+                Ok(None) => ("missing".to_string(), Some(format!("{} does not exist", replica.replica_path))),
+                Ok(Some(problems)) if problems.is_empty() => ("healthy".to_string(), None),
+                Ok(Some(problems)) => ("corrupted".to_string(), Some(problems.join("; "))),
+                Err(e) => (replica.status.clone(), Some(format!("{:#}", e))),
+            }
+        };
+        let verified = matches!(status.as_str(), "healthy" | "corrupted" | "missing");
+
+        Ok(ReplicaStatus {
+            status,
+            last_verified_at: if verified { Some(Utc::now()) } else { replica.last_verified_at },
+            last_error,
+            ..replica
+        })
+    }
+
+    /// Problems with a replica, or `None` if it is not there at all
+    ///
+    /// Folders are validated against the bag's manifests. Remote copies are
+    /// compared with the vault's copy by size and etag instead, as checksumming
+    /// would mean downloading the whole bag.
+    async fn replica_problems(
+        &self,
+        replica: &ReplicaStatus,
+        storage: &dyn StorageBackend,
+        key: &str,
+        primary: &Utf8Path,
+    ) -> anyhow::Result<Option<Vec<String>>> {
+        if let Some(path) = storage.local_path(key) {
+            if !path.is_dir() {
+                return Ok(None);
+            }
+            return Ok(Some(bag_problems(&path).await?));
+        }
+
+        let primary = BagItPackage::open(primary.to_path_buf()).context("The vault's copy is needed for comparison")?;
+        let files = bag_files(&primary).await?;
+        let stored = stored_objects(storage, key).await?;
+        let recorded = self.store.get_replica_objects(&replica.archived_project_id, &replica.target_id)?;
+        let differences = compare_stored(&files, &stored, &recorded, storage.etags_are_md5());
+        if differences.missing.len() == files.len() {
+            return Ok(None);
+        }
+        Ok(Some(differences.describe()))
+    }
+
+    /// Bring a remote replica in line with the bag, uploading only what differs
+    ///
+    /// Every file is recorded as soon as it is uploaded, so a copy that was
+    /// interrupted resumes where it stopped instead of starting over. bagit.txt
+    /// goes last, so a partial copy never looks like a complete bag. Returns the
+    /// files uploaded or removed.
+    async fn store_replica(
+        &self,
+        project_id: &str,
+        target: &ReplicationTarget,
+        bag: &BagItPackage,
+        storage: &dyn StorageBackend,
+        key: &str,
+    ) -> anyhow::Result<Vec<String>> {
+        let target_id = target.id.as_str();
+        let key_id = self.replica_key_id(target);
+        let files = bag_files(bag).await?;
+        let stored = stored_objects(storage, key).await?;
+        let recorded = self.store.get_replica_objects(project_id, target_id)?;
+        let differences = compare_stored(&files, &stored, &recorded, storage.etags_are_md5());
+        let differing: HashSet<&String> =
+            differences.missing.iter().chain(&differences.mismatched).chain(&differences.changed).collect();
+        let records: HashMap<&str, &ReplicaObject> = recorded.iter().map(|o| (o.path.as_str(), o)).collect();
+        // Without a record, only a matching MD5 etag shows an object is complete
+        let md5_etags: HashSet<&str> =
+            stored.iter().filter(|o| o.md5_etag().is_some()).map(|o| o.key.as_str()).collect();
+        let vouched_for = |file: &BagFile| {
+            storage.etags_are_md5() && file.md5.is_some() && md5_etags.contains(file.path.as_str())
+        };
+
+        let mut touched = Vec::new();
+        for file in &files {
+            let current = match records.get(file.path.as_str()) {
+                Some(record) => record.key_id == key_id,
+                None => vouched_for(file),
+            };
+            if current && !differing.contains(&file.path) {
+                continue;
+            }
+            let object_key = join_key(key, &file.path);
+            storage.put_file(&object_key, &bag.bag_root.join(&file.path)).await?;
+            let etag = storage.head(&object_key).await?.and_then(|object| object.etag);
+            self.store.upsert_replica_object(&ReplicaObject {
+                archived_project_id: project_id.to_string(),
+                target_id: target_id.to_string(),
+                path: file.path.clone(),
+                size: file.size as i64,
+                sha256: file.sha256.clone(),
+                etag,
+                key_id: key_id.clone(),
+                stored_at: Utc::now(),
+            })?;
+            touched.push(file.path.clone());
+        }
+
+        for path in &differences.unexpected {
+            storage.delete(&join_key(key, path)).await?;
+            touched.push(path.clone());
+        }
+        let paths: HashSet<&str> = files.iter().map(|f| f.path.as_str()).collect();
+        for stale in recorded.iter().filter(|o| !paths.contains(o.path.as_str())) {
+            self.store.delete_replica_object(project_id, target_id, &stale.path)?;
+        }
+        Ok(touched)
+    }
+
+    /// Integrity issues for replicas that are not healthy
+    ///
+    /// Remote replicas are compared with the vault's copy now, which is cheap;
+    /// folders keep the status of their last verification, as re-validating
+    /// every drive would make a scan take hours.
+    async fn replica_issues(&self) -> Result<Vec<IntegrityIssue>> {
+        let targets = self.store.get_replication_targets()?;
+        let quarantined: HashSet<String> = self
+            .store
+            .get_all_archived_projects()?
+            .into_iter()
+            .filter(|p| p.is_quarantined)
+            .map(|p| p.id)
+            .collect();
+
+        let mut issues = Vec::new();
+        for replica in self.store.get_replicas(None)? {
+            if quarantined.contains(&replica.archived_project_id) {
+                continue;
+            }
+            let Some(target) = targets.iter().find(|t| t.id == replica.target_id && t.enabled) else {
+                continue;
+            };
+            let replica = if target.kind != "local" || target.encrypted {
+                let checked = self.check_replica(replica, target).await?;
+                self.store.upsert_replica(&checked)?;
+                checked
+            } else {
+                replica
+            };
+
+            let (issue_type, severity, state) = match replica.status.as_str() {
+                "corrupted" => ("replica_divergence", "warning", "differs from the vault's copy"),
+                "missing" => ("replica_missing", "warning", "is missing"),
+                "unavailable" => ("replica_unavailable", "info", "could not be reached"),
+                _ => continue,
+            };
+            let mut message =
+                format!("Replica of project {} on {} {}", replica.archived_project_id, target.name, state);
+            if let Some(error) = &replica.last_error {
+                message.push_str(&format!(": {}", error));
+            }
+            issues.push(IntegrityIssue {
+                issue_type: issue_type.to_string(),
+                severity: severity.to_string(),
+                message,
+                affected_files: vec![replica.replica_path],
+            });
+        }
+        Ok(issues)
+    }
+
+    /// Rebuild the vault's copy of a bag from the first intact replica
+    async fn restore_primary(
+        &self,
+        project_id: &str,
+        primary: &Utf8Path,
+        key: &str,
+        replicas: &[(ReplicaStatus, Box<dyn StorageBackend>)],
+    ) -> Result<BagRepair> {
+        for (replica, storage) in replicas {
+            // Object stores are only trusted once downloaded and validated
+            let (source, staging) = match storage.local_path(key) {
+                Some(path) => (path, None),
+                None => {
+                    let staging = Utf8PathBuf::from(format!("{}.partial", primary));
+                    if staging.exists() {
+                        fs::remove_dir_all(&staging)?;
+                    }
+                    if BagItPackage::retrieve(storage.as_ref(), key, staging.clone()).await.is_err() {
+                        let _ = fs::remove_dir_all(&staging);
+                        continue;
+                    }
+                    (staging.clone(), Some(staging))
+                }
+            };
+            if !copy_is_intact(&source).await? {
+                if let Some(staging) = staging {
+                    fs::remove_dir_all(staging)?;
+                }
+                continue;
+            }
+
+            let outcome = if primary.is_dir() {
+                repair_bag(primary, &BagItPackage::open(source.clone())?).await
+            } else if let Some(staging) = &staging {
+                fs::rename(staging, primary).map(|_| vec![ENTIRE_BAG.to_string()]).map_err(anyhow::Error::from)
+            } else {
+                let (from, to) = (source.clone(), primary.to_path_buf());
+                blocking(move || copy_bag(&from, &to).map(|_| vec![ENTIRE_BAG.to_string()]))
+                    .await
+                    .map_err(anyhow::Error::from)
+            };
+            if let Some(staging) = staging.filter(|staging| staging.exists()) {
+                fs::remove_dir_all(staging)?;
+            }
+
+            let problems = bag_problems(primary).await.unwrap_or_else(|e| vec![e.to_string()]);
+            let mut repair = bag_repair(project_id, primary.as_str(), primary, outcome, problems);
+            repair.source_path = Some(replica.replica_path.clone());
+            return Ok(repair);
+        }
+        Err(Error::InvalidState(format!("No intact copy of project {} to repair from", project_id)))
+    }
+
+    /// Record the outcome of a repair, updating the replica's status if it was one
+    fn record_repair(&self, repair: &BagRepair, replica: Option<&ReplicaStatus>) -> Result<()> {
+        if let Some(replica) = replica {
+            let now = Utc::now();
+            self.store.upsert_replica(&ReplicaStatus {
+                status: if repair.success { "healthy" } else { "corrupted" }.to_string(),
+                last_copied_at: Some(now),
+                last_verified_at: Some(now),
+                last_error: repair.error.clone(),
+                ..replica.clone()
+            })?;
+        }
+        let event_payload = serde_json::json!({
+            "repaired_path": repair.repaired_path,
+            "source_path": repair.source_path,
+            "repaired_files": repair.repaired_files,
+            "success": repair.success,
+        });
+        self.store.insert_event("BagRepaired", &repair.archived_project_id, &event_payload.to_string())?;
+        Ok(())
+    }
+
+    /// Whether client-side encryption is set up and unlocked
+    pub async fn encryption_status(&self) -> Result<EncryptionStatus> {
+        let keys = self.store.get_encryption_keys()?;
+        Ok(EncryptionStatus {
+            is_set_up: self.store.get_setting(ENCRYPTION_PASSPHRASE_SETTING)?.is_some(),
+            is_unlocked: self.keyring().is_some(),
+            has_recovery_key: self.store.get_setting(ENCRYPTION_RECOVERY_SETTING)?.is_some(),
+            current_key_id: keys.last().map(|key| key.id.clone()),
+            key_count: keys.len(),
+        })
+    }
+
+    /// Set up client-side encryption under a passphrase and leave it unlocked
+    ///
+    /// Export a recovery key right after: without it, a forgotten passphrase
+    /// leaves encrypted replicas and exports unreadable.
+    pub async fn set_up_encryption(&self, passphrase: &str) -> Result<EncryptionStatus> {
+        check_passphrase(passphrase)?;
+        if self.store.get_setting(ENCRYPTION_PASSPHRASE_SETTING)?.is_some() {
+            return Err(Error::InvalidState("Encryption is already set up".to_string()));
+        }
+
+        let keyring = Keyring::generate();
+        let wrapped = wrap_master(&keyring, passphrase).await?;
+        self.store.insert_encryption_key(&EncryptionKey {
+            id: keyring.current().id.to_string(),
+            sealed_key: keyring.seal(keyring.current())?,
+            created_at: Utc::now(),
+        })?;
+        self.store.set_setting(ENCRYPTION_PASSPHRASE_SETTING, &wrapped)?;
+        self.set_keyring(Some(keyring));
+        self.encryption_status().await
+    }
+
+    /// Unlock encryption for this session
+    pub async fn unlock_encryption(&self, passphrase: &str) -> Result<EncryptionStatus> {
+        let master = self.unwrap_master(ENCRYPTION_PASSPHRASE_SETTING, passphrase).await?;
+        self.set_keyring(Some(self.load_keyring(master)?));
+        self.encryption_status().await
+    }
+
+    /// Forget the keys until encryption is unlocked again
+    pub async fn lock_encryption(&self) {
+        self.set_keyring(None);
+    }
+
+    pub async fn change_encryption_passphrase(&self, current: &str, new: &str) -> Result<()> {
+        check_passphrase(new)?;
+        let master = self.unwrap_master(ENCRYPTION_PASSPHRASE_SETTING, current).await?;
+        let keyring = self.load_keyring(master)?;
+        let wrapped = wrap_master(&keyring, new).await?;
+        self.store.set_setting(ENCRYPTION_PASSPHRASE_SETTING, &wrapped)?;
+        self.set_keyring(Some(keyring));
+        Ok(())
+    }
+
+    /// Set a new passphrase with the recovery key, for when the passphrase is forgotten
+    pub async fn recover_encryption(&self, recovery_key: &str, new_passphrase: &str) -> Result<EncryptionStatus> {
+        check_passphrase(new_passphrase)?;
+        let recovery_key = normalize_recovery_key(recovery_key);
+        let master = self.unwrap_master(ENCRYPTION_RECOVERY_SETTING, &recovery_key).await?;
+        let keyring = self.load_keyring(master)?;
+        let wrapped = wrap_master(&keyring, new_passphrase).await?;
+        self.store.set_setting(ENCRYPTION_PASSPHRASE_SETTING, &wrapped)?;
+        self.set_keyring(Some(keyring));
+        self.encryption_status().await
+    }
+
+    /// Write a new recovery key to `output`, replacing any earlier one
+    ///
+    /// The file is written before the key takes effect, so a failed export
+    /// leaves the previous recovery key working.
+    pub async fn export_recovery_key(&self, output: &Utf8Path) -> Result<()> {
+        let keyring = self.unlocked_keyring()?;
+        let recovery_key = generate_recovery_key();
+        let wrapped = wrap_master(&keyring, &normalize_recovery_key(&recovery_key)).await?;
+
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(output).map_err(|e| match e.kind() {
+            std::io::ErrorKind::AlreadyExists => Error::InvalidState(format!("{} already exists", output)),
+            _ => Error::Io(e),
+        })?;
+        std::io::Write::write_all(
+            &mut file,
+            format!(
+                "Creative Work Preservation Toolkit recovery key\n\n{}\n\n\
+                 Created {}. Anyone with this key can read your encrypted replicas and exports.\n\
+                 Keep it away from this computer. Exporting a new recovery key makes this one stop working.\n",
+                recovery_key.as_str(),
+                Utc::now().format("%Y-%m-%d")
+            )
+            .as_bytes(),
+        )?;
+        file.sync_all()?;
+
+        self.store.set_setting(ENCRYPTION_RECOVERY_SETTING, &wrapped)?;
+        Ok(())
+    }
+
+    /// Start encrypting with a new data key and rewrite encrypted replicas under it
+    ///
+    /// Older keys are kept, so replicas on targets that cannot be reached now
+    /// stay readable until the next sync rewrites them.
+    pub async fn rotate_encryption_key(&self) -> Result<Vec<ReplicaStatus>> {
+        let mut keyring = Keyring::clone(&*self.unlocked_keyring()?);
+        let key = DataKey::generate();
+        self.store.insert_encryption_key(&EncryptionKey {
+            id: key.id.to_string(),
+            sealed_key: keyring.seal(&key)?,
+            created_at: Utc::now(),
+        })?;
+        keyring.add(key);
+        self.set_keyring(Some(keyring));
+        self.replicate_all().await
+    }
+
+    /// Encrypt a target's replicas from now on, or store them as plain bags again
+    ///
+    /// Existing replicas are rewritten in the new form right away where the
+    /// target can be reached, and on a later sync otherwise.
+    pub async fn set_replication_target_encrypted(
+        &self,
+        target_id: &str,
+        encrypted: bool,
+    ) -> Result<ReplicationTarget> {
+        if encrypted {
+            self.unlocked_keyring()?;
+        }
+        if !self.store.set_replication_target_encrypted(target_id, encrypted)? {
+            return Err(Error::NotFound(format!("Replication target {}", target_id)));
+        }
+        self.replicate_all().await?;
+        self.store
+            .get_replication_targets()?
+            .into_iter()
+            .find(|t| t.id == target_id)
+            .ok_or_else(|| Error::NotFound(format!("Replication target {}", target_id)))
+    }
+
+    /// Serialize a project's bag and encrypt the archive, leaving no plaintext copy in `output`
+    ///
+    /// The bag's manifests still describe the plaintext, so the decrypted
+    /// archive validates like any other.
+    pub async fn export_encrypted_bag(
+        &self,
+        project_id: &str,
+        format: ExportFormat,
+        output: &Utf8Path,
+    ) -> Result<ExportResult> {
+        let serialization = format.serialization().ok_or_else(|| {
+            Error::InvalidState(format!("Only bag archives can be encrypted, not {}", format.as_str()))
+        })?;
+        let keyring = self.unlocked_keyring()?;
+        let project = self.get_project(project_id).await?;
+        let bag_root = self.bag_root(&project)?;
+        if !bag_root.is_dir() {
+            return Err(Error::NotFound(format!("Bag for project {}", project_id)));
+        }
+
+        let output = output.to_path_buf();
+        let (encrypted_path, key_id) = blocking(move || {
+            let staging = output.join(format!(".cwpt-export-{}", Uuid::new_v4()));
+            let encrypted = serialize_bag(&BagItPackage::open(bag_root)?, serialization, &staging).and_then(|archive| {
+                let name = archive.file_name().unwrap_or("bag");
+                let encrypted = output.join(format!("{}.{}", name, ENCRYPTED_EXTENSION));
+                encrypt_file(keyring.current(), &archive, &encrypted)?;
+                Ok(encrypted)
+            });
+            let _ = fs::remove_dir_all(&staging);
+            Ok((encrypted?, keyring.current().id.to_string()))
+        })
+        .await?;
+
+        let event_payload = serde_json::json!({
+            "format": serialization.media_type(),
+            "archive_path": encrypted_path.as_str(),
+            "key_id": key_id,
+        });
+        self.store.insert_event("EncryptedBagExported", project_id, &event_payload.to_string())?;
+
+        Ok(ExportResult {
+            success: true,
+            output_path: Some(encrypted_path.to_string()),
+            record_count: 1,
+            error: None,
+        })
+    }
+
+    /// Decrypt an encrypted export, or any file encrypted with this vault's keys
+    pub async fn decrypt_file(&self, input: &Utf8Path, output: &Utf8Path) -> Result<()> {
+        let keyring = self.unlocked_keyring()?;
+        if !input.is_file() {
+            return Err(Error::NotFound(input.to_string()));
+        }
+        let (input, output) = (input.to_path_buf(), output.to_path_buf());
+        blocking(move || decrypt_file(&keyring, &input, &output)).await
+    }
+
+    /// Whether the database is encrypted at rest and still waiting for its passphrase
+    pub async fn database_status(&self) -> Result<DatabaseStatus> {
+        Ok(DatabaseStatus {
+            is_encrypted: self.store.is_encrypted()?,
+            is_locked: self.store.is_locked(),
+        })
+    }
+
+    /// Whether the database is encrypted and not unlocked yet, so nothing can be read from it
+    pub fn is_database_locked(&self) -> bool {
+        self.store.is_locked()
+    }
+
+    /// Unlock an encrypted database, applying migrations it was waiting for
+    #[cfg(feature = "sqlcipher")]
+    pub async fn unlock_database(&self, passphrase: &str) -> Result<DatabaseStatus> {
+        let (store, passphrase) = (self.store.clone(), Zeroizing::new(passphrase.to_string()));
+        if !blocking(move || store.unlock(&passphrase)).await? {
+            return Err(Error::WrongPassphrase);
+        }
+        self.database_status().await
+    }
+
+    /// Encrypt the database at rest; it has to be unlocked with the passphrase from then on
+    ///
+    /// There is no recovery key for the database: a forgotten passphrase
+    /// leaves the catalog unreadable, though the bags themselves are not
+    /// affected.
+    #[cfg(feature = "sqlcipher")]
+    pub async fn encrypt_database(&self, passphrase: &str) -> Result<DatabaseStatus> {
+        check_passphrase(passphrase)?;
+        if self.store.is_encrypted()? {
+            return Err(Error::InvalidState("The database is already encrypted".to_string()));
+        }
+        let (store, passphrase) = (self.store.clone(), Zeroizing::new(passphrase.to_string()));
+        blocking(move || store.encrypt(&passphrase)).await?;
+        self.database_status().await
+    }
+
+    #[cfg(feature = "sqlcipher")]
+    pub async fn change_database_passphrase(&self, current: &str, new: &str) -> Result<()> {
+        check_passphrase(new)?;
+        if self.store.is_locked() {
+            return Err(Error::InvalidState("The database is locked".to_string()));
+        }
+        if !self.store.is_encrypted()? {
+            return Err(Error::InvalidState("The database is not encrypted".to_string()));
+        }
+        let store = self.store.clone();
+        let (current, new) = (Zeroizing::new(current.to_string()), Zeroizing::new(new.to_string()));
+        if !blocking(move || store.change_passphrase(&current, &new)).await? {
+            return Err(Error::WrongPassphrase);
+        }
+        Ok(())
+    }
+
+    /// Write an unencrypted copy of the database, for moving the vault to another install
+    #[cfg(feature = "sqlcipher")]
+    pub async fn export_unencrypted_database(&self, output: &Utf8Path) -> Result<()> {
+        if self.store.is_locked() {
+            return Err(Error::InvalidState("The database is locked".to_string()));
+        }
+        if output.exists() {
+            return Err(Error::InvalidState(format!("{} already exists", output)));
+        }
+        let (store, output) = (self.store.clone(), output.to_path_buf());
+        blocking(move || store.export_unencrypted(&output)).await
+    }
+
+    fn keyring(&self) -> Option<Arc<Keyring>> {
+        self.keyring.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    fn unlocked_keyring(&self) -> Result<Arc<Keyring>> {
+        self.keyring().ok_or_else(|| Error::InvalidState("Encryption is locked".to_string()))
+    }
+
+    /// Encrypt a credential for the settings table under the current data key
+    #[cfg(any(feature = "s3", feature = "webdav"))]
+    fn seal_secret(&self, secret: &str) -> Result<String> {
+        let keyring = self.unlocked_keyring()?;
+        Ok(hex::encode(encrypt_bytes(keyring.current(), secret.as_bytes())?))
+    }
+
+    fn set_keyring(&self, keyring: Option<Keyring>) {
+        *self.keyring.lock().unwrap_or_else(PoisonError::into_inner) = keyring.map(Arc::new);
+    }
+
+    /// The master key from the copy wrapped under a passphrase or recovery key
+    async fn unwrap_master(&self, setting: &str, secret: &str) -> Result<SecretKey> {
+        let wrapped = match self.store.get_setting(setting)? {
+            Some(wrapped) => serde_json::from_str::<WrappedKey>(&wrapped).context("Invalid wrapped master key")?,
+            None if setting == ENCRYPTION_RECOVERY_SETTING => {
+                return Err(Error::InvalidState("No recovery key has been exported".to_string()))
+            }
+            None => return Err(Error::InvalidState("Encryption is not set up".to_string())),
+        };
+        let secret = Zeroizing::new(secret.to_string());
+        blocking(move || wrapped.unwrap(&secret)).await?.ok_or(Error::WrongPassphrase)
+    }
+
+    /// Open every stored data key with the master key
+    fn load_keyring(&self, master: SecretKey) -> Result<Keyring> {
+        let mut keys = Vec::new();
+        for key in self.store.get_encryption_keys()? {
+            let id = Uuid::parse_str(&key.id).context("Invalid encryption key id")?;
+            keys.push(Keyring::open(&master, id, &key.sealed_key)?);
+        }
+        if keys.is_empty() {
+            return Err(Error::InvalidState("No encryption keys are stored".to_string()));
+        }
+        Ok(Keyring::new(master, keys))
+    }
+
+    /// Start archiving new work dropped into a folder
+    pub async fn add_watch_folder(
+        &self,
+        path: &Utf8Path,
+        settle_seconds: Option<i64>,
+        create_bag: bool,
+    ) -> Result<WatchFolder> {
+        let path = path
+            .canonicalize_utf8()
+            .map_err(|_| Error::NotFound(path.to_string()))?;
+        if !path.is_dir() {
+            return Err(Error::InvalidState(format!("{} is not a folder", path)));
+        }
+        // Watching the vault itself would ingest every new bag as another project
+        if let Ok(bags_root) = self.config.bags_root.canonicalize_utf8() {
+            if path.starts_with(&bags_root) || bags_root.starts_with(&path) {
+                return Err(Error::InvalidState(format!("{} overlaps the bags folder", path)));
+            }
+        }
+        if self.store.get_watch_folders()?.iter().any(|f| f.path == path.as_str()) {
+            return Err(Error::InvalidState(format!("{} is already watched", path)));
+        }
+
+        let folder = WatchFolder::new(
+            path.to_string(),
+            settle_seconds.unwrap_or(DEFAULT_SETTLE_SECONDS),
+            create_bag,
+        );
+        self.store.insert_watch_folder(&folder)?;
+        Ok(folder)
+    }
+
+    /// All watch folders, oldest first
+    pub async fn list_watch_folders(&self) -> Result<Vec<WatchFolder>> {
+        Ok(self.store.get_watch_folders()?)
+    }
+
+    /// Stop watching a folder; projects already ingested from it are kept
+    pub async fn remove_watch_folder(&self, watch_folder_id: &str) -> Result<()> {
+        if !self.store.delete_watch_folder(watch_folder_id)? {
+            return Err(Error::NotFound(format!("Watch folder {}", watch_folder_id)));
+        }
+        Ok(())
+    }
+
+    /// Scan every enabled watch folder once and archive the entries that have settled
+    ///
+    /// Each top-level file or folder becomes a project named after it. An entry
+    /// that was ingested before and has changed since becomes a new version of
+    /// the project it produced last time. Call this on a timer with the same
+    /// tracker so partial writes are debounced across polls.
+    pub async fn poll_watch_folders(&self, tracker: &mut WatchTracker) -> Result<Vec<WatchIngestion>> {
+        let mut ingestions = Vec::new();
+        for folder in self.store.get_watch_folders()?.into_iter().filter(|f| f.enabled) {
+            let folder_path = Utf8PathBuf::from(&folder.path);
+            // A folder on an unmounted drive is skipped until it comes back
+            let Ok(entries) = blocking(move || scan_watch_folder(&folder_path)).await else {
+                continue;
+            };
+
+            let settle = Duration::seconds(folder.settle_seconds);
+            for entry in tracker.settled(&folder.id, entries, settle, Utc::now()) {
+                let previous = self.store.get_watch_folder_entry(&folder.id, &entry.name)?;
+                if previous.as_ref().is_some_and(|(fingerprint, _)| fingerprint == &entry.fingerprint) {
+                    continue;
+                }
+                let parent_project_id = previous.map(|(_, project_id)| project_id);
+
+                let ingestion = match self.ingest_watch_entry(&folder, &entry, parent_project_id.as_deref()).await {
+                    Ok(project_id) => WatchIngestion {
+                        watch_folder_id: folder.id.clone(),
+                        entry_path: entry.path.to_string(),
+                        project_id: Some(project_id),
+                        parent_project_id,
+                        error: None,
+                    },
+                    Err(e) => {
+                        // Settled entries are reported once; a failed one is picked up again on the next poll
+                        tracker.forget(&folder.id, &entry.name);
+                        WatchIngestion {
+                            watch_folder_id: folder.id.clone(),
+                            entry_path: entry.path.to_string(),
+                            project_id: None,
+                            parent_project_id,
+                            error: Some(format!("{:#}", e)),
+                        }
+                    }
+                };
+                ingestions.push(ingestion);
+            }
+        }
+        Ok(ingestions)
+    }
+
+    /// Archive one settled watch folder entry, returning the new project's ID
+    async fn ingest_watch_entry(
+        &self,
+        folder: &WatchFolder,
+        entry: &WatchEntry,
+        parent_project_id: Option<&str>,
+    ) -> Result<String> {
+        let request = ArchiveRequest {
+            name: entry.name.clone(),
+            description: Some(format!("Archived automatically from watch folder {}", folder.path)),
+            files: vec![entry.path.to_string()],
+            environment_profile_ids: vec![],
+        };
+        let project = self.archive_project(&request).await?;
+
+        if folder.create_bag {
+            if let Err(e) = self.create_bag(&project.id, BagOptions::default()).await {
+                // Undo the whole ingest, so retrying the entry cannot leave duplicate projects behind
+                let _ = fs::remove_dir_all(self.bag_root(&project)?);
+                self.store.delete_archived_project(&project.id)?;
+                return Err(e);
+            }
+        }
+
+        if let Some(parent_project_id) = parent_project_id {
+            // The earlier project may have been removed from the lineage's reach; the new one stands alone then
+            let relations = self.store.get_all_project_relations()?;
+            if self.store.get_archived_project(parent_project_id)?.is_some()
+                && validate_new_relation(&relations, parent_project_id, &project.id, "version").is_ok()
+            {
+                let relation =
+                    ProjectRelation::new(parent_project_id.to_string(), project.id.clone(), "version".to_string(), None);
+                self.store.insert_project_relation(&relation)?;
+            }
+        }
+
+        let event_payload = serde_json::json!({
+            "trigger": "watch_folder",
+            "watch_folder_id": folder.id,
+            "watch_folder_path": folder.path,
+            "entry_path": entry.path.as_str(),
+            "fingerprint": entry.fingerprint,
+            "settle_seconds": folder.settle_seconds,
+            "parent_project_id": parent_project_id,
+        });
+        self.store
+            .insert_event("WatchFolderIngested", &project.id, &event_payload.to_string())?;
+        self.store
+            .upsert_watch_folder_entry(&folder.id, &entry.name, &entry.fingerprint, &project.id)?;
+        Ok(project.id)
+    }
+
+    /// The bearer token clients of the local HTTP API must send
+    ///
+    /// Created on first use and kept in the settings table, so scripts keep
+    /// working across restarts.
+    pub async fn http_api_token(&self) -> Result<String> {
+        if let Some(token) = self.store.get_setting(HTTP_API_TOKEN_SETTING)? {
+            return Ok(token);
+        }
+        let token = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        self.store.set_setting(HTTP_API_TOKEN_SETTING, &token)?;
+        Ok(token)
+    }
+
+    /// Refresh the index rows of one project from the catalog and its bag
+    async fn index_project(&self, project_id: &str) -> Result<()> {
+        let project = self.get_project(project_id).await?;
+        let bag_root = self.bag_root(&project)?;
+        let tags = self.store.get_all_tags()?;
+        let assignments = self.store.get_all_tag_assignments()?;
+        let technical = self.store.get_technical_metadata(project_id)?;
+
+        // Text extraction reads whole payload files, so keep it off the async runtime
+        let documents = blocking(move || {
+            let bag = BagItPackage::open(bag_root).ok();
+            project_documents(&project, bag.as_ref(), &tags, &assignments, &technical)
+        })
+        .await?;
+
+        self.store.replace_search_documents(project_id, &documents)?;
+        Ok(())
+    }
+
+    /// Open the bag of a project that is in the vault rather than in quarantine
+    async fn project_bag(&self, project_id: &str) -> Result<BagItPackage> {
+        let project = self.get_project(project_id).await?;
+        if project.is_quarantined {
+            return Err(Error::InvalidState(format!("Project {} is quarantined", project_id)));
+        }
+        BagItPackage::open(self.bag_root(&project)?).map_err(|e| Error::NotFound(e.to_string()))
+    }
+
+    /// Refresh the payload file catalog and technical metadata from a project's bag
+    async fn refresh_catalog(&self, project_id: &str, bag_root: &Utf8Path) -> Result<()> {
+        let (bag_root, catalog_project_id) = (bag_root.to_path_buf(), project_id.to_string());
+        let (files, technical) = blocking(move || {
+            let bag = BagItPackage::open(bag_root)?;
+            let files = catalog_payload(&bag, &catalog_project_id)?;
+            let technical = catalog_technical_metadata(&bag, &files)?;
+            Ok((files, technical))
+        })
+        .await?;
+
+        self.store.replace_project_files(project_id, &files)?;
+        self.store.replace_technical_metadata(project_id, &technical)?;
+        Ok(())
+    }
+}
+
+/// A project's sources as they are now, against the state they were archived in
+struct SourceScan {
+    source_root: Utf8PathBuf,
+    sources: Vec<String>,
+    current: Vec<SourceFile>,
+    baseline: Vec<SourceFileState>,
+    delta: SourceDelta,
+}
+
+/// SHA-256 of each payload file by payload path, from the bag's manifest or hashed when it has none
+///
+/// A bag made to a profile without SHA-256 lists its payload in another
+/// manifest, which `read_manifest` falls back to; those files are hashed here.
+async fn payload_sha256(bag: &BagItPackage) -> Result<HashMap<String, String>> {
+    let manifest = bag.read_manifest()?;
+    if bag.manifest_path_for(ChecksumAlgorithm::Sha256).exists() {
+        return Ok(manifest.into_iter().map(|(checksum, path)| (path, checksum)).collect());
+    }
+
+    let mut checksums = HashMap::new();
+    for (_, path) in manifest {
+        let file_path = bag.bag_root.join(&path);
+        if file_path.is_file() {
+            checksums.insert(path, calculate_sha256(&file_path).await?);
+        }
+    }
+    Ok(checksums)
+}
+
+fn source_changes(project_id: &str, source_root: &Utf8Path, delta: &SourceDelta) -> SourceChanges {
+    SourceChanges {
+        project_id: project_id.to_string(),
+        source_root: source_root.to_string(),
+        added: delta.added.clone(),
+        changed: delta.changed.clone(),
+        removed: delta.removed.clone(),
+        unchanged_count: delta.unchanged.len(),
+        has_changes: !delta.is_empty(),
+    }
+}
+
+/// Stands in for the file list when a whole bag was copied
+const ENTIRE_BAG: &str = "(entire bag)";
+
+/// The storage backend a replication target is reached through, before any encryption
+///
+/// Remote targets keep their credentials sealed, so they need `keyring` to be reached.
+#[cfg_attr(not(any(feature = "s3", feature = "webdav")), allow(unused_variables))]
+fn backend_storage(target: &ReplicationTarget, keyring: Option<&Keyring>) -> Result<Box<dyn StorageBackend>> {
+    match target.kind.as_str() {
+        "local" => Ok(Box::new(LocalStorage::new(&target.path))),
+        #[cfg(feature = "s3")]
+        "s3" => {
+            let settings = target
+                .settings
+                .as_deref()
+                .ok_or_else(|| Error::InvalidState(format!("Replication target {} has no S3 settings", target.name)))?;
+            let mut config: S3Config = serde_json::from_str(settings).context("Invalid S3 settings")?;
+            config.secret_access_key = open_secret(target, keyring, &config.secret_access_key)?;
+            Ok(Box::new(S3Storage::new(config)?))
+        }
+        #[cfg(feature = "webdav")]
+        "webdav" => {
+            let settings = target.settings.as_deref().ok_or_else(|| {
+                Error::InvalidState(format!("Replication target {} has no WebDAV settings", target.name))
+            })?;
+            let mut config: WebDavConfig = serde_json::from_str(settings).context("Invalid WebDAV settings")?;
+            config.password = open_secret(target, keyring, &config.password)?;
+            Ok(Box::new(WebDavStorage::new(config)?))
+        }
+        kind => Err(Error::InvalidState(format!(
+            "Replication target {} uses the {} backend, which this build does not support",
+            target.name, kind
+        ))),
+    }
+}
+
+/// Decrypt a credential sealed by `Vault::seal_secret`
+#[cfg(any(feature = "s3", feature = "webdav"))]
+fn open_secret(target: &ReplicationTarget, keyring: Option<&Keyring>, sealed: &str) -> Result<String> {
+    let keyring = keyring.ok_or_else(|| {
+        Error::InvalidState(format!("Encryption is locked, so the credentials of {} cannot be read", target.name))
+    })?;
+    let secret = hex::decode(sealed)
+        .map_err(anyhow::Error::from)
+        .and_then(|sealed| decrypt_bytes(keyring, &sealed))
+        .and_then(|secret| Ok(String::from_utf8(secret)?))
+        .with_context(|| format!("Credentials of {} cannot be decrypted", target.name))?;
+    Ok(secret)
+}
+
+/// Key a bag is stored under on a replication target: its directory name
+fn replica_key(bag_root: &Utf8Path) -> Result<String> {
+    Ok(bag_root
+        .file_name()
+        .ok_or_else(|| Error::InvalidState(format!("Bag path has no directory name: {}", bag_root)))?
+        .to_string())
+}
+
+/// Where a replica lives: a folder for local targets, otherwise a URL-like location
+fn replica_location(storage: &dyn StorageBackend, key: &str) -> String {
+    match storage.local_path(key) {
+        Some(path) => path.to_string(),
+        None => format!("{}/{}", storage.location(), key),
+    }
+}
+
+/// A replica's objects, keyed relative to the bag root
+async fn stored_objects(storage: &dyn StorageBackend, key: &str) -> anyhow::Result<Vec<StoredObject>> {
+    Ok(storage
+        .list(&format!("{}/", key))
+        .await?
+        .into_iter()
+        .filter_map(|object| {
+            let relative = object.key.strip_prefix(key)?.trim_start_matches('/').to_string();
+            Some(StoredObject { key: relative, ..object })
+        })
+        .collect())
+}
+
+fn bag_repair(
+    project_id: &str,
+    repaired_path: &str,
+    source: &Utf8Path,
+    outcome: anyhow::Result<Vec<String>>,
+    problems: Vec<String>,
+) -> BagRepair {
+    let (repaired_files, problems) = match outcome {
+        Ok(files) => (files, problems),
+        Err(e) => (Vec::new(), vec![format!("{:#}", e)]),
+    };
+    BagRepair {
+        archived_project_id: project_id.to_string(),
+        repaired_path: repaired_path.to_string(),
+        source_path: Some(source.to_string()),
+        repaired_files,
+        success: problems.is_empty(),
+        error: (!problems.is_empty()).then(|| problems.join("; ")),
+    }
+}
+
+fn check_passphrase(passphrase: &str) -> Result<()> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_LENGTH {
+        return Err(Error::InvalidState(format!(
+            "The passphrase must be at least {} characters",
+            MIN_PASSPHRASE_LENGTH
+        )));
+    }
+    Ok(())
+}
+
+/// The keyring's master key wrapped under a secret, as stored in settings
+async fn wrap_master(keyring: &Keyring, secret: &str) -> Result<String> {
+    let (master, secret) = (Zeroizing::new(*keyring.master()), Zeroizing::new(secret.to_string()));
+    let wrapped = blocking(move || WrappedKey::wrap(&secret, &master)).await?;
+    Ok(serde_json::to_string(&wrapped).context("Failed to store wrapped master key")?)
+}
+
+/// Whether a copy of a bag exists and passes validation
+async fn copy_is_intact(bag_root: &Utf8Path) -> Result<bool> {
+    Ok(bag_root.join("bagit.txt").is_file() && bag_problems(bag_root).await?.is_empty())
+}
+
+/// Run filesystem-heavy work on the blocking thread pool
+async fn blocking<T, F>(work: F) -> Result<T>
+where
+    F: FnOnce() -> anyhow::Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(work)
+        .await
+        .context("Background task failed")?
+        .map_err(Error::from)
+}
+
+fn source_stats(files: &[String]) -> anyhow::Result<(usize, u64)> {
+    let mut file_count = 0;
+    let mut total_size = 0;
+    for file_info in validate_paths(files)? {
+        if file_info.is_directory {
+            let stats = analyze_path(file_info.path.as_str())?;
+            file_count += stats.file_count;
+            total_size += stats.total_size;
+        } else {
+            file_count += 1;
+            total_size += file_info.size;
+        }
+    }
+    Ok((file_count, total_size))
+}
+
+fn bag_result(bag_path: &Utf8Path, issues: Vec<String>, success_message: &str) -> BagResult {
+    let mut validation_results: Vec<ValidationResult> = issues
+        .into_iter()
+        .map(|message| ValidationResult {
+            // A file listed in fetch.txt is part of the bag, just not copied in yet
+            result_type: if message.starts_with("File not yet fetched") { "warning" } else { "error" }.to_string(),
+            message,
+            file: None,
+        })
+        .collect();
+
+    if validation_results.is_empty() {
+        validation_results.push(ValidationResult {
+            result_type: "info".to_string(),
+            message: success_message.to_string(),
+            file: None,
+        });
+    }
+
+    BagResult {
+        success: validation_results.iter().all(|r| r.result_type != "error"),
+        bag_path: Some(bag_path.to_string()),
+        validation_results: Some(validation_results),
+        error: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::collections::BAG_GROUP_IDENTIFIER;
+    use tempfile::TempDir;
+
+    async fn test_vault(root: &Utf8Path) -> Vault {
+        Vault::open(VaultConfig {
+            database_path: root.join("preservation.db"),
+            bags_root: root.join("bags"),
+            quarantine_root: root.join("quarantine"),
+            derivatives_root: root.join("derivatives"),
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_archive_bag_and_export() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let vault = test_vault(root).await;
+
+        let source = root.join("Portfolio");
+        fs::create_dir_all(source.join("scans")).unwrap();
+        fs::write(source.join("statement.txt"), b"artist statement").unwrap();
+        fs::write(source.join("scans/plate-1.txt"), b"plate one").unwrap();
+
+        let request = ArchiveRequest {
+            name: "Portfolio".to_string(),
+            description: None,
+            files: vec![source.to_string()],
+            environment_profile_ids: vec![],
+        };
+        let project = vault.archive_project(&request).await.unwrap();
+        assert_eq!((project.file_count, project.total_size), (2, 25));
+
+        let bagged = vault.create_bag(&project.id, BagOptions::default()).await.unwrap();
+        assert!(bagged.success);
+        let bag_root = Utf8PathBuf::from(bagged.bag_path.unwrap());
+        assert!(bag_root.starts_with(root.join("bags")));
+        assert!(bag_root.join("data/scans/plate-1.txt").is_file());
+
+        // Bagging twice would mix two payloads, so it is refused
+        let again = vault.create_bag(&project.id, BagOptions::default()).await;
+        assert!(matches!(again, Err(Error::InvalidState(_))));
+
+        assert!(vault.validate_project(&project.id).await.unwrap().success);
+        let profile_path = root.join("profile.json");
+        fs::write(
+            &profile_path,
+            r#"{
+                "BagIt-Profile-Info": { "BagIt-Profile-Identifier": "https://example.edu/deposit.json" },
+                "Bag-Info": { "Contact-Email": { "required": true } }
+            }"#,
+        )
+        .unwrap();
+        let checked = vault.validate_path(&bag_root, Some(&profile_path)).await.unwrap();
+        assert!(!checked.success);
+
+        let hits = vault.search("plate", None).await.unwrap();
+        assert_eq!(hits[0].project_id, project.id);
+        assert_eq!(hits[0].file_path.as_deref(), Some("data/scans/plate-1.txt"));
+        assert!(vault.search(" *:- ", None).await.unwrap().is_empty());
+
+        let premis_path = root.join("premis.xml");
+        let exported = vault
+            .export_project(&project.id, ExportFormat::Premis, &premis_path)
+            .await
+            .unwrap();
+        assert_eq!(exported.record_count, 3);
+        assert!(fs::read_to_string(&premis_path).unwrap().contains("statement.txt"));
+
+        let events: Vec<String> = vault
+            .project_events(&project.id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|e| e.event_type)
+            .collect();
+        assert_eq!(events, vec!["ProjectArchived", "BagitPackageCreated", "BagValidated", "PremisExported"]);
+
+        // Projects without a bag have nothing to describe and are left out of the vault record
+        let unbagged = ArchiveRequest {
+            name: "Sketches".to_string(),
+            files: vec![source.join("statement.txt").to_string()],
+            ..request
+        };
+        vault.archive_project(&unbagged).await.unwrap();
+        let vra_path = root.join("vault.xml");
+        let exported = vault.export_vault_vra(&vra_path).await.unwrap();
+        assert_eq!(exported.record_count, 1);
+        let vra = fs::read_to_string(&vra_path).unwrap();
+        assert!(vra.contains("Portfolio") && !vra.contains("Sketches"));
+
+        let archive = vault
+            .export_project(&project.id, ExportFormat::TarGz, &root.join("exports"))
+            .await
+            .unwrap();
+        let archive_path = Utf8PathBuf::from(archive.output_path.unwrap());
+        let imported = vault.import_serialized_bag(&archive_path, None).await.unwrap();
+        assert!(imported.success);
+        let imported_root = Utf8PathBuf::from(imported.bag_path.unwrap());
+        assert_ne!(imported_root, bag_root);
+        assert!(imported_root.join("data/scans/plate-1.txt").is_file());
+        let staged = fs::read_dir(root.join("bags"))
+            .unwrap()
+            .any(|entry| entry.unwrap().file_name().to_string_lossy().starts_with('.'));
+        assert!(!staged);
+        let imported_project = vault
+            .list_projects()
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|p| p.name.starts_with("Portfolio-"))
+            .unwrap();
+        assert_eq!((imported_project.file_count, imported_project.total_size), (2, 25));
+        assert!(vault.validate_project(&imported_project.id).await.unwrap().success);
+    }
+
+    #[tokio::test]
+    async fn test_bag_made_to_a_sha512_only_profile() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let vault = test_vault(root).await;
+
+        let profile_path = root.join("sha512-profile.json");
+        let profile = r#"{
+            "BagIt-Profile-Info": { "BagIt-Profile-Identifier": "https://example.edu/profiles/sha512.json" },
+            "Manifests-Required": ["sha512"],
+            "Manifests-Allowed": ["sha512"]
+        }"#;
+        fs::write(&profile_path, profile).unwrap();
+
+        let source = root.join("Etchings");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("plate.txt"), b"copper plate").unwrap();
+        let request = ArchiveRequest {
+            name: "Etchings".to_string(),
+            description: None,
+            files: vec![source.to_string()],
+            environment_profile_ids: vec![],
+        };
+        let project = vault.archive_project(&request).await.unwrap();
+        let options = BagOptions {
+            profile_path: Some(profile_path),
+            ..BagOptions::default()
+        };
+        assert!(vault.create_bag(&project.id, options).await.unwrap().success);
+
+        let bag = BagItPackage::open(vault.bag_root(&project).unwrap()).unwrap();
+        assert_eq!(bag.manifest_algorithms().unwrap(), vec!["sha512"]);
+        assert_eq!(bag.read_manifest().unwrap()[0].1, "data/plate.txt");
+        vault.tag_project(&project.id, "intaglio", None, Some("data/plate.txt")).await.unwrap();
+        assert_eq!(vault.search("plate", None).await.unwrap()[0].project_id, project.id);
+
+        // Source states are hashed from the payload the SHA-512 manifest lists
+        assert!(!vault.detect_source_changes(&project.id).await.unwrap().has_changes);
+        fs::write(source.join("proof.txt"), b"first proof").unwrap();
+        let delta = vault.create_delta_version(&project.id).await.unwrap();
+        assert_eq!(delta.changes.added, vec!["proof.txt"]);
+        assert_eq!(delta.changes.unchanged_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_bag_info_follows_the_profile_and_the_caller() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let vault = test_vault(root).await;
+
+        let profile_path = root.join("deposit-profile.json");
+        let profile = r#"{
+            "BagIt-Profile-Info": { "BagIt-Profile-Identifier": "https://example.edu/profiles/deposit.json" },
+            "Bag-Info": {
+                "Source-Organization": { "required": true, "values": ["Example Art School Library"] },
+                "Contact-Email": { "required": true, "repeatable": false }
+            }
+        }"#;
+        fs::write(&profile_path, profile).unwrap();
+
+        let source = root.join("Lithographs");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("stone.txt"), b"limestone").unwrap();
+        let request = ArchiveRequest {
+            name: "Lithographs".to_string(),
+            description: None,
+            files: vec![source.to_string()],
+            environment_profile_ids: vec![],
+        };
+        let project = vault.archive_project(&request).await.unwrap();
+        let options = BagOptions {
+            bag_info_fields: vec![
+                BagInfoField {
+                    label: "Contact-Email".to_string(),
+                    value: "archives@example.edu".to_string(),
+                },
+                BagInfoField {
+                    label: "External-Description".to_string(),
+                    value: "Senior thesis prints".to_string(),
+                },
+            ],
+            profile_path: Some(profile_path),
+        };
+        let result = vault.create_bag(&project.id, options).await.unwrap();
+        assert!(result.success, "{:?}", result.validation_results);
+
+        let bag_info = BagItPackage::open(vault.bag_root(&project).unwrap())
+            .unwrap()
+            .read_bag_info()
+            .unwrap();
+        assert_eq!(bag_info.get_all("Source-Organization"), vec!["Example Art School Library"]);
+        assert_eq!(bag_info.get_all("External-Description"), vec!["Senior thesis prints"]);
+    }
+
+    #[tokio::test]
+    async fn test_watch_folder_ingests_settled_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let vault = test_vault(root).await;
+        let inbox = root.join("inbox");
+        fs::create_dir_all(inbox.join("Studio Week 3")).unwrap();
+        fs::write(inbox.join("Studio Week 3/notes.txt"), b"first pass").unwrap();
+
+        let folder = vault.add_watch_folder(&inbox, Some(0), true).await.unwrap();
+        assert!(matches!(
+            vault.add_watch_folder(&inbox, None, true).await,
+            Err(Error::InvalidState(_))
+        ));
+
+        let mut tracker = WatchTracker::default();
+        let first = vault.poll_watch_folders(&mut tracker).await.unwrap();
+        assert_eq!(first.len(), 1);
+        let first_id = first[0].project_id.clone().unwrap();
+        assert!(vault.bag_root(&vault.get_project(&first_id).await.unwrap()).unwrap().join("bagit.txt").exists());
+        assert!(vault.poll_watch_folders(&mut tracker).await.unwrap().is_empty());
+
+        // A restart must not ingest unchanged work again
+        let mut fresh_tracker = WatchTracker::default();
+        assert!(vault.poll_watch_folders(&mut fresh_tracker).await.unwrap().is_empty());
+
+        fs::write(inbox.join("Studio Week 3/notes.txt"), b"second pass, longer").unwrap();
+        let second = vault.poll_watch_folders(&mut tracker).await.unwrap();
+        assert_eq!(second[0].parent_project_id.as_deref(), Some(first_id.as_str()));
+
+        let events = vault.project_events(second[0].project_id.as_ref().unwrap()).await.unwrap();
+        assert!(events.iter().any(|e| e.event_type == "WatchFolderIngested"
+            && e.payload["watch_folder_id"] == folder.id.as_str()));
+
+        vault.remove_watch_folder(&folder.id).await.unwrap();
+        assert!(vault.list_watch_folders().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_watch_folder_retries_entries_whose_bag_failed() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let vault = test_vault(root).await;
+        let inbox = root.join("inbox");
+        fs::create_dir_all(inbox.join("Residency")).unwrap();
+        fs::write(inbox.join("Residency/log.txt"), b"day one").unwrap();
+        vault.add_watch_folder(&inbox, Some(0), true).await.unwrap();
+
+        // A file where the bags directory should be makes every bag fail
+        fs::write(root.join("bags"), b"in the way").unwrap();
+        let mut tracker = WatchTracker::default();
+        let failed = vault.poll_watch_folders(&mut tracker).await.unwrap();
+        assert!(failed[0].error.is_some());
+        assert!(vault.list_projects().await.unwrap().is_empty());
+
+        fs::remove_file(root.join("bags")).unwrap();
+        let retried = vault.poll_watch_folders(&mut tracker).await.unwrap();
+        assert_eq!(retried.len(), 1);
+        assert!(retried[0].error.is_none());
+        assert_eq!(vault.list_projects().await.unwrap().len(), 1);
+        assert!(vault.poll_watch_folders(&mut tracker).await.unwrap().is_empty());
+    }
+
+
+    #[tokio::test]
+    async fn test_bag_update_is_recorded_as_a_version() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let vault = test_vault(root).await;
+
+        let source = root.join("Zine");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("cover.txt"), b"cover").unwrap();
+        fs::write(root.join("back.txt"), b"back cover").unwrap();
+        let request = ArchiveRequest {
+            name: "Zine".to_string(),
+            description: None,
+            files: vec![source.to_string()],
+            environment_profile_ids: vec![],
+        };
+        let project = vault.archive_project(&request).await.unwrap();
+        vault.create_bag(&project.id, BagOptions::default()).await.unwrap();
+
+        let changes = vec![
+            PayloadChange::Add {
+                source_path: root.join("back.txt").to_string(),
+                payload_path: "back.txt".to_string(),
+            },
+            PayloadChange::Remove {
+                payload_path: "cover.txt".to_string(),
+            },
+        ];
+        let updated = vault.update_bag(&project.id, &changes).await.unwrap();
+        assert!(updated.success);
+        let bag_root = Utf8PathBuf::from(updated.bag_path.unwrap());
+        assert!(bag_root.join("data/back.txt").is_file() && !bag_root.join("data/cover.txt").exists());
+        assert_eq!(vault.search("back", None).await.unwrap()[0].project_id, project.id);
+
+        let events = vault.project_events(&project.id).await.unwrap();
+        let updated_event = events.iter().find(|e| e.event_type == "BagUpdated").unwrap();
+        assert_eq!(updated_event.payload["version"], 2);
+        assert!(matches!(vault.update_bag(&project.id, &[]).await, Err(Error::InvalidState(_))));
+    }
+
+    #[tokio::test]
+    async fn test_previews_are_generated_into_the_derivatives_root() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let vault = test_vault(root).await;
+
+        let source = root.join("Sketches");
+        fs::create_dir_all(&source).unwrap();
+        image::RgbImage::new(32, 16).save(source.join("study.png")).unwrap();
+        fs::write(source.join("notes.txt"), b"notes").unwrap();
+        let request = ArchiveRequest {
+            name: "Sketches".to_string(),
+            description: None,
+            files: vec![source.to_string()],
+            environment_profile_ids: vec![],
+        };
+        let project = vault.archive_project(&request).await.unwrap();
+        vault.create_bag(&project.id, BagOptions::default()).await.unwrap();
+
+        let previews = vault.project_previews(&project.id).await.unwrap().previews;
+        assert_eq!(previews.len(), 1);
+        assert_eq!(previews[0].path, "data/study.png");
+        assert!(previews[0].thumbnail_path.starts_with(root.join("derivatives").as_str()));
+        assert!(Utf8Path::new(&previews[0].preview_path).is_file());
+    }
+
+    #[tokio::test]
+    async fn test_missing_linked_files_are_pulled_into_the_bag() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let vault = test_vault(root).await;
+
+        let source = root.join("Poster");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("poster.svg"), r#"<svg><image href="plate.tif"/></svg>"#).unwrap();
+        let scans = root.join("scans");
+        fs::create_dir_all(&scans).unwrap();
+        fs::write(scans.join("plate.tif"), b"plate").unwrap();
+        let request = ArchiveRequest {
+            name: "Poster".to_string(),
+            description: None,
+            files: vec![source.to_string()],
+            environment_profile_ids: vec![],
+        };
+        let project = vault.archive_project(&request).await.unwrap();
+        vault.create_bag(&project.id, BagOptions::default()).await.unwrap();
+
+        let report = vault.analyze_dependencies(&project.id).await.unwrap();
+        assert_eq!((report.dependencies.len(), report.missing_count), (1, 1));
+
+        let nothing_found = vault.include_linked_files(&project.id, &[root.join("empty")]).await.unwrap();
+        assert!(nothing_found.success);
+        let included = vault.include_linked_files(&project.id, &[scans]).await.unwrap();
+        assert!(included.success);
+        assert_eq!(vault.analyze_dependencies(&project.id).await.unwrap().missing_count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_collections_keep_bag_group_identifiers_in_step() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let vault = test_vault(root).await;
+
+        let source = root.join("Thesis");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("chapter.txt"), b"chapter one").unwrap();
+        let request = ArchiveRequest {
+            name: "Thesis".to_string(),
+            description: None,
+            files: vec![source.to_string()],
+            environment_profile_ids: vec![],
+        };
+        let project = vault.archive_project(&request).await.unwrap();
+
+        let course = vault.create_collection("Course", None, None).await.unwrap();
+        let term = vault.create_collection("Fall", None, Some(&course.id)).await.unwrap();
+        vault.add_project_to_collection(&term.id, &project.id, None).await.unwrap();
+
+        // Filed before bagging, so the new bag picks up its collection path
+        let bag_path = vault.create_bag(&project.id, BagOptions::default()).await.unwrap().bag_path.unwrap();
+        let bag = BagItPackage::open(Utf8PathBuf::from(bag_path)).unwrap();
+        assert_eq!(bag.read_bag_info().unwrap().get_all(BAG_GROUP_IDENTIFIER), vec!["Course/Fall"]);
+
+        vault.move_collection(&term.id, None, Some(0)).await.unwrap();
+        assert_eq!(bag.read_bag_info().unwrap().get_all(BAG_GROUP_IDENTIFIER), vec!["Fall"]);
+        assert!(bag.validate().await.unwrap().is_empty());
+        assert!(matches!(
+            vault.move_collection(&course.id, Some(&course.id), None).await,
+            Err(Error::InvalidState(_))
+        ));
+
+        let exported = vault
+            .export_collection(&term.id, SerializationFormat::Zip, &root.join("out"))
+            .await
+            .unwrap();
+        assert_eq!(exported.record_count, 1);
+        assert!(root.join("out/Fall/collection.json").is_file());
+
+        vault.remove_project_from_collection(&term.id, &project.id).await.unwrap();
+        assert!(bag.read_bag_info().unwrap().get_all(BAG_GROUP_IDENTIFIER).is_empty());
+        let trees = vault.list_collections().await.unwrap();
+        assert_eq!(trees.iter().map(|t| t.collection.name.as_str()).collect::<Vec<_>>(), ["Fall", "Course"]);
+    }
+
+    #[tokio::test]
+    async fn test_environment_profiles_are_attached_at_archive_time() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let vault = test_vault(root).await;
+
+        let source = root.join("Render");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("scene.blend"), b"scene").unwrap();
+        let component = EnvironmentComponent {
+            component_type: "software".to_string(),
+            name: "Blender".to_string(),
+            version: Some("4.1".to_string()),
+        };
+        let profile = vault
+            .create_environment_profile("Studio PC", None, Some("Linux"), None, vec![component])
+            .await
+            .unwrap();
+
+        let mut request = ArchiveRequest {
+            name: "Render".to_string(),
+            description: None,
+            files: vec![source.to_string()],
+            environment_profile_ids: vec!["missing".to_string()],
+        };
+        assert!(matches!(vault.archive_project(&request).await, Err(Error::NotFound(_))));
+        assert!(vault.list_projects().await.unwrap().is_empty());
+
+        request.environment_profile_ids = vec![profile.id.clone()];
+        let project = vault.archive_project(&request).await.unwrap();
+        assert_eq!(vault.project_environment(&project.id).await.unwrap()[0].id, profile.id);
+
+        let bag_path = vault.create_bag(&project.id, BagOptions::default()).await.unwrap().bag_path.unwrap();
+        let environment_file = Utf8PathBuf::from(bag_path).join("environment.json");
+        assert!(fs::read_to_string(&environment_file).unwrap().contains("Blender"));
+
+        let remaining = vault.detach_environment_profile(&project.id, &profile.id).await.unwrap();
+        assert!(remaining.is_empty() && !environment_file.exists());
+        vault.attach_environment_profile(&project.id, &profile.id).await.unwrap();
+        assert!(environment_file.is_file());
+    }
+
+    #[tokio::test]
+    async fn test_tags_and_controlled_vocabularies() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let vault = test_vault(root).await;
+
+        let source = root.join("Etchings");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("plate.txt"), b"plate notes").unwrap();
+        let request = ArchiveRequest {
+            name: "Etchings".to_string(),
+            description: None,
+            files: vec![source.to_string()],
+            environment_profile_ids: vec![],
+        };
+        let project = vault.archive_project(&request).await.unwrap();
+        vault.create_bag(&project.id, BagOptions::default()).await.unwrap();
+
+        let medium = vault
+            .create_vocabulary("Medium", None, true, &["Intaglio".to_string(), "intaglio".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(vault.list_tags(Some(&medium.id)).await.unwrap().len(), 1);
+        assert!(matches!(
+            vault.tag_project(&project.id, "Watercolour", Some(&medium.id), None).await,
+            Err(Error::InvalidState(_))
+        ));
+
+        let term = vault.tag_project(&project.id, " intaglio ", Some(&medium.id), None).await.unwrap();
+        assert_eq!((term.name.as_str(), term.usage_count), ("Intaglio", 1));
+        vault.tag_project(&project.id, "drypoint", None, Some("data/plate.txt")).await.unwrap();
+        assert!(matches!(
+            vault.tag_project(&project.id, "drypoint", None, Some("data/missing.txt")).await,
+            Err(Error::NotFound(_))
+        ));
+        assert_eq!(vault.project_tags(&project.id).await.unwrap().len(), 2);
+        assert_eq!(vault.search("drypoint", None).await.unwrap()[0].project_id, project.id);
+
+        let query = ProjectQuery {
+            tag_ids: vec![term.id.clone()],
+            formats: vec!["image/tiff".to_string()],
+            ..ProjectQuery::default()
+        };
+        let page = vault.query_projects(&query).await.unwrap();
+        assert_eq!(page.total_count, 0);
+        // Each facet ignores its own filter, so the format facet still counts the tagged project
+        assert_eq!(page.facets.formats.iter().map(|f| f.count).sum::<i64>(), 1);
+        let page = vault
+            .query_projects(&ProjectQuery {
+                formats: vec![],
+                ..query
+            })
+            .await
+            .unwrap();
+        assert_eq!((page.total_count, page.projects[0].id.as_str()), (1, project.id.as_str()));
+        assert_eq!(page.facets.tags.len(), 2);
+
+        vault.untag_project(&project.id, &term.id, None).await.unwrap();
+        assert_eq!(vault.list_tags(Some(&medium.id)).await.unwrap()[0].usage_count, 0);
+        assert!(matches!(vault.untag_project(&project.id, &term.id, None).await, Err(Error::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_delta_version_references_unchanged_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let vault = test_vault(root).await;
+
+        let source = root.join("Thesis");
+        fs::create_dir_all(source.join("chapters")).unwrap();
+        fs::write(source.join("chapters/one.txt"), b"chapter one").unwrap();
+        fs::write(source.join("notes.txt"), b"notes").unwrap();
+        let request = ArchiveRequest {
+            name: "Thesis".to_string(),
+            description: None,
+            files: vec![source.to_string()],
+            environment_profile_ids: vec![],
+        };
+        let first = vault.archive_project(&request).await.unwrap();
+        vault.create_bag(&first.id, BagOptions::default()).await.unwrap();
+        assert!(!vault.detect_source_changes(&first.id).await.unwrap().has_changes);
+        assert!(matches!(vault.create_delta_version(&first.id).await, Err(Error::InvalidState(_))));
+
+        fs::write(source.join("chapters/two.txt"), b"chapter two").unwrap();
+        fs::write(source.join("notes.txt"), b"revised notes").unwrap();
+        let changes = vault.detect_source_changes(&first.id).await.unwrap();
+        assert_eq!(changes.added, vec!["chapters/two.txt"]);
+        assert_eq!(changes.changed, vec!["notes.txt"]);
+        assert_eq!(changes.unchanged_count, 1);
+
+        let delta = vault.create_delta_version(&first.id).await.unwrap();
+        assert!(delta.bag.success);
+        let second = vault.get_project(&delta.project_id).await.unwrap();
+        assert_eq!(second.file_count, 3);
+        let bag = BagItPackage::open(vault.bag_root(&second).unwrap()).unwrap();
+        assert!(bag.data_dir.join("chapters/two.txt").is_file());
+        assert!(!bag.data_dir.join("chapters/one.txt").exists());
+        assert_eq!(bag.read_fetch().unwrap()[0].path, "data/chapters/one.txt");
+        assert_eq!(bag.read_manifest().unwrap().len(), 3);
+        assert!(vault.scan_integrity().await.unwrap().is_healthy);
+
+        let history = vault.project_history(&second.id).await.unwrap();
+        assert_eq!(history.project_id, first.id);
+        assert_eq!(history.children[0].project_id, second.id);
+        let diff = vault.diff_versions(&first.id, &second.id).await.unwrap();
+        assert_eq!(diff.added, vec!["data/chapters/two.txt"]);
+        assert_eq!((diff.changed, diff.unchanged_count), (vec!["data/notes.txt".to_string()], 1));
+        assert!(matches!(
+            vault.link_projects(&second.id, &first.id, "version", None).await,
+            Err(Error::InvalidState(_))
+        ));
+        vault.unlink_projects(&first.id, &second.id).await.unwrap();
+        assert!(vault.project_history(&second.id).await.unwrap().children.is_empty());
+        vault.link_projects(&first.id, &second.id, "version", Some("Revised")).await.unwrap();
+
+        // The new version is the baseline for the next comparison
+        assert!(!vault.detect_source_changes(&second.id).await.unwrap().has_changes);
+        fs::remove_file(source.join("chapters/two.txt")).unwrap();
+        let third = vault.create_delta_version(&second.id).await.unwrap();
+        assert_eq!(third.changes.removed, vec!["chapters/two.txt"]);
+        let third_project = vault.get_project(&third.project_id).await.unwrap();
+        let bag = BagItPackage::open(vault.bag_root(&third_project).unwrap()).unwrap();
+        // Unchanged files point at the bag that actually holds them, not at another reference
+        let fetch = bag.read_fetch().unwrap();
+        let first_bag = vault.bag_root(&first).unwrap();
+        let one = fetch.iter().find(|e| e.path == "data/chapters/one.txt").unwrap();
+        assert!(one.url.contains(first_bag.file_name().unwrap()));
+
+        // A file kept elsewhere joins the payload as a reference until it is fetched
+        fs::write(root.join("scan.tif"), b"large scan").unwrap();
+        let added = vault
+            .add_external_reference(&first.id, &root.join("scan.tif"), "scans/scan.tif")
+            .await
+            .unwrap();
+        assert!(added.success);
+        assert!(vault.validate_project(&first.id).await.unwrap().success);
+        let completed = vault.complete_fetch(&first.id).await.unwrap();
+        assert!(completed.success);
+        assert_eq!(fs::read(first_bag.join("data/scans/scan.tif")).unwrap(), b"large scan");
+        let validated = vault.validate_project(&first.id).await.unwrap();
+        assert_eq!(validated.validation_results.unwrap()[0].result_type, "info");
+    }
+
+    #[tokio::test]
+    async fn test_failed_delta_version_leaves_nothing_behind() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let vault = test_vault(root).await;
+
+        let source = root.join("Score");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("part-a.txt"), b"part a").unwrap();
+        fs::write(source.join("part-b.txt"), b"part b").unwrap();
+        let request = ArchiveRequest {
+            name: "Score".to_string(),
+            description: None,
+            files: vec![source.to_string()],
+            environment_profile_ids: vec![],
+        };
+        let first = vault.archive_project(&request).await.unwrap();
+        vault.create_bag(&first.id, BagOptions::default()).await.unwrap();
+
+        // The unchanged file can no longer be referenced from the previous version
+        fs::remove_file(vault.bag_root(&first).unwrap().join("data/part-a.txt")).unwrap();
+        fs::write(source.join("part-b.txt"), b"part b, revised").unwrap();
+        assert!(vault.create_delta_version(&first.id).await.is_err());
+
+        assert_eq!(vault.list_projects().await.unwrap().len(), 1);
+        assert!(vault.project_history(&first.id).await.unwrap().children.is_empty());
+        assert_eq!(fs::read_dir(root.join("bags")).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_replicas_are_verified_and_repaired() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let vault = test_vault(root).await;
+        fs::create_dir_all(root.join("drive-a")).unwrap();
+        fs::create_dir_all(root.join("drive-b")).unwrap();
+        let drive_a = vault.add_replication_target("Drive A", &root.join("drive-a")).await.unwrap();
+        vault.add_replication_target("Drive B", &root.join("drive-b")).await.unwrap();
+        assert!(matches!(
+            vault.add_replication_target("Again", &root.join("drive-a")).await,
+            Err(Error::InvalidState(_))
+        ));
+
+        fs::write(root.join("score.txt"), b"movement one").unwrap();
+        let request = ArchiveRequest {
+            name: "Score".to_string(),
+            description: None,
+            files: vec![root.join("score.txt").to_string()],
+            environment_profile_ids: vec![],
+        };
+        let project = vault.archive_project(&request).await.unwrap();
+        vault.create_bag(&project.id, BagOptions::default()).await.unwrap();
+
+        // Bagging copies the bag to every target
+        let replicas = vault.replica_statuses(Some(&project.id)).await.unwrap();
+        assert_eq!(replicas.len(), 2);
+        assert!(replicas.iter().all(|r| r.status == "healthy"));
+        let on_a = replicas.iter().find(|r| r.target_id == drive_a.id).unwrap();
+        let on_a = Utf8PathBuf::from(&on_a.replica_path);
+
+        // Freshly verified replicas are not due yet
+        assert!(vault.verify_replicas(true).await.unwrap().is_empty());
+        fs::write(on_a.join("data/score.txt"), b"bit rot").unwrap();
+        let verified = vault.verify_replicas(false).await.unwrap();
+        let damaged: Vec<_> = verified.iter().filter(|r| r.status == "corrupted").collect();
+        assert_eq!(damaged.len(), 1);
+        assert_eq!(damaged[0].target_id, drive_a.id);
+
+        let repairs = vault.repair_project(&project.id).await.unwrap();
+        assert_eq!(repairs.len(), 1);
+        assert!(repairs[0].success);
+        assert_eq!(repairs[0].repaired_files, vec!["data/score.txt"]);
+        assert_eq!(fs::read(on_a.join("data/score.txt")).unwrap(), b"movement one");
+
+        // The vault's own copy is repaired from a replica too
+        let bag_root = vault.bag_root(&project).unwrap();
+        fs::remove_dir_all(&bag_root).unwrap();
+        let repairs = vault.repair_project(&project.id).await.unwrap();
+        assert_eq!(repairs[0].repaired_path, bag_root.as_str());
+        assert!(vault.validate_project(&project.id).await.unwrap().success);
+    }
+
+    #[tokio::test]
+    async fn test_replication_failure_does_not_fail_the_bag() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let vault = test_vault(root).await;
+
+        // A target the vault cannot open a backend for fails before anything is copied
+        let mut target = ReplicationTarget::new("Tape".to_string(), "tape://library".to_string());
+        target.kind = "tape".to_string();
+        vault.store.insert_replication_target(&target).unwrap();
+
+        let source = root.join("Mural");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("wall.txt"), b"north wall").unwrap();
+        let request = ArchiveRequest {
+            name: "Mural".to_string(),
+            description: None,
+            files: vec![source.to_string()],
+            environment_profile_ids: vec![],
+        };
+        let project = vault.archive_project(&request).await.unwrap();
+        assert!(vault.create_bag(&project.id, BagOptions::default()).await.unwrap().success);
+
+        let replicas = vault.replica_statuses(Some(&project.id)).await.unwrap();
+        assert_eq!((replicas.len(), replicas[0].status.as_str()), (1, "unavailable"));
+        assert!(replicas[0].last_error.as_deref().unwrap().contains("tape"));
+    }
+
+    #[cfg(feature = "s3")]
+    #[tokio::test]
+    async fn test_replication_to_object_storage() {
+        use crate::utils::s3::tests::{config, serve_stand_in};
+
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let vault = test_vault(root).await;
+        let (endpoint, objects) = serve_stand_in().await;
+        // The secret key is only ever stored encrypted
+        assert!(vault.add_s3_replication_target("Library S3", config(&endpoint)).await.is_err());
+        vault.set_up_encryption("correct horse battery").await.unwrap();
+        let target = vault.add_s3_replication_target("Library S3", config(&endpoint)).await.unwrap();
+        assert_eq!((target.kind.as_str(), target.path.as_str()), ("s3", "s3://archive/cwpt"));
+        let listed = serde_json::to_string(&vault.list_replication_targets().await.unwrap()).unwrap();
+        assert!(!listed.contains("test-secret"));
+        assert!(!target.settings.as_deref().unwrap().contains("test-secret"));
+        assert!(!format!("{:?}", config(&endpoint)).contains("test-secret"));
+
+        fs::write(root.join("film.txt"), b"reel one").unwrap();
+        let request = ArchiveRequest {
+            name: "Film".to_string(),
+            description: None,
+            files: vec![root.join("film.txt").to_string()],
+            environment_profile_ids: vec![],
+        };
+        let project = vault.archive_project(&request).await.unwrap();
+        vault.create_bag(&project.id, BagOptions::default()).await.unwrap();
+        let bag_root = vault.bag_root(&project).unwrap();
+        let key = format!("cwpt/{}/data/film.txt", bag_root.file_name().unwrap());
+
+        let replicas = vault.replica_statuses(Some(&project.id)).await.unwrap();
+        assert_eq!(replicas[0].status, "healthy");
+        assert!(replicas[0].replica_path.starts_with("s3://archive/cwpt/"));
+        assert_eq!(objects.lock().unwrap().get(&key).unwrap(), b"reel one");
+
+        objects.lock().unwrap().insert(key.clone(), b"truncated".to_vec());
+        assert_eq!(vault.verify_replicas(false).await.unwrap()[0].status, "corrupted");
+        let repairs = vault.repair_project(&project.id).await.unwrap();
+        assert_eq!(repairs[0].repaired_files, vec!["data/film.txt"]);
+        assert_eq!(objects.lock().unwrap().get(&key).unwrap(), b"reel one");
+
+        // A lost vault copy comes back from the bucket
+        fs::remove_dir_all(&bag_root).unwrap();
+        let repairs = vault.repair_project(&project.id).await.unwrap();
+        assert!(repairs[0].success);
+        assert!(vault.validate_project(&project.id).await.unwrap().success);
+
+        // Without the keys the bucket cannot be reached
+        vault.lock_encryption().await;
+        let replicas = vault.replicate_project(&project.id).await.unwrap();
+        assert_eq!(replicas[0].status, "unavailable");
+        assert!(replicas[0].last_error.as_deref().unwrap().contains("Encryption is locked"));
+    }
+
+    #[cfg(feature = "webdav")]
+    #[tokio::test]
+    async fn test_webdav_replica_resumes_and_reports_divergence() {
+        use crate::utils::webdav::tests::{config, serve_stand_in};
+
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let vault = test_vault(root).await;
+        let (url, share) = serve_stand_in().await;
+        vault.set_up_encryption("correct horse battery").await.unwrap();
+        let target = vault.add_webdav_replication_target("School Nextcloud", config(&url)).await.unwrap();
+        assert_eq!(target.kind, "webdav");
+        assert!(!target.settings.as_deref().unwrap().contains("app-password"));
+
+        fs::write(root.join("poster.txt"), b"spring show").unwrap();
+        let request = ArchiveRequest {
+            name: "Poster".to_string(),
+            description: None,
+            files: vec![root.join("poster.txt").to_string()],
+            environment_profile_ids: vec![],
+        };
+        let project = vault.archive_project(&request).await.unwrap();
+        let bag_name = vault.bag_root(&project).unwrap().file_name().unwrap().to_string();
+        let key = |path: &str| format!("{}/{}", bag_name, path);
+
+        // The connection drops before bagit.txt, so the replica is incomplete
+        share.fail_next_upload.lock().unwrap().insert(key("bagit.txt"));
+        vault.create_bag(&project.id, BagOptions::default()).await.unwrap();
+        let replicas = vault.replica_statuses(Some(&project.id)).await.unwrap();
+        assert_eq!(replicas[0].status, "unavailable");
+        assert!(share.uploads.lock().unwrap().contains(&key("data/poster.txt")));
+
+        // Resuming uploads only what did not make it
+        share.uploads.lock().unwrap().clear();
+        assert_eq!(vault.replicate_all().await.unwrap()[0].status, "healthy");
+        assert_eq!(*share.uploads.lock().unwrap(), vec![key("bagit.txt")]);
+
+        // Same size, different content: only the etag gives it away
+        share.files.lock().unwrap().insert(key("data/poster.txt"), b"spring snow".to_vec());
+        let verified = vault.verify_replicas(false).await.unwrap();
+        assert_eq!(verified[0].status, "corrupted");
+        assert!(verified[0].last_error.as_deref().unwrap().contains("changed since upload: data/poster.txt"));
+
+        let report = vault.scan_integrity().await.unwrap();
+        assert!(report.is_healthy);
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!(report.issues[0].issue_type, "replica_divergence");
+
+        let repairs = vault.repair_project(&project.id).await.unwrap();
+        assert_eq!(repairs[0].repaired_files, vec!["data/poster.txt"]);
+        assert_eq!(share.files.lock().unwrap()[&key("data/poster.txt")], b"spring show");
+        assert!(vault.scan_integrity().await.unwrap().issues.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_replicas_and_exports() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let vault = test_vault(root).await;
+        assert!(vault.set_up_encryption("short").await.is_err());
+        let status = vault.set_up_encryption("correct horse battery").await.unwrap();
+        assert!(status.is_unlocked && !status.has_recovery_key);
+
+        fs::create_dir(root.join("drive")).unwrap();
+        let drive = vault.add_replication_target("Drive", &root.join("drive")).await.unwrap();
+        assert!(vault.set_replication_target_encrypted(&drive.id, true).await.unwrap().encrypted);
+
+        fs::write(root.join("letter.txt"), b"dear committee").unwrap();
+        let request = ArchiveRequest {
+            name: "Letter".to_string(),
+            description: None,
+            files: vec![root.join("letter.txt").to_string()],
+            environment_profile_ids: vec![],
+        };
+        let project = vault.archive_project(&request).await.unwrap();
+        vault.create_bag(&project.id, BagOptions::default()).await.unwrap();
+        let bag_root = vault.bag_root(&project).unwrap();
+        let replica = root.join("drive").join(bag_root.file_name().unwrap());
+        assert_eq!(vault.replica_statuses(Some(&project.id)).await.unwrap()[0].status, "healthy");
+        assert!(!fs::read(replica.join("data/letter.txt")).unwrap().windows(4).any(|w| w == b"dear"));
+
+        // Locked, the replica cannot be checked; a wrong passphrase does not unlock it
+        vault.lock_encryption().await;
+        assert_eq!(vault.verify_replicas(false).await.unwrap()[0].status, "unavailable");
+        assert!(matches!(vault.unlock_encryption("wrong horse battery").await, Err(Error::WrongPassphrase)));
+        vault.unlock_encryption("correct horse battery").await.unwrap();
+        assert_eq!(vault.verify_replicas(false).await.unwrap()[0].status, "healthy");
+
+        // Rotation rewrites the replica under the new key
+        let rotated = vault.rotate_encryption_key().await.unwrap();
+        assert_eq!(rotated[0].status, "healthy");
+        let status = vault.encryption_status().await.unwrap();
+        assert_eq!(status.key_count, 2);
+        let objects = vault.store.get_replica_objects(&project.id, &drive.id).unwrap();
+        assert!(objects.iter().all(|o| o.key_id == status.current_key_id));
+
+        // A forgotten passphrase is replaced with the recovery key
+        vault.export_recovery_key(&root.join("recovery.txt")).await.unwrap();
+        assert!(vault.export_recovery_key(&root.join("recovery.txt")).await.is_err());
+        let recovery_key = fs::read_to_string(root.join("recovery.txt")).unwrap().lines().nth(2).unwrap().to_string();
+        vault.lock_encryption().await;
+        vault.recover_encryption(&recovery_key, "new horse battery").await.unwrap();
+        assert!(vault.unlock_encryption("correct horse battery").await.is_err());
+        vault.unlock_encryption("new horse battery").await.unwrap();
+
+        // The vault's copy comes back decrypted from the replica
+        fs::remove_dir_all(&bag_root).unwrap();
+        assert!(vault.repair_project(&project.id).await.unwrap()[0].success);
+        assert_eq!(fs::read(bag_root.join("data/letter.txt")).unwrap(), b"dear committee");
+
+        let exported = vault.export_encrypted_bag(&project.id, ExportFormat::Tar, root).await.unwrap();
+        let encrypted = Utf8PathBuf::from(exported.output_path.unwrap());
+        assert!(encrypted.as_str().ends_with(".tar.cwptenc"));
+        assert!(!root.join(encrypted.file_stem().unwrap()).exists());
+        let decrypted = root.join("letter.tar");
+        vault.decrypt_file(&encrypted, &decrypted).await.unwrap();
+        assert!(vault.validate_path(&decrypted, None).await.unwrap().success);
+    }
+
+    #[tokio::test]
+    async fn test_quarantine_restore_and_scan() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let vault = test_vault(root).await;
+
+        fs::write(root.join("sketch.txt"), b"first draft").unwrap();
+        let request = ArchiveRequest {
+            name: "Sketch".to_string(),
+            description: None,
+            files: vec![root.join("sketch.txt").to_string()],
+            environment_profile_ids: vec![],
+        };
+        let project = vault.archive_project(&request).await.unwrap();
+        vault.create_bag(&project.id, BagOptions::default()).await.unwrap();
+        let bag_root = vault.bag_root(&project).unwrap();
+
+        let entry = vault.quarantine_project(&project.id, "duplicate").await.unwrap();
+        assert_eq!(entry.original_bag_path, bag_root.as_str());
+        assert!(!bag_root.exists());
+        assert!(root.join("quarantine").join(bag_root.file_name().unwrap()).is_dir());
+
+        // A quarantined bag is not expected in the bags layer
+        assert!(vault.scan_integrity().await.unwrap().is_healthy);
+
+        let restored = vault.restore_project(&project.id).await.unwrap();
+        assert!(!restored.is_quarantined);
+        assert!(bag_root.join("data/sketch.txt").is_file());
+
+        fs::write(bag_root.join("data/sketch.txt"), b"overwritten").unwrap();
+        let report = vault.scan_integrity().await.unwrap();
+        assert!(!report.is_healthy);
+        assert_eq!(report.issues[0].issue_type, "corrupted_file");
+    }
+
+    #[cfg(feature = "sqlcipher")]
+    #[tokio::test]
+    async fn test_encrypted_database_unlocks_at_open() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = Utf8Path::from_path(temp_dir.path()).unwrap();
+        let vault = test_vault(root).await;
+        fs::write(root.join("demo.wav"), b"demo").unwrap();
+        let request = ArchiveRequest {
+            name: "Unreleased Album".to_string(),
+            description: None,
+            files: vec![root.join("demo.wav").to_string()],
+            environment_profile_ids: vec![],
+        };
+        let project = vault.archive_project(&request).await.unwrap();
+        assert!(!vault.database_status().await.unwrap().is_encrypted);
+        assert!(matches!(vault.encrypt_database("short").await, Err(Error::InvalidState(_))));
+        vault.encrypt_database("correct horse battery").await.unwrap();
+        drop(vault);
+
+        let vault = test_vault(root).await;
+        let status = vault.database_status().await.unwrap();
+        assert!(status.is_encrypted && status.is_locked);
+        assert!(matches!(vault.unlock_database("wrong horse battery").await, Err(Error::WrongPassphrase)));
+        assert!(!vault.unlock_database("correct horse battery").await.unwrap().is_locked);
+        assert_eq!(vault.get_project(&project.id).await.unwrap().name, "Unreleased Album");
+
+        vault.change_database_passphrase("correct horse battery", "battery staple horse").await.unwrap();
+        let plain = root.join("plain.db");
+        vault.export_unencrypted_database(&plain).await.unwrap();
+        assert!(!crate::store::database_is_encrypted(&plain).unwrap());
+        assert!(matches!(
+            vault.export_unencrypted_database(&plain).await,
+            Err(Error::InvalidState(_))
+        ));
+    }
+}
@@ -0,0 +1,26 @@
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+/// Event the UI listens to for failures of work nobody is waiting on
+pub const BACKGROUND_ERROR_EVENT: &str = "background-error";
+
+/// A failure in one of the app's background tasks
+#[derive(Debug, Clone, Serialize)]
+pub struct BackgroundError {
+    /// The task that failed, e.g. `watch-folders`
+    pub task: String,
+    pub message: String,
+}
+
+/// Tell the UI a background task failed
+///
+/// Falls back to stderr if no window can be reached, so the failure is not lost.
+pub fn report_background_error(app: &AppHandle, task: &str, message: String) {
+    let error = BackgroundError {
+        task: task.to_string(),
+        message,
+    };
+    if app.emit(BACKGROUND_ERROR_EVENT, &error).is_err() {
+        eprintln!("{} failed: {}", error.task, error.message);
+    }
+}
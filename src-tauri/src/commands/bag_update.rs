@@ -0,0 +1,15 @@
+use crate::commands::preservation_simple::PreservationError;
+use crate::models::preservation::BagResult;
+use cwpt_core::utils::bag_update::PayloadChange;
+use cwpt_core::Vault;
+use tauri::State;
+
+/// Add, remove or replace payload files in a project's existing bag
+#[tauri::command]
+pub async fn update_bag_payload(
+    vault: State<'_, Vault>,
+    project_id: String,
+    changes: Vec<PayloadChange>,
+) -> Result<BagResult, PreservationError> {
+    Ok(vault.update_bag(&project_id, &changes).await?)
+}
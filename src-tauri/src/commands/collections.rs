@@ -0,0 +1,84 @@
+use crate::commands::preservation_simple::PreservationError;
+use crate::models::preservation::{Collection, CollectionTree, ExportResult};
+use crate::utils::bag_serialization::SerializationFormat;
+use camino::Utf8PathBuf;
+use cwpt_core::Vault;
+use tauri::State;
+
+/// Create a collection, optionally nested inside another one
+#[tauri::command]
+pub async fn create_collection(
+    vault: State<'_, Vault>,
+    name: String,
+    description: Option<String>,
+    parent_collection_id: Option<String>,
+) -> Result<Collection, PreservationError> {
+    Ok(vault
+        .create_collection(&name, description.as_deref(), parent_collection_id.as_deref())
+        .await?)
+}
+
+/// List all collections as ordered trees with their member projects
+#[tauri::command]
+pub async fn list_collections(vault: State<'_, Vault>) -> Result<Vec<CollectionTree>, PreservationError> {
+    Ok(vault.list_collections().await?)
+}
+
+/// Add a project to a collection at a given position (appended when omitted)
+#[tauri::command]
+pub async fn add_project_to_collection(
+    vault: State<'_, Vault>,
+    collection_id: String,
+    project_id: String,
+    position: Option<usize>,
+) -> Result<(), PreservationError> {
+    Ok(vault.add_project_to_collection(&collection_id, &project_id, position).await?)
+}
+
+/// Remove a project from a collection
+#[tauri::command]
+pub async fn remove_project_from_collection(
+    vault: State<'_, Vault>,
+    collection_id: String,
+    project_id: String,
+) -> Result<(), PreservationError> {
+    Ok(vault.remove_project_from_collection(&collection_id, &project_id).await?)
+}
+
+/// Move a project from one collection to another, or to a new position in the same one
+#[tauri::command]
+pub async fn move_project(
+    vault: State<'_, Vault>,
+    project_id: String,
+    from_collection_id: String,
+    to_collection_id: String,
+    position: Option<usize>,
+) -> Result<(), PreservationError> {
+    Ok(vault
+        .move_project_between_collections(&project_id, &from_collection_id, &to_collection_id, position)
+        .await?)
+}
+
+/// Move a collection under a new parent (None for top level) and/or reorder it
+#[tauri::command]
+pub async fn move_collection(
+    vault: State<'_, Vault>,
+    collection_id: String,
+    new_parent_id: Option<String>,
+    position: Option<usize>,
+) -> Result<(), PreservationError> {
+    Ok(vault.move_collection(&collection_id, new_parent_id.as_deref(), position).await?)
+}
+
+/// Export every bag in a collection and its sub-collections as serialized archives
+#[tauri::command]
+pub async fn export_collection(
+    vault: State<'_, Vault>,
+    collection_id: String,
+    format: SerializationFormat,
+    output_dir: String,
+) -> Result<ExportResult, PreservationError> {
+    Ok(vault
+        .export_collection(&collection_id, format, &Utf8PathBuf::from(output_dir))
+        .await?)
+}
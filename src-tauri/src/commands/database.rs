@@ -0,0 +1,47 @@
+use crate::commands::preservation_simple::PreservationError;
+use crate::models::preservation::DatabaseStatus;
+use camino::Utf8PathBuf;
+use cwpt_core::Vault;
+use tauri::State;
+
+/// Whether the database is encrypted and waiting for its passphrase, which the app asks for at start
+#[tauri::command]
+pub async fn get_database_status(vault: State<'_, Vault>) -> Result<DatabaseStatus, PreservationError> {
+    Ok(vault.database_status().await?)
+}
+
+/// Unlock an encrypted database for as long as the app runs
+#[tauri::command]
+pub async fn unlock_database(
+    vault: State<'_, Vault>,
+    passphrase: String,
+) -> Result<DatabaseStatus, PreservationError> {
+    Ok(vault.unlock_database(&passphrase).await?)
+}
+
+/// Encrypt the database at rest under a passphrase
+#[tauri::command]
+pub async fn encrypt_database(
+    vault: State<'_, Vault>,
+    passphrase: String,
+) -> Result<DatabaseStatus, PreservationError> {
+    Ok(vault.encrypt_database(&passphrase).await?)
+}
+
+#[tauri::command]
+pub async fn change_database_passphrase(
+    vault: State<'_, Vault>,
+    current_passphrase: String,
+    new_passphrase: String,
+) -> Result<(), PreservationError> {
+    Ok(vault.change_database_passphrase(&current_passphrase, &new_passphrase).await?)
+}
+
+/// Write an unencrypted copy of the database to a file the user picked
+#[tauri::command]
+pub async fn export_unencrypted_database(
+    vault: State<'_, Vault>,
+    output_path: String,
+) -> Result<(), PreservationError> {
+    Ok(vault.export_unencrypted_database(&Utf8PathBuf::from(output_path)).await?)
+}
@@ -0,0 +1,28 @@
+use crate::commands::preservation_simple::PreservationError;
+use crate::models::preservation::{BagResult, DependencyReport};
+use camino::Utf8PathBuf;
+use cwpt_core::Vault;
+use tauri::State;
+
+/// Report the fonts and linked files a project's payload needs, flagging those the archive lacks
+#[tauri::command]
+pub async fn analyze_project_dependencies(
+    vault: State<'_, Vault>,
+    project_id: String,
+) -> Result<DependencyReport, PreservationError> {
+    Ok(vault.analyze_dependencies(&project_id).await?)
+}
+
+/// Copy missing linked files into the project's payload from the given folders
+///
+/// Absolute references are copied from where they point when the file still
+/// exists there; everything else is looked up under `search_roots`.
+#[tauri::command]
+pub async fn include_linked_files(
+    vault: State<'_, Vault>,
+    project_id: String,
+    search_roots: Vec<String>,
+) -> Result<BagResult, PreservationError> {
+    let search_roots: Vec<Utf8PathBuf> = search_roots.into_iter().map(Utf8PathBuf::from).collect();
+    Ok(vault.include_linked_files(&project_id, &search_roots).await?)
+}
@@ -0,0 +1,90 @@
+use crate::commands::preservation_simple::PreservationError;
+use crate::models::preservation::{EncryptionStatus, ExportResult, ReplicaStatus};
+use camino::Utf8PathBuf;
+use cwpt_core::{ExportFormat, Vault};
+use tauri::State;
+
+/// Whether encryption is set up and unlocked
+#[tauri::command]
+pub async fn get_encryption_status(vault: State<'_, Vault>) -> Result<EncryptionStatus, PreservationError> {
+    Ok(vault.encryption_status().await?)
+}
+
+/// Set up encryption under a passphrase
+#[tauri::command]
+pub async fn set_up_encryption(
+    vault: State<'_, Vault>,
+    passphrase: String,
+) -> Result<EncryptionStatus, PreservationError> {
+    Ok(vault.set_up_encryption(&passphrase).await?)
+}
+
+/// Unlock encryption for as long as the app runs
+#[tauri::command]
+pub async fn unlock_encryption(
+    vault: State<'_, Vault>,
+    passphrase: String,
+) -> Result<EncryptionStatus, PreservationError> {
+    Ok(vault.unlock_encryption(&passphrase).await?)
+}
+
+/// Forget the encryption keys until unlocked again
+#[tauri::command]
+pub async fn lock_encryption(vault: State<'_, Vault>) -> Result<(), PreservationError> {
+    vault.lock_encryption().await;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn change_encryption_passphrase(
+    vault: State<'_, Vault>,
+    current_passphrase: String,
+    new_passphrase: String,
+) -> Result<(), PreservationError> {
+    Ok(vault.change_encryption_passphrase(&current_passphrase, &new_passphrase).await?)
+}
+
+/// Set a new passphrase with the recovery key
+#[tauri::command]
+pub async fn recover_encryption(
+    vault: State<'_, Vault>,
+    recovery_key: String,
+    new_passphrase: String,
+) -> Result<EncryptionStatus, PreservationError> {
+    Ok(vault.recover_encryption(&recovery_key, &new_passphrase).await?)
+}
+
+/// Write a new recovery key to a file the user picked
+#[tauri::command]
+pub async fn export_recovery_key(vault: State<'_, Vault>, output_path: String) -> Result<(), PreservationError> {
+    Ok(vault.export_recovery_key(&Utf8PathBuf::from(output_path)).await?)
+}
+
+/// Encrypt with a new key and rewrite encrypted replicas under it
+#[tauri::command]
+pub async fn rotate_encryption_key(vault: State<'_, Vault>) -> Result<Vec<ReplicaStatus>, PreservationError> {
+    Ok(vault.rotate_encryption_key().await?)
+}
+
+/// Export a project's bag as an encrypted archive
+#[tauri::command]
+pub async fn export_encrypted_bag(
+    vault: State<'_, Vault>,
+    project_id: String,
+    format: String,
+    output_dir: String,
+) -> Result<ExportResult, PreservationError> {
+    let format = ExportFormat::parse(&format)
+        .ok_or_else(|| PreservationError::InvalidState(format!("Unknown export format: {}", format)))?;
+    Ok(vault.export_encrypted_bag(&project_id, format, &Utf8PathBuf::from(output_dir)).await?)
+}
+
+/// Decrypt an encrypted export
+#[tauri::command]
+pub async fn decrypt_export(
+    vault: State<'_, Vault>,
+    input_path: String,
+    output_path: String,
+) -> Result<(), PreservationError> {
+    Ok(vault.decrypt_file(&Utf8PathBuf::from(input_path), &Utf8PathBuf::from(output_path)).await?)
+}
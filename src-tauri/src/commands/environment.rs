@@ -0,0 +1,60 @@
+use crate::commands::preservation_simple::PreservationError;
+use crate::models::preservation::{EnvironmentComponent, EnvironmentProfile};
+use cwpt_core::Vault;
+use tauri::State;
+
+/// Create a reusable environment profile, e.g. "Studio Mac" with its software, plugins and fonts
+#[tauri::command]
+pub async fn create_environment_profile(
+    vault: State<'_, Vault>,
+    name: String,
+    description: Option<String>,
+    os_name: Option<String>,
+    os_version: Option<String>,
+    components: Vec<EnvironmentComponent>,
+) -> Result<EnvironmentProfile, PreservationError> {
+    Ok(vault
+        .create_environment_profile(
+            &name,
+            description.as_deref(),
+            os_name.as_deref(),
+            os_version.as_deref(),
+            components,
+        )
+        .await?)
+}
+
+/// List all environment profiles
+#[tauri::command]
+pub async fn list_environment_profiles(vault: State<'_, Vault>) -> Result<Vec<EnvironmentProfile>, PreservationError> {
+    Ok(vault.list_environment_profiles().await?)
+}
+
+/// Get the environment profiles attached to a project
+#[tauri::command]
+pub async fn get_project_environment(
+    vault: State<'_, Vault>,
+    project_id: String,
+) -> Result<Vec<EnvironmentProfile>, PreservationError> {
+    Ok(vault.project_environment(&project_id).await?)
+}
+
+/// Attach an environment profile to a project and record it in the project's bag
+#[tauri::command]
+pub async fn attach_environment_profile(
+    vault: State<'_, Vault>,
+    project_id: String,
+    profile_id: String,
+) -> Result<Vec<EnvironmentProfile>, PreservationError> {
+    Ok(vault.attach_environment_profile(&project_id, &profile_id).await?)
+}
+
+/// Detach an environment profile from a project and update the project's bag
+#[tauri::command]
+pub async fn detach_environment_profile(
+    vault: State<'_, Vault>,
+    project_id: String,
+    profile_id: String,
+) -> Result<Vec<EnvironmentProfile>, PreservationError> {
+    Ok(vault.detach_environment_profile(&project_id, &profile_id).await?)
+}
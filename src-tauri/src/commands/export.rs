@@ -0,0 +1,27 @@
+use crate::commands::preservation_simple::PreservationError;
+use crate::models::preservation::ExportResult;
+use camino::Utf8PathBuf;
+use cwpt_core::{ExportFormat, Vault};
+use tauri::State;
+
+/// Export a project's bag as an archive into a directory, or its VRA or PREMIS record to a file
+#[tauri::command]
+pub async fn export_project(
+    vault: State<'_, Vault>,
+    project_id: String,
+    format: String,
+    output_path: String,
+) -> Result<ExportResult, PreservationError> {
+    let format = ExportFormat::parse(&format)
+        .ok_or_else(|| PreservationError::InvalidState(format!("Unknown export format: {}", format)))?;
+    Ok(vault.export_project(&project_id, format, &Utf8PathBuf::from(output_path)).await?)
+}
+
+/// Export every bagged project in the vault as one VRA Core 4.0 XML document
+#[tauri::command]
+pub async fn export_vault_vra_core(
+    vault: State<'_, Vault>,
+    output_path: String,
+) -> Result<ExportResult, PreservationError> {
+    Ok(vault.export_vault_vra(&Utf8PathBuf::from(output_path)).await?)
+}
@@ -0,0 +1,24 @@
+use crate::commands::preservation_simple::PreservationError;
+use crate::models::preservation::BagResult;
+use camino::Utf8PathBuf;
+use cwpt_core::Vault;
+use tauri::State;
+
+/// Add a file on external storage to a project's bag as a fetch.txt reference
+#[tauri::command]
+pub async fn add_external_reference(
+    vault: State<'_, Vault>,
+    project_id: String,
+    source_path: String,
+    payload_path: String,
+) -> Result<BagResult, PreservationError> {
+    Ok(vault
+        .add_external_reference(&project_id, &Utf8PathBuf::from(source_path), &payload_path)
+        .await?)
+}
+
+/// Fetch every missing file listed in a project's fetch.txt from local sources
+#[tauri::command]
+pub async fn complete_holey_bag(vault: State<'_, Vault>, project_id: String) -> Result<BagResult, PreservationError> {
+    Ok(vault.complete_fetch(&project_id).await?)
+}
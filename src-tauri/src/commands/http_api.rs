@@ -0,0 +1,63 @@
+use crate::background::report_background_error;
+use crate::commands::preservation_simple::PreservationError;
+use cwpt_core::http::{HttpServer, DEFAULT_HTTP_PORT};
+use cwpt_core::Vault;
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{AppHandle, State};
+use tokio::sync::oneshot;
+
+/// Shuts down the running local HTTP API, if any
+#[derive(Default)]
+pub struct HttpApiHandle(Mutex<Option<oneshot::Sender<()>>>);
+
+#[derive(Debug, Serialize)]
+pub struct HttpApiInfo {
+    pub url: String,
+    pub token: String,
+}
+
+/// Start the local HTTP API on 127.0.0.1 so scripts can drive the vault
+///
+/// If the server fails later on, the UI is told through a background-error event.
+#[tauri::command]
+pub async fn start_http_api(
+    app: AppHandle,
+    vault: State<'_, Vault>,
+    handle: State<'_, HttpApiHandle>,
+    port: Option<u16>,
+) -> Result<HttpApiInfo, PreservationError> {
+    if handle.0.lock().unwrap().is_some() {
+        return Err(PreservationError::InvalidState("The HTTP API is already running".to_string()));
+    }
+
+    let server = HttpServer::bind(vault.inner().clone(), port.unwrap_or(DEFAULT_HTTP_PORT)).await?;
+    let info = HttpApiInfo {
+        url: server.url()?,
+        token: server.token().to_string(),
+    };
+
+    let (shutdown, stopped) = oneshot::channel();
+    *handle.0.lock().unwrap() = Some(shutdown);
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = server
+            .run(async {
+                let _ = stopped.await;
+            })
+            .await
+        {
+            report_background_error(&app, "http-api", format!("HTTP API stopped: {}", e));
+        }
+    });
+
+    Ok(info)
+}
+
+/// Stop the local HTTP API; does nothing if it is not running
+#[tauri::command]
+pub async fn stop_http_api(handle: State<'_, HttpApiHandle>) -> Result<(), PreservationError> {
+    if let Some(shutdown) = handle.0.lock().unwrap().take() {
+        let _ = shutdown.send(());
+    }
+    Ok(())
+}
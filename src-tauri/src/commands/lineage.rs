@@ -0,0 +1,47 @@
+use crate::commands::preservation_simple::PreservationError;
+use crate::models::preservation::{LineageNode, ProjectRelation, VersionDiff};
+use cwpt_core::Vault;
+use tauri::State;
+
+/// Record that one archived project is a later version or branch of another
+#[tauri::command]
+pub async fn link_project_versions(
+    vault: State<'_, Vault>,
+    parent_project_id: String,
+    child_project_id: String,
+    relation_type: String,
+    label: Option<String>,
+) -> Result<ProjectRelation, PreservationError> {
+    Ok(vault
+        .link_projects(&parent_project_id, &child_project_id, &relation_type, label.as_deref())
+        .await?)
+}
+
+/// Remove the lineage link between two archived projects
+#[tauri::command]
+pub async fn unlink_project_versions(
+    vault: State<'_, Vault>,
+    parent_project_id: String,
+    child_project_id: String,
+) -> Result<(), PreservationError> {
+    Ok(vault.unlink_projects(&parent_project_id, &child_project_id).await?)
+}
+
+/// Get the full history tree that a project belongs to
+#[tauri::command]
+pub async fn get_project_history(
+    vault: State<'_, Vault>,
+    project_id: String,
+) -> Result<LineageNode, PreservationError> {
+    Ok(vault.project_history(&project_id).await?)
+}
+
+/// Compare the payload files of two project versions by checksum
+#[tauri::command]
+pub async fn diff_project_versions(
+    vault: State<'_, Vault>,
+    from_project_id: String,
+    to_project_id: String,
+) -> Result<VersionDiff, PreservationError> {
+    Ok(vault.diff_versions(&from_project_id, &to_project_id).await?)
+}
@@ -1 +1,19 @@
-pub mod preservation_simple;
\ No newline at end of file
+pub mod bag_update;
+pub mod collections;
+pub mod database;
+pub mod dependencies;
+pub mod encryption;
+pub mod environment;
+pub mod export;
+pub mod fetch;
+pub mod http_api;
+pub mod lineage;
+pub mod preservation_simple;
+pub mod previews;
+pub mod profiles;
+pub mod replication;
+pub mod search;
+pub mod serialization;
+pub mod source_changes;
+pub mod tags;
+pub mod watch_folders;
@@ -1,7 +1,8 @@
 use crate::models::preservation::*;
 use anyhow::Result;
-use chrono::Utc;
-use uuid::Uuid;
+use camino::Utf8PathBuf;
+use cwpt_core::{BagOptions, Vault};
+use tauri::State;
 
 #[derive(Debug, thiserror::Error)]
 pub enum PreservationError {
@@ -11,6 +12,23 @@ pub enum PreservationError {
     FileNotFound(String),
     #[error("Invalid project ID: {0}")]
     InvalidProjectId(String),
+    #[error("{0}")]
+    InvalidState(String),
+    #[error("{0}")]
+    Failed(String),
+}
+
+impl From<cwpt_core::Error> for PreservationError {
+    fn from(error: cwpt_core::Error) -> Self {
+        match error {
+            cwpt_core::Error::Io(e) => PreservationError::Io(e),
+            cwpt_core::Error::ProjectNotFound(id) => PreservationError::InvalidProjectId(id),
+            cwpt_core::Error::NotFound(path) => PreservationError::FileNotFound(path),
+            cwpt_core::Error::InvalidState(message) => PreservationError::InvalidState(message),
+            cwpt_core::Error::WrongPassphrase => PreservationError::InvalidState("Wrong passphrase".to_string()),
+            cwpt_core::Error::Failed(e) => PreservationError::Failed(format!("{:#}", e)),
+        }
+    }
 }
 
 impl serde::Serialize for PreservationError {
@@ -25,17 +43,14 @@ impl serde::Serialize for PreservationError {
 /// Archive a complete project (folder or multiple files)
 #[tauri::command]
 pub async fn archive_project(
+    vault: State<'_, Vault>,
     request: ArchiveRequest,
 ) -> Result<ArchiveResult, PreservationError> {
-    println!("Archiving project: {}", request.name);
-    println!("Files to archive: {:?}", request.files);
-
-    // For now, just return a success with a generated ID
-    let project_id = Uuid::new_v4().to_string();
+    let project = vault.archive_project(&request).await?;
 
     Ok(ArchiveResult {
         success: true,
-        project_id: Some(project_id),
+        project_id: Some(project.id),
         error: None,
     })
 }
@@ -43,69 +58,53 @@ pub async fn archive_project(
 /// Create BagIt package from archived project
 #[tauri::command]
 pub async fn create_bagit_package(
+    vault: State<'_, Vault>,
     project_id: String,
+    bag_info_fields: Option<Vec<BagInfoField>>,
+    profile_path: Option<String>,
 ) -> Result<BagResult, PreservationError> {
-    println!("Creating BagIt package for project: {}", project_id);
-
-    Ok(BagResult {
-        success: true,
-        bag_path: Some(format!("/tmp/cwpt-bags/{}", project_id)),
-        validation_results: Some(vec![ValidationResult {
-            result_type: "info".to_string(),
-            message: "BagIt package created successfully".to_string(),
-            file: None,
-        }]),
-        error: None,
-    })
+    let options = BagOptions {
+        bag_info_fields: bag_info_fields.unwrap_or_default(),
+        profile_path: profile_path.map(Utf8PathBuf::from),
+    };
+    Ok(vault.create_bag(&project_id, options).await?)
 }
 
 /// Get all archived projects
 #[tauri::command]
-pub async fn get_archived_projects() -> Result<Vec<ArchivedProject>, PreservationError> {
-    println!("Getting all archived projects");
-
-    // Return a sample project for testing
-    let sample_project = ArchivedProject {
-        id: Uuid::new_v4().to_string(),
-        name: "Sample Project".to_string(),
-        description: Some("A test archived project".to_string()),
-        archived_at: Utc::now(),
-        bagit_package_id: Some(Uuid::new_v4().to_string()),
-        file_count: 5,
-        total_size: 1024000, // 1MB
-        is_quarantined: false,
-        created_at: Utc::now(),
-        updated_at: Utc::now(),
-    };
+pub async fn get_archived_projects(vault: State<'_, Vault>) -> Result<Vec<ArchivedProject>, PreservationError> {
+    Ok(vault.list_projects().await?)
+}
 
-    Ok(vec![sample_project])
+/// Get one page of archived projects with filters, sorting and facet counts
+#[tauri::command]
+pub async fn query_archived_projects(
+    vault: State<'_, Vault>,
+    query: ProjectQuery,
+) -> Result<ProjectPage, PreservationError> {
+    Ok(vault.query_projects(&query).await?)
 }
 
 /// Soft delete (quarantine) an archived project
 #[tauri::command]
 pub async fn quarantine_project(
+    vault: State<'_, Vault>,
     project_id: String,
     reason: String,
 ) -> Result<(), PreservationError> {
-    println!("Quarantining project: {} (reason: {})", project_id, reason);
+    vault.quarantine_project(&project_id, &reason).await?;
     Ok(())
 }
 
 /// Restore from quarantine
 #[tauri::command]
-pub async fn restore_project(project_id: String) -> Result<(), PreservationError> {
-    println!("Restoring project from quarantine: {}", project_id);
+pub async fn restore_project(vault: State<'_, Vault>, project_id: String) -> Result<(), PreservationError> {
+    vault.restore_project(&project_id).await?;
     Ok(())
 }
 
 /// Scan vault integrity
 #[tauri::command]
-pub async fn scan_vault_integrity() -> Result<IntegrityReport, PreservationError> {
-    println!("Scanning vault integrity");
-
-    Ok(IntegrityReport {
-        is_healthy: true,
-        issues: vec![],
-        last_scan_at: Utc::now(),
-    })
-}
\ No newline at end of file
+pub async fn scan_vault_integrity(vault: State<'_, Vault>) -> Result<IntegrityReport, PreservationError> {
+    Ok(vault.scan_integrity().await?)
+}
@@ -0,0 +1,13 @@
+use crate::commands::preservation_simple::PreservationError;
+use crate::models::preservation::ProjectPreviews;
+use cwpt_core::Vault;
+use tauri::State;
+
+/// Get thumbnails and previews of a project's raster images, generating any that are missing
+#[tauri::command]
+pub async fn get_project_previews(
+    vault: State<'_, Vault>,
+    project_id: String,
+) -> Result<ProjectPreviews, PreservationError> {
+    Ok(vault.project_previews(&project_id).await?)
+}
@@ -0,0 +1,24 @@
+use crate::commands::preservation_simple::PreservationError;
+use crate::models::preservation::BagResult;
+use camino::{Utf8Path, Utf8PathBuf};
+use cwpt_core::utils::bagit_profile::BagItProfile;
+use cwpt_core::Vault;
+use tauri::State;
+
+/// Load a BagIt profile from a local JSON file
+#[tauri::command]
+pub async fn load_bagit_profile(profile_path: String) -> Result<BagItProfile, PreservationError> {
+    BagItProfile::load(Utf8Path::new(&profile_path))
+        .map_err(|e| PreservationError::FileNotFound(format!("{}: {}", profile_path, e)))
+}
+
+/// Validate a bag directory or serialized bag against a BagIt profile
+#[tauri::command]
+pub async fn validate_bag_against_profile(
+    vault: State<'_, Vault>,
+    bag_path: String,
+    profile_path: String,
+) -> Result<BagResult, PreservationError> {
+    let profile_path = Utf8PathBuf::from(profile_path);
+    Ok(vault.validate_path(&Utf8PathBuf::from(bag_path), Some(&profile_path)).await?)
+}
@@ -0,0 +1,132 @@
+use crate::background::report_background_error;
+use crate::commands::preservation_simple::PreservationError;
+use crate::models::preservation::{BagRepair, ReplicaStatus, ReplicationTarget};
+use camino::Utf8PathBuf;
+use cwpt_core::utils::s3::S3Config;
+use cwpt_core::utils::webdav::WebDavConfig;
+use cwpt_core::Vault;
+use std::time::Duration;
+use tauri::{AppHandle, State};
+
+/// How often the app looks for replicas due for verification
+const VERIFY_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Add a folder, such as an external drive or NAS mount, that every bag is copied to
+#[tauri::command]
+pub async fn add_replication_target(
+    vault: State<'_, Vault>,
+    name: String,
+    path: String,
+) -> Result<ReplicationTarget, PreservationError> {
+    Ok(vault.add_replication_target(&name, &Utf8PathBuf::from(path)).await?)
+}
+
+/// Add an S3-compatible bucket that every bag is copied to
+#[tauri::command]
+pub async fn add_s3_replication_target(
+    vault: State<'_, Vault>,
+    name: String,
+    config: S3Config,
+) -> Result<ReplicationTarget, PreservationError> {
+    Ok(vault.add_s3_replication_target(&name, config).await?)
+}
+
+/// Add a WebDAV share, such as a school's Nextcloud folder, that every bag is copied to
+#[tauri::command]
+pub async fn add_webdav_replication_target(
+    vault: State<'_, Vault>,
+    name: String,
+    config: WebDavConfig,
+) -> Result<ReplicationTarget, PreservationError> {
+    Ok(vault.add_webdav_replication_target(&name, config).await?)
+}
+
+/// Get all replication targets
+#[tauri::command]
+pub async fn get_replication_targets(vault: State<'_, Vault>) -> Result<Vec<ReplicationTarget>, PreservationError> {
+    Ok(vault.list_replication_targets().await?)
+}
+
+/// Stop replicating to a target
+#[tauri::command]
+pub async fn remove_replication_target(vault: State<'_, Vault>, target_id: String) -> Result<(), PreservationError> {
+    Ok(vault.remove_replication_target(&target_id).await?)
+}
+
+/// Copy bags to targets that do not have them yet, or recopy one project's bag
+#[tauri::command]
+pub async fn replicate_bags(
+    vault: State<'_, Vault>,
+    project_id: Option<String>,
+) -> Result<Vec<ReplicaStatus>, PreservationError> {
+    Ok(match project_id {
+        Some(project_id) => vault.replicate_project(&project_id).await?,
+        None => vault.replicate_all().await?,
+    })
+}
+
+/// Get the recorded status of each replica, optionally for one project
+#[tauri::command]
+pub async fn get_replica_statuses(
+    vault: State<'_, Vault>,
+    project_id: Option<String>,
+) -> Result<Vec<ReplicaStatus>, PreservationError> {
+    Ok(vault.replica_statuses(project_id.as_deref()).await?)
+}
+
+/// Check every replica against its manifest now
+#[tauri::command]
+pub async fn verify_replicas(vault: State<'_, Vault>) -> Result<Vec<ReplicaStatus>, PreservationError> {
+    Ok(vault.verify_replicas(false).await?)
+}
+
+/// Repair a project's damaged bag or replicas from a healthy copy
+#[tauri::command]
+pub async fn repair_project(vault: State<'_, Vault>, project_id: String) -> Result<Vec<BagRepair>, PreservationError> {
+    Ok(vault.repair_project(&project_id).await?)
+}
+
+/// Encrypt a target's replicas from now on, or store them as plain bags again
+#[tauri::command]
+pub async fn set_replication_target_encrypted(
+    vault: State<'_, Vault>,
+    target_id: String,
+    encrypted: bool,
+) -> Result<ReplicationTarget, PreservationError> {
+    Ok(vault.set_replication_target_encrypted(&target_id, encrypted).await?)
+}
+
+/// Verify replicas that are due, and repair damaged ones, for as long as the app runs
+///
+/// Failures of the task itself are reported to the UI; problems with a replica
+/// are recorded in its status.
+pub async fn run_replica_verification(app: AppHandle, vault: Vault) {
+    let mut ticker = tokio::time::interval(VERIFY_CHECK_INTERVAL);
+    loop {
+        ticker.tick().await;
+        if vault.is_database_locked() {
+            continue;
+        }
+        let replicas = match vault.verify_replicas(true).await {
+            Ok(replicas) => replicas,
+            Err(e) => {
+                report_background_error(&app, "replica-verification", format!("Replica verification failed: {}", e));
+                continue;
+            }
+        };
+
+        let mut damaged: Vec<&str> = replicas
+            .iter()
+            .filter(|r| r.status == "corrupted" || r.status == "missing")
+            .map(|r| r.archived_project_id.as_str())
+            .collect();
+        damaged.sort();
+        damaged.dedup();
+        for project_id in damaged {
+            if let Err(e) = vault.repair_project(project_id).await {
+                let message = format!("Failed to repair project {}: {}", project_id, e);
+                report_background_error(&app, "replica-verification", message);
+            }
+        }
+    }
+}
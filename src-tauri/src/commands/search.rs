@@ -0,0 +1,20 @@
+use crate::commands::preservation_simple::PreservationError;
+use crate::models::preservation::SearchHit;
+use cwpt_core::Vault;
+use tauri::State;
+
+/// Search project names, descriptions, metadata, tags, filenames and extracted text
+#[tauri::command]
+pub async fn search_projects(
+    vault: State<'_, Vault>,
+    query: String,
+    limit: Option<u32>,
+) -> Result<Vec<SearchHit>, PreservationError> {
+    Ok(vault.search(&query, limit).await?)
+}
+
+/// Re-index every archived project, e.g. after restoring a database backup
+#[tauri::command]
+pub async fn rebuild_search_index(vault: State<'_, Vault>) -> Result<usize, PreservationError> {
+    Ok(vault.rebuild_search_index().await?)
+}
@@ -0,0 +1,27 @@
+use crate::commands::preservation_simple::PreservationError;
+use crate::models::preservation::BagResult;
+use camino::Utf8PathBuf;
+use cwpt_core::Vault;
+use tauri::State;
+
+/// Validate a tar, tar.gz or zip bag without extracting it
+#[tauri::command]
+pub async fn validate_serialized_bag(
+    vault: State<'_, Vault>,
+    archive_path: String,
+    profile_path: Option<String>,
+) -> Result<BagResult, PreservationError> {
+    let profile_path = profile_path.map(Utf8PathBuf::from);
+    Ok(vault.validate_path(&Utf8PathBuf::from(archive_path), profile_path.as_deref()).await?)
+}
+
+/// Validate a serialized bag and add it to the vault as a new project
+#[tauri::command]
+pub async fn import_serialized_bag(
+    vault: State<'_, Vault>,
+    archive_path: String,
+    profile_path: Option<String>,
+) -> Result<BagResult, PreservationError> {
+    let profile_path = profile_path.map(Utf8PathBuf::from);
+    Ok(vault.import_serialized_bag(&Utf8PathBuf::from(archive_path), profile_path.as_deref()).await?)
+}
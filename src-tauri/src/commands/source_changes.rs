@@ -0,0 +1,22 @@
+use crate::commands::preservation_simple::PreservationError;
+use crate::models::preservation::{DeltaVersionResult, SourceChanges};
+use cwpt_core::Vault;
+use tauri::State;
+
+/// List what changed in a project's working folder since it was archived
+#[tauri::command]
+pub async fn detect_source_changes(
+    vault: State<'_, Vault>,
+    project_id: String,
+) -> Result<SourceChanges, PreservationError> {
+    Ok(vault.detect_source_changes(&project_id).await?)
+}
+
+/// Archive the changes in a project's working folder as a new version
+#[tauri::command]
+pub async fn create_delta_version(
+    vault: State<'_, Vault>,
+    project_id: String,
+) -> Result<DeltaVersionResult, PreservationError> {
+    Ok(vault.create_delta_version(&project_id).await?)
+}
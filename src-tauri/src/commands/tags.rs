@@ -0,0 +1,67 @@
+use crate::commands::preservation_simple::PreservationError;
+use crate::models::preservation::{Tag, TagAssignment, Vocabulary};
+use cwpt_core::Vault;
+use tauri::State;
+
+/// Create a vocabulary such as "medium" or "technique", optionally restricted to the given terms
+#[tauri::command]
+pub async fn create_vocabulary(
+    vault: State<'_, Vault>,
+    name: String,
+    description: Option<String>,
+    is_controlled: bool,
+    terms: Vec<String>,
+) -> Result<Vocabulary, PreservationError> {
+    Ok(vault
+        .create_vocabulary(&name, description.as_deref(), is_controlled, &terms)
+        .await?)
+}
+
+/// List all vocabularies
+#[tauri::command]
+pub async fn list_vocabularies(vault: State<'_, Vault>) -> Result<Vec<Vocabulary>, PreservationError> {
+    Ok(vault.list_vocabularies().await?)
+}
+
+/// List tags with usage counts, optionally limited to one vocabulary
+#[tauri::command]
+pub async fn list_tags(
+    vault: State<'_, Vault>,
+    vocabulary_id: Option<String>,
+) -> Result<Vec<Tag>, PreservationError> {
+    Ok(vault.list_tags(vocabulary_id.as_deref()).await?)
+}
+
+/// Get the tag assignments of a project and its files
+#[tauri::command]
+pub async fn get_project_tags(
+    vault: State<'_, Vault>,
+    project_id: String,
+) -> Result<Vec<TagAssignment>, PreservationError> {
+    Ok(vault.project_tags(&project_id).await?)
+}
+
+/// Tag a project, or one of its payload files when `file_path` is given
+#[tauri::command]
+pub async fn tag_project(
+    vault: State<'_, Vault>,
+    project_id: String,
+    tag_name: String,
+    vocabulary_id: Option<String>,
+    file_path: Option<String>,
+) -> Result<Tag, PreservationError> {
+    Ok(vault
+        .tag_project(&project_id, &tag_name, vocabulary_id.as_deref(), file_path.as_deref())
+        .await?)
+}
+
+/// Remove a tag from a project, or from one of its payload files
+#[tauri::command]
+pub async fn untag_project(
+    vault: State<'_, Vault>,
+    project_id: String,
+    tag_id: String,
+    file_path: Option<String>,
+) -> Result<(), PreservationError> {
+    Ok(vault.untag_project(&project_id, &tag_id, file_path.as_deref()).await?)
+}
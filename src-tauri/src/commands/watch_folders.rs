@@ -0,0 +1,59 @@
+use crate::background::report_background_error;
+use crate::commands::preservation_simple::PreservationError;
+use crate::models::preservation::WatchFolder;
+use camino::Utf8PathBuf;
+use cwpt_core::utils::watch::WatchTracker;
+use cwpt_core::Vault;
+use std::time::Duration;
+use tauri::{AppHandle, State};
+
+/// How often the app polls its watch folders
+const WATCH_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Watch a folder; each file or folder dropped into it is archived once it stops changing
+#[tauri::command]
+pub async fn add_watch_folder(
+    vault: State<'_, Vault>,
+    path: String,
+    settle_seconds: Option<i64>,
+    create_bag: Option<bool>,
+) -> Result<WatchFolder, PreservationError> {
+    Ok(vault
+        .add_watch_folder(&Utf8PathBuf::from(path), settle_seconds, create_bag.unwrap_or(true))
+        .await?)
+}
+
+/// Get all watch folders
+#[tauri::command]
+pub async fn get_watch_folders(vault: State<'_, Vault>) -> Result<Vec<WatchFolder>, PreservationError> {
+    Ok(vault.list_watch_folders().await?)
+}
+
+/// Stop watching a folder
+#[tauri::command]
+pub async fn remove_watch_folder(vault: State<'_, Vault>, watch_folder_id: String) -> Result<(), PreservationError> {
+    Ok(vault.remove_watch_folder(&watch_folder_id).await?)
+}
+
+/// Poll the watch folders for as long as the app runs, reporting failures to the UI
+pub async fn run_watch_folders(app: AppHandle, vault: Vault) {
+    let mut tracker = WatchTracker::default();
+    let mut ticker = tokio::time::interval(WATCH_POLL_INTERVAL);
+    loop {
+        ticker.tick().await;
+        if vault.is_database_locked() {
+            continue;
+        }
+        match vault.poll_watch_folders(&mut tracker).await {
+            Ok(ingestions) => {
+                for ingestion in ingestions {
+                    if let Some(error) = &ingestion.error {
+                        let message = format!("Failed to archive {}: {}", ingestion.entry_path, error);
+                        report_background_error(&app, "watch-folders", message);
+                    }
+                }
+            }
+            Err(e) => report_background_error(&app, "watch-folders", format!("Watch folder poll failed: {}", e)),
+        }
+    }
+}
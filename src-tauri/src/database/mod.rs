@@ -1 +0,0 @@
-pub mod connection;
\ No newline at end of file
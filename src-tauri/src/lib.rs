@@ -1,26 +1,108 @@
+pub mod background;
 pub mod commands;
-pub mod models;
-// pub mod utils;
-// pub mod database;
+pub use cwpt_core::{models, utils};
 
-// use crate::database::connection::get_migrations;
+use cwpt_core::{Vault, VaultConfig};
+use tauri::Manager;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
-        // .plugin(
-        //     tauri_plugin_sql::Builder::default()
-        //         .add_migrations("sqlite:preservation.db", get_migrations())
-        //         .build(),
-        // )
+        .setup(|app| {
+            // Kept in the app config dir so the CLI and the app can share one vault
+            let database_path = app.path().app_config_dir()?.join("preservation.db");
+            let database_path = camino::Utf8PathBuf::from_path_buf(database_path)
+                .map_err(|p| anyhow::anyhow!("Non-UTF8 path encountered: {}", p.display()))?;
+            // Bags must outlive reboots and temp cleaners, so they go in the persistent app data dir
+            let data_dir = camino::Utf8PathBuf::from_path_buf(app.path().app_data_dir()?)
+                .map_err(|p| anyhow::anyhow!("Non-UTF8 path encountered: {}", p.display()))?;
+            let config = VaultConfig::with_data_dir(database_path, &data_dir);
+            // An encrypted database opens locked; the app unlocks it with unlock_database
+            let vault = tauri::async_runtime::block_on(Vault::open(config))?;
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(commands::watch_folders::run_watch_folders(handle.clone(), vault.clone()));
+            tauri::async_runtime::spawn(commands::replication::run_replica_verification(handle.clone(), vault.clone()));
+            app.manage(vault);
+            app.manage(commands::http_api::HttpApiHandle::default());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             commands::preservation_simple::archive_project,
             commands::preservation_simple::create_bagit_package,
             commands::preservation_simple::get_archived_projects,
+            commands::preservation_simple::query_archived_projects,
             commands::preservation_simple::quarantine_project,
             commands::preservation_simple::restore_project,
             commands::preservation_simple::scan_vault_integrity,
+            commands::profiles::load_bagit_profile,
+            commands::profiles::validate_bag_against_profile,
+            commands::serialization::validate_serialized_bag,
+            commands::serialization::import_serialized_bag,
+            commands::bag_update::update_bag_payload,
+            commands::dependencies::analyze_project_dependencies,
+            commands::dependencies::include_linked_files,
+            commands::lineage::link_project_versions,
+            commands::lineage::unlink_project_versions,
+            commands::lineage::get_project_history,
+            commands::lineage::diff_project_versions,
+            commands::collections::create_collection,
+            commands::collections::list_collections,
+            commands::collections::add_project_to_collection,
+            commands::collections::remove_project_from_collection,
+            commands::collections::move_project,
+            commands::collections::move_collection,
+            commands::collections::export_collection,
+            commands::tags::create_vocabulary,
+            commands::tags::list_vocabularies,
+            commands::tags::list_tags,
+            commands::tags::get_project_tags,
+            commands::tags::tag_project,
+            commands::tags::untag_project,
+            commands::search::search_projects,
+            commands::search::rebuild_search_index,
+            commands::environment::create_environment_profile,
+            commands::environment::list_environment_profiles,
+            commands::environment::get_project_environment,
+            commands::environment::attach_environment_profile,
+            commands::environment::detach_environment_profile,
+            commands::previews::get_project_previews,
+            commands::fetch::add_external_reference,
+            commands::fetch::complete_holey_bag,
+            commands::export::export_project,
+            commands::export::export_vault_vra_core,
+            commands::source_changes::detect_source_changes,
+            commands::source_changes::create_delta_version,
+            commands::http_api::start_http_api,
+            commands::http_api::stop_http_api,
+            commands::replication::add_replication_target,
+            commands::replication::add_s3_replication_target,
+            commands::replication::add_webdav_replication_target,
+            commands::replication::get_replication_targets,
+            commands::replication::remove_replication_target,
+            commands::replication::replicate_bags,
+            commands::replication::get_replica_statuses,
+            commands::replication::verify_replicas,
+            commands::replication::repair_project,
+            commands::replication::set_replication_target_encrypted,
+            commands::encryption::get_encryption_status,
+            commands::encryption::set_up_encryption,
+            commands::encryption::unlock_encryption,
+            commands::encryption::lock_encryption,
+            commands::encryption::change_encryption_passphrase,
+            commands::encryption::recover_encryption,
+            commands::encryption::export_recovery_key,
+            commands::encryption::rotate_encryption_key,
+            commands::encryption::export_encrypted_bag,
+            commands::encryption::decrypt_export,
+            commands::database::get_database_status,
+            commands::database::unlock_database,
+            commands::database::encrypt_database,
+            commands::database::change_database_passphrase,
+            commands::database::export_unencrypted_database,
+            commands::watch_folders::add_watch_folder,
+            commands::watch_folders::get_watch_folders,
+            commands::watch_folders::remove_watch_folder,
         ])
         .run(tauri::generate_context!())
         .expect("error while running Tauri application")
-}
\ No newline at end of file
+}
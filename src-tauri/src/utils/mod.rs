@@ -1,3 +0,0 @@
-pub mod checksums;
-pub mod file_operations;
-pub mod bagit;
\ No newline at end of file